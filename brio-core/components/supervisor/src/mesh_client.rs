@@ -135,7 +135,8 @@ impl AgentDispatcher for WitAgentDispatcher {
                     Err(MeshError::AgentError(json))
                 }
             }
-            wit_bindings::service_mesh::Payload::Binary(_) => Err(MeshError::SerializationError(
+            wit_bindings::service_mesh::Payload::Binary(_)
+            | wit_bindings::service_mesh::Payload::MsgPack(_) => Err(MeshError::SerializationError(
                 "Unexpected binary response".to_string(),
             )),
         }
@@ -63,6 +63,100 @@ pub mod sql_state {
     }
 }
 
+/// Key-value state interface bindings.
+///
+/// For components that only need to keep values by key, without writing SQL.
+/// The host implements the `kv-state` interface in `wit/host.wit`:
+///
+/// ```wit
+/// interface kv-state {
+///     get: func(key: string) -> result<option<string>, string>;
+///     set: func(key: string, value: string) -> result<tuple<>, string>;
+///     delete: func(key: string) -> result<bool, string>;
+///     %list: func(prefix: string) -> result<list<tuple<string, string>>, string>;
+/// }
+/// ```
+///
+/// The kernel keeps the entries in the guest's own scope of the SQL store,
+/// so the same scope policy applies as for `sql_state`.
+pub mod kv_state {
+    /// Read the value stored under `key`, if any.
+    ///
+    /// # Errors
+    /// Returns error string if the read fails.
+    pub fn get(key: &str) -> Result<Option<String>, String> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use crate::brio_host::kv_state as wit;
+            wit::get(key)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Stub for native testing
+            let _ = key;
+            Ok(None)
+        }
+    }
+
+    /// Store `value` under `key`, replacing any previous value.
+    ///
+    /// # Errors
+    /// Returns error string if the write fails.
+    pub fn set(key: &str, value: &str) -> Result<(), String> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use crate::brio_host::kv_state as wit;
+            wit::set(key, value)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Stub for native testing
+            let _ = (key, value);
+            Ok(())
+        }
+    }
+
+    /// Remove `key`, returning whether it was present.
+    ///
+    /// # Errors
+    /// Returns error string if the delete fails.
+    pub fn delete(key: &str) -> Result<bool, String> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use crate::brio_host::kv_state as wit;
+            wit::delete(key)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Stub for native testing
+            let _ = key;
+            Ok(false)
+        }
+    }
+
+    /// List the entries whose keys start with `prefix`, ordered by key.
+    ///
+    /// # Errors
+    /// Returns error string if the listing fails.
+    pub fn list(prefix: &str) -> Result<Vec<(String, String)>, String> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use crate::brio_host::kv_state as wit;
+            wit::list(prefix)
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Stub for native testing
+            let _ = prefix;
+            Ok(vec![])
+        }
+    }
+}
+
 /// Service Mesh interface bindings.
 pub mod service_mesh {
     /// Payload variant for mesh calls.
@@ -70,6 +164,7 @@ pub mod service_mesh {
     pub enum Payload {
         Json(String),
         Binary(Vec<u8>),
+        MsgPack(Vec<u8>),
     }
 
     /// Call a component via the service mesh.
@@ -81,24 +176,179 @@ pub mod service_mesh {
         {
             use crate::brio_host::service_mesh as wit;
 
-            let wit_args = match args {
-                Payload::Json(s) => wit::Payload::Json(s),
-                Payload::Binary(b) => wit::Payload::Binary(b),
-            };
+            wit::call(target, method, to_wit(args)).map(from_wit)
+        }
 
-            let result = wit::call(target, method, wit_args)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Stub for native testing
+            let _ = (target, method, args);
+            Ok(accepted())
+        }
+    }
+
+    /// Start a call via the service mesh without waiting for it.
+    ///
+    /// The call runs on the host while the component carries on; its result
+    /// is collected through the returned [`PendingCall`].
+    ///
+    /// # Errors
+    /// Returns error string if the host already holds as many uncollected
+    /// calls as it allows.
+    pub fn call_async(target: &str, method: &str, args: Payload) -> Result<PendingCall, String> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            use crate::brio_host::service_mesh as wit;
 
-            Ok(match result {
-                wit::Payload::Json(s) => Payload::Json(s),
-                wit::Payload::Binary(b) => Payload::Binary(b),
+            Ok(PendingCall {
+                handle: wit::call_async(target, method, to_wit(args))?,
             })
         }
 
         #[cfg(not(target_arch = "wasm32"))]
         {
-            // Stub for native testing
+            // Stub for native testing: the call finishes at once
             let _ = (target, method, args);
-            Ok(Payload::Json(r#"{"status":"accepted"}"#.to_string()))
+            let handle = stub::start();
+            Ok(PendingCall { handle })
         }
     }
+
+    /// A mesh call started by [`call_async`] that hasn't been collected yet.
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct PendingCall {
+        handle: u64,
+    }
+
+    impl PendingCall {
+        /// Correlation handle the host tracks the call by.
+        #[must_use]
+        pub fn handle(&self) -> u64 {
+            self.handle
+        }
+
+        /// The call's result if it has finished, or `None` while it's still
+        /// running. A finished call's result is returned only once.
+        pub fn poll(&self) -> Option<Result<Payload, String>> {
+            #[cfg(target_arch = "wasm32")]
+            {
+                use crate::brio_host::service_mesh as wit;
+
+                wit::poll_call(self.handle).map(|result| result.map(from_wit))
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                Some(stub::collect(self.handle))
+            }
+        }
+
+        /// Wait for the call to finish.
+        ///
+        /// # Errors
+        /// Returns error string if the call fails, or its result was already
+        /// taken by [`poll`](Self::poll).
+        pub fn wait(self) -> Result<Payload, String> {
+            #[cfg(target_arch = "wasm32")]
+            {
+                use crate::brio_host::service_mesh as wit;
+
+                wit::await_call(self.handle).map(from_wit)
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                stub::collect(self.handle)
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn to_wit(payload: Payload) -> crate::brio_host::service_mesh::Payload {
+        use crate::brio_host::service_mesh as wit;
+
+        match payload {
+            Payload::Json(s) => wit::Payload::Json(s),
+            Payload::Binary(b) => wit::Payload::Binary(b),
+            Payload::MsgPack(b) => wit::Payload::Msgpack(b),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn from_wit(payload: crate::brio_host::service_mesh::Payload) -> Payload {
+        use crate::brio_host::service_mesh as wit;
+
+        match payload {
+            wit::Payload::Json(s) => Payload::Json(s),
+            wit::Payload::Binary(b) => Payload::Binary(b),
+            wit::Payload::Msgpack(b) => Payload::MsgPack(b),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn accepted() -> Payload {
+        Payload::Json(r#"{"status":"accepted"}"#.to_string())
+    }
+
+    /// Calls started natively, which finish as soon as they start
+    #[cfg(not(target_arch = "wasm32"))]
+    mod stub {
+        use super::{Payload, accepted};
+        use std::cell::RefCell;
+        use std::collections::HashSet;
+
+        thread_local! {
+            static PENDING: RefCell<(u64, HashSet<u64>)> = RefCell::new((0, HashSet::new()));
+        }
+
+        pub(super) fn start() -> u64 {
+            PENDING.with_borrow_mut(|(last, pending)| {
+                *last += 1;
+                pending.insert(*last);
+                *last
+            })
+        }
+
+        pub(super) fn collect(handle: u64) -> Result<Payload, String> {
+            if PENDING.with_borrow_mut(|(_, pending)| pending.remove(&handle)) {
+                Ok(accepted())
+            } else {
+                Err(format!("Unknown mesh call handle {handle}"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kv_state_stubs_succeed_without_host() {
+        assert_eq!(kv_state::set("task/1", "pending"), Ok(()));
+        assert_eq!(kv_state::get("task/1"), Ok(None));
+        assert_eq!(kv_state::delete("task/1"), Ok(false));
+        assert_eq!(kv_state::list("task/"), Ok(vec![]));
+    }
+
+    #[test]
+    fn async_mesh_call_completes_through_its_handle() {
+        let first = service_mesh::call_async("coder", "run", service_mesh::Payload::Json("{}".to_string())).unwrap();
+        let second = service_mesh::call_async("reviewer", "run", service_mesh::Payload::Binary(vec![1])).unwrap();
+        assert_ne!(first.handle(), second.handle());
+
+        let polled = first.poll().expect("stub calls finish at once");
+        assert!(matches!(polled, Ok(service_mesh::Payload::Json(s)) if s.contains("accepted")));
+        assert!(matches!(second.wait(), Ok(service_mesh::Payload::Json(_))));
+    }
+
+    #[test]
+    fn async_mesh_call_result_is_collected_only_once() {
+        let call = service_mesh::call_async("coder", "run", service_mesh::Payload::Json("{}".to_string())).unwrap();
+        let handle = call.handle();
+        assert!(matches!(call.poll(), Some(Ok(_))));
+
+        let err = call.wait().unwrap_err();
+        assert_eq!(err, format!("Unknown mesh call handle {handle}"));
+    }
 }
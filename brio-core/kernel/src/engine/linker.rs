@@ -1,10 +1,21 @@
 use crate::engine::brio;
 use crate::host::BrioHostState;
 use crate::mesh::Payload;
+use crate::store::TypedStore;
 use anyhow::Result;
 use wasmtime::component::{HasSelf, Linker};
 use wasmtime::{Config, Engine};
 
+/// Scope of the tables WASM guests read and write
+const GUEST_SCOPE: &str = "wasm_guest";
+
+/// Runs a guest's host call to completion as one request, so every layer it
+/// reaches retries from the same budget.
+fn block_on_request<F: std::future::Future>(host: &BrioHostState, fut: F) -> F::Output {
+    let context = host.request_context();
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(context.inherit_or(fut)))
+}
+
 impl brio::core::service_mesh::Host for BrioHostState {
     fn call(
         &mut self,
@@ -12,27 +23,50 @@ impl brio::core::service_mesh::Host for BrioHostState {
         method: String,
         args: brio::core::service_mesh::Payload,
     ) -> Result<brio::core::service_mesh::Payload, String> {
-        // Convert WASM payload to internal Payload
-        let internal_payload = match args {
-            brio::core::service_mesh::Payload::Json(s) => Payload::Json(s),
-            brio::core::service_mesh::Payload::Binary(b) => Payload::Binary(b),
-        };
-
         // Bridge sync to async
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                self.mesh_call(&target, &method, internal_payload).await
-            })
+        let result = block_on_request(self, async {
+            self.mesh_call(&target, &method, from_guest_payload(args)).await
         });
 
-        // Convert result back to WASM payload
-        result
-            .map(|p| match p {
-                Payload::Json(s) => brio::core::service_mesh::Payload::Json(s),
-                Payload::Binary(b) => brio::core::service_mesh::Payload::Binary(b),
-            })
+        result.map(to_guest_payload).map_err(|e| e.to_string())
+    }
+
+    fn call_async(
+        &mut self,
+        target: String,
+        method: String,
+        args: brio::core::service_mesh::Payload,
+    ) -> Result<u64, String> {
+        self.start_guest_call(target, method, from_guest_payload(args))
             .map_err(|e| e.to_string())
     }
+
+    fn poll_call(&mut self, handle: u64) -> Option<Result<brio::core::service_mesh::Payload, String>> {
+        self.poll_guest_call(handle)
+            .map(|result| result.map(to_guest_payload).map_err(|e| e.to_string()))
+    }
+
+    fn await_call(&mut self, handle: u64) -> Result<brio::core::service_mesh::Payload, String> {
+        let result = block_on_request(self, self.await_guest_call(handle));
+
+        result.map(to_guest_payload).map_err(|e| e.to_string())
+    }
+}
+
+fn from_guest_payload(payload: brio::core::service_mesh::Payload) -> Payload {
+    match payload {
+        brio::core::service_mesh::Payload::Json(s) => Payload::Json(s),
+        brio::core::service_mesh::Payload::Binary(b) => Payload::Binary(b),
+        brio::core::service_mesh::Payload::Msgpack(b) => Payload::MsgPack(b),
+    }
+}
+
+fn to_guest_payload(payload: Payload) -> brio::core::service_mesh::Payload {
+    match payload {
+        Payload::Json(s) => brio::core::service_mesh::Payload::Json(s),
+        Payload::Binary(b) => brio::core::service_mesh::Payload::Binary(b),
+        Payload::MsgPack(b) => brio::core::service_mesh::Payload::Msgpack(b),
+    }
 }
 
 impl brio::core::sql_state::Host for BrioHostState {
@@ -42,13 +76,11 @@ impl brio::core::sql_state::Host for BrioHostState {
         params: Vec<String>,
     ) -> Result<Vec<brio::core::sql_state::Row>, String> {
         // Use a default scope for WASM guests
-        let scope = "wasm_guest";
+        let scope = GUEST_SCOPE;
         let store = self.get_store(scope);
 
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                store.query(scope, &sql, params).await
-            })
+        let result = block_on_request(self, async {
+            store.query(scope, &sql, params).await
         });
 
         result
@@ -64,26 +96,69 @@ impl brio::core::sql_state::Host for BrioHostState {
     }
 
     fn execute(&mut self, sql: String, params: Vec<String>) -> Result<u32, String> {
-        let scope = "wasm_guest";
+        let scope = GUEST_SCOPE;
         let store = self.get_store(scope);
 
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                store.execute(scope, &sql, params).await
-            })
+        let result = block_on_request(self, async {
+            store.execute(scope, &sql, params).await
         });
 
         result.map_err(|e| e.to_string())
     }
 }
 
+impl BrioHostState {
+    /// Key-value entries of WASM guests, in the same scope as their SQL
+    fn guest_kv(&self) -> TypedStore<String> {
+        TypedStore::new(self.get_store(GUEST_SCOPE), GUEST_SCOPE)
+    }
+}
+
+impl brio::core::kv_state::Host for BrioHostState {
+    fn get(&mut self, key: String) -> Result<Option<String>, String> {
+        let kv = self.guest_kv();
+        let result = block_on_request(self, async {
+            self.ensure_guest_kv(&kv).await?;
+            kv.get(&key).await
+        });
+        result.map_err(|e| e.to_string())
+    }
+
+    fn set(&mut self, key: String, value: String) -> Result<(), String> {
+        let kv = self.guest_kv();
+        let result = block_on_request(self, async {
+            self.ensure_guest_kv(&kv).await?;
+            kv.put(&key, &value).await
+        });
+        result.map_err(|e| e.to_string())
+    }
+
+    fn delete(&mut self, key: String) -> Result<bool, String> {
+        let kv = self.guest_kv();
+        let result = block_on_request(self, async {
+            self.ensure_guest_kv(&kv).await?;
+            kv.delete(&key).await
+        });
+        result.map_err(|e| e.to_string())
+    }
+
+    fn list(&mut self, prefix: String) -> Result<Vec<(String, String)>, String> {
+        let kv = self.guest_kv();
+        let result = block_on_request(self, async {
+            self.ensure_guest_kv(&kv).await?;
+            kv.list(&prefix).await
+        });
+        result.map_err(|e| e.to_string())
+    }
+}
+
 impl brio::core::session_fs::Host for BrioHostState {
     fn begin_session(&mut self, base_path: String) -> Result<String, String> {
         BrioHostState::begin_session(self, base_path)
     }
 
     fn commit_session(&mut self, session_id: String) -> Result<(), String> {
-        BrioHostState::commit_session(self, session_id)
+        BrioHostState::commit_session(self, session_id).map(|_| ())
     }
 }
 
@@ -99,32 +174,23 @@ impl brio::core::inference::Host for BrioHostState {
         // Convert WASM messages to internal messages
         let internal_messages: Vec<Message> = messages
             .into_iter()
-            .map(|m| Message {
-                role: match m.role {
+            .map(|m| {
+                let role = match m.role {
                     brio::core::inference::Role::System => Role::System,
                     brio::core::inference::Role::User => Role::User,
                     brio::core::inference::Role::Assistant => Role::Assistant,
-                },
-                content: m.content,
+                };
+                Message::new(role, m.content)
             })
             .collect();
 
         let request = ChatRequest {
             model,
             messages: internal_messages,
+            ..Default::default()
         };
 
-        let inference_provider = match self.inference() {
-            Some(provider) => provider,
-            None => return Err(brio::core::inference::InferenceError::ProviderError(
-                "No default inference provider configured".to_string()
-            )),
-        };
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                inference_provider.chat(request).await
-            })
-        });
+        let result = block_on_request(self, async { self.chat_as(self.tenant(), request).await });
 
         result
             .map(|response| brio::core::inference::CompletionResponse {
@@ -136,10 +202,12 @@ impl brio::core::inference::Host for BrioHostState {
                 }),
             })
             .map_err(|e| match e {
-                crate::inference::InferenceError::RateLimit => {
+                crate::inference::InferenceError::RateLimit
+                | crate::inference::InferenceError::RateLimited { .. }
+                | crate::inference::InferenceError::Throttled { status: 429, .. } => {
                     brio::core::inference::InferenceError::RateLimit
                 }
-                crate::inference::InferenceError::ContextLengthExceeded => {
+                crate::inference::InferenceError::ContextLengthExceeded { .. } => {
                     brio::core::inference::InferenceError::ContextLengthExceeded
                 }
                 other => brio::core::inference::InferenceError::ProviderError(other.to_string()),
@@ -191,6 +259,7 @@ fn register_host_interfaces(linker: &mut Linker<BrioHostState>) -> Result<()> {
 
     brio::core::service_mesh::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
     brio::core::sql_state::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
+    brio::core::kv_state::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
     brio::core::session_fs::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
     brio::core::inference::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
     brio::core::logging::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
@@ -198,6 +267,7 @@ fn register_host_interfaces(linker: &mut Linker<BrioHostState>) -> Result<()> {
     Ok(())
 }
 
+#[allow(dead_code)]
 fn stub_error(interface: &str) -> String {
     format!("Interface '{}' not yet implemented via WASM", interface)
 }
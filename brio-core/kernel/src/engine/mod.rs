@@ -11,9 +11,13 @@ wasmtime::component::bindgen!({
         interface service-mesh {
             variant payload {
                 json(string),
-                binary(list<u8>)
+                binary(list<u8>),
+                msgpack(list<u8>)
             }
             call: func(target: string, method: string, args: payload) -> result<payload, string>;
+            call-async: func(target: string, method: string, args: payload) -> result<u64, string>;
+            poll-call: func(handle: u64) -> option<result<payload, string>>;
+            await-call: func(handle: u64) -> result<payload, string>;
         }
 
         interface sql-state {
@@ -25,6 +29,13 @@ wasmtime::component::bindgen!({
             execute: func(sql: string, params: list<string>) -> result<u32, string>;
         }
 
+        interface kv-state {
+            get: func(key: string) -> result<option<string>, string>;
+            set: func(key: string, value: string) -> result<tuple<>, string>;
+            delete: func(key: string) -> result<bool, string>;
+            %list: func(prefix: string) -> result<list<tuple<string, string>>, string>;
+        }
+
         interface session-fs {
             begin-session: func(base-path: string) -> result<string, string>;
             commit-session: func(session-id: string) -> result<tuple<>, string>;
@@ -47,6 +58,7 @@ wasmtime::component::bindgen!({
         world brio-host {
             import service-mesh;
             import sql-state;
+            import kv-state;
             import session-fs;
             import inference;
             import logging;
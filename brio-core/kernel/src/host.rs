@@ -1,54 +1,438 @@
 use anyhow::{Result, anyhow};
-use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use futures_util::stream::FuturesUnordered;
+use futures_util::{FutureExt, StreamExt};
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
-use tokio::sync::oneshot;
-
-use crate::inference::{LLMProvider, ProviderRegistry};
-use crate::mesh::{MeshMessage, Payload};
-use crate::mesh::remote::RemoteRouter;
-use crate::mesh::types::{NodeId, NodeInfo};
-use crate::store::{PrefixPolicy, SqlStore};
-use crate::vfs::manager::SessionManager;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, error, info, info_span, warn};
+
+use crate::inference::recording::RECORDING_SCOPE;
+use crate::inference::{
+    AgentLoopError, AgentOutcome, AgentStep, AgentTool, Capability, ChatRequest, ChatResponse,
+    InferenceError, LLMProvider, Message, ProviderRegistry, ProviderSelector, RequestRecorder,
+    SelectionStrategy, TenantRateLimiter, ToolResult, Transform, apply_transforms,
+};
+use crate::infrastructure::audit::{AuditEvent, log_audit};
+use crate::infrastructure::context::{DEFAULT_RETRY_BUDGET, RequestContext};
+use crate::infrastructure::health::{
+    ComponentHealth, DATABASE_CHECK, DEFAULT_PROVIDER_CHECK, HealthReport, ReadinessReport,
+};
+use crate::mesh::changes::{ChangeSubscriptions, SeenEvents};
+use crate::mesh::grpc::{DEFAULT_COMPRESSION_THRESHOLD, StoreChangeEvent};
+use crate::mesh::remote::{RemoteRouter, is_transport_failure};
+use crate::mesh::service::MeshServer;
+use crate::mesh::trace::{current_traceparent, set_remote_parent};
+use crate::mesh::types::{NodeAddress, NodeId, NodeInfo};
+use crate::mesh::{
+    CircuitBreakers, CircuitState, DEFAULT_MESH_CALL_TIMEOUT, MeshCallTimeout, MeshMessage,
+    MeshTls, MethodRouter, Payload, SessionTransferRejected, SessionTransferUnconfirmed,
+    ShuttingDown,
+};
+use crate::store::{
+    ChangeSink, DEFAULT_BUSY_RETRIES, DbPool, KeyWatchers, LOCK_SCOPE, LockGuard, LockManager,
+    PolicyFailureMode, PrefixPolicy, SqlStore, StoreChange, StoreError, StoreSnapshot, TypedStore,
+    ValueSizeLimits,
+};
+use crate::vfs::diff::CommitSummary;
+use crate::vfs::manager::{
+    ExpiredSession, SessionInfo, SessionManager, SessionSnapshot, describe_sessions,
+};
 use crate::ws::{BroadcastMessage, Broadcaster, WsPatch};
 
+/// Local components by id
+type ComponentRoutes = Arc<std::sync::RwLock<HashMap<String, Sender<MeshMessage>>>>;
+
 pub struct BrioHostState {
-    mesh_router: std::sync::RwLock<HashMap<String, Sender<MeshMessage>>>,
+    mesh_router: ComponentRoutes,
+    /// Per-target breakers for mesh calls; `None` never fails calls fast
+    circuit_breakers: Option<Arc<CircuitBreakers>>,
     remote_router: Option<RemoteRouter>,
-    db_pool: SqlitePool,
+    /// Applied to the mesh server once started
+    mesh_tls: Option<MeshTls>,
+    db_pool: DbPool,
     broadcaster: Broadcaster,
-    session_manager: std::sync::Mutex<SessionManager>,
-    provider_registry: Arc<ProviderRegistry>,
+    /// Shared with the idle session reaper, if one is running
+    session_manager: Arc<std::sync::Mutex<SessionManager>>,
+    /// Swapped whole on reload; requests keep the snapshot they started with
+    providers: std::sync::RwLock<ProviderSet>,
+    retry_budget: u32,
+    busy_retries: u32,
+    policy_failure_mode: PolicyFailureMode,
+    value_size_limits: ValueSizeLimits,
+    /// Shared by every store handed out, so watches see writes made through any of them
+    key_watchers: Arc<KeyWatchers>,
+    mesh_broadcast_concurrency: usize,
+    mesh_call_timeout: Duration,
+    /// Mesh payloads at least this many bytes are compressed on the wire
+    mesh_compression_threshold: usize,
+    /// Secrets to redact when recording completion requests; `None` disables recording
+    request_recording: Option<Vec<String>>,
+    rate_limiter: TenantRateLimiter,
+    /// Tenant that guests running on this host are rate limited as
+    tenant: String,
+    post_process: Vec<Transform>,
+    change_subscriptions: std::sync::RwLock<ChangeSubscriptions>,
+    seen_changes: std::sync::Mutex<SeenEvents>,
+    /// Cancelled when [`shutdown`](BrioHostState::shutdown) starts
+    shutdown: CancellationToken,
+    /// Mesh calls currently running, watched while draining
+    in_flight_calls: watch::Sender<usize>,
+    /// Mesh calls WASM guests started without waiting, by handle
+    guest_calls: std::sync::Mutex<HashMap<u64, JoinHandle<Result<Payload>>>>,
+    next_guest_call: AtomicU64,
+    /// Most guest calls left uncollected at once
+    max_guest_calls: usize,
+    /// Set once the key-value table of WASM guests exists
+    guest_kv_ready: tokio::sync::OnceCell<()>,
+    /// Set once the table of recorded requests exists
+    recording_ready: tokio::sync::OnceCell<()>,
+}
+
+/// A provider registry together with the selector validated against it.
+#[derive(Clone)]
+struct ProviderSet {
+    registry: Arc<ProviderRegistry>,
+    selector: Arc<ProviderSelector>,
+}
+
+impl ProviderSet {
+    fn new(
+        registry: ProviderRegistry,
+        strategy: SelectionStrategy,
+    ) -> Result<Self, InferenceError> {
+        let selector = ProviderSelector::new(strategy, &registry)?;
+        Ok(Self {
+            registry: Arc::new(registry),
+            selector: Arc::new(selector),
+        })
+    }
+}
+
+/// What a mesh call needs from the host, cloned out of it so a call can be
+/// spawned to run on its own
+struct MeshCaller {
+    mesh_router: ComponentRoutes,
+    circuit_breakers: Option<Arc<CircuitBreakers>>,
+    remote_router: Option<RemoteRouter>,
+    in_flight_calls: watch::Sender<usize>,
+    shutdown: CancellationToken,
 }
 
+impl MeshCaller {
+    /// `remote_parent` is the traceparent of a caller on another node, which
+    /// is passed on as is if this node isn't tracing.
+    async fn call(
+        &self,
+        target: &str,
+        method: &str,
+        payload: Payload,
+        timeout: Duration,
+        remote_parent: Option<String>,
+    ) -> Result<Payload> {
+        let span = info_span!(
+            "mesh_call",
+            target = %target,
+            method = %method,
+            payload_bytes = payload.len(),
+            outcome = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        if let Some(parent) = &remote_parent {
+            set_remote_parent(&span, parent);
+        }
+        let trace_context = span.in_scope(current_traceparent).or(remote_parent);
+        let deadline = tokio::time::Instant::now() + timeout;
+        // Counted before checking for shutdown, so a drain can't miss a call that got past the check
+        let _in_flight = InFlightCall::start(&self.in_flight_calls);
+        let acquired = if self.shutdown.is_cancelled() {
+            Err(anyhow::Error::new(ShuttingDown {
+                target: target.to_string(),
+            }))
+        } else {
+            match &self.circuit_breakers {
+                Some(breakers) => breakers.try_acquire(target).map_err(anyhow::Error::new),
+                None => Ok(()),
+            }
+        };
+        let result = match acquired {
+            Err(e) => Err(e),
+            Ok(()) => {
+                let result = self
+                    .route(target, method, payload, trace_context, deadline)
+                    .instrument(span.clone())
+                    .await
+                    .map_err(|e| match e.downcast::<Elapsed>() {
+                        Ok(elapsed) => anyhow::Error::new(MeshCallTimeout {
+                            target: target.to_string(),
+                            timeout,
+                            delivered: elapsed.delivered,
+                        }),
+                        Err(e) => e,
+                    });
+                if let Some(breakers) = &self.circuit_breakers {
+                    match &result {
+                        Err(e) if trips_breaker(e) => breakers.record_failure(target),
+                        // An error reply still shows the target is up
+                        _ => breakers.record_success(target),
+                    }
+                }
+                result
+            }
+        };
+        let outcome = match &result {
+            Ok(_) => "ok",
+            Err(e) => {
+                span.record("error", tracing::field::display(e));
+                "error"
+            }
+        };
+        span.record("outcome", outcome);
+        metrics::counter!(MESH_CALLS_METRIC, "outcome" => outcome).increment(1);
+        result
+    }
+
+    async fn route(
+        &self,
+        target: &str,
+        method: &str,
+        payload: Payload,
+        trace_context: Option<String>,
+        deadline: tokio::time::Instant,
+    ) -> Result<Payload> {
+        // 1. Try local routing first
+        let sender = {
+            let router = self.mesh_router.read().expect("RwLock poisoned");
+            router.get(target).cloned()
+        };
+
+        if let Some(sender) = sender {
+            if sender.is_closed() {
+                self.deregister_closed(target, &sender);
+                return Err(anyhow!(
+                    "Target component '{}' is no longer running",
+                    target
+                ));
+            }
+            let (reply_tx, reply_rx) = oneshot::channel();
+            let message = MeshMessage {
+                target: target.to_string(),
+                method: method.to_string(),
+                payload,
+                trace_context,
+                reply_tx,
+            };
+
+            tokio::time::timeout_at(deadline, sender.send(message))
+                .await
+                .map_err(|_| Elapsed { delivered: false })?
+                .map_err(|e| {
+                    self.deregister_closed(target, &sender);
+                    anyhow!("Failed to send message to target '{}': {}", target, e)
+                })?;
+            let response = tokio::time::timeout_at(deadline, reply_rx)
+                .await
+                .map_err(|_| Elapsed { delivered: true })?
+                .map_err(|e| anyhow!("Failed to receive reply from target '{}': {}", target, e))?;
+            return response.map_err(|e| anyhow!("Target '{}' returned error: {}", target, e));
+        }
+
+        // 2. Try remote routing if enabled and target is formatted as "node_id/component"
+        // Explicit remote addressing: "node_id/component_id"
+        if let (Some(router), Some((node_id_str, component))) =
+            (&self.remote_router, target.split_once('/'))
+        {
+            let node_id = NodeId::from(node_id_str.to_string());
+
+            // If the target is a different node, route via gRPC
+            let message = MeshMessage {
+                target: component.to_string(),
+                method: method.to_string(),
+                payload,
+                trace_context,
+                reply_tx: oneshot::channel().0, // Reply handling is managed by RemoteRouter's request/response flow
+            };
+
+            // Whether a timed-out remote call reached the other node is unknown
+            return tokio::time::timeout_at(deadline, router.send(&node_id, message))
+                .await
+                .map_err(|_| Elapsed { delivered: false })?;
+        }
+
+        Err(anyhow!(
+            "Target component '{}' not found. Ensure format is 'component' (local) or 'node_id/component' (remote). Available targets: [{}]",
+            target,
+            component_ids(&self.mesh_router).join(", ")
+        ))
+    }
+
+    /// Drops the route to `id` if it still points at the closed `sender`, so
+    /// a component that re-registered in the meantime keeps its new route.
+    fn deregister_closed(&self, id: &str, sender: &Sender<MeshMessage>) {
+        let mut router = self.mesh_router.write().expect("RwLock poisoned");
+        if router
+            .get(id)
+            .is_some_and(|current| current.same_channel(sender))
+        {
+            router.remove(id);
+            warn!(component = %id, "Component channel closed, deregistered");
+        }
+    }
+}
+
+fn component_ids(routes: &ComponentRoutes) -> Vec<String> {
+    let mut ids: Vec<String> = {
+        let router = routes.read().expect("RwLock poisoned");
+        router.keys().cloned().collect()
+    };
+    ids.sort();
+    ids
+}
+
+/// Whether a failed mesh call counts against its target's breaker: only
+/// when the target couldn't be reached or didn't answer in time.
+fn trips_breaker(error: &anyhow::Error) -> bool {
+    is_transport_failure(error) || error.downcast_ref::<MeshCallTimeout>().is_some()
+}
+
+fn join_guest_call(joined: Result<Result<Payload>, tokio::task::JoinError>) -> Result<Payload> {
+    joined.unwrap_or_else(|e| Err(anyhow!("Mesh call task failed: {}", e)))
+}
+
+/// Counts a mesh call as in flight until dropped
+struct InFlightCall<'a>(&'a watch::Sender<usize>);
+
+impl<'a> InFlightCall<'a> {
+    fn start(counter: &'a watch::Sender<usize>) -> Self {
+        counter.send_modify(|n| *n += 1);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlightCall<'_> {
+    fn drop(&mut self) {
+        self.0.send_modify(|n| *n -= 1);
+    }
+}
+
+/// Marks where a mesh call ran out of time, before it's reported as [`MeshCallTimeout`]
+#[derive(Debug, thiserror::Error)]
+#[error("mesh call deadline elapsed")]
+struct Elapsed {
+    delivered: bool,
+}
+
+/// Messages queued for a component registered with typed methods
+const COMPONENT_CHANNEL_CAPACITY: usize = 32;
+
+/// Calls a bulk mesh broadcast keeps in flight at once
+const DEFAULT_MESH_BROADCAST_CONCURRENCY: usize = 8;
+
+/// Mesh calls guests may leave uncollected at once
+const DEFAULT_MAX_GUEST_CALLS: usize = 64;
+
+/// Counter of mesh calls made, labelled by outcome (`ok` or `error`)
+pub const MESH_CALLS_METRIC: &str = "brio_mesh_calls_total";
+
+/// Longest a readiness probe waits for the database
+pub const READINESS_DB_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest a store change waits to reach a subscribed node before it is
+/// given up on for that node
+pub const CHANGE_FORWARD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Tenant guests are rate limited as unless the host names another
+pub const DEFAULT_TENANT: &str = "default";
+
 impl BrioHostState {
     /// Creates a new BrioHostState with a pre-configured provider registry.
+    ///
+    /// `db_url` opens SQLite or, with the `postgres` feature, PostgreSQL; see
+    /// [`DbPool::connect`].
     pub async fn new(db_url: &str, registry: ProviderRegistry) -> Result<Self> {
-        let pool = SqlitePoolOptions::new().connect(db_url).await?;
+        let pool = DbPool::connect(db_url).await?;
 
         Ok(Self {
-            mesh_router: std::sync::RwLock::new(HashMap::new()),
+            mesh_router: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            circuit_breakers: None,
             remote_router: None, // Default to standalone mode
+            mesh_tls: None,
             db_pool: pool,
             broadcaster: Broadcaster::new(),
-            session_manager: std::sync::Mutex::new(SessionManager::new()),
-            provider_registry: Arc::new(registry),
+            session_manager: Arc::new(std::sync::Mutex::new(SessionManager::new())),
+            providers: std::sync::RwLock::new(ProviderSet::new(
+                registry,
+                SelectionStrategy::default(),
+            )?),
+            retry_budget: DEFAULT_RETRY_BUDGET,
+            busy_retries: DEFAULT_BUSY_RETRIES,
+            policy_failure_mode: PolicyFailureMode::default(),
+            value_size_limits: ValueSizeLimits::default(),
+            key_watchers: Arc::new(KeyWatchers::new()),
+            mesh_broadcast_concurrency: DEFAULT_MESH_BROADCAST_CONCURRENCY,
+            mesh_call_timeout: DEFAULT_MESH_CALL_TIMEOUT,
+            mesh_compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            request_recording: None,
+            rate_limiter: TenantRateLimiter::new(),
+            tenant: DEFAULT_TENANT.to_string(),
+            post_process: Vec::new(),
+            change_subscriptions: std::sync::RwLock::new(ChangeSubscriptions::new()),
+            seen_changes: std::sync::Mutex::new(SeenEvents::default()),
+            shutdown: CancellationToken::new(),
+            in_flight_calls: watch::Sender::new(0),
+            guest_calls: std::sync::Mutex::new(HashMap::new()),
+            next_guest_call: AtomicU64::new(1),
+            max_guest_calls: DEFAULT_MAX_GUEST_CALLS,
+            guest_kv_ready: tokio::sync::OnceCell::new(),
+            recording_ready: tokio::sync::OnceCell::new(),
         })
     }
 
     /// Creates a new BrioHostState with distributed mesh support
     pub async fn new_distributed(db_url: &str, registry: ProviderRegistry, node_id: NodeId) -> Result<Self> {
-        let pool = SqlitePoolOptions::new().connect(db_url).await?;
+        let pool = DbPool::connect(db_url).await?;
         let remote_router = RemoteRouter::new(node_id);
 
         Ok(Self {
-            mesh_router: std::sync::RwLock::new(HashMap::new()),
+            mesh_router: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            circuit_breakers: None,
             remote_router: Some(remote_router),
+            mesh_tls: None,
             db_pool: pool,
             broadcaster: Broadcaster::new(),
-            session_manager: std::sync::Mutex::new(SessionManager::new()),
-            provider_registry: Arc::new(registry),
+            session_manager: Arc::new(std::sync::Mutex::new(SessionManager::new())),
+            providers: std::sync::RwLock::new(ProviderSet::new(
+                registry,
+                SelectionStrategy::default(),
+            )?),
+            retry_budget: DEFAULT_RETRY_BUDGET,
+            busy_retries: DEFAULT_BUSY_RETRIES,
+            policy_failure_mode: PolicyFailureMode::default(),
+            value_size_limits: ValueSizeLimits::default(),
+            key_watchers: Arc::new(KeyWatchers::new()),
+            mesh_broadcast_concurrency: DEFAULT_MESH_BROADCAST_CONCURRENCY,
+            mesh_call_timeout: DEFAULT_MESH_CALL_TIMEOUT,
+            mesh_compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            request_recording: None,
+            rate_limiter: TenantRateLimiter::new(),
+            tenant: DEFAULT_TENANT.to_string(),
+            post_process: Vec::new(),
+            change_subscriptions: std::sync::RwLock::new(ChangeSubscriptions::new()),
+            seen_changes: std::sync::Mutex::new(SeenEvents::default()),
+            shutdown: CancellationToken::new(),
+            in_flight_calls: watch::Sender::new(0),
+            guest_calls: std::sync::Mutex::new(HashMap::new()),
+            next_guest_call: AtomicU64::new(1),
+            max_guest_calls: DEFAULT_MAX_GUEST_CALLS,
+            guest_kv_ready: tokio::sync::OnceCell::new(),
+            recording_ready: tokio::sync::OnceCell::new(),
         })
     }
 
@@ -60,78 +444,728 @@ impl BrioHostState {
         Self::new(db_url, registry).await
     }
 
+    /// Replaces the default broadcaster, e.g. with one built from settings.
+    pub fn with_broadcaster(mut self, broadcaster: Broadcaster) -> Self {
+        self.broadcaster = broadcaster;
+        self
+    }
+
+    /// Resyncs WebSocket clients that lag with a snapshot of these scopes'
+    /// key-value entries, rather than only signalling the loss.
+    pub fn with_store_snapshots(mut self, scopes: &[String]) -> Self {
+        let stores = scopes
+            .iter()
+            .map(|scope| TypedStore::new(self.get_store(scope), scope.clone()))
+            .collect();
+        self.broadcaster = self
+            .broadcaster
+            .with_snapshot_source(Arc::new(StoreSnapshot::new(stores)));
+        self
+    }
+
+    /// Sets how completions are dispatched across the registered providers.
+    ///
+    /// Fails if the strategy names a provider that is not registered.
+    pub fn with_selection_strategy(mut self, strategy: SelectionStrategy) -> Result<Self> {
+        let providers = self.providers.get_mut().expect("RwLock poisoned");
+        providers.selector = Arc::new(ProviderSelector::new(strategy, &providers.registry)?);
+        Ok(self)
+    }
+
+    /// Sets how many retries a single request may spend across all layers.
+    pub fn with_retry_budget(mut self, retry_budget: u32) -> Self {
+        self.retry_budget = retry_budget;
+        self
+    }
+
+    /// Sets how many times store writes are retried while the database is locked.
+    pub fn with_busy_retries(mut self, busy_retries: u32) -> Self {
+        self.busy_retries = busy_retries;
+        self
+    }
+
+    /// Sets whether store queries are denied or allowed when the policy fails to evaluate.
+    pub fn with_policy_failure_mode(mut self, mode: PolicyFailureMode) -> Self {
+        self.policy_failure_mode = mode;
+        self
+    }
+
+    /// Sets the largest values key-value writes accept, per scope.
+    pub fn with_value_size_limits(mut self, limits: ValueSizeLimits) -> Self {
+        self.value_size_limits = limits;
+        self
+    }
+
+    /// Sets how long a remote node must miss heartbeats before it is removed from routing.
+    /// Has no effect in standalone mode.
+    pub fn with_dead_node_grace_period(mut self, grace_period: Duration) -> Self {
+        self.remote_router = self
+            .remote_router
+            .map(|r| r.with_grace_period(grace_period));
+        self
+    }
+
+    /// Sets how long a target node has to confirm a
+    /// [`transfer_session`](Self::transfer_session). Has no effect in standalone mode.
+    pub fn with_session_transfer_timeout(mut self, timeout: Duration) -> Self {
+        self.remote_router = self.remote_router.map(|r| r.with_transfer_timeout(timeout));
+        self
+    }
+
+    /// Sets how long a node may go unheard of, directly or through gossip,
+    /// before it is pruned. Has no effect in standalone mode.
+    pub fn with_gossip_ttl(mut self, ttl: Duration) -> Self {
+        self.remote_router = self.remote_router.map(|r| r.with_node_ttl(ttl));
+        self
+    }
+
+    /// Sets the address gossiped to other nodes for reaching this one,
+    /// instead of the mesh server's bound address. Has no effect in standalone mode.
+    pub fn with_mesh_advertise_address(self, address: NodeAddress) -> Self {
+        if let Some(router) = &self.remote_router {
+            router.set_advertise_address(address);
+        }
+        self
+    }
+
+    /// Serves mesh calls over TLS and makes them to other nodes over TLS.
+    /// Has no effect in standalone mode.
+    pub fn with_mesh_tls(mut self, tls: MeshTls) -> Self {
+        self.remote_router = self.remote_router.map(|r| r.with_tls(tls.client_config()));
+        self.mesh_tls = Some(tls);
+        self
+    }
+
+    /// Fails calls to a target fast with [`CircuitOpen`](crate::mesh::CircuitOpen) for `cool_down` once
+    /// `failure_threshold` calls to it in a row failed to reach it or timed out;
+    /// see [`CircuitBreakers`].
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cool_down: Duration) -> Self {
+        self.circuit_breakers = Some(Arc::new(CircuitBreakers::new(failure_threshold, cool_down)));
+        self
+    }
+
+    /// Where the circuit breaker for `target` stands; always closed without one.
+    pub fn circuit_state(&self, target: &str) -> CircuitState {
+        self.circuit_breakers
+            .as_ref()
+            .map_or(CircuitState::Closed, |breakers| breakers.state(target))
+    }
+
+    /// Sets how many calls a [`mesh_broadcast`](Self::mesh_broadcast) runs concurrently.
+    pub fn with_mesh_broadcast_concurrency(mut self, concurrency: usize) -> Self {
+        self.mesh_broadcast_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets how many mesh calls guests may have started and not yet
+    /// collected; starting another fails until one is.
+    pub fn with_max_guest_calls(mut self, max: usize) -> Self {
+        self.max_guest_calls = max;
+        self
+    }
+
+    /// Sets how long a [`mesh_call`](Self::mesh_call) waits before giving up.
+    pub fn with_mesh_call_timeout(mut self, timeout: Duration) -> Self {
+        self.mesh_call_timeout = timeout;
+        self
+    }
+
+    /// Sets the payload size in bytes from which mesh calls and their
+    /// replies are compressed on the wire.
+    pub fn with_mesh_compression_threshold(mut self, threshold: usize) -> Self {
+        self.mesh_compression_threshold = threshold;
+        self.remote_router = self
+            .remote_router
+            .map(|r| r.with_compression_threshold(threshold));
+        self
+    }
+
+    pub(crate) fn mesh_compression_threshold(&self) -> usize {
+        self.mesh_compression_threshold
+    }
+
+    /// Rolls back sessions left unused for `timeout`, checking every `scan_interval`.
+    ///
+    /// The reaper runs on the current Tokio runtime until this host is dropped.
+    pub fn with_session_idle_timeout(self, timeout: Duration, scan_interval: Duration) -> Self {
+        self.session_manager
+            .lock()
+            .expect("Mutex poisoned")
+            .set_idle_timeout(Some(timeout));
+
+        let manager = Arc::downgrade(&self.session_manager);
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(scan_interval.max(Duration::from_millis(1)));
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = shutdown.cancelled() => return,
+                }
+                let Some(manager) = manager.upgrade() else {
+                    return;
+                };
+                // Working copies are walked and removed without holding the
+                // manager, so a slow disk doesn't stall other session calls
+                let idle = manager.lock().expect("Mutex poisoned").idle_sessions();
+                if idle.is_empty() {
+                    continue;
+                }
+                let checked = tokio::task::spawn_blocking(move || {
+                    idle.into_iter()
+                        .map(|session| {
+                            let last_edit = session.last_edit();
+                            (session, last_edit)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await
+                .expect("idle session check panicked");
+                let expired: Vec<ExpiredSession> = {
+                    let mut manager = manager.lock().expect("Mutex poisoned");
+                    checked
+                        .into_iter()
+                        .filter_map(|(session, last_edit)| manager.expire_idle(session, last_edit))
+                        .collect()
+                };
+                if !expired.is_empty() {
+                    let removed = tokio::task::spawn_blocking(move || {
+                        expired.into_iter().for_each(|session| {
+                            session.remove();
+                        })
+                    })
+                    .await;
+                    if let Err(e) = removed {
+                        error!("Failed to roll back idle sessions: {}", e);
+                    }
+                }
+            }
+        });
+        self
+    }
+
+    /// Cancelled once [`shutdown`](Self::shutdown) starts, so background tasks
+    /// and the control plane can stop with the host.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Starts shutting down: cancels the [`shutdown_token`](Self::shutdown_token),
+    /// refuses new mesh calls with [`ShuttingDown`], and waits up to
+    /// `drain_timeout` for the calls already running to finish.
+    ///
+    /// Returns true if they all finished in time. Broadcaster and sessions
+    /// are left to the caller to drain.
+    pub async fn shutdown(&self, drain_timeout: Duration) -> bool {
+        self.shutdown.cancel();
+        let mut in_flight = self.in_flight_calls.subscribe();
+        let drained = matches!(
+            tokio::time::timeout(drain_timeout, in_flight.wait_for(|n| *n == 0)).await,
+            Ok(Ok(_))
+        );
+        if drained {
+            info!("In-flight mesh calls drained");
+        } else {
+            warn!(
+                in_flight = *in_flight.borrow(),
+                drain_timeout_ms = drain_timeout.as_millis() as u64,
+                "Shutting down with mesh calls still running"
+            );
+        }
+        drained
+    }
+
+    /// Records every completion request so it can be [replayed](Self::replay),
+    /// with each of `redactions` (e.g. API keys) blanked out of the recording.
+    pub fn with_request_recording(mut self, redactions: Vec<String>) -> Self {
+        self.request_recording = Some(redactions);
+        self
+    }
+
+    /// Rate limits guests running on this host as `tenant`; see [`chat_as`](Self::chat_as).
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = tenant.into();
+        self
+    }
+
+    /// Tenant that guests running on this host are rate limited as.
+    pub fn tenant(&self) -> &str {
+        &self.tenant
+    }
+
+    /// Sets the completion cleanup applied to requests that don't list their own.
+    pub fn with_post_processing(mut self, transforms: Vec<Transform>) -> Self {
+        self.post_process = transforms;
+        self
+    }
+
+    /// Creates the context for a new request handled by this host.
+    pub fn request_context(&self) -> RequestContext {
+        RequestContext::new(self.retry_budget)
+    }
+
     pub fn register_component(&self, id: String, sender: Sender<MeshMessage>) {
         let mut router = self.mesh_router.write().expect("RwLock poisoned");
         router.insert(id, sender);
     }
 
+    /// Registers `id` with its calls dispatched by method through `methods`.
+    ///
+    /// Must be called within a Tokio runtime, which runs the dispatch loop
+    /// until the component is deregistered.
+    pub fn register_methods(&self, id: String, methods: MethodRouter) {
+        let (tx, rx) = tokio::sync::mpsc::channel(COMPONENT_CHANNEL_CAPACITY);
+        tokio::spawn(methods.serve(rx));
+        self.register_component(id, tx);
+    }
+
+    /// Removes a component from the local router, returning whether it was registered.
+    ///
+    /// Components whose channel has closed are also removed by the first
+    /// [`mesh_call`](Self::mesh_call) that finds them gone.
+    pub fn deregister_component(&self, id: &str) -> bool {
+        let mut router = self.mesh_router.write().expect("RwLock poisoned");
+        router.remove(id).is_some()
+    }
+
+    /// Lists the ids of locally registered components, sorted.
+    pub fn registered_components(&self) -> Vec<String> {
+        component_ids(&self.mesh_router)
+    }
+
     pub fn register_remote_node(&self, info: NodeInfo) {
         if let Some(router) = &self.remote_router {
             router.register_node(info);
         }
     }
 
-    pub fn db(&self) -> &SqlitePool {
+    /// Heartbeats all known remote nodes, updating which of them are routable.
+    pub async fn heartbeat_remote_nodes(&self) {
+        if let Some(router) = &self.remote_router {
+            router.heartbeat_all().await;
+            self.forget_removed_nodes(router);
+        }
+    }
+
+    /// Drops the change subscriptions of nodes the router has evicted or pruned.
+    fn forget_removed_nodes(&self, router: &RemoteRouter) {
+        let removed = router.take_removed_nodes();
+        if removed.is_empty() {
+            return;
+        }
+        let mut subscriptions = self.change_subscriptions.write().expect("RwLock poisoned");
+        for node_id in &removed {
+            subscriptions.unsubscribe_node(node_id);
+        }
+    }
+
+    /// Swaps known nodes with a random peer; see [`RemoteRouter::gossip_round`].
+    /// Returns how many nodes were newly learned; always 0 in standalone mode.
+    pub async fn gossip(&self) -> Result<usize> {
+        match &self.remote_router {
+            Some(router) => {
+                let learned = router.gossip_round().await;
+                self.forget_removed_nodes(router);
+                learned
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Merges nodes a peer gossiped and returns the ones known here, for the reply.
+    pub(crate) fn exchange_gossip(&self, nodes: Vec<NodeInfo>) -> Vec<NodeInfo> {
+        let Some(router) = &self.remote_router else {
+            return Vec::new();
+        };
+        router.merge_gossip(nodes);
+        router.gossip_snapshot()
+    }
+
+    /// Remote nodes that answered a heartbeat within the last `max_age`, sorted;
+    /// always empty in standalone mode.
+    pub fn live_nodes(&self, max_age: Duration) -> Vec<NodeId> {
+        self.remote_router
+            .as_ref()
+            .map(|r| r.live_nodes(max_age))
+            .unwrap_or_default()
+    }
+
+    /// Heartbeats every remote node and health-checks every provider now,
+    /// rather than waiting for the periodic sweep.
+    ///
+    /// Node results also feed the failure detector, as a scheduled
+    /// heartbeat would.
+    pub async fn health_sweep(&self) -> HealthReport {
+        let nodes = async {
+            let Some(router) = &self.remote_router else {
+                return Vec::new();
+            };
+            let nodes = join_all(router.node_ids().into_iter().map(|id| async move {
+                let started = Instant::now();
+                let result = router.heartbeat(&id).await.map_err(|e| e.to_string());
+                ComponentHealth::new(id.to_string(), result, started.elapsed())
+            }))
+            .await;
+            self.forget_removed_nodes(router);
+            nodes
+        };
+
+        let registry = self.registry();
+        let mut names = registry.list_providers();
+        names.sort();
+        let providers = join_all(names.into_iter().filter_map(|name| {
+            let provider = registry.get(&name)?;
+            Some(async move {
+                let started = Instant::now();
+                let result = provider.health_check().await.map_err(|e| e.to_string());
+                ComponentHealth::new(name, result, started.elapsed())
+            })
+        }));
+
+        let (nodes, providers) = tokio::join!(nodes, providers);
+        HealthReport::new(nodes, providers)
+    }
+
+    /// Checks whether the kernel can serve requests: the database answers
+    /// `SELECT 1` within [`READINESS_DB_TIMEOUT`] and a default provider is registered.
+    pub async fn readiness(&self) -> ReadinessReport {
+        let started = Instant::now();
+        let database = match tokio::time::timeout(READINESS_DB_TIMEOUT, self.db_pool.ping()).await {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err(format!(
+                "Database did not answer within {:?}",
+                READINESS_DB_TIMEOUT
+            )),
+        };
+        let database = ComponentHealth::new(DATABASE_CHECK, database, started.elapsed());
+
+        let started = Instant::now();
+        let provider = match self.registry().get_default() {
+            Some(_) => Ok(()),
+            None => Err("No default provider registered".to_string()),
+        };
+        let provider = ComponentHealth::new(DEFAULT_PROVIDER_CHECK, provider, started.elapsed());
+
+        ReadinessReport::new(vec![database, provider])
+    }
+
+    /// Returns whether calls to `node_id` are currently routed; always false in standalone mode.
+    pub fn is_node_routable(&self, node_id: &NodeId) -> bool {
+        self.remote_router
+            .as_ref()
+            .is_some_and(|r| r.is_routable(node_id))
+    }
+
+    /// Starts serving this node on the mesh at `addr`, completing distributed startup.
+    ///
+    /// Startup either finishes with a bound server or fails cleanly: if the
+    /// address can't be bound, the host is torn down (its database pool is
+    /// closed) so no half-started node is left running unreachable, and it
+    /// must not be used further. Fails in standalone mode.
+    pub async fn start_mesh_server(self: &Arc<Self>, addr: SocketAddr) -> Result<MeshServer> {
+        let Some(router) = &self.remote_router else {
+            return Err(anyhow!("Cannot start a mesh server in standalone mode"));
+        };
+
+        let tls = self.mesh_tls.as_ref().map(MeshTls::server_config);
+        match MeshServer::bind(self.clone(), router.local_node_id().clone(), addr, tls).await {
+            Ok(server) => {
+                info!("Mesh gRPC server listening on {}", server.local_addr());
+                // A wildcard bind isn't an address peers can dial
+                if router.advertise_address().is_none()
+                    && !server.local_addr().ip().is_unspecified()
+                {
+                    router.set_advertise_address(NodeAddress(server.local_addr().to_string()));
+                }
+                Ok(server)
+            }
+            Err(e) => {
+                self.db_pool.close().await;
+                Err(anyhow!("Failed to bind mesh server to {}: {}", addr, e))
+            }
+        }
+    }
+
+    pub fn db(&self) -> &DbPool {
         &self.db_pool
     }
 
     pub fn get_store(&self, _scope: &str) -> SqlStore {
         SqlStore::new(self.db_pool.clone(), Box::new(PrefixPolicy))
+            .with_busy_retries(self.busy_retries)
+            .with_policy_failure_mode(self.policy_failure_mode)
+            .with_value_size_limits(self.value_size_limits.clone())
+            .with_watchers(self.key_watchers.clone())
+    }
+
+    /// Creates the key-value table of WASM guests the first time it's needed.
+    pub(crate) async fn ensure_guest_kv(&self, kv: &TypedStore<String>) -> Result<(), StoreError> {
+        self.guest_kv_ready
+            .get_or_try_init(|| kv.ensure_table())
+            .await?;
+        Ok(())
+    }
+
+    /// Returns a store isolated in `tenant`'s namespace; see [`SqlStore::with_tenant`].
+    pub fn get_tenant_store(&self, scope: &str, tenant: &str) -> Result<SqlStore> {
+        Ok(self.get_store(scope).with_tenant(tenant)?)
+    }
+
+    /// Tries to take the mesh-wide lock `name` on behalf of this node.
+    ///
+    /// Returns `None` while it is held, by another node or another task on
+    /// this one; see [`LockManager`] for renewal and expiry.
+    pub async fn acquire_lock(&self, name: &str, ttl: Duration) -> Result<Option<LockGuard>> {
+        let owner = self
+            .remote_router
+            .as_ref()
+            .map_or_else(|| "local".to_string(), |r| r.local_node_id().to_string());
+        let manager = LockManager::new(self.get_store(LOCK_SCOPE), owner);
+        manager.ensure_table().await?;
+        Ok(manager.acquire_lock(name, ttl).await?)
     }
 
     pub fn broadcaster(&self) -> &Broadcaster {
         &self.broadcaster
     }
 
+    /// Broadcasts a patch; one tagged with [`WsPatch::with_topic`] reaches only
+    /// receivers subscribed to that topic. Malformed patches are rejected.
     pub fn broadcast_patch(&self, patch: WsPatch) -> Result<()> {
+        patch
+            .validate()
+            .map_err(|e| anyhow!("Broadcast failed: {}", e))?;
         self.broadcaster
             .broadcast(BroadcastMessage::Patch(patch))
             .map_err(|e| anyhow!("Broadcast failed: {}", e))
     }
 
+    /// Broadcasts a patch on a named topic (retained if the broadcaster is configured to).
+    pub fn publish_patch(&self, topic: &str, patch: WsPatch) -> Result<()> {
+        patch
+            .validate()
+            .map_err(|e| anyhow!("Broadcast failed: {}", e))?;
+        self.broadcaster
+            .publish(topic, patch)
+            .map_err(|e| anyhow!("Broadcast failed: {}", e))
+    }
+
+    /// Sends `method` to `target`, locally or across the mesh, and waits for its reply.
+    ///
+    /// Gives up after the host's default timeout; see [`mesh_call_timeout`](Self::mesh_call_timeout).
     pub async fn mesh_call(&self, target: &str, method: &str, payload: Payload) -> Result<Payload> {
-        // 1. Try local routing first
-        let sender = {
-            let router = self.mesh_router.read().expect("RwLock poisoned");
-            router.get(target).cloned()
-        };
+        self.mesh_call_timeout(target, method, payload, self.mesh_call_timeout)
+            .await
+    }
 
-        if let Some(sender) = sender {
-            let (reply_tx, reply_rx) = oneshot::channel();
-            let message = MeshMessage {
-                target: target.to_string(),
-                method: method.to_string(),
-                payload,
-                reply_tx,
-            };
+    /// Like [`mesh_call`](Self::mesh_call), failing with [`MeshCallTimeout`]
+    /// if the reply hasn't arrived within `timeout`.
+    ///
+    /// The timeout covers queueing the message as well as waiting for the
+    /// reply; a message still waiting for queue space is dropped undelivered.
+    /// Each call runs in a `mesh_call` span recording the target, method,
+    /// payload size and outcome, plus the error if the call failed.
+    ///
+    /// The call carries the span's trace context to its target, so spans the
+    /// target records, on this node or another, join the caller's trace.
+    pub async fn mesh_call_timeout(
+        &self,
+        target: &str,
+        method: &str,
+        payload: Payload,
+        timeout: Duration,
+    ) -> Result<Payload> {
+        self.traced_mesh_call(target, method, payload, timeout, None)
+            .await
+    }
 
-            sender
-                .send(message)
-                .await
-                .map_err(|e| anyhow!("Failed to send message to target '{}': {}", target, e))?;
-            let response = reply_rx
-                .await
-                .map_err(|e| anyhow!("Failed to receive reply from target '{}': {}", target, e))?;
-            return response.map_err(|e| anyhow!("Target '{}' returned error: {}", target, e));
+    /// Runs a call received from another node, continuing the caller's trace.
+    ///
+    /// Like every mesh call, it runs as a request of its own unless it was
+    /// made inside one.
+    pub(crate) async fn mesh_call_from_remote(
+        &self,
+        target: &str,
+        method: &str,
+        payload: Payload,
+        trace_context: Option<String>,
+    ) -> Result<Payload> {
+        self.traced_mesh_call(
+            target,
+            method,
+            payload,
+            self.mesh_call_timeout,
+            trace_context,
+        )
+        .await
+    }
+
+    async fn traced_mesh_call(
+        &self,
+        target: &str,
+        method: &str,
+        payload: Payload,
+        timeout: Duration,
+        remote_parent: Option<String>,
+    ) -> Result<Payload> {
+        let caller = self.mesh_caller();
+        let call = caller.call(target, method, payload, timeout, remote_parent);
+        self.request_context().inherit_or(call).await
+    }
+
+    fn mesh_caller(&self) -> MeshCaller {
+        MeshCaller {
+            mesh_router: Arc::clone(&self.mesh_router),
+            circuit_breakers: self.circuit_breakers.clone(),
+            remote_router: self.remote_router.clone(),
+            in_flight_calls: self.in_flight_calls.clone(),
+            shutdown: self.shutdown.clone(),
         }
+    }
 
-        // 2. Try remote routing if enabled and target is formatted as "node_id/component"
-        // Explicit remote addressing: "node_id/component_id"
-        if let (Some(router), Some((node_id_str, component))) = (&self.remote_router, target.split_once('/')) {
-            let node_id = NodeId::from(node_id_str.to_string());
-            
-            // If the target is a different node, route via gRPC
-            let message = MeshMessage {
-                target: component.to_string(),
-                method: method.to_string(),
-                payload,
-                reply_tx: oneshot::channel().0, // Reply handling is managed by RemoteRouter's request/response flow
-            };
-            
-            return router.send(&node_id, message).await;
+    /// Starts a [`mesh_call`](Self::mesh_call) on the current Tokio runtime
+    /// without waiting for it, so the call can outlive the borrow of this host.
+    ///
+    /// The call runs in the current span and request, and counts towards the
+    /// calls a [`shutdown`](Self::shutdown) drains.
+    pub fn spawn_mesh_call(
+        &self,
+        target: String,
+        method: String,
+        payload: Payload,
+    ) -> JoinHandle<Result<Payload>> {
+        let caller = self.mesh_caller();
+        let timeout = self.mesh_call_timeout;
+        // Task-locals don't cross `spawn`, so the request is carried over by hand
+        let context = RequestContext::current().unwrap_or_else(|| self.request_context());
+        tokio::spawn(
+            context
+                .scope(async move { caller.call(&target, &method, payload, timeout, None).await })
+                .instrument(tracing::Span::current()),
+        )
+    }
+
+    /// Starts a guest's mesh call, returning the handle the guest polls or
+    /// awaits it by.
+    ///
+    /// A call's handle is released once its result has been taken; calls
+    /// never collected are abandoned along with this host. Fails without
+    /// starting the call while [`max_guest_calls`](Self::with_max_guest_calls)
+    /// handles are outstanding.
+    pub(crate) fn start_guest_call(
+        &self,
+        target: String,
+        method: String,
+        payload: Payload,
+    ) -> Result<u64> {
+        let mut calls = self.guest_calls.lock().expect("Mutex poisoned");
+        if calls.len() >= self.max_guest_calls {
+            return Err(anyhow!(
+                "Too many outstanding mesh calls: {} started and not yet collected",
+                calls.len()
+            ));
+        }
+        let handle = self.next_guest_call.fetch_add(1, Ordering::Relaxed);
+        calls.insert(handle, self.spawn_mesh_call(target, method, payload));
+        Ok(handle)
+    }
+
+    /// The result of the guest call `handle` if it has finished, releasing
+    /// the handle; `None` while it's still running.
+    pub(crate) fn poll_guest_call(&self, handle: u64) -> Option<Result<Payload>> {
+        let mut calls = self.guest_calls.lock().expect("Mutex poisoned");
+        let Some(call) = calls.get_mut(&handle) else {
+            return Some(Err(anyhow!("Unknown mesh call handle {}", handle)));
+        };
+        if !call.is_finished() {
+            return None;
+        }
+        let call = calls.remove(&handle).expect("handle was just found");
+        drop(calls);
+        Some(join_guest_call(
+            call.now_or_never().expect("finished task is ready"),
+        ))
+    }
+
+    /// Waits for the guest call `handle` to finish, releasing the handle.
+    pub(crate) async fn await_guest_call(&self, handle: u64) -> Result<Payload> {
+        let call = self
+            .guest_calls
+            .lock()
+            .expect("Mutex poisoned")
+            .remove(&handle);
+        match call {
+            Some(call) => join_guest_call(call.await),
+            None => Err(anyhow!("Unknown mesh call handle {}", handle)),
         }
+    }
+
+    /// Calls `method` on every target, a bounded number at a time, returning
+    /// results in completion order.
+    ///
+    /// Cancelling `cancel` stops dispatching to the remaining targets and drops
+    /// replies still pending; the results collected so far are returned.
+    pub async fn mesh_broadcast(
+        &self,
+        targets: &[String],
+        method: &str,
+        payload: Payload,
+        cancel: &CancellationToken,
+    ) -> Vec<(String, Result<Payload>)> {
+        let mut remaining = targets.iter();
+        let mut in_flight = FuturesUnordered::new();
+        let mut results = Vec::with_capacity(targets.len());
+
+        loop {
+            while in_flight.len() < self.mesh_broadcast_concurrency && !cancel.is_cancelled() {
+                let Some(target) = remaining.next() else {
+                    break;
+                };
+                let payload = payload.clone();
+                in_flight.push(async move {
+                    (
+                        target.clone(),
+                        self.mesh_call(target, method, payload).await,
+                    )
+                });
+            }
+            if in_flight.is_empty() {
+                break;
+            }
+
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    debug!(
+                        completed = results.len(),
+                        abandoned = in_flight.len(),
+                        "Mesh broadcast cancelled"
+                    );
+                    break;
+                }
+                Some(result) = in_flight.next() => results.push(result),
+            }
+        }
+
+        results
+    }
 
-        Err(anyhow!("Target component '{}' not found. Ensure format is 'component' (local) or 'node_id/component' (remote).", target))
+    /// Calls `method` on every component registered on this node, returning
+    /// results sorted by component.
+    ///
+    /// Runs as a [`mesh_broadcast`](Self::mesh_broadcast) over the components
+    /// registered when it starts, so it shares the same bound on concurrency.
+    pub async fn mesh_broadcast_all(
+        &self,
+        method: &str,
+        payload: Payload,
+    ) -> Vec<(String, Result<Payload>)> {
+        let targets = self.registered_components();
+        let mut results = self
+            .mesh_broadcast(&targets, method, payload, &CancellationToken::new())
+            .await;
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results
     }
 
     pub fn begin_session(&self, base_path: String) -> Result<String, String> {
@@ -139,24 +1173,647 @@ impl BrioHostState {
         manager.begin_session(base_path)
     }
 
-    pub fn commit_session(&self, session_id: String) -> Result<(), String> {
+    pub fn commit_session(&self, session_id: String) -> Result<CommitSummary, String> {
         let mut manager = self.session_manager.lock().expect("Mutex poisoned");
         manager.commit_session(session_id)
     }
 
+    /// Abandons a session, discarding its changes without touching the base directory.
+    pub fn rollback_session(&self, session_id: String) -> Result<(), String> {
+        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager.rollback_session(session_id)
+    }
+
+    /// Describes every active session, e.g. to spot sessions left open.
+    ///
+    /// Working copies are diffed on the blocking pool, without holding the
+    /// session manager.
+    pub async fn list_sessions(&self) -> Vec<SessionInfo> {
+        let sessions = self
+            .session_manager
+            .lock()
+            .expect("Mutex poisoned")
+            .active_sessions();
+        tokio::task::spawn_blocking(move || describe_sessions(sessions))
+            .await
+            .expect("session listing panicked")
+    }
+
+    /// Returns the working directory of an active session.
+    ///
+    /// Counts as activity on the session, postponing its idle timeout.
+    pub fn session_path(&self, session_id: &str) -> Option<std::path::PathBuf> {
+        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager.touch_session(session_id);
+        manager.get_session_path(session_id)
+    }
+
+    /// Takes ownership of a session snapshot handed over by another node.
+    pub fn attach_session(&self, snapshot: SessionSnapshot) -> Result<(), String> {
+        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager.attach_session(snapshot)
+    }
+
+    /// Moves a session to another node in the mesh.
+    ///
+    /// The session is detached locally before sending so the two nodes never
+    /// both own it. If the target rejects it or can't be reached, the session
+    /// is re-attached here and the error returned. If the target may have
+    /// taken it but didn't confirm, e.g. the transfer timed out, the session
+    /// stays detached and the error is a [`SessionTransferUnconfirmed`]
+    /// holding the snapshot, to re-attach once the target is known not to
+    /// have it.
+    pub async fn transfer_session(&self, session_id: &str, target_node: &NodeId) -> Result<()> {
+        let router = self
+            .remote_router
+            .as_ref()
+            .ok_or_else(|| anyhow!("Session transfer requires distributed mode"))?;
+
+        let snapshot = {
+            let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+            manager.detach_session(session_id).map_err(|e| anyhow!(e))?
+        };
+
+        let Err(e) = router.transfer_session(target_node, snapshot.clone()).await else {
+            return Ok(());
+        };
+        if e.downcast_ref::<SessionTransferRejected>().is_none() && !is_transport_failure(&e) {
+            warn!(
+                session = session_id,
+                node = %target_node,
+                error = %e,
+                "Session transfer unconfirmed, leaving the session detached"
+            );
+            return Err(SessionTransferUnconfirmed {
+                node: target_node.to_string(),
+                reason: e.to_string(),
+                snapshot,
+            }
+            .into());
+        }
+
+        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager.attach_session(snapshot).map_err(|restore_err| {
+            anyhow!(
+                "Transfer failed ({}) and local restore failed: {}",
+                e,
+                restore_err
+            )
+        })?;
+        Err(e)
+    }
+
+    /// Asks `node_id` to forward its changes to `scope` keys under `prefix`,
+    /// which then reach this node's WebSocket clients as patches.
+    pub async fn subscribe_remote_changes(
+        &self,
+        node_id: &NodeId,
+        scope: &str,
+        prefix: &str,
+    ) -> Result<()> {
+        let router = self
+            .remote_router
+            .as_ref()
+            .ok_or_else(|| anyhow!("Change subscriptions require distributed mode"))?;
+        router.subscribe_changes(node_id, scope, prefix).await
+    }
+
+    /// Forwards this node's future changes to `scope` keys under `prefix` to `node_id`.
+    pub fn add_change_subscriber(&self, node_id: NodeId, scope: String, prefix: String) {
+        self.change_subscriptions
+            .write()
+            .expect("RwLock poisoned")
+            .subscribe(node_id, scope, prefix);
+    }
+
+    /// Broadcasts a change made on another node to local clients.
+    ///
+    /// Remote changes are never forwarded again, so propagation can't loop.
+    /// Returns `false` for changes that came from this node or were already applied.
+    pub fn apply_remote_change(
+        &self,
+        event_id: &str,
+        origin: &NodeId,
+        change: &StoreChange,
+    ) -> Result<bool> {
+        if self
+            .remote_router
+            .as_ref()
+            .is_some_and(|r| r.local_node_id() == origin)
+        {
+            return Ok(false);
+        }
+        if !self
+            .seen_changes
+            .lock()
+            .expect("Mutex poisoned")
+            .insert(event_id)
+        {
+            debug!(event_id, "Dropped duplicate store change");
+            return Ok(false);
+        }
+        self.broadcast_patch(change.to_patch())?;
+        Ok(true)
+    }
+
+    /// Snapshots all active sessions into `dir` so they can be restored after a restart.
+    pub fn persist_sessions(&self, dir: &std::path::Path) -> Result<usize, String> {
+        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager.persist_sessions(dir)
+    }
+
+    /// Restores sessions previously written by [`Self::persist_sessions`].
+    pub fn restore_sessions(&self, dir: &std::path::Path) -> Result<Vec<String>, String> {
+        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager.restore_sessions(dir)
+    }
+
+    /// Rolls back every active session, discarding uncommitted changes.
+    pub fn abort_sessions(&self) -> Result<usize, String> {
+        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager.abort_all_sessions()
+    }
+
+    fn providers(&self) -> ProviderSet {
+        self.providers.read().expect("RwLock poisoned").clone()
+    }
+
     /// Returns the provider registry for multi-model access.
     pub fn registry(&self) -> Arc<ProviderRegistry> {
-        self.provider_registry.clone()
+        self.providers().registry
     }
 
     /// Returns a specific LLM provider by name.
     pub fn inference_by_name(&self, name: &str) -> Option<Arc<dyn LLMProvider>> {
-        self.provider_registry.get(name)
+        self.providers().registry.get(name)
+    }
+
+    /// Swaps in a registry rebuilt from configuration, e.g. on SIGHUP.
+    ///
+    /// `strategy` is validated against the new registry first; on error the
+    /// current providers stay in place. Requests already in flight finish
+    /// on the providers they started with, which are dropped once the last
+    /// of them completes. Each added, removed or replaced provider is
+    /// recorded as a [`AuditEvent::ConfigChanged`].
+    pub fn reload_providers(
+        &self,
+        registry: ProviderRegistry,
+        strategy: SelectionStrategy,
+    ) -> Result<(), InferenceError> {
+        let next = ProviderSet::new(registry, strategy)?;
+        let previous = {
+            let mut providers = self.providers.write().expect("RwLock poisoned");
+            std::mem::replace(&mut *providers, next.clone())
+        };
+
+        let mut old_names = previous.registry.list_providers();
+        let mut new_names = next.registry.list_providers();
+        old_names.sort();
+        new_names.sort();
+        for name in &new_names {
+            let old_val = if old_names.contains(name) {
+                "registered"
+            } else {
+                "absent"
+            };
+            let new_val = if old_val == "absent" {
+                "registered"
+            } else {
+                "reloaded"
+            };
+            log_audit(AuditEvent::ConfigChanged {
+                key: format!("inference.providers.{}", name),
+                old_val: old_val.to_string(),
+                new_val: new_val.to_string(),
+            });
+        }
+        for name in old_names.iter().filter(|name| !new_names.contains(name)) {
+            log_audit(AuditEvent::ConfigChanged {
+                key: format!("inference.providers.{}", name),
+                old_val: "registered".to_string(),
+                new_val: "absent".to_string(),
+            });
+        }
+
+        info!(providers = ?new_names, "Reloaded inference providers");
+        Ok(())
+    }
+
+    /// Sends a chat request using the configured selection strategy.
+    ///
+    /// Like every inference call on the host, runs inside the current
+    /// [`RequestContext`], or a new one, so retries at every layer below
+    /// draw on one budget.
+    pub async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        self.chat_with_provider(request, None).await
+    }
+
+    /// Embeds `inputs` with the provider serving embeddings, one vector per input.
+    ///
+    /// Uses the default provider unless one is pinned to
+    /// [`Capability::Embeddings`].
+    pub async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        let registry = self.registry();
+        self.request_context()
+            .inherit_or(registry.embed_default(inputs))
+            .await
+    }
+
+    /// Sends a chat request on behalf of `tenant`, enforcing its rate limits.
+    ///
+    /// Returns [`InferenceError::RateLimited`] without calling a provider when
+    /// the tenant is over its limit; other tenants are unaffected.
+    pub async fn chat_as(
+        &self,
+        tenant: &str,
+        request: ChatRequest,
+    ) -> Result<ChatResponse, InferenceError> {
+        self.rate_limiter.acquire(tenant, &request)?;
+        let response = self
+            .request_context()
+            .inherit_or(self.chat(request))
+            .await?;
+        if let Some(usage) = &response.usage {
+            self.rate_limiter
+                .record_tokens(tenant, usage.completion_tokens);
+        }
+        Ok(response)
+    }
+
+    /// Per-tenant inference limits, adjustable while running.
+    pub fn rate_limiter(&self) -> &TenantRateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Sends a chat request to `provider` if given, bypassing registry resolution
+    /// (and its history policies) for this call only; otherwise behaves like [`chat`](Self::chat).
+    ///
+    /// The request's post-processing transforms, or the host's when it lists
+    /// none, are applied to the completion text either way.
+    pub async fn chat_with_provider(
+        &self,
+        request: ChatRequest,
+        provider: Option<Arc<dyn LLMProvider>>,
+    ) -> Result<ChatResponse, InferenceError> {
+        self.request_context()
+            .inherit_or(async {
+                if let Some(redactions) = &self.request_recording {
+                    self.record_request(&request, redactions.clone()).await;
+                }
+                self.send_chat(request, provider).await
+            })
+            .await
+    }
+
+    /// Sends a chat request to each provider of the registry's
+    /// [fallback chain](ProviderRegistry::set_fallback_chain) in turn,
+    /// returning the first success.
+    ///
+    /// Runs the chain as a [`SelectionStrategy::Fallback`], whatever strategy
+    /// the host uses for [`chat`](Self::chat): each failure is logged before
+    /// moving on, and if every provider fails the error is
+    /// [`InferenceError::AllProvidersFailed`] listing all of them. A chain
+    /// naming an unregistered provider fails with
+    /// [`InferenceError::ProviderNotFound`] before any is called.
+    pub async fn inference_with_fallback(
+        &self,
+        request: ChatRequest,
+    ) -> Result<ChatResponse, InferenceError> {
+        let registry = self.registry();
+        let chain = registry.fallback_chain();
+        if chain.is_empty() {
+            return Err(InferenceError::ConfigError(
+                "No fallback chain configured".to_string(),
+            ));
+        }
+        let selector = ProviderSelector::new(SelectionStrategy::Fallback(chain), &registry)?;
+        self.request_context()
+            .inherit_or(async {
+                if let Some(redactions) = &self.request_recording {
+                    self.record_request(&request, redactions.clone()).await;
+                }
+
+                let transforms = self.transforms_for(&request);
+                let mut response = selector.chat(&registry, request).await?;
+                response.content = apply_transforms(&transforms, response.content);
+                Ok(response)
+            })
+            .await
+    }
+
+    /// Streams a completion from the chat provider onto the broadcaster as
+    /// `stream_id`, one chunk patch per token (or coalesced batch).
+    ///
+    /// Returns the full completion once the stream ends, with post-processing
+    /// transforms applied; the broadcast chunks are the raw tokens. Selection
+    /// strategies are bypassed, since a stream can't be hedged or retried on
+    /// another provider once tokens have gone out. A provider error ends the
+    /// stream and is returned after the tokens already sent.
+    pub async fn stream_chat(
+        &self,
+        stream_id: &str,
+        request: ChatRequest,
+    ) -> Result<ChatResponse, InferenceError> {
+        self.request_context()
+            .inherit_or(self.send_stream(stream_id, request))
+            .await
+    }
+
+    async fn send_stream(
+        &self,
+        stream_id: &str,
+        request: ChatRequest,
+    ) -> Result<ChatResponse, InferenceError> {
+        let registry = self.registry();
+        let name = registry
+            .name_for_capability(Capability::Chat)
+            .ok_or_else(|| {
+                InferenceError::ProviderNotFound("No default provider configured".to_string())
+            })?;
+        let provider = registry
+            .get(&name)
+            .ok_or_else(|| InferenceError::ProviderNotFound(name.clone()))?;
+        if let Some(redactions) = &self.request_recording {
+            self.record_request(&request, redactions.clone()).await;
+        }
+
+        let transforms = if request.post_process.is_empty() {
+            self.post_process.clone()
+        } else {
+            request.post_process.clone()
+        };
+        let request = registry.apply_history_policy(&name, request);
+
+        let mut content = String::new();
+        let mut failure = None;
+        let tokens = provider.stream_completion(request).scan(
+            (&mut content, &mut failure),
+            |(content, failure), item| {
+                futures_util::future::ready(match item {
+                    Ok(token) => {
+                        content.push_str(&token);
+                        Some(token)
+                    }
+                    Err(e) => {
+                        **failure = Some(e);
+                        None
+                    }
+                })
+            },
+        );
+        self.broadcaster
+            .forward_stream(stream_id, tokens)
+            .await
+            .map_err(|e| {
+                InferenceError::ProviderError(format!("Failed to broadcast stream: {}", e))
+            })?;
+
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        Ok(ChatResponse {
+            content: apply_transforms(&transforms, content),
+            usage: None,
+            tool_calls: Vec::new(),
+        })
+    }
+
+    /// Re-issues the completion request recorded as `request_id`, exactly as
+    /// it was recorded (redactions included). Replays aren't recorded again.
+    ///
+    /// Which provider served the original isn't recorded, so the replay goes
+    /// to whichever one the current selection strategy picks, as [`chat`](Self::chat)
+    /// would; a request first sent through [`chat_with_provider`](Self::chat_with_provider)
+    /// or a stream may get its answer from a different provider.
+    pub async fn replay(&self, request_id: &str) -> Result<ChatResponse, InferenceError> {
+        let recorder = RequestRecorder::new(self.get_store(RECORDING_SCOPE));
+        let loaded = match self.ensure_recording_table(&recorder).await {
+            Ok(()) => recorder.load(request_id).await,
+            Err(e) => Err(e),
+        };
+        match loaded {
+            Ok(Some(request)) => {
+                self.request_context()
+                    .inherit_or(self.send_chat(request, None))
+                    .await
+            }
+            Ok(None) => Err(InferenceError::InvalidRequest(format!(
+                "No recorded request '{}'",
+                request_id
+            ))),
+            Err(e) => Err(InferenceError::ProviderError(format!(
+                "Failed to load recorded request '{}': {}",
+                request_id, e
+            ))),
+        }
+    }
+
+    /// Records `request`; a failed recording is logged and never fails the completion.
+    async fn record_request(&self, request: &ChatRequest, redactions: Vec<String>) {
+        let recorder =
+            RequestRecorder::new(self.get_store(RECORDING_SCOPE)).with_redactions(redactions);
+        let recorded = match self.ensure_recording_table(&recorder).await {
+            Ok(()) => recorder.record(request).await,
+            Err(e) => Err(e),
+        };
+        match recorded {
+            Ok(request_id) => {
+                info!(request_id = %request_id, model = %request.model, "Recorded completion request");
+                if let Some(ctx) = RequestContext::current() {
+                    ctx.add_recorded_request(request_id);
+                }
+            }
+            Err(e) => warn!("Failed to record completion request: {}", e),
+        }
+    }
+
+    /// Creates the table of recorded requests the first time it's needed.
+    async fn ensure_recording_table(&self, recorder: &RequestRecorder) -> Result<(), StoreError> {
+        self.recording_ready
+            .get_or_try_init(|| recorder.ensure_table())
+            .await?;
+        Ok(())
+    }
+
+    /// The request's post-processing transforms, or the host's when it lists none.
+    fn transforms_for(&self, request: &ChatRequest) -> Vec<Transform> {
+        if request.post_process.is_empty() {
+            self.post_process.clone()
+        } else {
+            request.post_process.clone()
+        }
+    }
+
+    async fn send_chat(
+        &self,
+        request: ChatRequest,
+        provider: Option<Arc<dyn LLMProvider>>,
+    ) -> Result<ChatResponse, InferenceError> {
+        let transforms = self.transforms_for(&request);
+        let mut response = match provider {
+            Some(provider) => provider.chat(request).await?,
+            None => {
+                let providers = self.providers();
+                providers
+                    .selector
+                    .chat(&providers.registry, request)
+                    .await?
+            }
+        };
+        response.content = apply_transforms(&transforms, response.content);
+        Ok(response)
+    }
+
+    /// Runs a model/tool cycle until the model answers without calling tools.
+    ///
+    /// Each tool call is dispatched over the mesh to the matching
+    /// [`AgentTool::target`] and its reply, or error, fed back to the model.
+    /// Every model call counts as an iteration; a model still calling tools
+    /// on the last one ends the loop with [`AgentLoopError::IterationLimit`].
+    ///
+    /// The whole loop is one request: its model and tool calls share a
+    /// single retry budget.
+    pub async fn run_agent_loop(
+        &self,
+        request: ChatRequest,
+        tools: &[AgentTool],
+        max_iterations: u32,
+    ) -> Result<AgentOutcome, AgentLoopError> {
+        self.request_context()
+            .inherit_or(self.agent_loop(request, tools, max_iterations))
+            .await
+    }
+
+    async fn agent_loop(
+        &self,
+        mut request: ChatRequest,
+        tools: &[AgentTool],
+        max_iterations: u32,
+    ) -> Result<AgentOutcome, AgentLoopError> {
+        request.tools.extend(tools.iter().map(|t| t.tool.clone()));
+        let mut trace = Vec::new();
+
+        for iteration in 1..=max_iterations {
+            let response = match self.chat(request.clone()).await {
+                Ok(response) => response,
+                Err(source) => return Err(AgentLoopError::Inference { source, trace }),
+            };
+            if response.tool_calls.is_empty() {
+                return Ok(AgentOutcome { response, trace });
+            }
+            if iteration == max_iterations {
+                trace.push(AgentStep {
+                    response,
+                    tool_results: Vec::new(),
+                });
+                break;
+            }
+
+            let mut tool_results = Vec::with_capacity(response.tool_calls.len());
+            for call in &response.tool_calls {
+                let output = match tools.iter().find(|t| t.tool.name == call.name) {
+                    Some(tool) => self
+                        .mesh_call(
+                            &tool.target,
+                            &call.name,
+                            Payload::Json(call.arguments.to_string()),
+                        )
+                        .await
+                        .map(|payload| match payload {
+                            Payload::Json(s) => s,
+                            Payload::Binary(b) => String::from_utf8_lossy(&b).into_owned(),
+                            Payload::MsgPack(b) => rmp_serde::from_slice::<serde_json::Value>(&b)
+                                .map(|value| value.to_string())
+                                .unwrap_or_else(|_| String::from_utf8_lossy(&b).into_owned()),
+                        })
+                        .map_err(|e| e.to_string()),
+                    None => Err(format!("Unknown tool '{}'", call.name)),
+                };
+                tool_results.push(ToolResult {
+                    call: call.clone(),
+                    output,
+                });
+            }
+
+            request.messages.push(Message::assistant_tool_calls(
+                response.content.clone(),
+                response.tool_calls.clone(),
+            ));
+            request.messages.extend(tool_results.iter().map(|result| {
+                let content = match &result.output {
+                    Ok(output) => output.clone(),
+                    Err(error) => serde_json::json!({ "error": error }).to_string(),
+                };
+                Message::tool_result(result.call.id.clone(), content)
+            }));
+            trace.push(AgentStep {
+                response,
+                tool_results,
+            });
+        }
+
+        Err(AgentLoopError::IterationLimit {
+            max_iterations,
+            trace,
+        })
     }
 
     /// Returns the default LLM provider (backward compatible).
     pub fn inference(&self) -> Option<Arc<dyn LLMProvider>> {
-        self.provider_registry.get_default()
+        self.providers().registry.get_default()
     }
 }
 
+#[async_trait]
+impl ChangeSink for BrioHostState {
+    /// Broadcasts the change to local clients, then forwards it to the nodes
+    /// subscribed to its key, all at once and each within
+    /// [`CHANGE_FORWARD_TIMEOUT`], so a slow node holds up the write no longer.
+    async fn publish(&self, change: StoreChange) {
+        if let Err(e) = self.broadcast_patch(change.to_patch()) {
+            warn!("Broadcasting store change failed: {}", e);
+        }
+
+        let Some(router) = &self.remote_router else {
+            return;
+        };
+        let targets = self
+            .change_subscriptions
+            .read()
+            .expect("RwLock poisoned")
+            .interested(&change);
+        if targets.is_empty() {
+            return;
+        }
+
+        let event = StoreChangeEvent {
+            event_id: uuid::Uuid::new_v4().to_string(),
+            origin_node: router.local_node_id().to_string(),
+            deleted: change.value.is_none(),
+            value_json: change.value.map(|v| v.to_string()).unwrap_or_default(),
+            scope: change.scope,
+            key: change.key,
+        };
+        join_all(targets.iter().map(|node_id| {
+            let event = event.clone();
+            async move {
+                match tokio::time::timeout(
+                    CHANGE_FORWARD_TIMEOUT,
+                    router.publish_change(node_id, event),
+                )
+                .await
+                {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => {
+                        warn!("Forwarding store change to node {} failed: {}", node_id, e)
+                    }
+                    Err(_) => warn!(
+                        "Forwarding store change to node {} timed out after {:?}",
+                        node_id, CHANGE_FORWARD_TIMEOUT
+                    ),
+                }
+            }
+        }))
+        .await;
+    }
+}
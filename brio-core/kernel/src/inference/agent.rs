@@ -0,0 +1,68 @@
+use crate::inference::types::{ChatResponse, InferenceError, Tool, ToolCall};
+
+/// A tool the model may call in an agent loop, served by a mesh component.
+///
+/// Calls are sent to `target` with the tool's name as the method and its
+/// arguments as a JSON payload.
+#[derive(Debug, Clone)]
+pub struct AgentTool {
+    pub tool: Tool,
+    pub target: String,
+}
+
+impl AgentTool {
+    pub fn new(tool: Tool, target: impl Into<String>) -> Self {
+        Self {
+            tool,
+            target: target.into(),
+        }
+    }
+}
+
+/// Outcome of one tool call, as fed back to the model.
+#[derive(Debug, Clone)]
+pub struct ToolResult {
+    pub call: ToolCall,
+    /// The component's reply, or why the call failed
+    pub output: Result<String, String>,
+}
+
+/// One model call in an agent loop and the tool calls it led to.
+#[derive(Debug, Clone)]
+pub struct AgentStep {
+    pub response: ChatResponse,
+    pub tool_results: Vec<ToolResult>,
+}
+
+/// The final answer of an agent loop and every step taken to reach it.
+#[derive(Debug, Clone)]
+pub struct AgentOutcome {
+    pub response: ChatResponse,
+    pub trace: Vec<AgentStep>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AgentLoopError {
+    /// The model was still calling tools when the iteration cap was reached
+    #[error("Agent loop gave no final answer within {max_iterations} iterations")]
+    IterationLimit {
+        max_iterations: u32,
+        trace: Vec<AgentStep>,
+    },
+
+    #[error("Agent loop inference failed: {source}")]
+    Inference {
+        #[source]
+        source: InferenceError,
+        trace: Vec<AgentStep>,
+    },
+}
+
+impl AgentLoopError {
+    /// Steps completed before the loop stopped.
+    pub fn trace(&self) -> &[AgentStep] {
+        match self {
+            Self::IterationLimit { trace, .. } | Self::Inference { trace, .. } => trace,
+        }
+    }
+}
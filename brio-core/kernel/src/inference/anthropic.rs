@@ -1,5 +1,9 @@
 use crate::inference::provider::LLMProvider;
-use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, Message, Role, Usage};
+use crate::inference::retry::rand_jitter;
+use crate::infrastructure::context;
+use crate::inference::types::{
+    ChatRequest, ChatResponse, InferenceError, Message, ResponseFormat, Role, ToolCall, Usage,
+};
 use async_trait::async_trait;
 use reqwest::{Client, StatusCode, Url};
 use secrecy::{ExposeSecret, SecretString};
@@ -20,10 +24,12 @@ const DEFAULT_API_VERSION: &str = "2023-06-01";
 // Anthropic API Types
 // =============================================================================
 
+/// `content` is a string for plain text turns, and a list of content blocks
+/// for turns carrying `tool_use` or `tool_result` blocks
 #[derive(Serialize)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    content: serde_json::Value,
 }
 
 #[derive(Serialize)]
@@ -33,11 +39,32 @@ struct AnthropicChatRequest {
     messages: Vec<AnthropicMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+}
+
+#[derive(Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
 }
 
 #[derive(Deserialize)]
-struct AnthropicContent {
-    text: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicContent {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    #[serde(other)]
+    Other,
 }
 
 #[derive(Deserialize)]
@@ -168,16 +195,51 @@ impl AnthropicProvider {
                     // Anthropic uses a separate system field, not in messages array
                     system_message = Some(msg.content.clone());
                 }
-                Role::User => {
+                // Anthropic returns tool results to the model as user turns,
+                // with results of the same assistant turn grouped into one
+                Role::Tool if msg.tool_call_id.is_some() => {
+                    let block = serde_json::json!({
+                        "type": "tool_result",
+                        "tool_use_id": msg.tool_call_id,
+                        "content": msg.content,
+                    });
+                    match anthropic_messages.last_mut() {
+                        Some(AnthropicMessage {
+                            role,
+                            content: serde_json::Value::Array(blocks),
+                        }) if role == "user" => blocks.push(block),
+                        _ => anthropic_messages.push(AnthropicMessage {
+                            role: "user".to_string(),
+                            content: serde_json::Value::Array(vec![block]),
+                        }),
+                    }
+                }
+                Role::User | Role::Tool => {
                     anthropic_messages.push(AnthropicMessage {
                         role: "user".to_string(),
-                        content: msg.content.clone(),
+                        content: msg.content.clone().into(),
+                    });
+                }
+                Role::Assistant if !msg.tool_calls.is_empty() => {
+                    let text = (!msg.content.is_empty())
+                        .then(|| serde_json::json!({"type": "text", "text": msg.content}));
+                    let tool_uses = msg.tool_calls.iter().map(|call| {
+                        serde_json::json!({
+                            "type": "tool_use",
+                            "id": call.id,
+                            "name": call.name,
+                            "input": call.arguments,
+                        })
+                    });
+                    anthropic_messages.push(AnthropicMessage {
+                        role: "assistant".to_string(),
+                        content: text.into_iter().chain(tool_uses).collect(),
                     });
                 }
                 Role::Assistant => {
                     anthropic_messages.push(AnthropicMessage {
                         role: "assistant".to_string(),
-                        content: msg.content.clone(),
+                        content: msg.content.clone().into(),
                     });
                 }
             }
@@ -227,19 +289,32 @@ impl AnthropicProvider {
                     )
                 })?;
 
-                let content = body
-                    .content
-                    .first()
-                    .map(|c| c.text.clone())
-                    .unwrap_or_default();
+                let mut content = None;
+                let mut tool_calls = Vec::new();
+                for block in body.content {
+                    match block {
+                        AnthropicContent::Text { text } => {
+                            content.get_or_insert(text);
+                        }
+                        AnthropicContent::ToolUse { id, name, input } => {
+                            tool_calls.push(ToolCall {
+                                id,
+                                name,
+                                arguments: input,
+                            });
+                        }
+                        AnthropicContent::Other => {}
+                    }
+                }
 
                 Ok(ChatResponse {
-                    content,
+                    content: content.unwrap_or_default(),
                     usage: body.usage.map(|u| Usage {
                         prompt_tokens: u.input_tokens,
                         completion_tokens: u.output_tokens,
                         total_tokens: u.input_tokens + u.output_tokens,
                     }),
+                    tool_calls,
                 })
             }
             // Anthropic uses 529 for overloaded, 429 for rate limit
@@ -252,7 +327,7 @@ impl AnthropicProvider {
             StatusCode::BAD_REQUEST => {
                 let text = res.text().await.unwrap_or_default();
                 if text.contains("context_length") || text.contains("max_tokens") {
-                    Err((InferenceError::ContextLengthExceeded, false))
+                    Err((InferenceError::ContextLengthExceeded { limit: None, requested: None }, false))
                 } else {
                     Err((
                         InferenceError::ProviderError(format!("Bad Request: {}", text)),
@@ -267,7 +342,10 @@ impl AnthropicProvider {
                 let status = res.status();
                 let text = res.text().await.unwrap_or_default();
                 Err((
-                    InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
+                    InferenceError::Http {
+                        status: status.as_u16(),
+                        message: text,
+                    },
                     true,
                 ))
             }
@@ -275,7 +353,10 @@ impl AnthropicProvider {
                 let status = res.status();
                 let text = res.text().await.unwrap_or_default();
                 Err((
-                    InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
+                    InferenceError::Http {
+                        status: status.as_u16(),
+                        message: text,
+                    },
                     false,
                 ))
             }
@@ -288,11 +369,27 @@ impl LLMProvider for AnthropicProvider {
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
         let (system, messages) = Self::prepare_messages(&request.messages);
 
+        if request.response_format == Some(ResponseFormat::JsonObject) {
+            return Err(InferenceError::Unsupported(
+                "Anthropic does not support JSON mode".to_string(),
+            ));
+        }
+
         let provider_req = AnthropicChatRequest {
             model: request.model,
-            max_tokens: self.max_tokens,
+            max_tokens: request.max_tokens.unwrap_or(self.max_tokens),
             messages,
             system,
+            temperature: request.temperature,
+            tools: request
+                .tools
+                .into_iter()
+                .map(|tool| AnthropicTool {
+                    name: tool.name,
+                    description: tool.description,
+                    input_schema: tool.parameters,
+                })
+                .collect(),
         };
 
         let mut last_error = InferenceError::NetworkError("No attempts made".to_string());
@@ -307,6 +404,11 @@ impl LLMProvider for AnthropicProvider {
                         break;
                     }
 
+                    if !context::try_consume_retry() {
+                        warn!(error = %last_error, "Request retry budget exhausted, giving up");
+                        break;
+                    }
+
                     let delay = self.calculate_backoff_delay(attempt);
                     warn!(
                         attempt = attempt + 1,
@@ -328,16 +430,6 @@ impl LLMProvider for AnthropicProvider {
     }
 }
 
-/// Simple pseudo-random jitter between 0.0 and 1.0
-fn rand_jitter() -> f64 {
-    use std::time::SystemTime;
-    let nanos = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map(|d| d.subsec_nanos())
-        .unwrap_or(0);
-    (nanos % 1000) as f64 / 1000.0
-}
-
 // =============================================================================
 // Tests
 // =============================================================================
@@ -395,14 +487,8 @@ mod tests {
     #[test]
     fn test_prepare_messages_extracts_system() {
         let messages = vec![
-            Message {
-                role: Role::System,
-                content: "You are helpful.".to_string(),
-            },
-            Message {
-                role: Role::User,
-                content: "Hello!".to_string(),
-            },
+            Message::new(Role::System, "You are helpful."),
+            Message::new(Role::User, "Hello!"),
         ];
 
         let (system, msgs) = AnthropicProvider::prepare_messages(&messages);
@@ -415,14 +501,8 @@ mod tests {
     #[test]
     fn test_prepare_messages_no_system() {
         let messages = vec![
-            Message {
-                role: Role::User,
-                content: "Hello!".to_string(),
-            },
-            Message {
-                role: Role::Assistant,
-                content: "Hi there!".to_string(),
-            },
+            Message::new(Role::User, "Hello!"),
+            Message::new(Role::Assistant, "Hi there!"),
         ];
 
         let (system, msgs) = AnthropicProvider::prepare_messages(&messages);
@@ -430,6 +510,47 @@ mod tests {
         assert_eq!(msgs.len(), 2);
     }
 
+    #[test]
+    fn test_prepare_messages_sends_tool_use_and_grouped_results() {
+        let calls = vec![
+            ToolCall {
+                id: "toolu_1".to_string(),
+                name: "get_weather".to_string(),
+                arguments: serde_json::json!({"city": "Oslo"}),
+            },
+            ToolCall {
+                id: "toolu_2".to_string(),
+                name: "get_weather".to_string(),
+                arguments: serde_json::json!({"city": "Bergen"}),
+            },
+        ];
+        let messages = vec![
+            Message::new(Role::User, "Weather?"),
+            Message::assistant_tool_calls("", calls),
+            Message::tool_result("toolu_1", "sunny"),
+            Message::tool_result("toolu_2", "rain"),
+        ];
+
+        let (_, msgs) = AnthropicProvider::prepare_messages(&messages);
+        assert_eq!(msgs.len(), 3);
+        assert_eq!(msgs[1].role, "assistant");
+        assert_eq!(
+            msgs[1].content,
+            serde_json::json!([
+                {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Oslo"}},
+                {"type": "tool_use", "id": "toolu_2", "name": "get_weather", "input": {"city": "Bergen"}}
+            ])
+        );
+        assert_eq!(msgs[2].role, "user");
+        assert_eq!(
+            msgs[2].content,
+            serde_json::json!([
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": "sunny"},
+                {"type": "tool_result", "tool_use_id": "toolu_2", "content": "rain"}
+            ])
+        );
+    }
+
     #[test]
     fn test_backoff_delay_calculation() {
         let api_key = SecretString::new("test-key".into());
@@ -0,0 +1,291 @@
+use crate::inference::provider::{LLMProvider, TokenStream};
+use crate::inference::strategy::weighted_turn;
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ModerationResult};
+use crate::mesh::CircuitBreakers;
+use async_trait::async_trait;
+use futures_util::{StreamExt, stream};
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Consecutive failures after which a member is skipped, by default
+pub const DEFAULT_BALANCER_FAILURE_THRESHOLD: u32 = 5;
+/// How long a failing member is skipped before being probed again, by default
+pub const DEFAULT_BALANCER_COOL_DOWN: Duration = Duration::from_secs(30);
+
+/// Spreads calls across several providers of the same model by weight.
+///
+/// Each call goes to the next member in weighted round-robin order; a member
+/// with weight 2 gets twice the calls of one with weight 1. Members whose
+/// circuit is open after repeated failures are skipped in favour of the next
+/// one in turn until their cool-down ends. Only
+/// [transient](InferenceError::is_transient) failures count towards opening
+/// a circuit; a client error says nothing about the member's health. A
+/// failed call is returned as is, not retried on another member.
+pub struct BalancedProvider {
+    members: Vec<(Arc<dyn LLMProvider>, u32)>,
+    total_weight: usize,
+    next: AtomicUsize,
+    breakers: CircuitBreakers,
+}
+
+impl BalancedProvider {
+    /// Fails if no member has a non-zero weight.
+    pub fn new(members: Vec<(Arc<dyn LLMProvider>, u32)>) -> Result<Self, InferenceError> {
+        let total_weight: usize = members.iter().map(|(_, weight)| *weight as usize).sum();
+        if total_weight == 0 {
+            return Err(InferenceError::ConfigError(
+                "Balanced provider needs a member with non-zero weight".to_string(),
+            ));
+        }
+        Ok(Self {
+            members,
+            total_weight,
+            next: AtomicUsize::new(0),
+            breakers: CircuitBreakers::new(DEFAULT_BALANCER_FAILURE_THRESHOLD, DEFAULT_BALANCER_COOL_DOWN),
+        })
+    }
+
+    /// Skips a member for `cool_down` once it fails `failure_threshold` calls in a row.
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cool_down: Duration) -> Self {
+        self.breakers = CircuitBreakers::new(failure_threshold, cool_down);
+        self
+    }
+
+    /// Index of the member whose turn it is, or the next one after it whose
+    /// circuit lets a call through
+    fn pick(&self) -> Option<usize> {
+        let slot = self.next.fetch_add(1, Ordering::Relaxed) % self.total_weight;
+        let start = weighted_turn(self.members.iter().map(|(_, weight)| *weight), slot)
+            .expect("slot is below the total weight");
+
+        (0..self.members.len())
+            .map(|offset| (start + offset) % self.members.len())
+            .filter(|&i| self.members[i].1 > 0)
+            .find(|&i| match self.breakers.try_acquire(&i.to_string()) {
+                Ok(()) => true,
+                Err(e) => {
+                    debug!(member = i, retry_in = ?e.retry_in, "Skipping balanced member with open circuit");
+                    false
+                }
+            })
+    }
+
+    /// Counts `error` against the member's circuit if it is transient; a
+    /// member that answered with a client error is working.
+    fn record_error(&self, key: &str, error: &InferenceError) {
+        if error.is_transient() {
+            self.breakers.record_failure(key);
+        } else {
+            self.breakers.record_success(key);
+        }
+    }
+
+    async fn dispatch<T, F, Fut>(&self, call: F) -> Result<T, InferenceError>
+    where
+        F: FnOnce(Arc<dyn LLMProvider>) -> Fut,
+        Fut: Future<Output = Result<T, InferenceError>>,
+    {
+        let index = self.pick().ok_or_else(|| {
+            InferenceError::ProviderError("Every balanced provider has an open circuit".to_string())
+        })?;
+        let key = index.to_string();
+        match call(Arc::clone(&self.members[index].0)).await {
+            Ok(value) => {
+                self.breakers.record_success(&key);
+                Ok(value)
+            }
+            Err(e) => {
+                warn!(member = index, error = %e, "Balanced provider call failed");
+                self.record_error(&key, &e);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for BalancedProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        self.dispatch(|provider| async move { provider.chat(request).await }).await
+    }
+
+    /// Streams from the member whose turn it is, ending at its first error.
+    /// The outcome reaches the member's circuit when the stream ends; one
+    /// dropped part way through reports nothing, and a probe left that way is
+    /// let through again after a cool-down.
+    fn stream_completion<'a>(&'a self, request: ChatRequest) -> TokenStream<'a> {
+        let Some(index) = self.pick() else {
+            return Box::pin(stream::once(async {
+                Err(InferenceError::ProviderError(
+                    "Every balanced provider has an open circuit".to_string(),
+                ))
+            }));
+        };
+        let tokens = self.members[index].0.stream_completion(request);
+        Box::pin(stream::unfold(Some(tokens), move |tokens| async move {
+            let mut tokens = tokens?;
+            let key = index.to_string();
+            match tokens.next().await {
+                Some(Ok(token)) => Some((Ok(token), Some(tokens))),
+                Some(Err(e)) => {
+                    warn!(member = index, error = %e, "Balanced provider stream failed");
+                    self.record_error(&key, &e);
+                    Some((Err(e), None))
+                }
+                None => {
+                    self.breakers.record_success(&key);
+                    None
+                }
+            }
+        }))
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.dispatch(|provider| async move { provider.embed(inputs).await }).await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.dispatch(|provider| async move { provider.embed_with_dimensions(inputs, dimensions).await })
+            .await
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        self.dispatch(|provider| async move { provider.moderate(input).await }).await
+    }
+
+    /// Healthy while any weighted member is
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        let mut last_error = None;
+        for (provider, _) in self.members.iter().filter(|(_, weight)| *weight > 0) {
+            match provider.health_check().await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.expect("at least one member has non-zero weight"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::ProviderRegistry;
+    use crate::inference::test_support::CountingProvider;
+
+    #[tokio::test]
+    async fn equal_weights_alternate_evenly() {
+        let (a, b) = (CountingProvider::new(), CountingProvider::new());
+        let registry = ProviderRegistry::new();
+        registry
+            .register_balanced("pool", vec![(a.clone() as Arc<dyn LLMProvider>, 1), (b.clone(), 1)])
+            .unwrap();
+
+        for i in 0..100 {
+            registry.chat("pool", ChatRequest::default()).await.unwrap();
+            assert!(a.calls().abs_diff(b.calls()) <= 1, "uneven after {} calls", i + 1);
+        }
+        assert_eq!((a.calls(), b.calls()), (50, 50));
+    }
+
+    #[tokio::test]
+    async fn weights_set_each_members_share() {
+        let (a, b) = (CountingProvider::new(), CountingProvider::new());
+        let balanced = BalancedProvider::new(vec![(a.clone() as Arc<dyn LLMProvider>, 3), (b.clone(), 1)]).unwrap();
+
+        for _ in 0..40 {
+            balanced.chat(ChatRequest::default()).await.unwrap();
+        }
+        assert_eq!((a.calls(), b.calls()), (30, 10));
+    }
+
+    #[tokio::test]
+    async fn member_with_open_circuit_is_skipped() {
+        let (a, b) = (CountingProvider::new(), CountingProvider::new());
+        a.failing.store(true, Ordering::SeqCst);
+        let balanced = BalancedProvider::new(vec![(a.clone() as Arc<dyn LLMProvider>, 1), (b.clone(), 1)])
+            .unwrap()
+            .with_circuit_breaker(1, Duration::from_secs(60));
+
+        assert!(balanced.chat(ChatRequest::default()).await.is_err());
+        for _ in 0..4 {
+            balanced.chat(ChatRequest::default()).await.unwrap();
+        }
+        assert_eq!((a.calls(), b.calls()), (1, 4));
+    }
+
+    #[tokio::test]
+    async fn streamed_calls_report_to_the_circuit() {
+        use crate::mesh::CircuitState;
+
+        let member = CountingProvider::new();
+        member.failing.store(true, Ordering::SeqCst);
+        let balanced = BalancedProvider::new(vec![(member.clone() as Arc<dyn LLMProvider>, 1)])
+            .unwrap()
+            .with_circuit_breaker(1, Duration::ZERO);
+
+        let tokens: Vec<_> = balanced.stream_completion(ChatRequest::default()).collect().await;
+        assert!(tokens[0].is_err());
+        assert_eq!(balanced.breakers.state("0"), CircuitState::Open);
+
+        // The next stream is the probe; finishing it closes the circuit
+        member.failing.store(false, Ordering::SeqCst);
+        let tokens: Vec<_> = balanced.stream_completion(ChatRequest::default()).collect().await;
+        assert_eq!(tokens[0].as_deref().unwrap(), "answer 2");
+        assert_eq!(balanced.breakers.state("0"), CircuitState::Closed);
+    }
+
+    struct RejectingProvider;
+
+    #[async_trait]
+    impl LLMProvider for RejectingProvider {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            Err(InferenceError::Http {
+                status: 400,
+                message: "bad request".to_string(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn client_errors_leave_the_circuit_closed() {
+        use crate::mesh::CircuitState;
+
+        let balanced = BalancedProvider::new(vec![(Arc::new(RejectingProvider) as Arc<dyn LLMProvider>, 1)])
+            .unwrap()
+            .with_circuit_breaker(1, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            let err = balanced.chat(ChatRequest::default()).await.unwrap_err();
+            assert!(matches!(err, InferenceError::Http { status: 400, .. }), "{:?}", err);
+        }
+        let tokens: Vec<_> = balanced.stream_completion(ChatRequest::default()).collect().await;
+        assert!(matches!(tokens[0], Err(InferenceError::Http { status: 400, .. })));
+        assert_eq!(balanced.breakers.state("0"), CircuitState::Closed);
+    }
+
+    #[test]
+    fn transient_errors_are_told_from_client_errors() {
+        let http = |status| InferenceError::Http {
+            status,
+            message: String::new(),
+        };
+        assert!(InferenceError::NetworkError("reset".to_string()).is_transient());
+        assert!(InferenceError::RateLimit.is_transient());
+        assert!(http(503).is_transient());
+        assert!(!http(400).is_transient());
+        assert!(!InferenceError::ContextLengthExceeded { limit: None, requested: None }.is_transient());
+    }
+
+    #[test]
+    fn all_zero_weights_are_rejected() {
+        let result = BalancedProvider::new(vec![(CountingProvider::new() as Arc<dyn LLMProvider>, 0)]);
+        assert!(matches!(result, Err(InferenceError::ConfigError(_))));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::inference::postprocess::Transform;
+use crate::inference::types::{ChatRequest, InferenceError, Message, ResponseFormat, Role, Tool};
+use std::collections::HashSet;
+
+/// Fluent builder for [`ChatRequest`], validated on [`build`](Self::build).
+///
+/// ```
+/// use brio_kernel::inference::CompletionRequestBuilder;
+///
+/// let request = CompletionRequestBuilder::new("gpt-4")
+///     .system("You are terse.")
+///     .user("Summarise the diff.")
+///     .temperature(0.2)
+///     .max_tokens(256)
+///     .build()
+///     .unwrap();
+/// assert_eq!(request.messages.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompletionRequestBuilder {
+    request: ChatRequest,
+}
+
+impl CompletionRequestBuilder {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            request: ChatRequest {
+                model: model.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn message(mut self, role: Role, content: impl Into<String>) -> Self {
+        self.request.messages.push(Message::new(role, content));
+        self
+    }
+
+    pub fn system(self, content: impl Into<String>) -> Self {
+        self.message(Role::System, content)
+    }
+
+    pub fn user(self, content: impl Into<String>) -> Self {
+        self.message(Role::User, content)
+    }
+
+    pub fn assistant(self, content: impl Into<String>) -> Self {
+        self.message(Role::Assistant, content)
+    }
+
+    /// Sampling temperature, between 0.0 and 2.0
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.request.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.request.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn tool(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        self.request.tools.push(Tool {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+        });
+        self
+    }
+
+    /// Constrains the completion to a single JSON object
+    pub fn json_mode(mut self) -> Self {
+        self.request.response_format = Some(ResponseFormat::JsonObject);
+        self
+    }
+
+    /// Appends a cleanup step run on the completion text
+    pub fn post_process(mut self, transform: Transform) -> Self {
+        self.request.post_process.push(transform);
+        self
+    }
+
+    /// Validates the request, rejecting empty or contradictory combinations.
+    pub fn build(self) -> Result<ChatRequest, InferenceError> {
+        let request = self.request;
+        let invalid = |msg: &str| Err(InferenceError::InvalidRequest(msg.to_string()));
+
+        if request.model.trim().is_empty() {
+            return invalid("Model must not be empty");
+        }
+        if !request.messages.iter().any(|m| m.role != Role::System) {
+            return invalid("Request needs at least one user or assistant message");
+        }
+        if let Some(first_other) = request.messages.iter().position(|m| m.role != Role::System)
+            && request.messages[first_other..].iter().any(|m| m.role == Role::System)
+        {
+            return invalid("System messages must come before the conversation");
+        }
+        if let Some(t) = request.temperature
+            && !(0.0..=2.0).contains(&t)
+        {
+            return invalid("Temperature must be between 0.0 and 2.0");
+        }
+        if request.max_tokens == Some(0) {
+            return invalid("max_tokens must be greater than zero");
+        }
+
+        let mut names = HashSet::new();
+        if let Some(dup) = request.tools.iter().find(|t| !names.insert(t.name.as_str())) {
+            return Err(InferenceError::InvalidRequest(format!(
+                "Duplicate tool name: {}",
+                dup.name
+            )));
+        }
+        if request.response_format == Some(ResponseFormat::JsonObject) && !request.tools.is_empty() {
+            return invalid("JSON mode cannot be combined with tools");
+        }
+
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_matches_manual_construction() {
+        let schema = serde_json::json!({ "type": "object", "properties": {} });
+        let built = CompletionRequestBuilder::new("gpt-4")
+            .system("Be helpful")
+            .user("Hi")
+            .temperature(0.5)
+            .max_tokens(100)
+            .tool("lookup", "Looks things up", schema.clone())
+            .build()
+            .unwrap();
+
+        let manual = ChatRequest {
+            model: "gpt-4".to_string(),
+            messages: vec![
+                Message::new(Role::System, "Be helpful"),
+                Message::new(Role::User, "Hi"),
+            ],
+            temperature: Some(0.5),
+            max_tokens: Some(100),
+            tools: vec![Tool {
+                name: "lookup".to_string(),
+                description: "Looks things up".to_string(),
+                parameters: schema,
+            }],
+            response_format: None,
+            post_process: Vec::new(),
+        };
+
+        assert_eq!(built, manual);
+    }
+
+    #[test]
+    fn test_json_mode_sets_response_format() {
+        let request = CompletionRequestBuilder::new("gpt-4")
+            .user("Reply in JSON")
+            .json_mode()
+            .build()
+            .unwrap();
+        assert_eq!(request.response_format, Some(ResponseFormat::JsonObject));
+    }
+
+    fn rejected(builder: CompletionRequestBuilder) -> bool {
+        matches!(builder.build(), Err(InferenceError::InvalidRequest(_)))
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_combinations() {
+        let base = || CompletionRequestBuilder::new("gpt-4").user("Hi");
+        let schema = serde_json::json!({});
+
+        assert!(rejected(CompletionRequestBuilder::new("").user("Hi")));
+        assert!(rejected(CompletionRequestBuilder::new("gpt-4").system("only system")));
+        assert!(rejected(base().system("late system")));
+        assert!(rejected(base().temperature(2.5)));
+        assert!(rejected(base().max_tokens(0)));
+        assert!(rejected(
+            base()
+                .tool("a", "first", schema.clone())
+                .tool("a", "second", schema.clone())
+        ));
+        assert!(rejected(base().tool("a", "tool", schema).json_mode()));
+    }
+}
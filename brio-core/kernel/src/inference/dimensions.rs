@@ -0,0 +1,88 @@
+use crate::inference::types::InferenceError;
+
+/// Full embedding size of known models, and whether the provider can shorten
+/// vectors itself via a `dimensions` request parameter
+const KNOWN_MODELS: &[(&str, usize, bool)] = &[
+    ("text-embedding-3-small", 1536, true),
+    ("text-embedding-3-large", 3072, true),
+    ("text-embedding-ada-002", 1536, false),
+];
+
+fn known_model(model: &str) -> Option<(usize, bool)> {
+    KNOWN_MODELS
+        .iter()
+        .find(|(name, _, _)| *name == model)
+        .map(|(_, max, native)| (*max, *native))
+}
+
+/// Largest embedding `model` produces, if the model is known.
+pub fn max_embedding_dimensions(model: &str) -> Option<usize> {
+    known_model(model).map(|(max, _)| max)
+}
+
+/// True if `model` accepts a target dimension natively rather than needing
+/// its vectors reduced locally.
+pub fn supports_native_dimensions(model: &str) -> bool {
+    known_model(model).is_some_and(|(_, native)| native)
+}
+
+/// Checks that `dimensions` is a size `model` can be reduced to.
+///
+/// Models missing from the known list only have zero rejected; anything
+/// larger than their real size fails once the vectors come back.
+pub fn validate_dimensions(model: &str, dimensions: usize) -> Result<(), InferenceError> {
+    if dimensions == 0 {
+        return Err(InferenceError::InvalidRequest(
+            "Embedding dimensions must be at least 1".to_string(),
+        ));
+    }
+    if let Some(max) = max_embedding_dimensions(model)
+        && dimensions > max
+    {
+        return Err(InferenceError::InvalidRequest(format!(
+            "Model '{}' produces at most {} dimensions, {} requested",
+            model, max, dimensions
+        )));
+    }
+    Ok(())
+}
+
+/// Shortens `vector` to its first `dimensions` components and rescales it to
+/// unit length, so cosine and dot-product scores stay comparable.
+///
+/// Deterministic, and matches what providers that shorten natively do.
+pub fn reduce_embedding(mut vector: Vec<f32>, dimensions: usize) -> Result<Vec<f32>, InferenceError> {
+    if vector.len() < dimensions {
+        return Err(InferenceError::InvalidRequest(format!(
+            "Embedding has {} dimensions, fewer than the {} requested",
+            vector.len(),
+            dimensions
+        )));
+    }
+    vector.truncate(dimensions);
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    Ok(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduced_vector_is_truncated_and_unit_length() {
+        let reduced = reduce_embedding(vec![3.0, 4.0, 12.0], 2).unwrap();
+        assert_eq!(reduced, vec![0.6, 0.8]);
+        assert!(reduce_embedding(vec![1.0], 2).is_err());
+    }
+
+    #[test]
+    fn dimensions_are_validated_against_the_model_max() {
+        assert!(validate_dimensions("text-embedding-3-small", 256).is_ok());
+        assert!(validate_dimensions("text-embedding-3-small", 4096).is_err());
+        assert!(validate_dimensions("text-embedding-3-small", 0).is_err());
+        assert!(validate_dimensions("local-model", 4096).is_ok());
+    }
+}
@@ -0,0 +1,343 @@
+use crate::inference::provider::{LLMProvider, TokenStream};
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ModerationResult};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Embeddings kept by default before the least recently used are evicted
+pub const DEFAULT_EMBEDDING_CACHE_CAPACITY: usize = 1024;
+
+/// Model, requested dimensions and input text an embedding was computed for
+type CacheKey = (String, Option<usize>, String);
+
+/// Outcome shared with callers waiting on an in-flight embedding
+type Shared = Option<Result<Vec<f32>, String>>;
+
+/// Bounded map that evicts the least recently used entry when full.
+struct LruCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<CacheKey, (Vec<f32>, u64)>,
+    recency: BTreeMap<u64, CacheKey>,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &CacheKey) -> Option<Vec<f32>> {
+        self.tick += 1;
+        let (value, used) = self.entries.get_mut(key)?;
+        self.recency.remove(used);
+        self.recency.insert(self.tick, key.clone());
+        *used = self.tick;
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: CacheKey, value: Vec<f32>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.recency.remove(&used);
+        } else if self.entries.len() >= self.capacity
+            && let Some((_, oldest)) = self.recency.pop_first()
+        {
+            self.entries.remove(&oldest);
+        }
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+    }
+}
+
+struct State {
+    cache: LruCache,
+    in_flight: HashMap<CacheKey, watch::Receiver<Shared>>,
+}
+
+/// Unregisters a leader's in-flight keys if its call is dropped before finishing,
+/// so later requests don't wait on it forever.
+struct FlightGuard<'a> {
+    state: &'a Mutex<State>,
+    keys: Vec<CacheKey>,
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().expect("Mutex poisoned");
+        for key in &self.keys {
+            state.in_flight.remove(key);
+        }
+    }
+}
+
+/// Wraps a provider so repeated embeddings of the same text are served from
+/// an LRU cache, and concurrent requests for the same text share one call.
+///
+/// Embeddings are deterministic for a given model, so entries never go stale;
+/// chat and moderation pass straight through.
+pub struct CachedEmbeddingProvider {
+    inner: Arc<dyn LLMProvider>,
+    model: String,
+    state: Mutex<State>,
+}
+
+impl CachedEmbeddingProvider {
+    /// `model` names what `inner` embeds with and is part of every cache key.
+    pub fn new(inner: Arc<dyn LLMProvider>, model: impl Into<String>) -> Self {
+        Self {
+            inner,
+            model: model.into(),
+            state: Mutex::new(State {
+                cache: LruCache::new(DEFAULT_EMBEDDING_CACHE_CAPACITY),
+                in_flight: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Bounds how many embeddings are kept; zero disables caching but keeps coalescing.
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        self.state.lock().expect("Mutex poisoned").cache = LruCache::new(capacity);
+        self
+    }
+
+    fn key(&self, input: &str, dimensions: Option<usize>) -> CacheKey {
+        (self.model.clone(), dimensions, input.to_string())
+    }
+
+    /// Embeds `inputs` at full size, or at `dimensions` if given, answering
+    /// from the cache and in-flight requests where possible.
+    async fn embed_cached(
+        &self,
+        inputs: Vec<String>,
+        dimensions: Option<usize>,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        let mut slots = Vec::with_capacity(inputs.len());
+        let mut upstream = Vec::new();
+        let mut senders = Vec::new();
+        let mut guard = FlightGuard {
+            state: &self.state,
+            keys: Vec::new(),
+        };
+
+        {
+            let mut state = self.state.lock().expect("Mutex poisoned");
+            for input in &inputs {
+                let key = self.key(input, dimensions);
+                if let Some(value) = state.cache.get(&key) {
+                    slots.push(Slot::Ready(value));
+                } else if let Some(index) = guard.keys.iter().position(|k| *k == key) {
+                    // Repeated within this request, checked first as its key is already in flight
+                    slots.push(Slot::Leading(index));
+                } else if let Some(rx) = state.in_flight.get(&key) {
+                    slots.push(Slot::Waiting(rx.clone()));
+                } else {
+                    let (tx, rx) = watch::channel(None);
+                    state.in_flight.insert(key.clone(), rx);
+                    slots.push(Slot::Leading(upstream.len()));
+                    upstream.push(input.clone());
+                    senders.push(tx);
+                    guard.keys.push(key);
+                }
+            }
+        }
+
+        let computed = if upstream.is_empty() {
+            Vec::new()
+        } else {
+            let result = match dimensions {
+                Some(dimensions) => self.inner.embed_with_dimensions(upstream, dimensions).await,
+                None => self.inner.embed(upstream).await,
+            };
+            let mut state = self.state.lock().expect("Mutex poisoned");
+            for key in &guard.keys {
+                state.in_flight.remove(key);
+            }
+            match result {
+                Ok(vectors) => {
+                    for ((key, tx), vector) in guard.keys.drain(..).zip(&senders).zip(&vectors) {
+                        state.cache.insert(key, vector.clone());
+                        tx.send_replace(Some(Ok(vector.clone())));
+                    }
+                    vectors
+                }
+                Err(e) => {
+                    guard.keys.clear();
+                    for tx in &senders {
+                        tx.send_replace(Some(Err(e.to_string())));
+                    }
+                    return Err(e);
+                }
+            }
+        };
+
+        let mut embeddings = Vec::with_capacity(slots.len());
+        for slot in slots {
+            embeddings.push(match slot {
+                Slot::Ready(value) => value,
+                Slot::Leading(index) => computed.get(index).cloned().ok_or_else(|| {
+                    InferenceError::ProviderError("Provider returned fewer embeddings than inputs".to_string())
+                })?,
+                Slot::Waiting(mut rx) => {
+                    let shared = rx.wait_for(Option::is_some).await.map_err(|_| {
+                        InferenceError::ProviderError("In-flight embedding request was cancelled".to_string())
+                    })?;
+                    match shared.as_ref().expect("waited for a value") {
+                        Ok(value) => value.clone(),
+                        Err(e) => return Err(InferenceError::ProviderError(e.clone())),
+                    }
+                }
+            });
+        }
+        Ok(embeddings)
+    }
+}
+
+enum Slot {
+    Ready(Vec<f32>),
+    Waiting(watch::Receiver<Shared>),
+    /// Index into the inputs this call sends upstream
+    Leading(usize),
+}
+
+#[async_trait]
+impl LLMProvider for CachedEmbeddingProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        self.inner.chat(request).await
+    }
+
+    fn stream_completion<'a>(&'a self, request: ChatRequest) -> TokenStream<'a> {
+        self.inner.stream_completion(request)
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.embed_cached(inputs, None).await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.embed_cached(inputs, Some(dimensions)).await
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        self.inner.moderate(input).await
+    }
+
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    struct CountingEmbedder {
+        calls: Arc<AtomicUsize>,
+        inputs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CountingEmbedder {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            Err(InferenceError::Unsupported("CountingEmbedder only embeds".to_string()))
+        }
+
+        async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.inputs.fetch_add(inputs.len(), Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(inputs.iter().map(|s| vec![s.len() as f32]).collect())
+        }
+
+        async fn embed_with_dimensions(
+            &self,
+            inputs: Vec<String>,
+            dimensions: usize,
+        ) -> Result<Vec<Vec<f32>>, InferenceError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.inputs.fetch_add(inputs.len(), Ordering::SeqCst);
+            Ok(inputs.iter().map(|s| vec![s.len() as f32; dimensions]).collect())
+        }
+    }
+
+    fn cached(capacity: usize) -> (CachedEmbeddingProvider, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let inputs = Arc::new(AtomicUsize::new(0));
+        let inner = CountingEmbedder {
+            calls: calls.clone(),
+            inputs: inputs.clone(),
+        };
+        let provider = CachedEmbeddingProvider::new(Arc::new(inner), "test-model").with_capacity(capacity);
+        (provider, calls, inputs)
+    }
+
+    #[tokio::test]
+    async fn same_text_twice_makes_one_upstream_call() {
+        let (provider, calls, _) = cached(16);
+
+        let first = provider.embed(vec!["hello".to_string()]).await.unwrap();
+        let second = provider.embed(vec!["hello".to_string()]).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_duplicates_share_one_call() {
+        let (provider, calls, inputs) = cached(0);
+
+        let (a, b) = tokio::join!(
+            provider.embed(vec!["hello".to_string(), "hello".to_string()]),
+            provider.embed(vec!["hello".to_string()]),
+        );
+
+        assert_eq!(a.unwrap(), vec![vec![5.0], vec![5.0]]);
+        assert_eq!(b.unwrap(), vec![vec![5.0]]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(inputs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_entry_is_evicted() {
+        let (provider, calls, _) = cached(2);
+
+        provider.embed(vec!["a".to_string(), "bb".to_string()]).await.unwrap();
+        provider.embed(vec!["a".to_string()]).await.unwrap();
+        provider.embed(vec!["ccc".to_string()]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // "bb" was least recently used, so it's the one recomputed
+        provider.embed(vec!["a".to_string()]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        provider.embed(vec!["bb".to_string()]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn dimensions_reach_the_provider_and_are_cached_separately() {
+        let (provider, calls, _) = cached(16);
+
+        let full = provider.embed(vec!["hello".to_string()]).await.unwrap();
+        let short = provider.embed_with_dimensions(vec!["hello".to_string()], 3).await.unwrap();
+        let again = provider.embed_with_dimensions(vec!["hello".to_string()], 3).await.unwrap();
+
+        assert_eq!(full, vec![vec![5.0]]);
+        assert_eq!(short, vec![vec![5.0; 3]]);
+        assert_eq!(again, short);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::inference::provider::{LLMProvider, TokenStream};
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ModerationResult};
+use async_trait::async_trait;
+use futures_util::{StreamExt, stream};
+use futures_util::stream::FuturesUnordered;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Default delay before a hedge request is sent
+const DEFAULT_HEDGE_DELAY: Duration = Duration::from_millis(500);
+/// Default maximum number of providers called for one request
+const DEFAULT_MAX_FANOUT: usize = 2;
+
+/// Sends the same request to several providers to cut tail latency.
+///
+/// The first provider is called immediately. If it hasn't answered after
+/// the hedge delay, the next provider is called as well, up to the fan-out
+/// limit; a failure launches the next provider straight away. The first
+/// success wins and the remaining requests are cancelled by being dropped.
+/// This trades extra tokens for lower p99 latency.
+///
+/// Only chat calls are hedged. Streams, embeddings and moderation go to the
+/// first provider alone: a stream can't switch providers once tokens have
+/// gone out, and the others aren't worth paying for twice.
+pub struct HedgedProvider {
+    providers: Vec<Arc<dyn LLMProvider>>,
+    hedge_delay: Duration,
+    max_fanout: usize,
+}
+
+impl HedgedProvider {
+    /// Creates a hedged provider trying `providers` in order.
+    pub fn new(providers: Vec<Arc<dyn LLMProvider>>) -> Self {
+        Self {
+            providers,
+            hedge_delay: DEFAULT_HEDGE_DELAY,
+            max_fanout: DEFAULT_MAX_FANOUT,
+        }
+    }
+
+    /// Sets how long to wait for a response before hedging
+    pub fn with_hedge_delay(mut self, delay: Duration) -> Self {
+        self.hedge_delay = delay;
+        self
+    }
+
+    /// Sets the maximum number of providers called for one request
+    pub fn with_max_fanout(mut self, max_fanout: usize) -> Self {
+        self.max_fanout = max_fanout.max(1);
+        self
+    }
+
+    /// The provider called first, which serves everything but chat alone
+    fn first(&self) -> Result<&Arc<dyn LLMProvider>, InferenceError> {
+        self.providers
+            .first()
+            .ok_or_else(|| InferenceError::ConfigError("Hedged provider has no providers".to_string()))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for HedgedProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let limit = self.max_fanout.min(self.providers.len());
+        let mut remaining = self.providers.iter().take(limit);
+        let mut in_flight = FuturesUnordered::new();
+
+        match remaining.next() {
+            Some(provider) => in_flight.push(provider.chat(request.clone())),
+            None => {
+                return Err(InferenceError::ConfigError(
+                    "Hedged provider has no providers".to_string(),
+                ));
+            }
+        }
+
+        loop {
+            let hedge = tokio::time::sleep(self.hedge_delay);
+            tokio::select! {
+                Some(result) = in_flight.next() => match result {
+                    Ok(response) => return Ok(response),
+                    Err(e) => {
+                        debug!(error = %e, "Hedged request failed");
+                        match remaining.next() {
+                            Some(provider) => in_flight.push(provider.chat(request.clone())),
+                            // The last outstanding request decides the error
+                            None if in_flight.is_empty() => return Err(e),
+                            None => {}
+                        }
+                    }
+                },
+                _ = hedge, if remaining.len() > 0 => {
+                    debug!(delay_ms = self.hedge_delay.as_millis() as u64, "Sending hedge request");
+                    if let Some(provider) = remaining.next() {
+                        in_flight.push(provider.chat(request.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    fn stream_completion<'a>(&'a self, request: ChatRequest) -> TokenStream<'a> {
+        match self.first() {
+            Ok(provider) => provider.stream_completion(request),
+            Err(e) => Box::pin(stream::once(async { Err(e) })),
+        }
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.first()?.embed(inputs).await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.first()?.embed_with_dimensions(inputs, dimensions).await
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        self.first()?.moderate(input).await
+    }
+
+    /// Healthy while any of the providers is, as a request can still be served.
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        let mut last_error = InferenceError::ConfigError("Hedged provider has no providers".to_string());
+        for provider in &self.providers {
+            match provider.health_check().await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    struct DelayedProvider {
+        delay: Duration,
+        response: Result<&'static str, ()>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    /// Returns a provider answering after `delay_ms`, and a counter of its calls.
+    fn delayed(
+        delay_ms: u64,
+        response: Result<&'static str, ()>,
+    ) -> (Arc<dyn LLMProvider>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = DelayedProvider {
+            delay: Duration::from_millis(delay_ms),
+            response,
+            calls: calls.clone(),
+        };
+        (Arc::new(provider), calls)
+    }
+
+    #[async_trait]
+    impl LLMProvider for DelayedProvider {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            match self.response {
+                Ok(content) => Ok(ChatResponse {
+                    content: content.to_string(),
+                    usage: None,
+                    tool_calls: Vec::new(),
+                }),
+                Err(()) => Err(InferenceError::ProviderError("failed".to_string())),
+            }
+        }
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            model: "test".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_then_fast_returns_fast_result() {
+        let (slow, _) = delayed(5_000, Ok("slow"));
+        let (fast, _) = delayed(10, Ok("fast"));
+        let hedged = HedgedProvider::new(vec![slow, fast]).with_hedge_delay(Duration::from_millis(20));
+
+        let start = Instant::now();
+        let response = hedged.chat(request()).await.unwrap();
+        assert_eq!(response.content, "fast");
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_fast_primary_does_not_hedge() {
+        let (primary, _) = delayed(1, Ok("primary"));
+        let (backup, backup_calls) = delayed(1, Ok("backup"));
+        let hedged = HedgedProvider::new(vec![primary, backup]).with_hedge_delay(Duration::from_secs(5));
+
+        assert_eq!(hedged.chat(request()).await.unwrap().content, "primary");
+        assert_eq!(backup_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fanout_is_bounded() {
+        let (a, _) = delayed(50, Err(()));
+        let (b, _) = delayed(50, Err(()));
+        let (c, c_calls) = delayed(1, Ok("c"));
+        let hedged = HedgedProvider::new(vec![a, b, c])
+            .with_hedge_delay(Duration::from_millis(5))
+            .with_max_fanout(2);
+
+        let result = hedged.chat(request()).await;
+        assert!(matches!(result, Err(InferenceError::ProviderError(_))));
+        assert_eq!(c_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failure_launches_next_provider_immediately() {
+        let (broken, _) = delayed(1, Err(()));
+        let (backup, _) = delayed(1, Ok("backup"));
+        let hedged = HedgedProvider::new(vec![broken, backup]).with_hedge_delay(Duration::from_secs(5));
+
+        let start = Instant::now();
+        assert_eq!(hedged.chat(request()).await.unwrap().content, "backup");
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    /// Answers every operation with its own dimension, so callers can tell members apart
+    struct Member(usize);
+
+    #[async_trait]
+    impl LLMProvider for Member {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            Ok(ChatResponse {
+                content: self.0.to_string(),
+                usage: None,
+                tool_calls: Vec::new(),
+            })
+        }
+
+        async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+            Ok(vec![vec![0.0; self.0]; inputs.len()])
+        }
+
+        async fn embed_with_dimensions(
+            &self,
+            inputs: Vec<String>,
+            dimensions: usize,
+        ) -> Result<Vec<Vec<f32>>, InferenceError> {
+            Ok(vec![vec![0.0; dimensions + self.0]; inputs.len()])
+        }
+
+        async fn moderate(&self, _input: String) -> Result<ModerationResult, InferenceError> {
+            Ok(ModerationResult {
+                flagged: true,
+                categories: vec![self.0.to_string()],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_other_operations_go_to_the_first_provider() {
+        let hedged = HedgedProvider::new(vec![Arc::new(Member(1)), Arc::new(Member(2))]);
+
+        assert_eq!(hedged.embed(vec!["a".to_string()]).await.unwrap(), vec![vec![0.0]]);
+        assert_eq!(hedged.embed_with_dimensions(vec!["a".to_string()], 2).await.unwrap()[0].len(), 3);
+        assert_eq!(hedged.moderate("a".to_string()).await.unwrap().categories, ["1"]);
+        let tokens: Vec<_> = hedged.stream_completion(request()).collect().await;
+        assert_eq!(tokens[0].as_deref().unwrap(), "1");
+
+        let empty = HedgedProvider::new(Vec::new());
+        assert!(matches!(empty.embed(Vec::new()).await, Err(InferenceError::ConfigError(_))));
+    }
+}
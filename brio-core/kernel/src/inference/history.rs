@@ -0,0 +1,137 @@
+use crate::inference::types::{Message, Role};
+use serde::Deserialize;
+
+/// Rough characters-per-token ratio used to estimate prompt size
+const CHARS_PER_TOKEN: usize = 4;
+
+/// How conversation history is trimmed before a request is dispatched.
+///
+/// System messages are always kept, and an assistant message is never
+/// separated from the tool results that follow it. Whole turns are dropped
+/// oldest-first until the history fits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryPolicy {
+    /// Keep at most this many non-system messages
+    LastMessages(usize),
+    /// Keep the most recent turns whose estimated size fits in this many tokens,
+    /// counting system messages against the budget
+    TokenBudget(usize),
+}
+
+/// Estimates the token count of a message from its length.
+pub fn estimate_tokens(message: &Message) -> usize {
+    message.content.len().div_ceil(CHARS_PER_TOKEN)
+}
+
+impl HistoryPolicy {
+    /// Returns the messages to send, preserving their original order.
+    pub fn trim(&self, messages: Vec<Message>) -> Vec<Message> {
+        let (system, conversation): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .enumerate()
+            .partition(|(_, m)| m.role == Role::System);
+
+        // Group the conversation into units that must be kept or dropped together
+        let mut units: Vec<Vec<(usize, Message)>> = Vec::new();
+        for entry in conversation {
+            match units.last_mut() {
+                Some(unit) if entry.1.role == Role::Tool => unit.push(entry),
+                _ => units.push(vec![entry]),
+            }
+        }
+
+        let (mut used, limit) = match *self {
+            Self::LastMessages(n) => (0, n),
+            Self::TokenBudget(budget) => {
+                let system_tokens = system.iter().map(|(_, m)| estimate_tokens(m)).sum();
+                (system_tokens, budget)
+            }
+        };
+        let cost = |unit: &[(usize, Message)]| match self {
+            Self::LastMessages(_) => unit.len(),
+            Self::TokenBudget(_) => unit.iter().map(|(_, m)| estimate_tokens(m)).sum(),
+        };
+
+        let mut kept = system;
+        for unit in units.into_iter().rev() {
+            let unit_cost = cost(&unit);
+            if used + unit_cost > limit {
+                break;
+            }
+            used += unit_cost;
+            kept.extend(unit);
+        }
+
+        kept.sort_by_key(|(index, _)| *index);
+        kept.into_iter().map(|(_, m)| m).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: Role, content: &str) -> Message {
+        Message::new(role, content)
+    }
+
+    fn contents(messages: &[Message]) -> Vec<&str> {
+        messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            msg(Role::System, "sys!"), // 1 token
+            msg(Role::User, "old question 1"), // 4 tokens
+            msg(Role::Assistant, "old answer 1"), // 3 tokens
+            msg(Role::User, "recent question"), // 4 tokens
+            msg(Role::Assistant, "recent answer"), // 4 tokens
+        ]
+    }
+
+    #[test]
+    fn test_token_budget_keeps_system_and_recent_turns() {
+        let trimmed = HistoryPolicy::TokenBudget(9).trim(conversation());
+        assert_eq!(contents(&trimmed), ["sys!", "recent question", "recent answer"]);
+    }
+
+    #[test]
+    fn test_token_budget_keeps_everything_that_fits() {
+        let trimmed = HistoryPolicy::TokenBudget(100).trim(conversation());
+        assert_eq!(trimmed, conversation());
+    }
+
+    #[test]
+    fn test_system_message_survives_tiny_budget() {
+        let trimmed = HistoryPolicy::TokenBudget(0).trim(conversation());
+        assert_eq!(contents(&trimmed), ["sys!"]);
+    }
+
+    #[test]
+    fn test_last_messages_keeps_latest_non_system() {
+        let trimmed = HistoryPolicy::LastMessages(1).trim(conversation());
+        assert_eq!(contents(&trimmed), ["sys!", "recent answer"]);
+    }
+
+    #[test]
+    fn test_tool_results_stay_with_their_call() {
+        let messages = vec![
+            msg(Role::System, "sys"),
+            msg(Role::User, "look it up"),
+            msg(Role::Assistant, "calling lookup"),
+            msg(Role::Tool, "lookup result"),
+            msg(Role::Tool, "second result"),
+        ];
+
+        // Room for two messages: the call and its results don't fit together, so both go
+        let trimmed = HistoryPolicy::LastMessages(2).trim(messages.clone());
+        assert_eq!(contents(&trimmed), ["sys"]);
+
+        let trimmed = HistoryPolicy::LastMessages(3).trim(messages);
+        assert_eq!(
+            contents(&trimmed),
+            ["sys", "calling lookup", "lookup result", "second result"]
+        );
+    }
+}
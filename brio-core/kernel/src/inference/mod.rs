@@ -1,12 +1,46 @@
+pub mod agent;
 pub mod anthropic;
+pub mod balanced;
+pub mod builder;
+pub mod dimensions;
+pub mod embedding_cache;
+pub mod hedged;
+pub mod history;
+pub mod moderation;
 pub mod openai;
+pub mod postprocess;
 pub mod provider;
+pub mod rate_limit;
+pub mod recording;
 pub mod registry;
+pub mod response_cache;
+pub mod retry;
+pub mod sse;
+pub mod strategy;
+#[cfg(test)]
+mod test_support;
+pub mod tools;
 pub mod types;
+pub mod usage;
 
+pub use agent::{AgentLoopError, AgentOutcome, AgentStep, AgentTool, ToolResult};
 pub use anthropic::{AnthropicConfig, AnthropicProvider};
-pub use openai::{OpenAIConfig, OpenAIProvider};
-pub use provider::LLMProvider;
+pub use balanced::BalancedProvider;
+pub use builder::CompletionRequestBuilder;
+pub use embedding_cache::CachedEmbeddingProvider;
+pub use hedged::HedgedProvider;
+pub use history::HistoryPolicy;
+pub use moderation::{ModeratedProvider, ModerationVerdict, Moderator, NoopModerator, ProviderModerator};
+pub use openai::{OpenAIConfig, OpenAIProvider, ResponseMapper, standard_response};
+pub use postprocess::{Transform, apply_transforms};
+pub use provider::{LLMProvider, TokenStream};
+pub use rate_limit::{TenantLimits, TenantRateLimiter};
+pub use recording::RequestRecorder;
 pub use registry::ProviderRegistry;
+pub use response_cache::CachingProvider;
+pub use retry::RetryProvider;
+pub use strategy::{ProviderSelector, SelectionStrategy, WeightedProvider};
+pub use tools::{ToolValidatingProvider, validate_arguments, validate_tool_calls};
 pub use types::*;
+pub use usage::{UsageTotals, UsageTrackingProvider};
 
@@ -0,0 +1,188 @@
+use crate::inference::provider::LLMProvider;
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ModerationResult};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Decision a [`Moderator`] reaches about a piece of content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModerationVerdict {
+    Allow,
+    /// Let the content through but log the flagged categories
+    Flag(Vec<String>),
+    /// Refuse the content with [`InferenceError::ContentBlocked`]
+    Block(Vec<String>),
+}
+
+/// Screens prompts before they reach a provider and completions before they
+/// reach the caller.
+#[async_trait]
+pub trait Moderator: Send + Sync {
+    async fn check(&self, content: &str) -> Result<ModerationVerdict, InferenceError>;
+}
+
+/// Allows everything; the default when moderation is not configured.
+pub struct NoopModerator;
+
+#[async_trait]
+impl Moderator for NoopModerator {
+    async fn check(&self, _content: &str) -> Result<ModerationVerdict, InferenceError> {
+        Ok(ModerationVerdict::Allow)
+    }
+}
+
+/// Moderates through a provider's moderation endpoint, such as OpenAI's.
+///
+/// Flagged content is blocked unless [`flag_only`](Self::flag_only) is set.
+pub struct ProviderModerator {
+    provider: Arc<dyn LLMProvider>,
+    block_flagged: bool,
+}
+
+impl ProviderModerator {
+    pub fn new(provider: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            provider,
+            block_flagged: true,
+        }
+    }
+
+    /// Logs flagged content instead of blocking it
+    pub fn flag_only(mut self) -> Self {
+        self.block_flagged = false;
+        self
+    }
+}
+
+#[async_trait]
+impl Moderator for ProviderModerator {
+    async fn check(&self, content: &str) -> Result<ModerationVerdict, InferenceError> {
+        let ModerationResult { flagged, categories } = self.provider.moderate(content.to_string()).await?;
+        Ok(match (flagged, self.block_flagged) {
+            (false, _) => ModerationVerdict::Allow,
+            (true, true) => ModerationVerdict::Block(categories),
+            (true, false) => ModerationVerdict::Flag(categories),
+        })
+    }
+}
+
+/// Wraps a provider so every request and response passes through a [`Moderator`].
+///
+/// A blocked prompt never reaches the inner provider.
+pub struct ModeratedProvider {
+    inner: Arc<dyn LLMProvider>,
+    moderator: Arc<dyn Moderator>,
+}
+
+impl ModeratedProvider {
+    pub fn new(inner: Arc<dyn LLMProvider>, moderator: Arc<dyn Moderator>) -> Self {
+        Self { inner, moderator }
+    }
+
+    async fn screen(&self, stage: &str, content: &str) -> Result<(), InferenceError> {
+        match self.moderator.check(content).await? {
+            ModerationVerdict::Allow => Ok(()),
+            ModerationVerdict::Flag(categories) => {
+                warn!(stage, ?categories, "Content flagged by moderation");
+                Ok(())
+            }
+            ModerationVerdict::Block(categories) => {
+                warn!(stage, ?categories, "Content blocked by moderation");
+                Err(InferenceError::ContentBlocked { categories })
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ModeratedProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let prompt: Vec<&str> = request.messages.iter().map(|m| m.content.as_str()).collect();
+        self.screen("prompt", &prompt.join("\n")).await?;
+
+        let response = self.inner.chat(request).await?;
+        self.screen("completion", &response.content).await?;
+        Ok(response)
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.inner.embed(inputs).await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.inner.embed_with_dimensions(inputs, dimensions).await
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        self.inner.moderate(input).await
+    }
+
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::test_support::CountingProvider;
+    use crate::inference::types::{Message, Role};
+
+    /// Blocks any content containing the word
+    struct KeywordModerator(&'static str);
+
+    #[async_trait]
+    impl Moderator for KeywordModerator {
+        async fn check(&self, content: &str) -> Result<ModerationVerdict, InferenceError> {
+            Ok(if content.contains(self.0) {
+                ModerationVerdict::Block(vec!["violence".to_string()])
+            } else {
+                ModerationVerdict::Allow
+            })
+        }
+    }
+
+    fn setup(reply: &'static str, moderator: Arc<dyn Moderator>) -> (ModeratedProvider, Arc<CountingProvider>) {
+        let inner = CountingProvider::replying(reply);
+        (ModeratedProvider::new(inner.clone(), moderator), inner)
+    }
+
+    fn request(content: &str) -> ChatRequest {
+        ChatRequest {
+            model: "test".to_string(),
+            messages: vec![Message::new(Role::User, content)],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocked_prompt_never_reaches_provider() {
+        let (provider, inner) = setup("ok", Arc::new(KeywordModerator("attack")));
+
+        let result = provider.chat(request("plan an attack")).await;
+        assert!(matches!(
+            result,
+            Err(InferenceError::ContentBlocked { categories }) if categories == ["violence"]
+        ));
+        assert_eq!(inner.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_completion_is_withheld() {
+        let (provider, inner) = setup("here is an attack", Arc::new(KeywordModerator("attack")));
+
+        let result = provider.chat(request("hello")).await;
+        assert!(matches!(result, Err(InferenceError::ContentBlocked { .. })));
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_noop_moderator_allows_everything() {
+        let (provider, _) = setup("attack", Arc::new(NoopModerator));
+        assert_eq!(provider.chat(request("attack")).await.unwrap().content, "attack");
+    }
+}
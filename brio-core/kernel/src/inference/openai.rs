@@ -1,11 +1,22 @@
-use crate::inference::provider::LLMProvider;
-use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, Message, Usage};
+use crate::inference::dimensions::{
+    reduce_embedding, supports_native_dimensions, validate_dimensions,
+};
+use crate::inference::provider::{LLMProvider, TokenStream};
+use crate::inference::retry::rand_jitter;
+use crate::inference::sse::SseDecoder;
+use crate::inference::types::{
+    ChatRequest, ChatResponse, InferenceError, Message, ModerationResult, ResponseFormat, Role,
+    Tool, ToolCall, Usage,
+};
+use crate::infrastructure::context;
 use anyhow::Result;
 use async_trait::async_trait;
 use reqwest::{Client, StatusCode, Url};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 
 /// Default maximum number of retries for transient errors
@@ -14,16 +25,157 @@ const DEFAULT_MAX_RETRIES: u32 = 3;
 const DEFAULT_BASE_DELAY_MS: u64 = 1000;
 /// Maximum delay cap (in milliseconds)
 const MAX_DELAY_MS: u64 = 30000;
+/// Longest error body kept in an [`InferenceError::Upstream`]
+const MAX_ERROR_BODY_BYTES: usize = 512;
+/// Default model used for embeddings requests
+const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
 
 #[derive(Serialize)]
 struct OpenAIChatRequest {
     model: String,
-    messages: Vec<Message>,
+    messages: Vec<OpenAIMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OpenAITool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<OpenAIResponseFormat>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+impl OpenAIChatRequest {
+    fn new(request: ChatRequest, stream: bool) -> Self {
+        Self {
+            model: request.model,
+            messages: request
+                .messages
+                .into_iter()
+                .map(OpenAIMessage::from)
+                .collect(),
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            tools: request
+                .tools
+                .into_iter()
+                .map(|function| OpenAITool {
+                    r#type: "function",
+                    function,
+                })
+                .collect(),
+            response_format: request
+                .response_format
+                .map(|format| OpenAIResponseFormat { r#type: format }),
+            stream,
+        }
+    }
+}
+
+/// A conversation turn as sent; `content` is null on an assistant turn
+/// that only calls tools
+#[derive(Serialize)]
+struct OpenAIMessage {
+    role: Role,
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenAIRequestToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl From<Message> for OpenAIMessage {
+    fn from(message: Message) -> Self {
+        let content = if message.content.is_empty() && !message.tool_calls.is_empty() {
+            None
+        } else {
+            Some(message.content)
+        };
+        Self {
+            role: message.role,
+            content,
+            tool_calls: message
+                .tool_calls
+                .into_iter()
+                .map(|call| OpenAIRequestToolCall {
+                    id: call.id,
+                    r#type: "function",
+                    function: OpenAIRequestFunctionCall {
+                        name: call.name,
+                        arguments: match call.arguments {
+                            serde_json::Value::String(raw) => raw,
+                            arguments => arguments.to_string(),
+                        },
+                    },
+                })
+                .collect(),
+            tool_call_id: message.tool_call_id,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OpenAIRequestToolCall {
+    id: String,
+    r#type: &'static str,
+    function: OpenAIRequestFunctionCall,
+}
+
+#[derive(Serialize)]
+struct OpenAIRequestFunctionCall {
+    name: String,
+    /// JSON-encoded, as the model generated them
+    arguments: String,
+}
+
+#[derive(Serialize)]
+struct OpenAITool {
+    r#type: &'static str,
+    function: Tool,
+}
+
+#[derive(Serialize)]
+struct OpenAIResponseFormat {
+    r#type: ResponseFormat,
 }
 
 #[derive(Deserialize)]
 struct OpenAIChoice {
-    message: Message,
+    message: OpenAIResponseMessage,
+}
+
+/// Assistant message in a response; `content` is null when the model only calls tools
+#[derive(Deserialize)]
+struct OpenAIResponseMessage {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAIToolCall>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIToolCall {
+    id: String,
+    function: OpenAIFunctionCall,
+}
+
+#[derive(Deserialize)]
+struct OpenAIFunctionCall {
+    name: String,
+    /// JSON-encoded arguments, as generated by the model
+    arguments: String,
+}
+
+impl From<&OpenAIToolCall> for ToolCall {
+    fn from(call: &OpenAIToolCall) -> Self {
+        let raw = &call.function.arguments;
+        Self {
+            id: call.id.clone(),
+            name: call.function.name.clone(),
+            arguments: serde_json::from_str(raw)
+                .unwrap_or_else(|_| serde_json::Value::String(raw.clone())),
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -39,6 +191,102 @@ struct OpenAIChatResponse {
     usage: Option<OpenAIUsage>,
 }
 
+/// One `data` event of a streamed completion
+#[derive(Deserialize)]
+struct OpenAIStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenAIStreamChoice>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChoice {
+    delta: OpenAIStreamDelta,
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamDelta {
+    content: Option<String>,
+}
+
+/// Where a streamed completion has got to
+enum StreamState {
+    Connecting(OpenAIChatRequest),
+    Reading {
+        response: reqwest::Response,
+        decoder: SseDecoder,
+        events: VecDeque<String>,
+    },
+    Finished,
+}
+
+#[derive(Serialize)]
+struct OpenAIEmbeddingsRequest {
+    model: String,
+    input: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIEmbedding {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct OpenAIEmbeddingsResponse {
+    data: Vec<OpenAIEmbedding>,
+}
+
+#[derive(Serialize)]
+struct OpenAIModerationRequest {
+    input: String,
+}
+
+#[derive(Deserialize)]
+struct OpenAIModeration {
+    flagged: bool,
+    categories: std::collections::BTreeMap<String, bool>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIModerationResponse {
+    results: Vec<OpenAIModeration>,
+}
+
+/// Turns the JSON body of a successful completion into a [`ChatResponse`].
+pub type ResponseMapper =
+    Arc<dyn Fn(serde_json::Value) -> Result<ChatResponse, InferenceError> + Send + Sync>;
+
+/// Reads a completion in the standard OpenAI shape; the default [`ResponseMapper`].
+///
+/// Custom mappers for gateways that wrap or rename parts of the response
+/// can reshape the body and hand it to this.
+pub fn standard_response(body: serde_json::Value) -> Result<ChatResponse, InferenceError> {
+    let body: OpenAIChatResponse = serde_json::from_value(body)
+        .map_err(|e| InferenceError::ProviderError(format!("Parse error: {}", e)))?;
+
+    let choice = body
+        .choices
+        .first()
+        .ok_or_else(|| InferenceError::ProviderError("No choices returned".to_string()))?;
+
+    Ok(ChatResponse {
+        content: choice.message.content.clone().unwrap_or_default(),
+        usage: body.usage.map(|u| Usage {
+            prompt_tokens: u.prompt_tokens,
+            completion_tokens: u.completion_tokens,
+            total_tokens: u.total_tokens,
+        }),
+        tool_calls: choice
+            .message
+            .tool_calls
+            .iter()
+            .map(ToolCall::from)
+            .collect(),
+    })
+}
+
 /// Configuration for the OpenAI provider
 pub struct OpenAIConfig {
     pub api_key: SecretString,
@@ -47,6 +295,14 @@ pub struct OpenAIConfig {
     pub max_retries: Option<u32>,
     /// Base delay in milliseconds for exponential backoff
     pub base_delay_ms: Option<u64>,
+    /// Model used for embeddings requests
+    pub embedding_model: Option<String>,
+    /// Size embeddings are reduced to when the caller doesn't ask for one
+    pub embedding_dimensions: Option<usize>,
+    /// Completions taking longer than this are logged as warnings
+    pub slow_request_threshold: Option<Duration>,
+    /// Reads completion bodies; [`standard_response`] when unset
+    pub response_mapper: Option<ResponseMapper>,
 }
 
 impl OpenAIConfig {
@@ -57,6 +313,10 @@ impl OpenAIConfig {
             base_url,
             max_retries: None,
             base_delay_ms: None,
+            embedding_model: None,
+            embedding_dimensions: None,
+            slow_request_threshold: None,
+            response_mapper: None,
         }
     }
 
@@ -71,6 +331,36 @@ impl OpenAIConfig {
         self.base_delay_ms = Some(delay_ms);
         self
     }
+
+    /// Logs a warning for each completion slower than `threshold`, including
+    /// retries; prompts are never logged, only their size
+    pub fn with_slow_request_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_request_threshold = Some(threshold);
+        self
+    }
+
+    /// Reads completion bodies with `mapper`, for OpenAI-compatible endpoints
+    /// whose responses are off-spec. Errors it returns are not retried.
+    pub fn with_response_mapper<F>(mut self, mapper: F) -> Self
+    where
+        F: Fn(serde_json::Value) -> Result<ChatResponse, InferenceError> + Send + Sync + 'static,
+    {
+        self.response_mapper = Some(Arc::new(mapper));
+        self
+    }
+
+    /// Sets the model used for embeddings requests
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = Some(model.into());
+        self
+    }
+
+    /// Reduces embeddings to `dimensions` by default, trading accuracy for
+    /// storage; checked against the model's size on each request
+    pub fn with_embedding_dimensions(mut self, dimensions: usize) -> Self {
+        self.embedding_dimensions = Some(dimensions);
+        self
+    }
 }
 
 pub struct OpenAIProvider {
@@ -78,16 +368,22 @@ pub struct OpenAIProvider {
     config: OpenAIConfig,
     max_retries: u32,
     base_delay_ms: u64,
+    embedding_model: String,
 }
 
 impl OpenAIProvider {
     pub fn new(config: OpenAIConfig) -> Self {
         let max_retries = config.max_retries.unwrap_or(DEFAULT_MAX_RETRIES);
         let base_delay_ms = config.base_delay_ms.unwrap_or(DEFAULT_BASE_DELAY_MS);
+        let embedding_model = config
+            .embedding_model
+            .clone()
+            .unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string());
         Self {
             client: Client::new(),
             max_retries,
             base_delay_ms,
+            embedding_model,
             config,
         }
     }
@@ -103,21 +399,19 @@ impl OpenAIProvider {
         Duration::from_millis(capped_delay + jitter)
     }
 
-    /// Makes a single request attempt
-    async fn make_request(
+    /// Sends a single POST to `endpoint`, returning the response on HTTP 200.
+    /// Errors carry a flag telling the retry loop whether to try again.
+    async fn post_json<B: Serialize>(
         &self,
-        provider_req: &OpenAIChatRequest,
-    ) -> Result<ChatResponse, (InferenceError, bool)> {
-        let url = self
-            .config
-            .base_url
-            .join("chat/completions")
-            .map_err(|e| {
-                (
-                    InferenceError::ConfigError(format!("Invalid URL join: {}", e)),
-                    false, // Don't retry config errors
-                )
-            })?;
+        endpoint: &str,
+        body: &B,
+    ) -> Result<reqwest::Response, (InferenceError, bool)> {
+        let url = self.config.base_url.join(endpoint).map_err(|e| {
+            (
+                InferenceError::ConfigError(format!("Invalid URL join: {}", e)),
+                false, // Don't retry config errors
+            )
+        })?;
 
         let res = self
             .client
@@ -127,7 +421,7 @@ impl OpenAIProvider {
                 format!("Bearer {}", self.config.api_key.expose_secret()),
             )
             .header("Content-Type", "application/json")
-            .json(provider_req)
+            .json(body)
             .send()
             .await
             .map_err(|e| {
@@ -137,43 +431,33 @@ impl OpenAIProvider {
                 )
             })?;
 
+        if let Some(retry_after) = retry_after(&res)
+            && matches!(
+                res.status(),
+                StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+            )
+        {
+            let status = res.status().as_u16();
+            return Err((
+                InferenceError::Throttled {
+                    status,
+                    retry_after,
+                },
+                true,
+            ));
+        }
+
         match res.status() {
-            StatusCode::OK => {
-                let body: OpenAIChatResponse = res.json().await.map_err(|e| {
-                    (
-                        InferenceError::ProviderError(format!("Parse error: {}", e)),
-                        false, // Don't retry parse errors
-                    )
-                })?;
-
-                let choice = body.choices.first().ok_or_else(|| {
-                    (
-                        InferenceError::ProviderError("No choices returned".to_string()),
-                        false,
-                    )
-                })?;
-
-                Ok(ChatResponse {
-                    content: choice.message.content.clone(),
-                    usage: body.usage.map(|u| Usage {
-                        prompt_tokens: u.prompt_tokens,
-                        completion_tokens: u.completion_tokens,
-                        total_tokens: u.total_tokens,
-                    }),
-                })
-            }
+            StatusCode::OK => Ok(res),
             StatusCode::TOO_MANY_REQUESTS => {
                 Err((InferenceError::RateLimit, true)) // Retry rate limits
             }
             StatusCode::BAD_REQUEST => {
                 let text = res.text().await.unwrap_or_default();
-                if text.contains("context_length_exceeded") {
-                    Err((InferenceError::ContextLengthExceeded, false))
+                if let Some(err) = parse_context_length_error(&text) {
+                    Err((err, false))
                 } else {
-                    Err((
-                        InferenceError::ProviderError(format!("Bad Request: {}", text)),
-                        false,
-                    ))
+                    Err((error_from_body(StatusCode::BAD_REQUEST, text), false))
                 }
             }
             StatusCode::INTERNAL_SERVER_ERROR
@@ -183,7 +467,7 @@ impl OpenAIProvider {
                 let status = res.status();
                 let text = res.text().await.unwrap_or_default();
                 Err((
-                    InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
+                    error_from_body(status, text),
                     true, // Retry server errors
                 ))
             }
@@ -191,26 +475,106 @@ impl OpenAIProvider {
                 let status = res.status();
                 let text = res.text().await.unwrap_or_default();
                 Err((
-                    InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
+                    error_from_body(status, text),
                     false, // Don't retry other errors
                 ))
             }
         }
     }
-}
 
-#[async_trait]
-impl LLMProvider for OpenAIProvider {
-    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
-        let provider_req = OpenAIChatRequest {
-            model: request.model,
-            messages: request.messages,
-        };
+    /// Makes a single chat request attempt
+    async fn make_request(
+        &self,
+        provider_req: &OpenAIChatRequest,
+    ) -> Result<ChatResponse, (InferenceError, bool)> {
+        let res = self.post_json("chat/completions", provider_req).await?;
+
+        let body: serde_json::Value = res.json().await.map_err(|e| {
+            (
+                InferenceError::ProviderError(format!("Parse error: {}", e)),
+                false, // Don't retry parse errors
+            )
+        })?;
+
+        match &self.config.response_mapper {
+            Some(mapper) => mapper(body),
+            None => standard_response(body),
+        }
+        .map_err(|e| (e, false))
+    }
+
+    /// Makes a single embeddings request attempt
+    async fn make_embeddings_request(
+        &self,
+        provider_req: &OpenAIEmbeddingsRequest,
+    ) -> Result<Vec<Vec<f32>>, (InferenceError, bool)> {
+        let res = self.post_json("embeddings", provider_req).await?;
+
+        let mut body: OpenAIEmbeddingsResponse = res.json().await.map_err(|e| {
+            (
+                InferenceError::ProviderError(format!("Parse error: {}", e)),
+                false,
+            )
+        })?;
+
+        if body.data.len() != provider_req.input.len() {
+            return Err((
+                InferenceError::ProviderError(format!(
+                    "Expected {} embeddings, got {}",
+                    provider_req.input.len(),
+                    body.data.len()
+                )),
+                false,
+            ));
+        }
 
+        // The API does not guarantee ordering, so restore input order
+        body.data.sort_by_key(|e| e.index);
+        Ok(body.data.into_iter().map(|e| e.embedding).collect())
+    }
+
+    /// Makes a single moderation request attempt
+    async fn make_moderation_request(
+        &self,
+        provider_req: &OpenAIModerationRequest,
+    ) -> Result<ModerationResult, (InferenceError, bool)> {
+        let res = self.post_json("moderations", provider_req).await?;
+
+        let body: OpenAIModerationResponse = res.json().await.map_err(|e| {
+            (
+                InferenceError::ProviderError(format!("Parse error: {}", e)),
+                false,
+            )
+        })?;
+
+        let result = body.results.into_iter().next().ok_or_else(|| {
+            (
+                InferenceError::ProviderError("No moderation results returned".to_string()),
+                false,
+            )
+        })?;
+
+        Ok(ModerationResult {
+            flagged: result.flagged,
+            categories: result
+                .categories
+                .into_iter()
+                .filter_map(|(name, hit)| hit.then_some(name))
+                .collect(),
+        })
+    }
+
+    /// Repeats `attempt_fn` with backoff until it succeeds, fails with a
+    /// non-retryable error, or the retry limit is reached.
+    async fn with_retries<T, F, Fut>(&self, mut attempt_fn: F) -> Result<T, InferenceError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, (InferenceError, bool)>>,
+    {
         let mut last_error = InferenceError::NetworkError("No attempts made".to_string());
 
         for attempt in 0..=self.max_retries {
-            match self.make_request(&provider_req).await {
+            match attempt_fn().await {
                 Ok(response) => return Ok(response),
                 Err((error, should_retry)) => {
                     last_error = error;
@@ -219,7 +583,15 @@ impl LLMProvider for OpenAIProvider {
                         break;
                     }
 
-                    let delay = self.calculate_backoff_delay(attempt);
+                    if !context::try_consume_retry() {
+                        warn!(error = %last_error, "Request retry budget exhausted, giving up");
+                        break;
+                    }
+
+                    let delay = last_error
+                        .retry_after()
+                        .map(|wait| wait.min(Duration::from_millis(MAX_DELAY_MS)))
+                        .unwrap_or_else(|| self.calculate_backoff_delay(attempt));
                     warn!(
                         attempt = attempt + 1,
                         max_retries = self.max_retries,
@@ -240,15 +612,265 @@ impl LLMProvider for OpenAIProvider {
     }
 }
 
-/// Simple pseudo-random jitter between 0.0 and 1.0
-/// Uses system time for simplicity (no external crate needed)
-fn rand_jitter() -> f64 {
-    use std::time::SystemTime;
-    let nanos = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map(|d| d.subsec_nanos())
-        .unwrap_or(0);
-    (nanos % 1000) as f64 / 1000.0
+#[async_trait]
+impl LLMProvider for OpenAIProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let started = Instant::now();
+        let provider_req = OpenAIChatRequest::new(request, false);
+
+        let result = self.with_retries(|| self.make_request(&provider_req)).await;
+
+        let latency = started.elapsed();
+        if let Some(threshold) = self.config.slow_request_threshold
+            && latency > threshold
+        {
+            let usage = result.as_ref().ok().and_then(|r| r.usage.as_ref());
+            warn!(
+                model = %provider_req.model,
+                latency_ms = latency.as_millis() as u64,
+                threshold_ms = threshold.as_millis() as u64,
+                prompt_messages = provider_req.messages.len(),
+                prompt_chars = provider_req.messages.iter().map(|m| m.content.as_ref().map_or(0, String::len)).sum::<usize>(),
+                prompt_tokens = usage.map(|u| u.prompt_tokens),
+                completion_tokens = usage.map(|u| u.completion_tokens),
+                succeeded = result.is_ok(),
+                "Slow completion request"
+            );
+        }
+        result
+    }
+
+    /// Requests a server-sent event stream and yields each content delta as
+    /// it arrives. Only the initial request is retried; a stream that breaks
+    /// off part way ends with an error.
+    fn stream_completion<'a>(&'a self, request: ChatRequest) -> TokenStream<'a> {
+        let state = StreamState::Connecting(OpenAIChatRequest::new(request, true));
+
+        Box::pin(futures_util::stream::unfold(
+            state,
+            move |mut state| async move {
+                loop {
+                    state = match state {
+                        StreamState::Connecting(provider_req) => {
+                            match self
+                                .with_retries(|| self.post_json("chat/completions", &provider_req))
+                                .await
+                            {
+                                Ok(response) => StreamState::Reading {
+                                    response,
+                                    decoder: SseDecoder::new(),
+                                    events: VecDeque::new(),
+                                },
+                                Err(e) => return Some((Err(e), StreamState::Finished)),
+                            }
+                        }
+                        StreamState::Reading {
+                            mut response,
+                            mut decoder,
+                            mut events,
+                        } => {
+                            if let Some(data) = events.pop_front() {
+                                if data == "[DONE]" {
+                                    return None;
+                                }
+                                match stream_delta(&data) {
+                                    Ok(Some(token)) => {
+                                        let state = StreamState::Reading {
+                                            response,
+                                            decoder,
+                                            events,
+                                        };
+                                        return Some((Ok(token), state));
+                                    }
+                                    Ok(None) => StreamState::Reading {
+                                        response,
+                                        decoder,
+                                        events,
+                                    },
+                                    Err(e) => return Some((Err(e), StreamState::Finished)),
+                                }
+                            } else {
+                                match response.chunk().await {
+                                    Ok(Some(bytes)) => {
+                                        events.extend(decoder.push(&bytes));
+                                        StreamState::Reading {
+                                            response,
+                                            decoder,
+                                            events,
+                                        }
+                                    }
+                                    Ok(None) => return None,
+                                    Err(e) => {
+                                        let error = InferenceError::NetworkError(e.to_string());
+                                        return Some((Err(error), StreamState::Finished));
+                                    }
+                                }
+                            }
+                        }
+                        StreamState::Finished => return None,
+                    };
+                }
+            },
+        ))
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        if let Some(dimensions) = self.config.embedding_dimensions {
+            return self.embed_with_dimensions(inputs, dimensions).await;
+        }
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let provider_req = OpenAIEmbeddingsRequest {
+            model: self.embedding_model.clone(),
+            input: inputs,
+            dimensions: None,
+        };
+
+        self.with_retries(|| self.make_embeddings_request(&provider_req))
+            .await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        validate_dimensions(&self.embedding_model, dimensions)?;
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Models that can't shorten natively are embedded whole and reduced below
+        let provider_req = OpenAIEmbeddingsRequest {
+            model: self.embedding_model.clone(),
+            input: inputs,
+            dimensions: supports_native_dimensions(&self.embedding_model).then_some(dimensions),
+        };
+
+        self.with_retries(|| self.make_embeddings_request(&provider_req))
+            .await?
+            .into_iter()
+            .map(|vector| reduce_embedding(vector, dimensions))
+            .collect()
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        let provider_req = OpenAIModerationRequest { input };
+        self.with_retries(|| self.make_moderation_request(&provider_req))
+            .await
+    }
+
+    /// Lists the available models, which needs a reachable API and a valid key.
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        let url = self
+            .config
+            .base_url
+            .join("models")
+            .map_err(|e| InferenceError::ConfigError(format!("Invalid URL join: {}", e)))?;
+
+        let res = self
+            .client
+            .get(url)
+            .header(
+                "Authorization",
+                format!("Bearer {}", self.config.api_key.expose_secret()),
+            )
+            .send()
+            .await
+            .map_err(|e| InferenceError::NetworkError(e.to_string()))?;
+
+        match res.status() {
+            StatusCode::OK => Ok(()),
+            status => Err(error_from_body(
+                status,
+                res.text().await.unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// Text carried by one streamed completion event, if it has any.
+fn stream_delta(data: &str) -> Result<Option<String>, InferenceError> {
+    let chunk: OpenAIStreamChunk = serde_json::from_str(data)
+        .map_err(|e| InferenceError::ProviderError(format!("Parse error: {}", e)))?;
+    Ok(chunk
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.delta.content)
+        .filter(|content| !content.is_empty()))
+}
+
+/// Builds the error for a failed response, using the API's JSON error message
+/// when there is one and keeping the raw (truncated) body otherwise.
+fn error_from_body(status: StatusCode, text: String) -> InferenceError {
+    match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(json) => {
+            let message = json["error"]["message"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or(text);
+            InferenceError::Http {
+                status: status.as_u16(),
+                message,
+            }
+        }
+        Err(_) => {
+            let mut body = text;
+            if body.len() > MAX_ERROR_BODY_BYTES {
+                let mut end = MAX_ERROR_BODY_BYTES;
+                while !body.is_char_boundary(end) {
+                    end -= 1;
+                }
+                body.truncate(end);
+                body.push_str("...");
+            }
+            InferenceError::Upstream {
+                status: status.as_u16(),
+                body,
+            }
+        }
+    }
+}
+
+/// Recognises a context-length-exceeded error body, extracting the token
+/// counts when present, e.g. "This model's maximum context length is 8192
+/// tokens. However, you requested 9000 tokens (...)".
+fn parse_context_length_error(text: &str) -> Option<InferenceError> {
+    let lower = text.to_lowercase();
+    if !lower.contains("context_length_exceeded") && !lower.contains("maximum context length") {
+        return None;
+    }
+
+    let limit = number_after(&lower, "maximum context length is");
+    let requested =
+        number_after(&lower, "you requested").or_else(|| number_after(&lower, "resulted in"));
+    Some(InferenceError::ContextLengthExceeded { limit, requested })
+}
+
+/// Parses the first integer following `marker`, skipping words like "about".
+fn number_after(text: &str, marker: &str) -> Option<u32> {
+    let rest = &text[text.find(marker)? + marker.len()..];
+    let digits: String = rest
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// Delay requested by a response's `Retry-After` header, in whole seconds.
+///
+/// The HTTP-date form isn't used by the APIs we talk to and is ignored.
+fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    let value = res
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
 }
 
 #[cfg(test)]
@@ -317,4 +939,69 @@ mod tests {
         assert!(delay1.as_millis() >= 2000);
         assert!(delay1.as_millis() <= 2500);
     }
+
+    #[test]
+    fn test_error_from_body_prefers_json_message() {
+        let err = error_from_body(
+            StatusCode::BAD_REQUEST,
+            r#"{"error":{"message":"invalid model"}}"#.to_string(),
+        );
+        assert!(
+            matches!(err, InferenceError::Http { status: 400, message } if message.contains("invalid model"))
+        );
+    }
+
+    #[test]
+    fn test_error_from_body_truncates_raw_body() {
+        let err = error_from_body(StatusCode::BAD_GATEWAY, "é".repeat(MAX_ERROR_BODY_BYTES));
+        match err {
+            InferenceError::Upstream { status, body } => {
+                assert_eq!(status, 502);
+                assert!(body.len() <= MAX_ERROR_BODY_BYTES + 3);
+                assert!(body.ends_with("..."));
+            }
+            other => panic!("Expected Upstream, got {:?}", other),
+        }
+    }
+
+    fn context_limits(text: &str) -> Option<(Option<u32>, Option<u32>)> {
+        match parse_context_length_error(text)? {
+            InferenceError::ContextLengthExceeded { limit, requested } => Some((limit, requested)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_context_length_error_formats() {
+        // OpenAI
+        assert_eq!(
+            context_limits(
+                r#"{"error":{"message":"This model's maximum context length is 8192 tokens. However, you requested 9000 tokens (8000 in the messages, 1000 in the completion). Please reduce the length of the messages or completion.","code":"context_length_exceeded"}}"#
+            ),
+            Some((Some(8192), Some(9000)))
+        );
+        // Older OpenAI wording
+        assert_eq!(
+            context_limits(
+                "This model's maximum context length is 4097 tokens, however your messages resulted in 5000 tokens."
+            ),
+            Some((Some(4097), Some(5000)))
+        );
+        // OpenRouter
+        assert_eq!(
+            context_limits(
+                "This endpoint's maximum context length is 128000 tokens. However, you requested about 130500 tokens"
+            ),
+            Some((Some(128000), Some(130500)))
+        );
+        // Error code only
+        assert_eq!(
+            context_limits(r#"{"error":{"code":"context_length_exceeded"}}"#),
+            Some((None, None))
+        );
+        assert_eq!(
+            context_limits(r#"{"error":{"message":"invalid model"}}"#),
+            None
+        );
+    }
 }
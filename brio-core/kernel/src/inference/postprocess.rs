@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+/// A cleanup step applied to completion text before it is returned.
+///
+/// Transforms run in the order listed on the request, so e.g.
+/// `[StripCodeFences, Trim]` trims the contents of the fenced block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    /// Remove leading and trailing whitespace
+    Trim,
+    /// Replace the text with the contents of its first ``` fenced block, if any
+    StripCodeFences,
+    /// Replace the text with its first complete JSON object or array, if any
+    ExtractJson,
+}
+
+impl Transform {
+    pub fn apply(self, text: String) -> String {
+        match self {
+            Transform::Trim => text.trim().to_string(),
+            Transform::StripCodeFences => strip_code_fences(&text).unwrap_or(text),
+            Transform::ExtractJson => extract_json(&text).map(str::to_string).unwrap_or(text),
+        }
+    }
+}
+
+/// Runs `transforms` over `text` in order.
+pub fn apply_transforms(transforms: &[Transform], text: String) -> String {
+    transforms.iter().fold(text, |text, transform| transform.apply(text))
+}
+
+fn strip_code_fences(text: &str) -> Option<String> {
+    let open = text.find("```")?;
+    // The opening fence line may carry a language tag, e.g. ```json
+    let body_start = open + text[open..].find('\n')? + 1;
+    let body = &text[body_start..];
+    let close = body.find("```").unwrap_or(body.len());
+    Some(body[..close].trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// Finds the first balanced `{...}` or `[...]`, skipping brackets inside strings.
+fn extract_json(text: &str) -> Option<&str> {
+    let start = text.find(['{', '['])?;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (offset, c) in text[start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[start..start + offset + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fenced_json_comes_back_clean() {
+        let completion = "Here you go:\n\n```json\n{\"status\": \"ok\", \"items\": [1, 2]}\n```\n".to_string();
+        let cleaned = apply_transforms(&[Transform::StripCodeFences, Transform::Trim], completion);
+
+        assert_eq!(cleaned, "{\"status\": \"ok\", \"items\": [1, 2]}");
+        assert!(serde_json::from_str::<serde_json::Value>(&cleaned).is_ok());
+    }
+
+    #[test]
+    fn test_extract_json_skips_prose_and_brackets_in_strings() {
+        let completion = "Sure! {\"note\": \"a } inside\", \"n\": {\"x\": 1}} Hope that helps.".to_string();
+        assert_eq!(
+            Transform::ExtractJson.apply(completion),
+            "{\"note\": \"a } inside\", \"n\": {\"x\": 1}}"
+        );
+    }
+
+    #[test]
+    fn test_transforms_leave_unmatched_text_alone() {
+        assert_eq!(Transform::StripCodeFences.apply("plain".to_string()), "plain");
+        assert_eq!(Transform::ExtractJson.apply("{ unterminated".to_string()), "{ unterminated");
+        assert_eq!(apply_transforms(&[], "  kept  ".to_string()), "  kept  ");
+    }
+}
@@ -1,8 +1,64 @@
-use crate::inference::types::{ChatRequest, ChatResponse, InferenceError};
+use crate::inference::dimensions::reduce_embedding;
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ModerationResult};
 use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream};
+
+/// Pieces of a completion's text, in the order the provider produced them.
+pub type TokenStream<'a> = BoxStream<'a, Result<String, InferenceError>>;
 
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
     /// Executes a chat completion request.
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError>;
+
+    /// Streams a chat completion's text as the provider generates it.
+    ///
+    /// The default waits for [`chat`](Self::chat) and yields the whole
+    /// response as a single item. An error ends the stream.
+    fn stream_completion<'a>(&'a self, request: ChatRequest) -> TokenStream<'a> {
+        Box::pin(stream::once(async move {
+            self.chat(request).await.map(|response| response.content)
+        }))
+    }
+
+    /// Computes one embedding vector per input.
+    ///
+    /// Providers without an embeddings endpoint keep the default, which
+    /// reports the operation as unsupported.
+    async fn embed(&self, _inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        Err(InferenceError::Unsupported(
+            "Embeddings not supported by this provider".to_string(),
+        ))
+    }
+
+    /// Computes one embedding per input, each `dimensions` long.
+    ///
+    /// The default embeds at full size and reduces each vector locally;
+    /// providers whose API can shorten embeddings should pass the size through.
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.embed(inputs)
+            .await?
+            .into_iter()
+            .map(|vector| reduce_embedding(vector, dimensions))
+            .collect()
+    }
+
+    /// Checks that the provider is reachable and accepting requests.
+    ///
+    /// The default assumes it is; providers backed by a remote API should
+    /// make a cheap authenticated call.
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        Ok(())
+    }
+
+    /// Screens text against the provider's moderation endpoint.
+    async fn moderate(&self, _input: String) -> Result<ModerationResult, InferenceError> {
+        Err(InferenceError::Unsupported(
+            "Moderation not supported by this provider".to_string(),
+        ))
+    }
 }
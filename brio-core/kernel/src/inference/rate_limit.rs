@@ -0,0 +1,234 @@
+use crate::inference::history::estimate_tokens;
+use crate::inference::types::{ChatRequest, InferenceError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Limits applied to one tenant's inference calls; unset fields are unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct TenantLimits {
+    pub requests_per_sec: Option<f64>,
+    pub tokens_per_min: Option<u64>,
+}
+
+/// Token bucket refilled continuously up to its capacity.
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn new(capacity: f64, refill_per_sec: f64, now: Instant) -> Self {
+        Self {
+            capacity,
+            available: capacity,
+            refill_per_sec,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.available = (self.available + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Time until `amount` can be taken, or zero if it can be taken now.
+    ///
+    /// Amounts above the capacity only need a full bucket, and then leave it
+    /// in debt, so oversized requests are slowed rather than refused forever.
+    fn wait_for(&self, amount: f64) -> Duration {
+        let needed = amount.min(self.capacity) - self.available;
+        if needed <= 0.0 || self.refill_per_sec <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(needed / self.refill_per_sec)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TenantState {
+    limits: TenantLimits,
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+}
+
+impl TenantState {
+    fn new(limits: TenantLimits) -> Self {
+        let now = Instant::now();
+        Self {
+            limits,
+            requests: limits
+                .requests_per_sec
+                .map(|rps| Bucket::new(rps.max(1.0), rps, now)),
+            tokens: limits
+                .tokens_per_min
+                .map(|tpm| Bucket::new(tpm as f64, tpm as f64 / 60.0, now)),
+        }
+    }
+}
+
+/// Per-tenant token-bucket limits on inference calls.
+///
+/// Tenants without configured limits are never throttled. Limits can be
+/// changed while running; a tenant's buckets restart full when its limits
+/// change.
+#[derive(Debug, Default)]
+pub struct TenantRateLimiter {
+    tenants: Mutex<HashMap<String, TenantState>>,
+}
+
+impl TenantRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_limits(&self, tenant: impl Into<String>, limits: TenantLimits) {
+        let tenant = tenant.into();
+        debug!(tenant = %tenant, ?limits, "Setting tenant rate limits");
+        let mut tenants = self.tenants.lock().expect("Mutex poisoned");
+        tenants.insert(tenant, TenantState::new(limits));
+    }
+
+    /// Removes a tenant's limits, returning whether it had any.
+    pub fn remove_limits(&self, tenant: &str) -> bool {
+        let mut tenants = self.tenants.lock().expect("Mutex poisoned");
+        tenants.remove(tenant).is_some()
+    }
+
+    pub fn limits(&self, tenant: &str) -> Option<TenantLimits> {
+        let tenants = self.tenants.lock().expect("Mutex poisoned");
+        tenants.get(tenant).map(|state| state.limits)
+    }
+
+    /// Admits one request for `tenant`, charging its estimated prompt tokens.
+    ///
+    /// Nothing is charged when the request is refused.
+    pub fn acquire(&self, tenant: &str, request: &ChatRequest) -> Result<(), InferenceError> {
+        let mut tenants = self.tenants.lock().expect("Mutex poisoned");
+        let Some(state) = tenants.get_mut(tenant) else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let tokens: usize = request.messages.iter().map(estimate_tokens).sum();
+        let tokens = tokens as f64;
+
+        let mut retry_after = Duration::ZERO;
+        if let Some(bucket) = state.requests.as_mut() {
+            bucket.refill(now);
+            retry_after = retry_after.max(bucket.wait_for(1.0));
+        }
+        if let Some(bucket) = state.tokens.as_mut() {
+            bucket.refill(now);
+            retry_after = retry_after.max(bucket.wait_for(tokens));
+        }
+        if !retry_after.is_zero() {
+            debug!(tenant, retry_after_ms = retry_after.as_millis() as u64, "Tenant rate limited");
+            return Err(InferenceError::RateLimited { retry_after });
+        }
+
+        if let Some(bucket) = state.requests.as_mut() {
+            bucket.available -= 1.0;
+        }
+        if let Some(bucket) = state.tokens.as_mut() {
+            bucket.available -= tokens;
+        }
+        Ok(())
+    }
+
+    /// Charges tokens only known after the call, such as the completion.
+    pub fn record_tokens(&self, tenant: &str, tokens: u32) {
+        let mut tenants = self.tenants.lock().expect("Mutex poisoned");
+        if let Some(bucket) = tenants.get_mut(tenant).and_then(|s| s.tokens.as_mut()) {
+            bucket.available -= tokens as f64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::types::{Message, Role};
+
+    fn request(content: &str) -> ChatRequest {
+        ChatRequest {
+            model: "test".to_string(),
+            messages: vec![Message::new(Role::User, content)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_throttled_tenant_does_not_affect_others() {
+        let limiter = TenantRateLimiter::new();
+        let limits = TenantLimits {
+            requests_per_sec: Some(1.0),
+            tokens_per_min: None,
+        };
+        limiter.set_limits("noisy", limits);
+        limiter.set_limits("quiet", limits);
+
+        assert!(limiter.acquire("noisy", &request("hi")).is_ok());
+        match limiter.acquire("noisy", &request("hi")) {
+            Err(InferenceError::RateLimited { retry_after }) => {
+                assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(1));
+            }
+            other => panic!("Expected RateLimited, got {:?}", other),
+        }
+
+        assert!(limiter.acquire("quiet", &request("hi")).is_ok());
+        assert!(limiter.acquire("unlimited", &request("hi")).is_ok());
+    }
+
+    #[test]
+    fn test_token_budget_limits_large_prompts() {
+        let limiter = TenantRateLimiter::new();
+        limiter.set_limits(
+            "tenant",
+            TenantLimits {
+                requests_per_sec: None,
+                tokens_per_min: Some(10),
+            },
+        );
+
+        // 40 chars is ~10 tokens, which spends the whole minute's budget
+        assert!(limiter.acquire("tenant", &request(&"x".repeat(40))).is_ok());
+        assert!(matches!(
+            limiter.acquire("tenant", &request("x")),
+            Err(InferenceError::RateLimited { .. })
+        ));
+    }
+
+    #[test]
+    fn test_limits_update_at_runtime() {
+        let limiter = TenantRateLimiter::new();
+        limiter.set_limits(
+            "tenant",
+            TenantLimits {
+                requests_per_sec: Some(1.0),
+                tokens_per_min: None,
+            },
+        );
+        assert!(limiter.acquire("tenant", &request("hi")).is_ok());
+        assert!(limiter.acquire("tenant", &request("hi")).is_err());
+
+        limiter.set_limits(
+            "tenant",
+            TenantLimits {
+                requests_per_sec: Some(100.0),
+                tokens_per_min: None,
+            },
+        );
+        assert!(limiter.acquire("tenant", &request("hi")).is_ok());
+
+        assert!(limiter.remove_limits("tenant"));
+        assert!(limiter.limits("tenant").is_none());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::inference::types::ChatRequest;
+use crate::store::{SqlStore, StoreError, TypedStore};
+
+/// Scope whose `recorded_kv` table holds recorded requests
+pub const RECORDING_SCOPE: &str = "recorded";
+
+/// Stands in for redacted text in a recorded request
+const REDACTED: &str = "[REDACTED]";
+
+/// Persists completion requests under an id so they can be replayed exactly.
+///
+/// Requests are kept whole (model, messages, parameters and tools), except
+/// that every occurrence of a redacted secret in a message is replaced before
+/// anything is written; a replay sends the redacted text. The provider that
+/// answered isn't part of a recording.
+pub struct RequestRecorder {
+    store: TypedStore<ChatRequest>,
+    redactions: Vec<String>,
+}
+
+impl RequestRecorder {
+    pub fn new(store: SqlStore) -> Self {
+        Self {
+            store: TypedStore::new(store, RECORDING_SCOPE),
+            redactions: Vec::new(),
+        }
+    }
+
+    /// Strings, such as API keys, that must never be written to a recording.
+    pub fn with_redactions(mut self, secrets: Vec<String>) -> Self {
+        self.redactions = secrets.into_iter().filter(|s| !s.is_empty()).collect();
+        self
+    }
+
+    /// Creates the recording table if it doesn't exist yet.
+    pub async fn ensure_table(&self) -> Result<(), StoreError> {
+        self.store.ensure_table().await
+    }
+
+    /// Stores `request` with secrets redacted, returning the id to replay it by.
+    pub async fn record(&self, request: &ChatRequest) -> Result<String, StoreError> {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        self.store.put(&request_id, &self.redact(request)).await?;
+        Ok(request_id)
+    }
+
+    /// The request recorded as `request_id`, if there is one.
+    pub async fn load(&self, request_id: &str) -> Result<Option<ChatRequest>, StoreError> {
+        self.store.get(request_id).await
+    }
+
+    fn redact(&self, request: &ChatRequest) -> ChatRequest {
+        let mut request = request.clone();
+        for message in &mut request.messages {
+            for secret in &self.redactions {
+                if message.content.contains(secret.as_str()) {
+                    message.content = message.content.replace(secret.as_str(), REDACTED);
+                }
+            }
+        }
+        request
+    }
+}
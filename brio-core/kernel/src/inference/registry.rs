@@ -1,16 +1,34 @@
+use crate::inference::balanced::BalancedProvider;
+use crate::inference::history::HistoryPolicy;
 use crate::inference::provider::LLMProvider;
-use crate::inference::types::{ChatRequest, ChatResponse, InferenceError};
+use crate::inference::retry::RetryProvider;
+use crate::inference::types::{Capability, ChatRequest, ChatResponse, InferenceError};
+use crate::inference::usage::{UsageLedger, UsageTotals, UsageTrackingProvider};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use tracing::debug;
+use std::time::Duration;
+use tracing::{debug, warn};
 
 /// A registry for managing multiple LLM providers.
 ///
 /// Allows routing requests to different providers by name, enabling
 /// concurrent use of multiple LLM backends (OpenAI, Anthropic, etc.).
+/// Each [`Capability`] may also be pinned to its own provider, so one
+/// registry can serve chat from one endpoint and embeddings from another.
 pub struct ProviderRegistry {
     providers: RwLock<HashMap<String, Arc<dyn LLMProvider>>>,
-    default_provider: RwLock<Option<String>>,
+    /// The default's name together with the provider registered under it,
+    /// so a reader never pairs one default's name with another's provider
+    default_provider: RwLock<Option<(String, Arc<dyn LLMProvider>)>>,
+    capability_defaults: RwLock<HashMap<Capability, String>>,
+    history_policies: RwLock<HashMap<String, HistoryPolicy>>,
+    provider_regions: RwLock<HashMap<String, String>>,
+    local_region: RwLock<Option<String>>,
+    fallback_chain: RwLock<Vec<String>>,
+    /// Retry limit and base delay providers are wrapped with as they register
+    retries: RwLock<Option<(u32, Duration)>>,
+    /// Token usage reported by each registered provider's responses
+    usage: UsageLedger,
 }
 
 impl ProviderRegistry {
@@ -19,6 +37,13 @@ impl ProviderRegistry {
         Self {
             providers: RwLock::new(HashMap::new()),
             default_provider: RwLock::new(None),
+            capability_defaults: RwLock::new(HashMap::new()),
+            history_policies: RwLock::new(HashMap::new()),
+            provider_regions: RwLock::new(HashMap::new()),
+            local_region: RwLock::new(None),
+            fallback_chain: RwLock::new(Vec::new()),
+            retries: RwLock::new(None),
+            usage: UsageLedger::default(),
         }
     }
 
@@ -26,24 +51,152 @@ impl ProviderRegistry {
     pub fn register(&self, name: impl Into<String>, provider: impl LLMProvider + 'static) {
         let name = name.into();
         debug!(provider_name = %name, "Registering LLM provider");
-        let mut providers = self.providers.write().expect("RwLock poisoned");
-        providers.insert(name, Arc::new(provider));
+        let provider = self.with_usage_tracking(&name, self.with_retries(Arc::new(provider)));
+        self.insert(name, provider);
     }
 
     /// Registers a provider wrapped in Arc
     pub fn register_arc(&self, name: impl Into<String>, provider: Arc<dyn LLMProvider>) {
         let name = name.into();
         debug!(provider_name = %name, "Registering LLM provider (Arc)");
+        let provider = self.with_usage_tracking(&name, self.with_retries(provider));
+        self.insert(name, provider);
+    }
+
+    /// Adds or replaces a provider, replacing the default's too if it has this name.
+    ///
+    /// Like every writer of both, takes `providers` before `default_provider`.
+    fn insert(&self, name: String, provider: Arc<dyn LLMProvider>) {
         let mut providers = self.providers.write().expect("RwLock poisoned");
+        let mut default = self.default_provider.write().expect("RwLock poisoned");
+        if let Some((default_name, default_provider)) = default.as_mut()
+            && *default_name == name
+        {
+            *default_provider = Arc::clone(&provider);
+        }
         providers.insert(name, provider);
     }
 
-    /// Sets the default provider name
+    /// Registers a [`BalancedProvider`] spreading calls across `providers_with_weights`.
+    ///
+    /// Fails if no provider has a non-zero weight.
+    pub fn register_balanced(
+        &self,
+        name: impl Into<String>,
+        providers_with_weights: Vec<(Arc<dyn LLMProvider>, u32)>,
+    ) -> Result<(), InferenceError> {
+        let balanced = BalancedProvider::new(providers_with_weights)?;
+        self.register_arc(name, Arc::new(balanced));
+        Ok(())
+    }
+
+    /// Wraps every provider registered from now on in a [`RetryProvider`]
+    /// with these settings, so transient failures are retried without
+    /// callers changing. Providers already registered are left as they are.
+    pub fn set_retries(&self, max_retries: u32, base_delay: Duration) {
+        debug!(max_retries, base_delay_ms = base_delay.as_millis() as u64, "Setting provider retries");
+        let mut retries = self.retries.write().expect("RwLock poisoned");
+        *retries = Some((max_retries, base_delay));
+    }
+
+    fn with_retries(&self, provider: Arc<dyn LLMProvider>) -> Arc<dyn LLMProvider> {
+        match *self.retries.read().expect("RwLock poisoned") {
+            Some((max_retries, base_delay)) => Arc::new(RetryProvider::new(provider, max_retries, base_delay)),
+            None => provider,
+        }
+    }
+
+    /// Outside any retries, so only the attempt that succeeded is counted
+    fn with_usage_tracking(&self, name: &str, provider: Arc<dyn LLMProvider>) -> Arc<dyn LLMProvider> {
+        Arc::new(UsageTrackingProvider::new(name, provider, Arc::clone(&self.usage)))
+    }
+
+    /// Token usage reported so far, by provider name.
+    ///
+    /// Providers whose responses never reported usage are absent. Totals
+    /// outlive the provider, so removing or replacing one keeps its history.
+    pub fn usage_totals(&self) -> HashMap<String, UsageTotals> {
+        self.usage.lock().expect("Mutex poisoned").clone()
+    }
+
+    /// Makes the provider registered as `name` the default.
+    ///
+    /// The swap is atomic: readers see either the previous default or this one.
+    /// A name with no provider registered is ignored, keeping the previous default.
     pub fn set_default(&self, name: impl Into<String>) {
         let name = name.into();
+        let providers = self.providers.read().expect("RwLock poisoned");
+        let Some(provider) = providers.get(&name) else {
+            warn!(provider_name = %name, "Ignoring unregistered default LLM provider");
+            return;
+        };
         debug!(provider_name = %name, "Setting default LLM provider");
         let mut default = self.default_provider.write().expect("RwLock poisoned");
-        *default = Some(name);
+        *default = Some((name, Arc::clone(provider)));
+    }
+
+    /// Routes a capability to the named provider, overriding the general default
+    pub fn set_capability_default(&self, capability: Capability, name: impl Into<String>) {
+        let name = name.into();
+        debug!(provider_name = %name, ?capability, "Setting capability default provider");
+        let mut defaults = self.capability_defaults.write().expect("RwLock poisoned");
+        defaults.insert(capability, name);
+    }
+
+    /// Trims conversation history before requests to the named provider
+    pub fn set_history_policy(&self, name: impl Into<String>, policy: HistoryPolicy) {
+        let name = name.into();
+        debug!(provider_name = %name, ?policy, "Setting history policy");
+        let mut policies = self.history_policies.write().expect("RwLock poisoned");
+        policies.insert(name, policy);
+    }
+
+    /// Applies the named provider's history policy, if any, to the request
+    pub fn apply_history_policy(&self, name: &str, mut request: ChatRequest) -> ChatRequest {
+        let policy = {
+            let policies = self.history_policies.read().expect("RwLock poisoned");
+            policies.get(name).copied()
+        };
+        if let Some(policy) = policy {
+            request.messages = policy.trim(request.messages);
+        }
+        request
+    }
+
+    /// Labels the named provider with the region its endpoint runs in
+    pub fn set_region(&self, name: impl Into<String>, region: impl Into<String>) {
+        let mut regions = self.provider_regions.write().expect("RwLock poisoned");
+        regions.insert(name.into(), region.into());
+    }
+
+    /// Region of the named provider, if labelled
+    pub fn region(&self, name: &str) -> Option<String> {
+        let regions = self.provider_regions.read().expect("RwLock poisoned");
+        regions.get(name).cloned()
+    }
+
+    /// Sets the region this kernel runs in, used to prefer nearby providers
+    pub fn set_local_region(&self, region: Option<String>) {
+        let mut local = self.local_region.write().expect("RwLock poisoned");
+        *local = region;
+    }
+
+    pub fn local_region(&self) -> Option<String> {
+        let local = self.local_region.read().expect("RwLock poisoned");
+        local.clone()
+    }
+
+    /// Sets the providers tried in order by
+    /// [`inference_with_fallback`](crate::host::BrioHostState::inference_with_fallback)
+    pub fn set_fallback_chain(&self, names: Vec<&str>) {
+        debug!(chain = ?names, "Setting provider fallback chain");
+        let mut chain = self.fallback_chain.write().expect("RwLock poisoned");
+        *chain = names.into_iter().map(str::to_string).collect();
+    }
+
+    pub fn fallback_chain(&self) -> Vec<String> {
+        let chain = self.fallback_chain.read().expect("RwLock poisoned");
+        chain.clone()
     }
 
     /// Gets a provider by name
@@ -52,20 +205,49 @@ impl ProviderRegistry {
         providers.get(name).cloned()
     }
 
-    /// Gets the default provider
+    /// Gets the default provider.
+    ///
+    /// A concurrent [`set_default`](Self::set_default) is seen either entirely
+    /// or not at all.
     pub fn get_default(&self) -> Option<Arc<dyn LLMProvider>> {
-        let default_name = {
-            let default = self.default_provider.read().expect("RwLock poisoned");
-            default.clone()
+        self.default_entry().map(|(_, provider)| provider)
+    }
+
+    /// Name of the default provider and the provider itself, read as one snapshot
+    fn default_entry(&self) -> Option<(String, Arc<dyn LLMProvider>)> {
+        let default = self.default_provider.read().expect("RwLock poisoned").clone();
+        default.or_else(|| {
+            // If no default set, use first registered provider
+            let providers = self.providers.read().expect("RwLock poisoned");
+            providers
+                .iter()
+                .next()
+                .map(|(name, provider)| (name.clone(), Arc::clone(provider)))
+        })
+    }
+
+    /// Gets the provider serving the given capability.
+    ///
+    /// Falls back to the general default when no provider is pinned to the capability.
+    pub fn get_for_capability(&self, capability: Capability) -> Option<Arc<dyn LLMProvider>> {
+        self.entry_for_capability(capability).map(|(_, provider)| provider)
+    }
+
+    /// Name of the provider serving the given capability
+    pub fn name_for_capability(&self, capability: Capability) -> Option<String> {
+        self.entry_for_capability(capability).map(|(name, _)| name)
+    }
+
+    /// Name and provider serving the given capability, read together
+    fn entry_for_capability(&self, capability: Capability) -> Option<(String, Arc<dyn LLMProvider>)> {
+        let pinned = {
+            let defaults = self.capability_defaults.read().expect("RwLock poisoned");
+            defaults.get(&capability).cloned()
         };
 
-        match default_name {
-            Some(name) => self.get(&name),
-            None => {
-                // If no default set, return first registered provider
-                let providers = self.providers.read().expect("RwLock poisoned");
-                providers.values().next().cloned()
-            }
+        match pinned {
+            Some(name) => self.get(&name).map(|provider| (name, provider)),
+            None => self.default_entry(),
         }
     }
 
@@ -90,6 +272,10 @@ impl ProviderRegistry {
     pub fn remove(&self, name: &str) -> Option<Arc<dyn LLMProvider>> {
         debug!(provider_name = %name, "Removing LLM provider");
         let mut providers = self.providers.write().expect("RwLock poisoned");
+        let mut default = self.default_provider.write().expect("RwLock poisoned");
+        if default.as_ref().is_some_and(|(default_name, _)| default_name == name) {
+            *default = None;
+        }
         providers.remove(name)
     }
 
@@ -103,16 +289,31 @@ impl ProviderRegistry {
             InferenceError::ProviderNotFound(provider_name.to_string())
         })?;
 
-        provider.chat(request).await
+        provider
+            .chat(self.apply_history_policy(provider_name, request))
+            .await
     }
 
-    /// Sends a chat request to the default provider
+    /// Sends a chat request to the provider serving [`Capability::Chat`]
     pub async fn chat_default(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
-        let provider = self.get_default().ok_or_else(|| {
+        let (name, provider) = self.entry_for_capability(Capability::Chat).ok_or_else(|| {
             InferenceError::ProviderNotFound("No default provider configured".to_string())
         })?;
 
-        provider.chat(request).await
+        provider.chat(self.apply_history_policy(&name, request)).await
+    }
+
+    /// Embeds the inputs with the provider serving [`Capability::Embeddings`]
+    pub async fn embed_default(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        let provider = self
+            .get_for_capability(Capability::Embeddings)
+            .ok_or_else(|| {
+                InferenceError::ProviderNotFound(
+                    "No embeddings provider configured".to_string(),
+                )
+            })?;
+
+        provider.embed(inputs).await
     }
 }
 
@@ -129,8 +330,10 @@ impl Default for ProviderRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::inference::test_support::flaky;
     use crate::inference::types::{Message, Role};
     use async_trait::async_trait;
+    use futures_util::FutureExt;
 
     struct MockProvider {
         response: String,
@@ -142,10 +345,55 @@ mod tests {
             Ok(ChatResponse {
                 content: self.response.clone(),
                 usage: None,
+                tool_calls: Vec::new(),
             })
         }
     }
 
+    #[test]
+    fn test_concurrent_set_default_never_leaves_registry_without_default() {
+        let registry = Arc::new(ProviderRegistry::new());
+        registry.register("a", MockProvider {
+            response: "a".to_string(),
+        });
+        registry.set_default("a");
+
+        // Each swap registers the new default, then retires the old one
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let writer = {
+            let (registry, done) = (registry.clone(), done.clone());
+            std::thread::spawn(move || {
+                for i in 0..100_000 {
+                    let (next, previous) = if i % 2 == 0 { ("b", "a") } else { ("a", "b") };
+                    registry.register(next, MockProvider {
+                        response: next.to_string(),
+                    });
+                    registry.set_default(next);
+                    registry.remove(previous);
+                }
+                done.store(true, std::sync::atomic::Ordering::SeqCst);
+            })
+        };
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let (registry, done) = (registry.clone(), done.clone());
+                std::thread::spawn(move || {
+                    while !done.load(std::sync::atomic::Ordering::SeqCst) {
+                        assert!(registry.get_default().is_some());
+                        // The mock answers without awaiting, so one poll completes the call
+                        let response = registry.chat_default(ChatRequest::default()).now_or_never();
+                        assert!(matches!(response, Some(Ok(_))), "{:?}", response);
+                    }
+                })
+            })
+            .collect();
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
     #[test]
     fn test_registry_new() {
         let registry = ProviderRegistry::new();
@@ -234,10 +482,8 @@ mod tests {
 
         let request = ChatRequest {
             model: "gpt-4".to_string(),
-            messages: vec![Message {
-                role: Role::User,
-                content: "Hi".to_string(),
-            }],
+            messages: vec![Message::new(Role::User, "Hi")],
+            ..Default::default()
         };
 
         let response = registry.chat("openai", request).await.unwrap();
@@ -251,6 +497,7 @@ mod tests {
         let request = ChatRequest {
             model: "gpt-4".to_string(),
             messages: vec![],
+            ..Default::default()
         };
 
         let result = registry.chat("nonexistent", request).await;
@@ -268,13 +515,93 @@ mod tests {
 
         let request = ChatRequest {
             model: "claude-3".to_string(),
-            messages: vec![Message {
-                role: Role::User,
-                content: "Hello".to_string(),
-            }],
+            messages: vec![Message::new(Role::User, "Hello")],
+            ..Default::default()
         };
 
         let response = registry.chat_default(request).await.unwrap();
         assert_eq!(response.content, "Anthropic response");
     }
+
+    #[tokio::test]
+    async fn test_capability_default_overrides_general_default() {
+        let registry = ProviderRegistry::new();
+        registry.register("chat", MockProvider {
+            response: "chat".to_string(),
+        });
+        registry.register("local", MockProvider {
+            response: "local".to_string(),
+        });
+        registry.set_default("chat");
+        registry.set_capability_default(Capability::Chat, "local");
+
+        let request = ChatRequest {
+            model: "gpt-4".to_string(),
+            messages: vec![],
+            ..Default::default()
+        };
+
+        let response = registry.chat_default(request).await.unwrap();
+        assert_eq!(response.content, "local");
+    }
+
+    struct EchoCountProvider;
+
+    #[async_trait]
+    impl LLMProvider for EchoCountProvider {
+        async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            Ok(ChatResponse {
+                content: request.messages.len().to_string(),
+                usage: None,
+                tool_calls: Vec::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_policy_applies_per_provider() {
+        let registry = ProviderRegistry::new();
+        registry.register("trimmed", EchoCountProvider);
+        registry.register("full", EchoCountProvider);
+        registry.set_history_policy("trimmed", HistoryPolicy::LastMessages(1));
+
+        let request = ChatRequest {
+            model: "gpt-4".to_string(),
+            messages: (0..4)
+                .map(|i| Message::new(Role::User, i.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+
+        let trimmed = registry.chat("trimmed", request.clone()).await.unwrap();
+        let full = registry.chat("full", request).await.unwrap();
+        assert_eq!(trimmed.content, "1");
+        assert_eq!(full.content, "4");
+    }
+
+    #[tokio::test]
+    async fn test_embed_default_unsupported_provider() {
+        let registry = ProviderRegistry::new();
+        registry.register("chat", MockProvider {
+            response: "chat".to_string(),
+        });
+
+        let result = registry.embed_default(vec!["hello".to_string()]).await;
+        assert!(matches!(result, Err(InferenceError::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn test_registered_providers_are_retried_transparently() {
+        let registry = ProviderRegistry::new();
+        registry.set_retries(2, Duration::from_millis(1));
+        let inner = flaky(2, || InferenceError::Http {
+            status: 503,
+            message: "busy".to_string(),
+        });
+        registry.register_arc("flaky", inner.clone());
+
+        let response = registry.chat("flaky", ChatRequest::default()).await.unwrap();
+        assert_eq!(response.content, "ok");
+        assert_eq!(inner.calls(), 3);
+    }
 }
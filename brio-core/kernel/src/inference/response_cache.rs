@@ -0,0 +1,211 @@
+use crate::inference::provider::{LLMProvider, TokenStream};
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ModerationResult};
+use crate::store::{SqlStore, StoreError, TypedStore};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// Scope whose `inference_cache_kv` table holds cached responses
+pub const RESPONSE_CACHE_SCOPE: &str = "inference_cache";
+/// Counter of chat calls answered from the cache
+pub const CACHE_HITS_METRIC: &str = "brio_inference_cache_hits_total";
+/// Counter of cacheable chat calls that had to go to the provider
+pub const CACHE_MISSES_METRIC: &str = "brio_inference_cache_misses_total";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResponse {
+    response: ChatResponse,
+    expires_at_ms: u64,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Serves repeated deterministic chat requests from the store.
+///
+/// Only requests with a temperature of exactly 0 are cached; anything else,
+/// including an unset temperature (which leaves the provider's own default),
+/// passes straight through. Entries are keyed by a hash of the model,
+/// messages and sampling parameters, and expire `ttl` after being stored.
+/// Store failures are logged and the provider is called as if uncached.
+pub struct CachingProvider {
+    inner: Arc<dyn LLMProvider>,
+    store: TypedStore<CachedResponse>,
+    ttl: Duration,
+}
+
+impl CachingProvider {
+    pub fn new(inner: Arc<dyn LLMProvider>, store: SqlStore, ttl: Duration) -> Self {
+        Self {
+            inner,
+            store: TypedStore::new(store, RESPONSE_CACHE_SCOPE),
+            ttl,
+        }
+    }
+
+    /// Creates the cache table if it doesn't exist yet.
+    pub async fn ensure_table(&self) -> Result<(), StoreError> {
+        self.store.ensure_table().await
+    }
+
+    fn is_cacheable(request: &ChatRequest) -> bool {
+        request.temperature == Some(0.0)
+    }
+
+    /// Hash of everything that shapes the completion; post-processing runs
+    /// after the provider and is left out
+    fn key(request: &ChatRequest) -> Result<String, InferenceError> {
+        let shaping = serde_json::json!({
+            "model": request.model,
+            "messages": request.messages,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "tools": request.tools,
+            "response_format": request.response_format,
+        });
+        let bytes = serde_json::to_vec(&shaping).map_err(|e| InferenceError::ProviderError(e.to_string()))?;
+        Ok(hex::encode(Sha256::digest(bytes)))
+    }
+
+    async fn lookup(&self, key: &str) -> Option<ChatResponse> {
+        match self.store.get(key).await {
+            Ok(Some(cached)) if cached.expires_at_ms > now_ms() => Some(cached.response),
+            Ok(Some(_)) => {
+                if let Err(e) = self.store.delete(key).await {
+                    warn!(error = %e, "Failed to evict expired cached response");
+                }
+                None
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!(error = %e, "Response cache lookup failed");
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CachingProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        if !Self::is_cacheable(&request) {
+            return self.inner.chat(request).await;
+        }
+
+        let key = Self::key(&request)?;
+        if let Some(response) = self.lookup(&key).await {
+            debug!(model = %request.model, "Response cache hit");
+            metrics::counter!(CACHE_HITS_METRIC).increment(1);
+            return Ok(response);
+        }
+        metrics::counter!(CACHE_MISSES_METRIC).increment(1);
+
+        let response = self.inner.chat(request).await?;
+        let cached = CachedResponse {
+            response: response.clone(),
+            expires_at_ms: now_ms().saturating_add(self.ttl.as_millis() as u64),
+        };
+        if let Err(e) = self.store.put(&key, &cached).await {
+            warn!(error = %e, "Failed to cache response");
+        }
+        Ok(response)
+    }
+
+    fn stream_completion<'a>(&'a self, request: ChatRequest) -> TokenStream<'a> {
+        self.inner.stream_completion(request)
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.inner.embed(inputs).await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.inner.embed_with_dimensions(inputs, dimensions).await
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        self.inner.moderate(input).await
+    }
+
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::test_support::CountingProvider;
+    use crate::inference::types::{Message, Role};
+    use crate::store::PrefixPolicy;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn caching(ttl: Duration) -> (Arc<CountingProvider>, CachingProvider) {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let inner = CountingProvider::new();
+        let provider = CachingProvider::new(inner.clone(), SqlStore::new(pool, Box::new(PrefixPolicy)), ttl);
+        provider.ensure_table().await.unwrap();
+        (inner, provider)
+    }
+
+    fn request(content: &str, temperature: Option<f32>) -> ChatRequest {
+        ChatRequest {
+            model: "gpt-4".to_string(),
+            messages: vec![Message::new(Role::User, content)],
+            temperature,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_deterministic_request_is_served_from_cache() {
+        let (inner, provider) = caching(Duration::from_secs(60)).await;
+
+        let first = provider.chat(request("hi", Some(0.0))).await.unwrap();
+        let second = provider.chat(request("hi", Some(0.0))).await.unwrap();
+
+        assert_eq!(first.content, "answer 1");
+        assert_eq!(second.content, "answer 1");
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn different_or_sampled_requests_miss() {
+        let (inner, provider) = caching(Duration::from_secs(60)).await;
+
+        provider.chat(request("hi", Some(0.0))).await.unwrap();
+        provider.chat(request("bye", Some(0.0))).await.unwrap();
+        provider.chat(request("hi", Some(0.7))).await.unwrap();
+        provider.chat(request("hi", Some(0.7))).await.unwrap();
+        provider.chat(request("hi", None)).await.unwrap();
+
+        assert_eq!(inner.calls(), 5);
+    }
+
+    #[tokio::test]
+    async fn expired_entry_is_refetched() {
+        let (inner, provider) = caching(Duration::from_millis(50)).await;
+
+        provider.chat(request("hi", Some(0.0))).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let refreshed = provider.chat(request("hi", Some(0.0))).await.unwrap();
+
+        assert_eq!(refreshed.content, "answer 2");
+        assert_eq!(inner.calls(), 2);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::inference::provider::{LLMProvider, TokenStream};
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ModerationResult};
+use crate::infrastructure::context;
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// Statuses retried unless configured otherwise: rate limits and gateway or
+/// server errors that usually clear up on their own
+pub const DEFAULT_RETRY_STATUSES: &[u16] = &[429, 500, 502, 503, 504];
+
+/// Longest wait between two attempts, whatever the backoff or `Retry-After` says
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Retries a provider's failed calls with exponential backoff and jitter.
+///
+/// Only transient failures are retried: network errors, and errors whose
+/// HTTP status is one of the retry statuses. When the provider reports a
+/// `Retry-After` delay (see [`InferenceError::Throttled`]) that delay is used
+/// instead of the backoff. Retries draw on the request's retry budget, like
+/// the providers' own retry loops.
+///
+/// Streams aren't retried once started, since tokens may already have been
+/// consumed, and health checks go straight through so they report the
+/// provider's state as it is.
+pub struct RetryProvider {
+    inner: Arc<dyn LLMProvider>,
+    max_retries: u32,
+    base_delay: Duration,
+    statuses: Vec<u16>,
+}
+
+impl RetryProvider {
+    pub fn new(inner: Arc<dyn LLMProvider>, max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            inner,
+            max_retries,
+            base_delay,
+            statuses: DEFAULT_RETRY_STATUSES.to_vec(),
+        }
+    }
+
+    /// HTTP statuses worth retrying, replacing [`DEFAULT_RETRY_STATUSES`].
+    pub fn with_statuses(mut self, statuses: Vec<u16>) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    fn is_retryable(&self, error: &InferenceError) -> bool {
+        matches!(error, InferenceError::NetworkError(_))
+            || error.status().is_some_and(|status| self.statuses.contains(&status))
+    }
+
+    /// Backoff before retry number `attempt + 1`: `base_delay * 2^attempt`
+    /// plus up to 25% jitter, capped at [`MAX_RETRY_DELAY`]
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+            .min(MAX_RETRY_DELAY);
+        (delay + delay.mul_f64(0.25 * rand_jitter())).min(MAX_RETRY_DELAY)
+    }
+
+    async fn retry<T, F, Fut>(&self, mut call: F) -> Result<T, InferenceError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, InferenceError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let error = match call().await {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            if attempt >= self.max_retries || !self.is_retryable(&error) {
+                return Err(error);
+            }
+            if !context::try_consume_retry() {
+                warn!(error = %error, "Request retry budget exhausted, giving up");
+                return Err(error);
+            }
+
+            let delay = error
+                .retry_after()
+                .map_or_else(|| self.backoff_delay(attempt), |wait| wait.min(MAX_RETRY_DELAY));
+            attempt += 1;
+            warn!(
+                attempt,
+                max_retries = self.max_retries,
+                delay_ms = delay.as_millis() as u64,
+                error = %error,
+                "Provider call failed, retrying after backoff"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RetryProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        self.retry(|| self.inner.chat(request.clone())).await
+    }
+
+    fn stream_completion<'a>(&'a self, request: ChatRequest) -> TokenStream<'a> {
+        self.inner.stream_completion(request)
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.retry(|| self.inner.embed(inputs.clone())).await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.retry(|| self.inner.embed_with_dimensions(inputs.clone(), dimensions))
+            .await
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        self.retry(|| self.inner.moderate(input.clone())).await
+    }
+
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        self.inner.health_check().await
+    }
+}
+
+/// Uniform in `[0, 1)`, scaling the jitter added to a backoff delay
+pub(crate) fn rand_jitter() -> f64 {
+    rand::random()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::test_support::flaky;
+
+    #[tokio::test]
+    async fn provider_failing_twice_then_succeeding_is_retried() {
+        let inner = flaky(2, || InferenceError::RateLimit);
+        let provider = RetryProvider::new(inner.clone(), 3, Duration::from_millis(1));
+
+        let response = provider.chat(ChatRequest::default()).await.unwrap();
+        assert_eq!(response.content, "ok");
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn retry_after_overrides_backoff() {
+        let inner = flaky(1, || InferenceError::Throttled {
+            status: 503,
+            retry_after: Duration::from_millis(50),
+        });
+        let provider = RetryProvider::new(inner.clone(), 1, Duration::ZERO);
+
+        let started = std::time::Instant::now();
+        provider.chat(ChatRequest::default()).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn unlisted_statuses_and_exhausted_retries_fail() {
+        let inner = flaky(1, || InferenceError::InvalidRequest("bad".to_string()));
+        let provider = RetryProvider::new(inner.clone(), 3, Duration::from_millis(1));
+        assert!(provider.chat(ChatRequest::default()).await.is_err());
+        assert_eq!(inner.calls(), 1);
+
+        let inner = flaky(5, || InferenceError::RateLimit);
+        let provider = RetryProvider::new(inner.clone(), 2, Duration::from_millis(1)).with_statuses(vec![503]);
+        assert!(matches!(provider.chat(ChatRequest::default()).await, Err(InferenceError::RateLimit)));
+        assert_eq!(inner.calls(), 1);
+    }
+}
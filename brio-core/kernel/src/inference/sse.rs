@@ -0,0 +1,54 @@
+/// Incremental parser for a `text/event-stream` body.
+///
+/// Bytes are fed in as they arrive, split anywhere, and the `data` of each
+/// completed event comes back in order. Multi-line data is joined with `\n`;
+/// comments and other fields are ignored.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    /// Bytes after the last complete line
+    buffer: Vec<u8>,
+    /// Data lines of the event being read
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes `chunk`, returning the data of every event it completes.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(std::mem::take(&mut self.data).join("\n"));
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            } else if line == "data" {
+                self.data.push(String::new());
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_split_across_chunks_are_reassembled() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: {\"a\"").is_empty());
+        assert_eq!(decoder.push(b":1}\r\n\r\n: ping\n\ndata: x\ndata: y\n"), vec!["{\"a\":1}"]);
+        assert_eq!(decoder.push(b"\ndata: [DONE]\n\n"), vec!["x\ny", "[DONE]"]);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::inference::provider::LLMProvider;
+use crate::inference::registry::ProviderRegistry;
+use crate::inference::types::{Capability, ChatRequest, ChatResponse, InferenceError};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{debug, warn};
+
+/// A provider and its share of traffic under weighted round-robin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WeightedProvider {
+    pub name: String,
+    pub weight: u32,
+}
+
+/// How completions are dispatched across the registered providers.
+///
+/// Chosen in configuration and validated against the registry at startup,
+/// so a strategy naming a missing provider fails before serving traffic.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    /// Always use the named provider
+    Single(String),
+    /// Try providers in order, moving to the next when one fails; if all do,
+    /// the error is [`InferenceError::AllProvidersFailed`]
+    Fallback(Vec<String>),
+    /// Spread requests across providers in proportion to their weights
+    WeightedRoundRobin(Vec<WeightedProvider>),
+    /// Try providers in the kernel's region first, then the rest, each group
+    /// in the listed order; remote regions are only used when local ones fail,
+    /// and failures are reported as with `Fallback`
+    RegionAffinity(Vec<String>),
+    /// Use the provider the registry routes [`Capability::Chat`] to
+    #[default]
+    ByCapability,
+}
+
+/// Applies a [`SelectionStrategy`] to each completion.
+pub struct ProviderSelector {
+    strategy: SelectionStrategy,
+    next: AtomicUsize,
+}
+
+impl ProviderSelector {
+    /// Creates a selector, checking that every provider the strategy names is registered.
+    pub fn new(
+        strategy: SelectionStrategy,
+        registry: &ProviderRegistry,
+    ) -> Result<Self, InferenceError> {
+        let names: Vec<&str> = match &strategy {
+            SelectionStrategy::Single(name) => vec![name.as_str()],
+            SelectionStrategy::Fallback(order) | SelectionStrategy::RegionAffinity(order) => {
+                if order.is_empty() {
+                    let kind = match strategy {
+                        SelectionStrategy::Fallback(_) => "Fallback",
+                        _ => "Region affinity",
+                    };
+                    return Err(InferenceError::ConfigError(format!(
+                        "{} strategy needs at least one provider",
+                        kind
+                    )));
+                }
+                order.iter().map(String::as_str).collect()
+            }
+            SelectionStrategy::WeightedRoundRobin(weights) => {
+                if weights.iter().all(|w| w.weight == 0) {
+                    return Err(InferenceError::ConfigError(
+                        "Weighted round-robin strategy needs a non-zero weight".to_string(),
+                    ));
+                }
+                weights.iter().map(|w| w.name.as_str()).collect()
+            }
+            SelectionStrategy::ByCapability => vec![],
+        };
+
+        if let Some(missing) = names.into_iter().find(|name| registry.get(name).is_none()) {
+            return Err(InferenceError::ProviderNotFound(missing.to_string()));
+        }
+
+        Ok(Self {
+            strategy,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn strategy(&self) -> &SelectionStrategy {
+        &self.strategy
+    }
+
+    /// Sends a chat request according to the strategy.
+    pub async fn chat(
+        &self,
+        registry: &ProviderRegistry,
+        request: ChatRequest,
+    ) -> Result<ChatResponse, InferenceError> {
+        let candidates = self.candidates(registry);
+        if candidates.is_empty() {
+            return Err(InferenceError::ProviderNotFound(
+                "No provider available for selection strategy".to_string(),
+            ));
+        }
+
+        let mut failures = Vec::new();
+        for (name, provider) in candidates {
+            debug!(provider_name = %name, "Dispatching completion");
+            let request = registry.apply_history_policy(&name, request.clone());
+            match provider.chat(request).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    warn!(provider_name = %name, error = %e, "Provider failed");
+                    failures.push((name, e));
+                }
+            }
+        }
+
+        match self.strategy {
+            // Strategies that move on to the next provider report every failure
+            SelectionStrategy::Fallback(_) | SelectionStrategy::RegionAffinity(_) => {
+                Err(InferenceError::AllProvidersFailed {
+                    failures: failures.into_iter().map(|(name, e)| (name, e.to_string())).collect(),
+                })
+            }
+            _ => Err(failures.pop().expect("at least one candidate was tried").1),
+        }
+    }
+
+    /// Providers to try for one request, in order.
+    fn candidates(&self, registry: &ProviderRegistry) -> Vec<(String, Arc<dyn LLMProvider>)> {
+        let lookup = |name: &str| registry.get(name).map(|p| (name.to_string(), p));
+
+        match &self.strategy {
+            SelectionStrategy::Single(name) => lookup(name).into_iter().collect(),
+            SelectionStrategy::Fallback(order) => order.iter().filter_map(|n| lookup(n)).collect(),
+            SelectionStrategy::RegionAffinity(order) => {
+                let mut candidates: Vec<_> = order.iter().filter_map(|n| lookup(n)).collect();
+                if let Some(local) = registry.local_region() {
+                    // Stable sort keeps the configured order within each group
+                    candidates.sort_by_key(|(name, _)| registry.region(name).as_deref() != Some(local.as_str()));
+                }
+                candidates
+            }
+            SelectionStrategy::WeightedRoundRobin(weights) => {
+                let total: usize = weights.iter().map(|w| w.weight as usize).sum();
+                let slot = self.next.fetch_add(1, Ordering::Relaxed) % total;
+                weighted_turn(weights.iter().map(|w| w.weight), slot)
+                    .and_then(|i| lookup(&weights[i].name))
+                    .into_iter()
+                    .collect()
+            }
+            SelectionStrategy::ByCapability => registry
+                .name_for_capability(Capability::Chat)
+                .and_then(|name| lookup(&name))
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// Index of the weight whose share of `0..sum(weights)` holds `slot`, so that
+/// counting `slot` up through the total gives each index `weight` turns
+pub(crate) fn weighted_turn(weights: impl IntoIterator<Item = u32>, mut slot: usize) -> Option<usize> {
+    weights.into_iter().position(|weight| {
+        if slot < weight as usize {
+            true
+        } else {
+            slot -= weight as usize;
+            false
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct MockProvider {
+        response: &'static str,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl LLMProvider for MockProvider {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            if self.fail {
+                return Err(InferenceError::ProviderError(self.response.to_string()));
+            }
+            Ok(ChatResponse {
+                content: self.response.to_string(),
+                usage: None,
+                tool_calls: Vec::new(),
+            })
+        }
+    }
+
+    fn registry() -> ProviderRegistry {
+        let registry = ProviderRegistry::new();
+        registry.register("a", MockProvider { response: "a", fail: false });
+        registry.register("b", MockProvider { response: "b", fail: false });
+        registry.register("broken", MockProvider { response: "broken", fail: true });
+        registry
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            model: "test".to_string(),
+            messages: vec![],
+            ..Default::default()
+        }
+    }
+
+    async fn content(selector: &ProviderSelector, registry: &ProviderRegistry) -> String {
+        selector.chat(registry, request()).await.unwrap().content
+    }
+
+    #[tokio::test]
+    async fn test_single_routes_to_named_provider() {
+        let registry = registry();
+        let selector = ProviderSelector::new(SelectionStrategy::Single("b".into()), &registry).unwrap();
+        assert_eq!(content(&selector, &registry).await, "b");
+    }
+
+    #[tokio::test]
+    async fn test_fallback_skips_failing_provider() {
+        let registry = registry();
+        let selector = ProviderSelector::new(
+            SelectionStrategy::Fallback(vec!["broken".into(), "a".into()]),
+            &registry,
+        )
+        .unwrap();
+        assert_eq!(content(&selector, &registry).await, "a");
+    }
+
+    #[tokio::test]
+    async fn test_fallback_lists_every_failure_when_all_fail() {
+        let registry = registry();
+        registry.register("also-broken", MockProvider { response: "also-broken", fail: true });
+        let selector = ProviderSelector::new(
+            SelectionStrategy::Fallback(vec!["broken".into(), "also-broken".into()]),
+            &registry,
+        )
+        .unwrap();
+        let err = selector.chat(&registry, request()).await.unwrap_err();
+        let InferenceError::AllProvidersFailed { failures } = &err else {
+            panic!("Expected AllProvidersFailed, got {:?}", err);
+        };
+        let names: Vec<&str> = failures.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["broken", "also-broken"]);
+    }
+
+    #[tokio::test]
+    async fn test_single_provider_failure_is_returned_as_is() {
+        let registry = registry();
+        let selector = ProviderSelector::new(SelectionStrategy::Single("broken".into()), &registry).unwrap();
+        let result = selector.chat(&registry, request()).await;
+        assert!(matches!(result, Err(InferenceError::ProviderError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_weighted_round_robin_follows_weights() {
+        let registry = registry();
+        let selector = ProviderSelector::new(
+            SelectionStrategy::WeightedRoundRobin(vec![
+                WeightedProvider { name: "a".into(), weight: 2 },
+                WeightedProvider { name: "b".into(), weight: 1 },
+            ]),
+            &registry,
+        )
+        .unwrap();
+
+        let mut seen = Vec::new();
+        for _ in 0..6 {
+            seen.push(content(&selector, &registry).await);
+        }
+        assert_eq!(seen, ["a", "a", "b", "a", "a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_by_capability_uses_registry_routing() {
+        let registry = registry();
+        registry.set_default("a");
+        registry.set_capability_default(Capability::Chat, "b");
+        let selector = ProviderSelector::new(SelectionStrategy::ByCapability, &registry).unwrap();
+        assert_eq!(content(&selector, &registry).await, "b");
+    }
+
+    fn regional_registry() -> ProviderRegistry {
+        let registry = ProviderRegistry::new();
+        registry.register("us", MockProvider { response: "us", fail: false });
+        registry.register("eu", MockProvider { response: "eu", fail: false });
+        registry.register("eu-broken", MockProvider { response: "eu-broken", fail: true });
+        registry.set_region("us", "us-east");
+        registry.set_region("eu", "eu-west");
+        registry.set_region("eu-broken", "eu-west");
+        registry.set_local_region(Some("eu-west".into()));
+        registry
+    }
+
+    #[tokio::test]
+    async fn test_region_affinity_prefers_local_provider() {
+        let registry = regional_registry();
+        let selector = ProviderSelector::new(
+            SelectionStrategy::RegionAffinity(vec!["us".into(), "eu".into()]),
+            &registry,
+        )
+        .unwrap();
+        assert_eq!(content(&selector, &registry).await, "eu");
+    }
+
+    #[tokio::test]
+    async fn test_region_affinity_falls_back_across_regions() {
+        let registry = regional_registry();
+        let selector = ProviderSelector::new(
+            SelectionStrategy::RegionAffinity(vec!["us".into(), "eu-broken".into()]),
+            &registry,
+        )
+        .unwrap();
+        assert_eq!(content(&selector, &registry).await, "us");
+    }
+
+    #[test]
+    fn test_missing_provider_fails_validation() {
+        let registry = registry();
+        let result = ProviderSelector::new(
+            SelectionStrategy::Fallback(vec!["a".into(), "missing".into()]),
+            &registry,
+        );
+        assert!(matches!(result, Err(InferenceError::ProviderNotFound(name)) if name == "missing"));
+    }
+
+    #[test]
+    fn test_zero_weights_fail_validation() {
+        let registry = registry();
+        let result = ProviderSelector::new(
+            SelectionStrategy::WeightedRoundRobin(vec![WeightedProvider { name: "a".into(), weight: 0 }]),
+            &registry,
+        );
+        assert!(matches!(result, Err(InferenceError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_strategy_deserializes_from_config() {
+        let strategy: SelectionStrategy = serde_json::from_value(serde_json::json!({
+            "weighted_round_robin": [{ "name": "a", "weight": 3 }]
+        }))
+        .unwrap();
+        assert_eq!(
+            strategy,
+            SelectionStrategy::WeightedRoundRobin(vec![WeightedProvider { name: "a".into(), weight: 3 }])
+        );
+
+        let strategy: SelectionStrategy = serde_json::from_value(serde_json::json!("by_capability")).unwrap();
+        assert_eq!(strategy, SelectionStrategy::ByCapability);
+    }
+}
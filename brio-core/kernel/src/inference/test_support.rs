@@ -0,0 +1,91 @@
+//! Providers shared by the inference unit tests
+
+use crate::inference::provider::LLMProvider;
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Counts its chat calls, failing them with a network error while `failing` is set.
+pub struct CountingProvider {
+    pub calls: AtomicUsize,
+    pub failing: AtomicBool,
+    reply: Option<&'static str>,
+}
+
+impl CountingProvider {
+    /// Answers its nth call with "answer n"
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            calls: AtomicUsize::new(0),
+            failing: AtomicBool::new(false),
+            reply: None,
+        })
+    }
+
+    /// Answers every call with `reply`
+    pub fn replying(reply: &'static str) -> Arc<Self> {
+        Arc::new(Self {
+            calls: AtomicUsize::new(0),
+            failing: AtomicBool::new(false),
+            reply: Some(reply),
+        })
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CountingProvider {
+    async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(InferenceError::NetworkError("down".to_string()));
+        }
+        Ok(ChatResponse {
+            content: match self.reply {
+                Some(reply) => reply.to_string(),
+                None => format!("answer {}", call),
+            },
+            usage: None,
+            tool_calls: Vec::new(),
+        })
+    }
+}
+
+/// Fails `failures` times with `error`, then answers "ok"
+pub struct FlakyProvider {
+    failures: usize,
+    error: fn() -> InferenceError,
+    calls: AtomicUsize,
+}
+
+impl FlakyProvider {
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+pub fn flaky(failures: usize, error: fn() -> InferenceError) -> Arc<FlakyProvider> {
+    Arc::new(FlakyProvider {
+        failures,
+        error,
+        calls: AtomicUsize::new(0),
+    })
+}
+
+#[async_trait]
+impl LLMProvider for FlakyProvider {
+    async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+            return Err((self.error)());
+        }
+        Ok(ChatResponse {
+            content: "ok".to_string(),
+            usage: None,
+            tool_calls: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,324 @@
+use crate::inference::provider::LLMProvider;
+use crate::inference::types::{
+    ChatRequest, ChatResponse, InferenceError, Message, ModerationResult, Role, Tool, ToolCall,
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Checks `arguments` against a JSON Schema, returning one message per violation.
+///
+/// Covers the subset of JSON Schema that tool definitions use in practice:
+/// `type`, `enum`, `properties`, `required`, `additionalProperties: false`
+/// and `items`. Other keywords are ignored rather than rejected.
+pub fn validate_arguments(schema: &Value, arguments: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, arguments, "", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let location = if path.is_empty() { "/" } else { path };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, value)) {
+            errors.push(format!(
+                "{}: expected {}, got {}",
+                location,
+                allowed.join(" or "),
+                type_name(value)
+            ));
+            // Keywords below assume the declared type
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum")
+        && !options.contains(value)
+    {
+        errors.push(format!("{}: {} is not one of the allowed values", location, value));
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    errors.push(format!("{}: missing required property '{}'", location, name));
+                }
+            }
+        }
+        for (name, member) in object {
+            match properties.and_then(|p| p.get(name)) {
+                Some(member_schema) => {
+                    validate_at(member_schema, member, &format!("{}/{}", path, name), errors)
+                }
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    errors.push(format!("{}: unexpected property '{}'", location, name));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate_at(item_schema, item, &format!("{}/{}", path, i), errors);
+        }
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        // Unknown type names can't be checked
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Validates every call against the declared tool of the same name.
+///
+/// Fails on the first call that names an undeclared tool or whose arguments
+/// don't match the tool's schema.
+pub fn validate_tool_calls(tools: &[Tool], calls: &[ToolCall]) -> Result<(), InferenceError> {
+    for call in calls {
+        let errors = match tools.iter().find(|t| t.name == call.name) {
+            Some(tool) => validate_arguments(&tool.parameters, &call.arguments),
+            None => vec!["tool is not declared in the request".to_string()],
+        };
+        if !errors.is_empty() {
+            return Err(InferenceError::InvalidToolArguments {
+                tool: call.name.clone(),
+                errors,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Wraps a provider so tool calls are checked against the request's tool
+/// schemas before they reach the caller.
+///
+/// By default invalid arguments fail with [`InferenceError::InvalidToolArguments`].
+/// With [`with_repair_attempts`](Self::with_repair_attempts), the model is
+/// instead shown the validation errors and asked to call the tool again.
+pub struct ToolValidatingProvider {
+    inner: Arc<dyn LLMProvider>,
+    repair_attempts: u32,
+}
+
+impl ToolValidatingProvider {
+    pub fn new(inner: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            inner,
+            repair_attempts: 0,
+        }
+    }
+
+    /// Sets how many times the model is re-prompted to fix invalid arguments
+    pub fn with_repair_attempts(mut self, attempts: u32) -> Self {
+        self.repair_attempts = attempts;
+        self
+    }
+}
+
+/// Appends the invalid reply and a correction request to the conversation.
+fn repair_prompt(request: &mut ChatRequest, response: &ChatResponse, err: &InferenceError) {
+    let calls = serde_json::to_string(&response.tool_calls).unwrap_or_default();
+    request.messages.push(Message::new(
+        Role::Assistant,
+        format!("{}\nTool calls: {}", response.content, calls),
+    ));
+    request.messages.push(Message::new(
+        Role::User,
+        format!(
+            "{}. Call the tool again with arguments that match its parameter schema.",
+            err
+        ),
+    ));
+}
+
+#[async_trait]
+impl LLMProvider for ToolValidatingProvider {
+    async fn chat(&self, mut request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let mut attempt = 0;
+        loop {
+            let response = self.inner.chat(request.clone()).await?;
+            let Err(err) = validate_tool_calls(&request.tools, &response.tool_calls) else {
+                return Ok(response);
+            };
+            if attempt >= self.repair_attempts {
+                return Err(err);
+            }
+            attempt += 1;
+            warn!(attempt, error = %err, "Re-prompting model to repair tool arguments");
+            repair_prompt(&mut request, &response, &err);
+        }
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.inner.embed(inputs).await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.inner.embed_with_dimensions(inputs, dimensions).await
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        self.inner.moderate(input).await
+    }
+
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn weather_tool() -> Tool {
+        Tool {
+            name: "get_weather".to_string(),
+            description: "Current weather for a city".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "city": { "type": "string" },
+                    "days": { "type": "integer" },
+                    "unit": { "type": "string", "enum": ["celsius", "fahrenheit"] },
+                    "tags": { "type": "array", "items": { "type": "string" } }
+                },
+                "required": ["city"],
+                "additionalProperties": false
+            }),
+        }
+    }
+
+    fn call(arguments: Value) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            name: "get_weather".to_string(),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn test_valid_arguments_pass() {
+        let schema = weather_tool().parameters;
+        let args = json!({ "city": "Oslo", "days": 3, "unit": "celsius", "tags": ["a"] });
+        assert!(validate_arguments(&schema, &args).is_empty());
+    }
+
+    #[test]
+    fn test_reports_missing_required_and_type_errors() {
+        let schema = weather_tool().parameters;
+        let args = json!({ "days": "three", "unit": "kelvin", "tags": [1], "extra": true });
+
+        let errors = validate_arguments(&schema, &args);
+        assert!(errors.contains(&"/: missing required property 'city'".to_string()));
+        assert!(errors.contains(&"/days: expected integer, got string".to_string()));
+        assert!(errors.contains(&"/unit: \"kelvin\" is not one of the allowed values".to_string()));
+        assert!(errors.contains(&"/tags/0: expected string, got integer".to_string()));
+        assert!(errors.contains(&"/: unexpected property 'extra'".to_string()));
+        assert_eq!(errors.len(), 5);
+    }
+
+    #[test]
+    fn test_unparsed_arguments_fail_object_schema() {
+        let err = validate_tool_calls(&[weather_tool()], &[call(json!("{city: Oslo"))]).unwrap_err();
+        match err {
+            InferenceError::InvalidToolArguments { tool, errors } => {
+                assert_eq!(tool, "get_weather");
+                assert_eq!(errors, vec!["/: expected object, got string"]);
+            }
+            other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+
+    /// Returns the queued responses in order, recording each request
+    struct ScriptedProvider {
+        responses: Mutex<Vec<ChatResponse>>,
+        requests: Mutex<Vec<ChatRequest>>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for ScriptedProvider {
+        async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            self.requests.lock().expect("Mutex poisoned").push(request);
+            Ok(self.responses.lock().expect("Mutex poisoned").remove(0))
+        }
+    }
+
+    fn tool_response(arguments: Value) -> ChatResponse {
+        ChatResponse {
+            content: String::new(),
+            usage: None,
+            tool_calls: vec![call(arguments)],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repair_reprompts_with_validation_errors() {
+        let inner = Arc::new(ScriptedProvider {
+            responses: Mutex::new(vec![
+                tool_response(json!({ "days": 2 })),
+                tool_response(json!({ "city": "Oslo", "days": 2 })),
+            ]),
+            requests: Mutex::new(Vec::new()),
+        });
+        let provider = ToolValidatingProvider::new(inner.clone()).with_repair_attempts(1);
+        let request = ChatRequest {
+            tools: vec![weather_tool()],
+            ..Default::default()
+        };
+
+        let response = provider.chat(request.clone()).await.unwrap();
+        assert_eq!(response.tool_calls[0].arguments["city"], "Oslo");
+
+        {
+            let requests = inner.requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            let correction = &requests[1].messages.last().unwrap().content;
+            assert!(correction.contains("missing required property 'city'"));
+        }
+
+        // Without repair attempts the first invalid call is returned as an error
+        let inner = Arc::new(ScriptedProvider {
+            responses: Mutex::new(vec![tool_response(json!({ "days": 2 }))]),
+            requests: Mutex::new(Vec::new()),
+        });
+        let result = ToolValidatingProvider::new(inner).chat(request).await;
+        assert!(matches!(result, Err(InferenceError::InvalidToolArguments { .. })));
+    }
+}
@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::inference::postprocess::Transform;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -6,12 +9,47 @@ pub enum Role {
     System,
     User,
     Assistant,
+    /// Result of a tool call; follows the assistant message that requested it
+    Tool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
     pub content: String,
+    /// Tools an assistant turn called, answered by the `Tool` messages after it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Call a `Tool` message is the result of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl Message {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    /// An assistant turn that called `tool_calls`.
+    pub fn assistant_tool_calls(content: impl Into<String>, tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            tool_calls,
+            ..Self::new(Role::Assistant, content)
+        }
+    }
+
+    /// The result of the tool call `tool_call_id`.
+    pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(Role::Tool, content)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,12 +63,86 @@ pub struct Usage {
 pub struct ChatResponse {
     pub content: String,
     pub usage: Option<Usage>,
+    /// Tools the model asked to call, in the order it listed them
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// A function the model may call, described by a JSON Schema for its arguments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+/// A call to one of the request's [`Tool`]s, as returned by the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    /// Parsed arguments; kept as a JSON string when the model's output wasn't valid JSON
+    pub arguments: serde_json::Value,
+}
+
+/// Output format requested from the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    /// Constrain the completion to a single JSON object
+    JsonObject,
 }
 
-#[derive(Debug, Clone)]
+/// A completion request passed to [`LLMProvider::chat`](crate::inference::LLMProvider::chat).
+///
+/// Prefer [`CompletionRequestBuilder`](crate::inference::CompletionRequestBuilder),
+/// which validates the combination of fields before any call is made.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ChatRequest {
     pub model: String,
     pub messages: Vec<Message>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub tools: Vec<Tool>,
+    pub response_format: Option<ResponseFormat>,
+    /// Cleanup applied to the completion text, in order
+    pub post_process: Vec<Transform>,
+}
+
+/// A kind of work a provider can serve.
+///
+/// Used by the registry to route each capability to its own provider, e.g.
+/// chat to a hosted gateway and embeddings to a self-hosted model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    Chat,
+    Embeddings,
+}
+
+/// Outcome of screening text with a provider's moderation endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    /// Names of the categories that were flagged
+    pub categories: Vec<String>,
+}
+
+fn context_length_detail(limit: Option<u32>, requested: Option<u32>) -> String {
+    match (limit, requested) {
+        (Some(limit), Some(requested)) => format!(": requested {} tokens, limit is {}", requested, limit),
+        (Some(limit), None) => format!(": limit is {} tokens", limit),
+        (None, Some(requested)) => format!(": requested {} tokens", requested),
+        (None, None) => String::new(),
+    }
+}
+
+fn failure_list(failures: &[(String, String)]) -> String {
+    failures
+        .iter()
+        .map(|(provider, error)| format!("{}: {}", provider, error))
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -39,12 +151,71 @@ pub enum InferenceError {
     ProviderError(String),
     #[error("Rate Limit Exceeded")]
     RateLimit,
-    #[error("Context Length Exceeded")]
-    ContextLengthExceeded,
+    /// The caller's own rate limit was hit; distinct from a provider's [`RateLimit`](Self::RateLimit)
+    #[error("Rate Limited: retry after {retry_after:?}")]
+    RateLimited { retry_after: Duration },
+    /// The prompt didn't fit the model's context window. Either count may be
+    /// missing when the provider's error doesn't include it.
+    #[error("Context Length Exceeded{}", context_length_detail(*.limit, *.requested))]
+    ContextLengthExceeded {
+        limit: Option<u32>,
+        requested: Option<u32>,
+    },
     #[error("Network Error: {0}")]
     NetworkError(String),
     #[error("Configuration Error: {0}")]
     ConfigError(String),
     #[error("Provider Not Found: {0}")]
     ProviderNotFound(String),
+    #[error("Unsupported Operation: {0}")]
+    Unsupported(String),
+    #[error("Invalid Request: {0}")]
+    InvalidRequest(String),
+    /// A moderator refused the prompt or completion
+    #[error("Content Blocked: {}", categories.join(", "))]
+    ContentBlocked { categories: Vec<String> },
+    /// A tool call's arguments don't match the tool's declared schema
+    #[error("Invalid Tool Arguments for '{tool}': {}", errors.join("; "))]
+    InvalidToolArguments { tool: String, errors: Vec<String> },
+    /// An error response from the provider, with the message from its body
+    #[error("Provider Error: HTTP {status}: {message}")]
+    Http { status: u16, message: String },
+    /// An error response whose body wasn't JSON, e.g. an HTML page from a gateway
+    #[error("Upstream Error: HTTP {status}: {body}")]
+    Upstream { status: u16, body: String },
+    /// Every provider in a fallback chain failed; each failure in chain order
+    #[error("All Providers Failed: {}", failure_list(failures))]
+    AllProvidersFailed { failures: Vec<(String, String)> },
+    /// The provider was overloaded or rate limiting and said when to try again
+    #[error("Throttled: HTTP {status}, retry after {retry_after:?}")]
+    Throttled { status: u16, retry_after: Duration },
+}
+
+impl InferenceError {
+    /// HTTP status of the provider response behind this error, when known.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::RateLimit => Some(429),
+            Self::Http { status, .. } | Self::Upstream { status, .. } | Self::Throttled { status, .. } => {
+                Some(*status)
+            }
+            _ => None,
+        }
+    }
+
+    /// How long the provider asked callers to wait before retrying.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Throttled { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+
+    /// Whether the provider failed in a way that usually clears up on its
+    /// own: a network error, a rate limit, or a server error. Client errors,
+    /// like a bad request or an oversized prompt, would fail anywhere.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::NetworkError(_) | Self::Throttled { .. })
+            || self.status().is_some_and(|status| status == 429 || status >= 500)
+    }
 }
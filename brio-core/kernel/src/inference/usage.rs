@@ -0,0 +1,152 @@
+use crate::inference::provider::{LLMProvider, TokenStream};
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ModerationResult, Usage};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Counter of prompt tokens billed, labelled by provider
+pub const PROMPT_TOKENS_METRIC: &str = "brio_inference_prompt_tokens_total";
+/// Counter of completion tokens billed, labelled by provider
+pub const COMPLETION_TOKENS_METRIC: &str = "brio_inference_completion_tokens_total";
+/// Counter of all tokens billed, labelled by provider
+pub const TOTAL_TOKENS_METRIC: &str = "brio_inference_total_tokens_total";
+/// Histogram of chat call durations in seconds, labelled by provider
+pub const LATENCY_METRIC: &str = "brio_inference_latency_seconds";
+
+/// Token usage accumulated by one provider since the registry started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsageTotals {
+    /// Responses that reported usage
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &Usage) {
+        self.calls += 1;
+        self.prompt_tokens += u64::from(usage.prompt_tokens);
+        self.completion_tokens += u64::from(usage.completion_tokens);
+        self.total_tokens += u64::from(usage.total_tokens);
+    }
+}
+
+/// Usage totals by provider name, shared by the providers recording into it.
+pub type UsageLedger = Arc<Mutex<HashMap<String, UsageTotals>>>;
+
+/// Records the token usage a provider's chat responses report, into a
+/// ledger and as metrics labelled with the provider's name, along with how
+/// long each chat call took.
+///
+/// Responses without a `usage` block are passed through uncounted.
+pub struct UsageTrackingProvider {
+    name: String,
+    inner: Arc<dyn LLMProvider>,
+    ledger: UsageLedger,
+}
+
+impl UsageTrackingProvider {
+    pub fn new(name: impl Into<String>, inner: Arc<dyn LLMProvider>, ledger: UsageLedger) -> Self {
+        Self {
+            name: name.into(),
+            inner,
+            ledger,
+        }
+    }
+
+    fn record(&self, usage: &Usage) {
+        let labels = [("provider", self.name.clone())];
+        metrics::counter!(PROMPT_TOKENS_METRIC, &labels).increment(u64::from(usage.prompt_tokens));
+        metrics::counter!(COMPLETION_TOKENS_METRIC, &labels).increment(u64::from(usage.completion_tokens));
+        metrics::counter!(TOTAL_TOKENS_METRIC, &labels).increment(u64::from(usage.total_tokens));
+
+        let mut ledger = self.ledger.lock().expect("Mutex poisoned");
+        ledger.entry(self.name.clone()).or_default().add(usage);
+    }
+}
+
+#[async_trait]
+impl LLMProvider for UsageTrackingProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let started = Instant::now();
+        let result = self.inner.chat(request).await;
+        metrics::histogram!(LATENCY_METRIC, "provider" => self.name.clone()).record(started.elapsed().as_secs_f64());
+
+        let response = result?;
+        if let Some(usage) = &response.usage {
+            self.record(usage);
+        }
+        Ok(response)
+    }
+
+    fn stream_completion<'a>(&'a self, request: ChatRequest) -> TokenStream<'a> {
+        self.inner.stream_completion(request)
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.inner.embed(inputs).await
+    }
+
+    async fn embed_with_dimensions(
+        &self,
+        inputs: Vec<String>,
+        dimensions: usize,
+    ) -> Result<Vec<Vec<f32>>, InferenceError> {
+        self.inner.embed_with_dimensions(inputs, dimensions).await
+    }
+
+    async fn moderate(&self, input: String) -> Result<ModerationResult, InferenceError> {
+        self.inner.moderate(input).await
+    }
+
+    async fn health_check(&self) -> Result<(), InferenceError> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedUsage;
+
+    #[async_trait]
+    impl LLMProvider for FixedUsage {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            Ok(ChatResponse {
+                content: "ok".to_string(),
+                usage: Some(Usage {
+                    prompt_tokens: 3,
+                    completion_tokens: 2,
+                    total_tokens: 5,
+                }),
+                tool_calls: Vec::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn usage_is_emitted_as_labelled_metrics() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let metrics = recorder.handle();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        let ledger = UsageLedger::default();
+        let provider = UsageTrackingProvider::new("fixed", Arc::new(FixedUsage), Arc::clone(&ledger));
+        metrics::with_local_recorder(&recorder, || {
+            runtime.block_on(provider.chat(ChatRequest::default())).unwrap();
+        });
+
+        let rendered = metrics.render();
+        assert!(
+            rendered.contains(&format!("{}{{provider=\"fixed\"}} 3", PROMPT_TOKENS_METRIC)),
+            "{}",
+            rendered
+        );
+        assert!(rendered.contains(&format!("{}{{provider=\"fixed\"}} 5", TOTAL_TOKENS_METRIC)));
+        assert!(rendered.contains(&format!("{}_count{{provider=\"fixed\"}} 1", LATENCY_METRIC)));
+        assert_eq!(ledger.lock().unwrap()["fixed"].completion_tokens, 2);
+    }
+}
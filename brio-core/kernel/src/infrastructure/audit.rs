@@ -1,9 +1,22 @@
-use serde::Serialize;
-use tracing::{info, info_span};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber, info, info_span};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+
+use crate::ws::ClientInfo;
 
 /// Domain event for audit logging.
 /// Structured for JSON serialization to enable machine-readable audit trails.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(tag = "event_type", rename_all = "snake_case")]
 pub enum AuditEvent {
     SystemStartup {
@@ -21,17 +34,287 @@ pub enum AuditEvent {
         old_val: String,
         new_val: String,
     },
+    ClientConnected {
+        client_id: String,
+        subject: Option<String>,
+        remote_addr: Option<String>,
+    },
+    ClientDisconnected {
+        client_id: String,
+        subject: Option<String>,
+    },
+    /// A WebSocket client kept exceeding its inbound rate limit and was disconnected
+    ClientRateLimited {
+        client_id: String,
+        subject: Option<String>,
+        dropped_messages: u32,
+    },
+    /// A query policy errored instead of deciding; `allowed` records whether
+    /// the query ran anyway under a fail-open stance
+    PolicyEvaluationFailed {
+        scope: String,
+        error: String,
+        allowed: bool,
+    },
+    /// A remote node stopped answering heartbeats and was removed from the mesh
+    NodeEvicted {
+        node_id: String,
+        missed_heartbeats: u32,
+    },
+    /// A VFS session went unused past the idle timeout and was rolled back
+    SessionExpired {
+        session_id: String,
+        base_path: String,
+        idle_secs: u64,
+    },
+}
+
+impl AuditEvent {
+    /// The `event_type` tag of the serialized event, for filtering without
+    /// parsing the payload.
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            Self::SystemStartup { .. } => "system_startup",
+            Self::SystemShutdown { .. } => "system_shutdown",
+            Self::AccessDenied { .. } => "access_denied",
+            Self::ConfigChanged { .. } => "config_changed",
+            Self::ClientConnected { .. } => "client_connected",
+            Self::ClientDisconnected { .. } => "client_disconnected",
+            Self::ClientRateLimited { .. } => "client_rate_limited",
+            Self::PolicyEvaluationFailed { .. } => "policy_evaluation_failed",
+            Self::NodeEvicted { .. } => "node_evicted",
+            Self::SessionExpired { .. } => "session_expired",
+        }
+    }
+
+    pub fn client_connected(info: &ClientInfo) -> Self {
+        Self::ClientConnected {
+            client_id: info.id.to_string(),
+            subject: info.subject.clone(),
+            remote_addr: info.remote_addr.map(|addr| addr.to_string()),
+        }
+    }
+
+    pub fn client_disconnected(info: &ClientInfo) -> Self {
+        Self::ClientDisconnected {
+            client_id: info.id.to_string(),
+            subject: info.subject.clone(),
+        }
+    }
+
+    pub fn client_rate_limited(info: &ClientInfo, dropped_messages: u32) -> Self {
+        Self::ClientRateLimited {
+            client_id: info.id.to_string(),
+            subject: info.subject.clone(),
+            dropped_messages,
+        }
+    }
+}
+
+/// Events buffered per subscriber before the slowest one starts missing events
+const AUDIT_CHANNEL_CAPACITY: usize = 1024;
+
+static AUDIT_EVENTS: LazyLock<broadcast::Sender<AuditEvent>> =
+    LazyLock::new(|| broadcast::channel(AUDIT_CHANNEL_CAPACITY).0);
+
+/// Subscribes to audit events as they are emitted, e.g. to feed a control-plane stream.
+///
+/// Only events logged after subscribing are received. A subscriber that falls
+/// more than the channel capacity behind gets `RecvError::Lagged` and skips
+/// ahead; emission never waits for subscribers.
+pub fn subscribe_audit() -> broadcast::Receiver<AuditEvent> {
+    AUDIT_EVENTS.subscribe()
 }
 
 /// Logs an audit event to the dedicated audit channel as structured JSON.
 /// This uses a specific `target` which can be filtered by the subscriber to redirect to a secure file.
+///
+/// The record carries the event's `event_type` as its own field next to the
+/// serialized `event`, so SIEM tooling can filter on it directly.
 pub fn log_audit(event: AuditEvent) {
     let span = info_span!(target: "audit", "audit_event");
     let _enter = span.enter();
 
     // Serialize to JSON for machine-readable audit logs
     let json = serde_json::to_string(&event).unwrap_or_else(|e| format!("{{\"error\": \"{}\"}}", e));
-    info!(target: "audit", audit_json = %json, "Security Audit Event");
+    info!(target: "audit", event_type = event.event_type(), event = %json, "Security Audit Event");
+
+    // Fails only when nobody is subscribed
+    let _ = AUDIT_EVENTS.send(event);
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// One line of an audit file. `mac` chains the record to the one before it.
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditRecord {
+    seq: u64,
+    timestamp_ms: u64,
+    event: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+}
+
+impl AuditRecord {
+    /// HMAC over the previous record's MAC and this record without its own
+    fn chain_mac(&self, key: &[u8], prev_mac: &str) -> String {
+        let unsigned = AuditRecord {
+            seq: self.seq,
+            timestamp_ms: self.timestamp_ms,
+            event: self.event.clone(),
+            mac: None,
+        };
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(prev_mac.as_bytes());
+        mac.update(&serde_json::to_vec(&unsigned).expect("Audit records always serialize"));
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+/// Where the chain stands after the last record written
+struct ChainState {
+    file: File,
+    seq: u64,
+    prev_mac: String,
+}
+
+/// `tracing` layer that appends audit events to a file as newline-delimited JSON.
+///
+/// Every record carries a sequence number and an HMAC-SHA256 over the
+/// previous record's MAC and its own contents, so editing, deleting or
+/// reordering lines breaks the chain; see [`verify_chain`]. Cutting records
+/// off the end of the file can't be detected from the file alone, so ship it
+/// somewhere append-only if that matters.
+///
+/// An existing file is appended to, carrying its chain on.
+pub struct AuditFileLayer {
+    key: Vec<u8>,
+    state: Mutex<ChainState>,
+}
+
+impl AuditFileLayer {
+    pub fn open(path: impl AsRef<Path>, key: impl Into<Vec<u8>>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let (seq, prev_mac) = match File::open(path) {
+            Ok(file) => last_link(file)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (0, String::new()),
+            Err(e) => return Err(e),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            key: key.into(),
+            state: Mutex::new(ChainState { file, seq, prev_mac }),
+        })
+    }
+
+    fn append(&self, event: serde_json::Value) {
+        let mut state = self.state.lock().expect("Mutex poisoned");
+        let mut record = AuditRecord {
+            seq: state.seq + 1,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            event,
+            mac: None,
+        };
+        let mac = record.chain_mac(&self.key, &state.prev_mac);
+        record.mac = Some(mac.clone());
+
+        let mut line = serde_json::to_vec(&record).expect("Audit records always serialize");
+        line.push(b'\n');
+        // Logging from inside a layer would recurse, so stderr it is
+        if let Err(e) = state.file.write_all(&line) {
+            eprintln!("Audit: failed to append to audit file: {}", e);
+            return;
+        }
+        state.seq = record.seq;
+        state.prev_mac = mac;
+    }
+}
+
+/// Sequence number and MAC of the last record in an existing audit file
+fn last_link(file: File) -> std::io::Result<(u64, String)> {
+    let mut last = (0, String::new());
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if let Ok(AuditRecord { seq, mac: Some(mac), .. }) = serde_json::from_str(&line) {
+            last = (seq, mac);
+        }
+    }
+    Ok(last)
+}
+
+impl<S: Subscriber> Layer<S> for AuditFileLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != "audit" {
+            return;
+        }
+        let mut visitor = AuditFieldVisitor::default();
+        event.record(&mut visitor);
+        self.append(visitor.into_event());
+    }
+}
+
+/// Picks the serialized [`AuditEvent`] out of a `log_audit` record, keeping
+/// the plain fields of any other event logged to the audit target.
+#[derive(Default)]
+struct AuditFieldVisitor {
+    event: Option<serde_json::Value>,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl AuditFieldVisitor {
+    fn into_event(self) -> serde_json::Value {
+        self.event.unwrap_or(serde_json::Value::Object(self.fields))
+    }
+}
+
+impl Visit for AuditFieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let value = format!("{:?}", value);
+        if field.name() == "event" {
+            self.event = serde_json::from_str(&value).ok();
+        }
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditChainError {
+    #[error("Failed to read audit file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Audit record on line {line} is not valid JSON")]
+    Malformed { line: usize },
+    #[error("Audit chain broken at line {line}")]
+    Broken { line: usize },
+}
+
+/// Checks every record of an audit file written by [`AuditFileLayer`] against
+/// `key`, returning how many records there are.
+///
+/// Fails at the first line that was edited, inserted, removed or moved.
+pub fn verify_chain(path: impl AsRef<Path>, key: &[u8]) -> Result<usize, AuditChainError> {
+    let file = File::open(path)?;
+    let mut prev_mac = String::new();
+    let mut count = 0;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let number = index + 1;
+        let record: AuditRecord =
+            serde_json::from_str(&line).map_err(|_| AuditChainError::Malformed { line: number })?;
+        let expected = record.chain_mac(key, &prev_mac);
+        if record.seq != count as u64 + 1 || record.mac.as_deref() != Some(expected.as_str()) {
+            return Err(AuditChainError::Broken { line: number });
+        }
+        prev_mac = expected;
+        count += 1;
+    }
+    Ok(count)
 }
 
 #[cfg(test)]
@@ -57,4 +340,178 @@ mod tests {
             new_val: "8080".into(),
         });
     }
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_audit_log_line_is_json_with_event_type() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            log_audit(AuditEvent::NodeEvicted {
+                node_id: "node-7".into(),
+                missed_heartbeats: 3,
+            });
+        });
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.trim()).expect("log line is not JSON");
+        let fields = &line["fields"];
+        assert_eq!(line["target"], "audit");
+        assert_eq!(fields["event_type"], "node_evicted");
+
+        let event: serde_json::Value =
+            serde_json::from_str(fields["event"].as_str().unwrap()).expect("event is not JSON");
+        assert_eq!(event["event_type"], "node_evicted");
+        assert_eq!(event["node_id"], "node-7");
+        assert_eq!(event["missed_heartbeats"], 3);
+    }
+
+    #[test]
+    fn test_event_type_matches_serialized_tag() {
+        let info = ClientInfo::new(None, None);
+        let events = [
+            AuditEvent::SystemStartup { component: "c".into() },
+            AuditEvent::SystemShutdown { reason: "r".into() },
+            AuditEvent::AccessDenied {
+                user: "u".into(),
+                resource: "r".into(),
+            },
+            AuditEvent::ConfigChanged {
+                key: "k".into(),
+                old_val: "a".into(),
+                new_val: "b".into(),
+            },
+            AuditEvent::client_connected(&info),
+            AuditEvent::client_disconnected(&info),
+            AuditEvent::client_rate_limited(&info, 10),
+            AuditEvent::PolicyEvaluationFailed {
+                scope: "s".into(),
+                error: "e".into(),
+                allowed: false,
+            },
+            AuditEvent::NodeEvicted {
+                node_id: "n".into(),
+                missed_heartbeats: 3,
+            },
+            AuditEvent::SessionExpired {
+                session_id: "s".into(),
+                base_path: "/b".into(),
+                idle_secs: 60,
+            },
+        ];
+        for event in events {
+            assert_eq!(serde_json::to_value(&event).unwrap()["event_type"], event.event_type());
+        }
+    }
+
+    fn write_audit_file(path: &Path, key: &[u8], components: &[&str]) {
+        use tracing_subscriber::layer::SubscriberExt;
+        let layer = AuditFileLayer::open(path, key.to_vec()).unwrap();
+        let subscriber = tracing_subscriber::Registry::default().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Not an audit event");
+            for component in components {
+                log_audit(AuditEvent::SystemStartup {
+                    component: component.to_string(),
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_audit_file_chain_detects_tampering() {
+        let path = std::env::temp_dir().join(format!("brio_audit_{}.jsonl", uuid::Uuid::new_v4()));
+        let key = b"audit-test-key";
+        write_audit_file(&path, key, &["a", "b"]);
+        // Reopening carries the chain on
+        write_audit_file(&path, key, &["c"]);
+        assert_eq!(verify_chain(&path, key).unwrap(), 3);
+        assert!(matches!(verify_chain(&path, b"wrong-key"), Err(AuditChainError::Broken { line: 1 })));
+
+        let original = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = original.lines().collect();
+        let record: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(record["event"]["component"], "b");
+
+        let corrupted = original.replacen("\"component\":\"b\"", "\"component\":\"x\"", 1);
+        std::fs::write(&path, corrupted).unwrap();
+        assert!(matches!(verify_chain(&path, key), Err(AuditChainError::Broken { line: 2 })));
+
+        let reordered = format!("{}\n{}\n{}\n", lines[0], lines[2], lines[1]);
+        std::fs::write(&path, reordered).unwrap();
+        assert!(matches!(verify_chain(&path, key), Err(AuditChainError::Broken { line: 2 })));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_client_events_carry_subject() {
+        let info = ClientInfo::new(Some("alice".into()), Some("127.0.0.1:9000".parse().unwrap()));
+
+        let connected = serde_json::to_value(AuditEvent::client_connected(&info)).unwrap();
+        assert_eq!(connected["event_type"], "client_connected");
+        assert_eq!(connected["subject"], "alice");
+        assert_eq!(connected["client_id"], info.id.to_string());
+        assert_eq!(connected["remote_addr"], "127.0.0.1:9000");
+
+        let disconnected = serde_json::to_value(AuditEvent::client_disconnected(&info)).unwrap();
+        assert_eq!(disconnected["subject"], "alice");
+        assert_eq!(disconnected["client_id"], info.id.to_string());
+    }
+
+    /// Receives events until one for `component` shows up; other tests share the channel.
+    async fn recv_startup(rx: &mut broadcast::Receiver<AuditEvent>, component: &str) {
+        loop {
+            match rx.recv().await {
+                Ok(AuditEvent::SystemStartup { component: c }) if c == component => return,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(e) => panic!("Audit channel closed: {}", e),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_receives_emitted_events() {
+        let mut rx = subscribe_audit();
+        log_audit(AuditEvent::SystemStartup {
+            component: "audit-subscriber-test".into(),
+        });
+
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            recv_startup(&mut rx, "audit-subscriber-test"),
+        )
+        .await
+        .expect("Subscriber did not receive the event");
+    }
+
+    #[test]
+    fn test_stalled_and_dropped_subscribers_do_not_block_emission() {
+        let _stalled = subscribe_audit();
+        drop(subscribe_audit());
+
+        // Well past the channel capacity; returning at all means nothing blocked
+        for _ in 0..AUDIT_CHANNEL_CAPACITY * 2 {
+            log_audit(AuditEvent::SystemShutdown {
+                reason: "burst".into(),
+            });
+        }
+    }
 }
@@ -1,6 +1,12 @@
 use config::{Config, ConfigError, Environment};
 use secrecy::SecretString;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::inference::{HistoryPolicy, SelectionStrategy};
+use crate::store::{PolicyFailureMode, ValueSizeLimits};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
@@ -9,12 +15,58 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub mesh: Option<MeshSettings>,
     pub inference: Option<InferenceSettings>,
+    #[serde(default)]
+    pub sessions: SessionSettings,
+    #[serde(default)]
+    pub ws: WsSettings,
+    #[serde(default)]
+    pub requests: RequestSettings,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ServerSettings {
     pub host: String,
     pub port: u16,
+    /// Shortest time between two health sweeps triggered on the control plane
+    #[serde(default = "default_health_sweep_interval_secs")]
+    pub health_sweep_interval_secs: u64,
+    /// How long shutdown waits for in-flight mesh calls and control plane
+    /// requests to finish
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+    /// Require `Authorization: Bearer <token>` on every control plane route
+    /// except the health probes; `/ws` also takes `?access_token=<token>`
+    #[serde(default)]
+    pub auth_token: Option<SecretString>,
+    /// Cross-origin access for browser clients; off unless origins are listed
+    #[serde(default)]
+    pub cors: CorsSettings,
+}
+
+/// Which browser origins may call the control plane.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CorsSettings {
+    /// Origins allowed to make cross-origin requests, e.g. `https://app.example.com`
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Methods allowed on cross-origin requests; empty allows `GET` and `POST`
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    /// Headers cross-origin requests may carry; empty allows `authorization`
+    /// and `content-type`
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    /// Allow any origin, method and header; for local development only
+    #[serde(default)]
+    pub permissive: bool,
+}
+
+fn default_health_sweep_interval_secs() -> u64 {
+    crate::infrastructure::health::DEFAULT_SWEEP_INTERVAL.as_secs()
+}
+
+fn default_drain_timeout_secs() -> u64 {
+    30
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -23,6 +75,19 @@ pub struct TelemetrySettings {
     pub otlp_endpoint: Option<String>,
     #[serde(default = "default_sampling")]
     pub sampling_ratio: f64,
+    /// Refuse to start if telemetry can't be initialized, instead of
+    /// continuing with local logging only
+    #[serde(default)]
+    pub strict: bool,
+    /// Append audit events to this file as a tamper-evident HMAC chain
+    #[serde(default)]
+    pub audit_file: Option<PathBuf>,
+    /// Key for the audit file's HMAC chain; required with `audit_file`
+    #[serde(default)]
+    pub audit_key: Option<SecretString>,
+    /// Serve Prometheus metrics at `http://<addr>/metrics`
+    #[serde(default)]
+    pub prometheus_addr: Option<SocketAddr>,
 }
 
 fn default_sampling() -> f64 {
@@ -31,13 +96,72 @@ fn default_sampling() -> f64 {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DatabaseSettings {
+    /// `sqlite:` path, or a `postgres://` URL with the `postgres` feature
     pub url: SecretString,
+    /// Retries for writes that fail because the database is locked
+    #[serde(default = "default_busy_retries")]
+    pub busy_retries: u32,
+    /// Whether queries are denied (`closed`) or allowed (`open`) when the
+    /// store's policy errors instead of deciding
+    #[serde(default)]
+    pub policy_failure_mode: PolicyFailureMode,
+    /// Largest encoded value a key-value write may store, optionally per scope
+    #[serde(default)]
+    pub value_size_limits: ValueSizeLimits,
+}
+
+fn default_busy_retries() -> u32 {
+    crate::store::DEFAULT_BUSY_RETRIES
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct MeshSettings {
     pub node_id: Option<String>,
     pub port: Option<u16>,
+    /// Seconds a node may miss heartbeats before its traffic is rerouted, and
+    /// at least how long before it is evicted
+    pub dead_node_grace_secs: Option<u64>,
+    /// Seconds between heartbeats to known nodes
+    pub heartbeat_interval_secs: Option<u64>,
+    /// Seconds a mesh call waits for its reply before failing
+    pub call_timeout_secs: Option<u64>,
+    /// Payload size in bytes from which mesh calls and replies are compressed
+    pub compression_threshold_bytes: Option<usize>,
+    /// Consecutive calls to a target that fail to reach it or time out, after
+    /// which calls to it fail fast for a while; unset never fails calls fast
+    pub circuit_breaker_failures: Option<u32>,
+    /// Seconds calls to a target fail fast before one is let through to probe it
+    #[serde(default = "default_circuit_breaker_cool_down_secs")]
+    pub circuit_breaker_cool_down_secs: u64,
+    /// Encrypt mesh traffic; cleartext when absent
+    pub tls: Option<MeshTlsSettings>,
+    /// Nodes to join the mesh through, as node id to address, e.g.
+    /// `node-b = "10.0.0.2:50051"`; the rest are learned through gossip
+    #[serde(default)]
+    pub bootstrap_nodes: HashMap<String, String>,
+    /// Address other nodes reach this one at; defaults to the bound mesh
+    /// address, which is only usable when it isn't a wildcard
+    pub advertise_address: Option<String>,
+    /// Seconds between gossip rounds with a random peer; unset disables gossip
+    pub gossip_interval_secs: Option<u64>,
+    /// Seconds a node may go unheard of, directly or through gossip, before it is pruned
+    pub gossip_ttl_secs: Option<u64>,
+}
+
+fn default_circuit_breaker_cool_down_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MeshTlsSettings {
+    /// PEM certificate chain this node presents, as server and as client
+    pub cert_path: PathBuf,
+    /// PEM private key for `cert_path`
+    pub key_path: PathBuf,
+    /// PEM CA certificate; when set, peers must present a certificate it signed
+    pub ca_path: Option<PathBuf>,
+    /// Name to check other nodes' certificates against, instead of their address
+    pub domain_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -45,6 +169,263 @@ pub struct InferenceSettings {
     pub openai_api_key: Option<SecretString>,
     pub anthropic_api_key: Option<SecretString>,
     pub openai_base_url: Option<String>,
+    /// Log completions from the OpenAI provider slower than this
+    pub slow_request_threshold_ms: Option<u64>,
+    /// Separate endpoint for embeddings; chat keeps using `openai_base_url`
+    pub embeddings_base_url: Option<String>,
+    pub embeddings_api_key: Option<SecretString>,
+    pub embeddings_model: Option<String>,
+    /// Reduce embeddings to this many dimensions, natively where the model
+    /// supports it and by truncation otherwise
+    pub embeddings_dimensions: Option<usize>,
+    /// Embeddings remembered per provider, evicting the least recently used;
+    /// 0 disables caching but identical in-flight requests still share a call
+    #[serde(default = "default_embedding_cache_capacity")]
+    pub embedding_cache_capacity: usize,
+    /// How completions are dispatched across providers (defaults to by_capability)
+    #[serde(default)]
+    pub selection: SelectionStrategy,
+    /// History trimming applied before dispatch, keyed by provider name
+    #[serde(default)]
+    pub history: HashMap<String, HistoryPolicy>,
+    /// Region this kernel runs in (e.g. from `BRIO__INFERENCE__REGION`)
+    pub region: Option<String>,
+    /// Region label of each provider, keyed by provider name
+    #[serde(default)]
+    pub provider_regions: HashMap<String, String>,
+    /// Inference rate limits keyed by tenant
+    #[serde(default)]
+    pub tenant_limits: HashMap<String, crate::inference::TenantLimits>,
+    /// Block prompts and completions flagged by the provider's moderation endpoint
+    #[serde(default)]
+    pub moderation: bool,
+    /// Check tool call arguments against the declared tool schemas
+    #[serde(default)]
+    pub validate_tool_arguments: bool,
+    /// Persist every completion request so it can be replayed for debugging
+    #[serde(default)]
+    pub record_requests: bool,
+    /// Strings blanked out of recorded requests, in addition to the configured API keys
+    #[serde(default)]
+    pub record_redactions: Vec<String>,
+    /// Times the model is asked to fix invalid tool arguments before failing
+    #[serde(default)]
+    pub tool_repair_attempts: u32,
+    /// Completion cleanup for requests that don't specify their own
+    #[serde(default)]
+    pub post_process: Vec<crate::inference::Transform>,
+    /// Providers tried in order by fallback inference
+    #[serde(default)]
+    pub fallback_chain: Vec<String>,
+    /// Times a failed provider call is retried on 429/5xx or network errors;
+    /// 0 leaves retries to the providers themselves
+    #[serde(default)]
+    pub retry_attempts: u32,
+    /// First retry delay, doubled on each further attempt
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+}
+
+fn default_embedding_cache_capacity() -> usize {
+    crate::inference::embedding_cache::DEFAULT_EMBEDDING_CACHE_CAPACITY
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    500
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RequestSettings {
+    /// Retries a single request may spend across provider, mesh and store layers
+    #[serde(default = "default_retry_budget")]
+    pub retry_budget: u32,
+}
+
+fn default_retry_budget() -> u32 {
+    crate::infrastructure::context::DEFAULT_RETRY_BUDGET
+}
+
+impl Default for RequestSettings {
+    fn default() -> Self {
+        Self {
+            retry_budget: default_retry_budget(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SessionSettings {
+    /// Snapshot active VFS sessions on shutdown and restore them on boot,
+    /// instead of aborting them
+    #[serde(default)]
+    pub persist_on_shutdown: bool,
+    #[serde(default = "default_session_persist_dir")]
+    pub persist_dir: String,
+    /// Roll back sessions left unused this long; 0 keeps them until they end
+    #[serde(default)]
+    pub idle_timeout_secs: u64,
+    /// How often to look for idle sessions
+    #[serde(default = "default_session_reap_interval_secs")]
+    pub reap_interval_secs: u64,
+}
+
+fn default_session_reap_interval_secs() -> u64 {
+    60
+}
+
+fn default_session_persist_dir() -> String {
+    ".brio/sessions".to_string()
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            persist_on_shutdown: false,
+            persist_dir: default_session_persist_dir(),
+            idle_timeout_secs: 0,
+            reap_interval_secs: default_session_reap_interval_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WsSettings {
+    /// Pretty-print patch frames (compact by default)
+    #[serde(default)]
+    pub pretty_json: bool,
+    /// Drop null members inside patch values
+    #[serde(default = "default_true")]
+    pub skip_nulls: bool,
+    /// Largest frame a single broadcast may produce
+    #[serde(default = "default_max_message_bytes")]
+    pub max_message_bytes: usize,
+    /// Batch streamed tokens for this many milliseconds per frame; 0 disables
+    #[serde(default)]
+    pub stream_coalesce_window_ms: u64,
+    /// Send a coalesced frame early once it reaches this size
+    #[serde(default = "default_stream_coalesce_max_bytes")]
+    pub stream_coalesce_max_bytes: usize,
+    /// Send a heartbeat frame when a token stream is quiet for this many milliseconds; 0 disables
+    #[serde(default)]
+    pub stream_heartbeat_interval_ms: u64,
+    /// Topics whose last message is replayed to new subscribers
+    #[serde(default)]
+    pub retained_topics: Vec<String>,
+    /// Buffer absorbing broadcast bursts; unset sends straight to the channel
+    #[serde(default)]
+    pub write_ahead: Option<crate::ws::WriteAheadOptions>,
+    /// Seconds between keepalive pings to each client
+    #[serde(default = "default_ping_interval_secs")]
+    pub ping_interval_secs: u64,
+    /// Close connections with no client activity (pongs included) for this many seconds; 0 disables
+    #[serde(default)]
+    pub idle_timeout_secs: u64,
+    /// Seconds a client has to answer each ping; two unanswered in a row close
+    /// the connection. 0 disables
+    #[serde(default = "default_pong_timeout_secs")]
+    pub pong_timeout_secs: u64,
+    /// Inbound messages each client may send per second; 0 disables the limit
+    #[serde(default = "default_inbound_messages_per_sec")]
+    pub inbound_messages_per_sec: u32,
+    /// Inbound messages a client may send back to back before being throttled
+    #[serde(default = "default_inbound_burst")]
+    pub inbound_burst: u32,
+    /// Close a client after this many throttled messages, less one forgiven
+    /// each second; 0 never closes
+    #[serde(default = "default_inbound_max_violations")]
+    pub inbound_max_violations: u32,
+    /// How long shutdown waits for clients to receive queued messages
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// Close connections that haven't sent their upgrade request within this many seconds; 0 disables
+    #[serde(default = "default_handshake_timeout_secs")]
+    pub handshake_timeout_secs: u64,
+    /// Keep this many numbered patches so reconnecting clients can resume; 0 disables
+    #[serde(default)]
+    pub replay_capacity: usize,
+    /// Refuse WebSocket clients beyond this many at once; 0 is unbounded
+    #[serde(default)]
+    pub max_clients: usize,
+    /// Messages buffered per subscriber before a slow one starts lagging
+    #[serde(default = "default_broadcast_capacity")]
+    pub broadcast_capacity: usize,
+    /// Store scopes sent as a full snapshot to clients that lag; empty only
+    /// tells them to resync
+    #[serde(default)]
+    pub snapshot_scopes: Vec<String>,
+}
+
+fn default_broadcast_capacity() -> usize {
+    crate::ws::broadcaster::DEFAULT_BROADCAST_CAPACITY
+}
+
+fn default_handshake_timeout_secs() -> u64 {
+    crate::ws::handler::DEFAULT_HANDSHAKE_TIMEOUT.as_secs()
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    5
+}
+
+fn default_ping_interval_secs() -> u64 {
+    crate::ws::ConnectionOptions::default().ping_interval.as_secs()
+}
+
+fn default_pong_timeout_secs() -> u64 {
+    crate::ws::ConnectionOptions::default()
+        .pong_timeout
+        .map_or(0, |timeout| timeout.as_secs())
+}
+
+fn default_inbound_messages_per_sec() -> u32 {
+    crate::ws::InboundRateLimit::default().messages_per_sec
+}
+
+fn default_inbound_burst() -> u32 {
+    crate::ws::InboundRateLimit::default().burst
+}
+
+fn default_inbound_max_violations() -> u32 {
+    crate::ws::InboundRateLimit::default().max_violations
+}
+
+fn default_stream_coalesce_max_bytes() -> usize {
+    crate::ws::CoalesceOptions::default().max_bytes
+}
+
+fn default_max_message_bytes() -> usize {
+    crate::ws::broadcaster::DEFAULT_MAX_MESSAGE_BYTES
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for WsSettings {
+    fn default() -> Self {
+        Self {
+            pretty_json: false,
+            skip_nulls: true,
+            max_message_bytes: default_max_message_bytes(),
+            stream_coalesce_window_ms: 0,
+            stream_coalesce_max_bytes: default_stream_coalesce_max_bytes(),
+            stream_heartbeat_interval_ms: 0,
+            retained_topics: Vec::new(),
+            write_ahead: None,
+            ping_interval_secs: default_ping_interval_secs(),
+            idle_timeout_secs: 0,
+            pong_timeout_secs: default_pong_timeout_secs(),
+            inbound_messages_per_sec: default_inbound_messages_per_sec(),
+            inbound_burst: default_inbound_burst(),
+            inbound_max_violations: default_inbound_max_violations(),
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            handshake_timeout_secs: default_handshake_timeout_secs(),
+            replay_capacity: 0,
+            max_clients: 0,
+            broadcast_capacity: default_broadcast_capacity(),
+            snapshot_scopes: Vec::new(),
+        }
+    }
 }
 
 impl Settings {
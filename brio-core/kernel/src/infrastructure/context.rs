@@ -0,0 +1,176 @@
+//! Per-request context shared by every layer that handles a single request.
+//!
+//! The context is carried in a tokio task-local, so layers deep in the call
+//! stack (providers, mesh, store) can reach it without threading it through
+//! every signature. Code running outside a request scope sees no context.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Default number of retries a single request may spend across all layers
+pub const DEFAULT_RETRY_BUDGET: u32 = 5;
+
+tokio::task_local! {
+    static CURRENT: RequestContext;
+}
+
+/// Retry allowance shared across all layers handling one request.
+///
+/// Each retry at any layer consumes one unit; once the budget is spent no
+/// layer retries further, which keeps nested retry loops from multiplying.
+#[derive(Debug)]
+pub struct RetryBudget {
+    remaining: AtomicU32,
+}
+
+impl RetryBudget {
+    pub fn new(retries: u32) -> Self {
+        Self {
+            remaining: AtomicU32::new(retries),
+        }
+    }
+
+    /// Consumes one retry, returning false if the budget is already spent.
+    pub fn try_consume(&self) -> bool {
+        self.remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.remaining.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    retry_budget: Arc<RetryBudget>,
+    /// Ids of the completion requests recorded while handling this request
+    recorded: Arc<Mutex<Vec<String>>>,
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETRY_BUDGET)
+    }
+}
+
+impl RequestContext {
+    pub fn new(retry_budget: u32) -> Self {
+        Self {
+            retry_budget: Arc::new(RetryBudget::new(retry_budget)),
+            recorded: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn retry_budget(&self) -> &RetryBudget {
+        &self.retry_budget
+    }
+
+    /// Ids of the completions recorded for this request, oldest first;
+    /// see [`BrioHostState::replay`](crate::host::BrioHostState::replay).
+    pub fn recorded_requests(&self) -> Vec<String> {
+        self.recorded.lock().expect("Mutex poisoned").clone()
+    }
+
+    pub(crate) fn add_recorded_request(&self, request_id: String) {
+        self.recorded.lock().expect("Mutex poisoned").push(request_id);
+    }
+
+    /// Returns the context of the request currently being handled, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|ctx| ctx.clone()).ok()
+    }
+
+    /// Runs `fut` with this context as the current request context.
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self, fut).await
+    }
+
+    /// Runs `fut` inside the current request if there is one, otherwise
+    /// starts a new request from `self`. Nested calls share one budget.
+    pub async fn inherit_or<F: Future>(self, fut: F) -> F::Output {
+        Self::current().unwrap_or(self).scope(fut).await
+    }
+}
+
+/// Asks the current request for permission to retry.
+///
+/// Outside a request scope retries are bounded only by the caller's own limit.
+pub fn try_consume_retry() -> bool {
+    CURRENT
+        .try_with(|ctx| ctx.retry_budget.try_consume())
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Retry loop as used by each layer: bounded by its own limit and the budget.
+    fn attempts_with_retries(max_retries: u32, mut op: impl FnMut() -> bool) -> bool {
+        for attempt in 0..=max_retries {
+            if op() {
+                return true;
+            }
+            if attempt >= max_retries || !try_consume_retry() {
+                break;
+            }
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_nested_retries_stop_when_budget_spent() {
+        let mut inner_calls = 0;
+
+        RequestContext::new(4)
+            .scope(async {
+                // Outer layer retries up to 3 times, inner layer up to 3 times:
+                // 16 attempts without a shared budget.
+                attempts_with_retries(3, || {
+                    attempts_with_retries(3, || {
+                        inner_calls += 1;
+                        false
+                    })
+                });
+                assert_eq!(RequestContext::current().unwrap().retry_budget().remaining(), 0);
+            })
+            .await;
+
+        // First attempt plus 4 budgeted retries, the last of which is spent
+        // by the outer layer and leaves nothing for the inner one.
+        assert_eq!(inner_calls, 5);
+    }
+
+    #[tokio::test]
+    async fn test_no_context_does_not_limit_retries() {
+        assert!(RequestContext::current().is_none());
+        for _ in 0..100 {
+            assert!(try_consume_retry());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inherit_or_shares_outer_budget() {
+        RequestContext::new(2)
+            .scope(async {
+                RequestContext::new(10)
+                    .inherit_or(async {
+                        assert!(try_consume_retry());
+                    })
+                    .await;
+                assert_eq!(RequestContext::current().unwrap().retry_budget().remaining(), 1);
+            })
+            .await;
+    }
+
+    #[test]
+    fn test_budget_never_underflows() {
+        let budget = RetryBudget::new(1);
+        assert!(budget.try_consume());
+        assert!(!budget.try_consume());
+        assert_eq!(budget.remaining(), 0);
+    }
+}
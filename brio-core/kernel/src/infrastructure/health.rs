@@ -0,0 +1,120 @@
+//! On-demand health sweeps over mesh nodes and inference providers, and the
+//! readiness probe.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default shortest time between two sweeps triggered on the control plane
+pub const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+/// Readiness check that the database answers a query
+pub const DATABASE_CHECK: &str = "database";
+/// Readiness check that a default inference provider is registered
+pub const DEFAULT_PROVIDER_CHECK: &str = "default_provider";
+
+/// Outcome of checking one node or provider.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub healthy: bool,
+    /// Why the check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub latency_ms: u64,
+}
+
+impl ComponentHealth {
+    pub fn new(name: impl Into<String>, result: Result<(), String>, latency: Duration) -> Self {
+        Self {
+            name: name.into(),
+            healthy: result.is_ok(),
+            error: result.err(),
+            latency_ms: latency.as_millis() as u64,
+        }
+    }
+}
+
+/// Results of a [`BrioHostState::health_sweep`](crate::host::BrioHostState::health_sweep).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    /// True when every node and provider is healthy
+    pub healthy: bool,
+    /// Remote mesh nodes, by node id; empty in standalone mode
+    pub nodes: Vec<ComponentHealth>,
+    /// Registered inference providers, by name
+    pub providers: Vec<ComponentHealth>,
+}
+
+impl HealthReport {
+    pub fn new(nodes: Vec<ComponentHealth>, providers: Vec<ComponentHealth>) -> Self {
+        Self {
+            healthy: nodes.iter().chain(&providers).all(|c| c.healthy),
+            nodes,
+            providers,
+        }
+    }
+}
+
+/// Results of a [`BrioHostState::readiness`](crate::host::BrioHostState::readiness) probe.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReadinessReport {
+    /// True when every check passed
+    pub ready: bool,
+    /// The [`DATABASE_CHECK`] and [`DEFAULT_PROVIDER_CHECK`], in that order
+    pub checks: Vec<ComponentHealth>,
+}
+
+impl ReadinessReport {
+    pub fn new(checks: Vec<ComponentHealth>) -> Self {
+        Self {
+            ready: checks.iter().all(|c| c.healthy),
+            checks,
+        }
+    }
+}
+
+/// Lets at most one sweep start per interval, since each one fans out to
+/// every node and provider.
+#[derive(Debug)]
+pub struct SweepLimiter {
+    interval: Duration,
+    last: Mutex<Option<Instant>>,
+}
+
+impl SweepLimiter {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Claims the next sweep, or returns how long until one is allowed.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut last = self.last.lock().expect("Mutex poisoned");
+        let now = Instant::now();
+        if let Some(previous) = *last {
+            let elapsed = now.saturating_duration_since(previous);
+            if elapsed < self.interval {
+                return Err(self.interval - elapsed);
+            }
+        }
+        *last = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limiter_allows_one_sweep_per_interval() {
+        let limiter = SweepLimiter::new(Duration::from_secs(60));
+        assert!(limiter.try_acquire().is_ok());
+        let wait = limiter.try_acquire().unwrap_err();
+        assert!(wait > Duration::from_secs(59));
+
+        assert!(SweepLimiter::new(Duration::ZERO).try_acquire().is_ok());
+    }
+}
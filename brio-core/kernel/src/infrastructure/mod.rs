@@ -1,4 +1,6 @@
 pub mod audit;
 pub mod config;
+pub mod context;
+pub mod health;
 pub mod server;
 pub mod telemetry;
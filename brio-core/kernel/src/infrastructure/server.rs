@@ -1,8 +1,24 @@
-use crate::infrastructure::config::Settings;
-use crate::ws::{Broadcaster, handler::ws_router};
-use axum::{Router, routing::get};
-use metrics_exporter_prometheus::PrometheusBuilder;
+use crate::host::BrioHostState;
+use crate::infrastructure::audit::{AuditEvent, log_audit};
+use crate::infrastructure::config::{CorsSettings, Settings};
+use crate::infrastructure::health::SweepLimiter;
+use crate::infrastructure::telemetry::{LogLevelError, LogLevelHandle, prometheus_handle};
+use crate::ws::AuthenticatedSubject;
+use crate::ws::handler::{with_handshake_timeout, ws_router};
+use axum::{
+    Json, Router,
+    extract::{ConnectInfo, Query, Request, State},
+    http::{HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::CorsLayer;
 
 #[cfg(unix)]
 use pprof::protos::Message;
@@ -56,20 +72,237 @@ async fn pprof_profile() -> impl axum::response::IntoResponse {
     )
 }
 
-/// Runs the control plane HTTP server with WebSocket support.
-pub async fn run_server(config: &Settings, broadcaster: Broadcaster) -> anyhow::Result<()> {
-    let builder = PrometheusBuilder::new();
-    let handle = builder
-        .install_recorder()
-        .expect("failed to install Prometheus recorder");
+#[derive(Clone)]
+struct SweepState {
+    host: Arc<BrioHostState>,
+    limiter: Arc<SweepLimiter>,
+}
+
+/// Runs a health sweep and returns the report; 503 if anything is unhealthy,
+/// 429 if the previous sweep was too recent.
+async fn health_sweep(State(state): State<SweepState>) -> Response {
+    if let Err(wait) = state.limiter.try_acquire() {
+        let retry_after = wait.as_secs_f64().ceil() as u64;
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            Json(serde_json::json!({ "error": "Health sweep rate limited", "retry_after_secs": retry_after })),
+        )
+            .into_response();
+    }
 
-    let control_plane = Router::new()
+    let report = state.host.health_sweep().await;
+    let status = if report.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report)).into_response()
+}
+
+/// Serves `POST /health/sweep`, allowing one sweep per `min_interval`.
+pub fn health_sweep_router(host: Arc<BrioHostState>, min_interval: Duration) -> Router {
+    Router::new()
+        .route("/health/sweep", post(health_sweep))
+        .with_state(SweepState {
+            host,
+            limiter: Arc::new(SweepLimiter::new(min_interval)),
+        })
+}
+
+/// 200 with the report if the kernel can serve requests, otherwise 503
+/// naming the check that failed.
+async fn readiness(State(host): State<Arc<BrioHostState>>) -> Response {
+    let report = host.readiness().await;
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report)).into_response()
+}
+
+/// Serves the `/healthz` liveness probe, which passes while the process is
+/// up, and the `/readyz` readiness probe; see [`BrioHostState::readiness`].
+/// `/health/live` and `/health/ready` are kept as older names for the same.
+pub fn probe_router(host: Arc<BrioHostState>) -> Router {
+    Router::new()
+        .route("/healthz", get(health_check))
         .route("/health/live", get(health_check))
-        .route("/health/ready", get(health_check))
+        .route("/readyz", get(readiness))
+        .route("/health/ready", get(readiness))
+        .with_state(host)
+}
+
+#[derive(serde::Deserialize)]
+struct LogLevelRequest {
+    /// Filter directives in `RUST_LOG` syntax
+    level: String,
+}
+
+/// Swaps in a new log filter; 400 if the directives don't parse.
+async fn set_log_level(State(log_level): State<LogLevelHandle>, Json(request): Json<LogLevelRequest>) -> Response {
+    match log_level.set(&request.level) {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({ "level": log_level.current() }))).into_response(),
+        Err(e @ LogLevelError::InvalidDirective { .. }) => {
+            (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() }))).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// Serves `POST /admin/log-level`, which takes `{"level": "<directives>"}`
+/// and changes the log filter without a restart.
+pub fn admin_router(log_level: LogLevelHandle) -> Router {
+    Router::new()
+        .route("/admin/log-level", post(set_log_level))
+        .with_state(log_level)
+}
+
+/// Subject recorded for clients that presented the control plane's bearer token
+pub const BEARER_TOKEN_SUBJECT: &str = "bearer-token";
+
+/// Compares digests rather than the tokens themselves, so the time taken
+/// reveals neither the token's length nor how much of it matched.
+fn tokens_match(presented: &str, expected: &str) -> bool {
+    let (presented, expected) = (Sha256::digest(presented), Sha256::digest(expected));
+    presented.iter().zip(expected.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[derive(serde::Deserialize)]
+struct AccessTokenParams {
+    access_token: Option<String>,
+}
+
+/// The token from `Authorization: Bearer <token>`, or for a WebSocket
+/// upgrade, from `?access_token=<token>`, since browsers can't set headers
+/// on the handshake.
+fn presented_token(request: &Request) -> Option<String> {
+    let headers = request.headers();
+    if let Some(value) = headers.get(header::AUTHORIZATION) {
+        return value.to_str().ok()?.strip_prefix("Bearer ").map(str::to_string);
+    }
+    let upgrade = headers.get(header::UPGRADE).and_then(|value| value.to_str().ok());
+    if !upgrade.is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket")) {
+        return None;
+    }
+    Query::<AccessTokenParams>::try_from_uri(request.uri()).ok()?.0.access_token
+}
+
+/// Lets the request through if it carries the bearer token (see
+/// [`presented_token`]), otherwise answers 401 and audits the attempt.
+async fn require_bearer_token(State(token): State<Arc<SecretString>>, mut request: Request, next: Next) -> Response {
+    let presented = presented_token(&request);
+    if presented.as_deref().is_some_and(|presented| tokens_match(presented, token.expose_secret())) {
+        request
+            .extensions_mut()
+            .insert(AuthenticatedSubject(BEARER_TOKEN_SUBJECT.to_string()));
+        return next.run(request).await;
+    }
+
+    let user = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or_else(|| "unknown".to_string(), |ConnectInfo(addr)| addr.to_string());
+    log_audit(AuditEvent::AccessDenied {
+        user,
+        resource: request.uri().path().to_string(),
+    });
+    let reason = if presented.is_some() { "Invalid bearer token" } else { "Missing bearer token" };
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(serde_json::json!({ "error": reason })),
+    )
+        .into_response()
+}
+
+/// Requires `Authorization: Bearer <token>` on every route of `router`;
+/// WebSocket upgrades may pass `?access_token=<token>` instead.
+pub fn with_bearer_auth(router: Router, token: SecretString) -> Router {
+    router.layer(middleware::from_fn_with_state(Arc::new(token), require_bearer_token))
+}
+
+/// Builds the CORS layer `settings` describe, or `None` if no origin is
+/// allowed. Fails on an origin, method or header that isn't valid HTTP.
+pub fn cors_layer(settings: &CorsSettings) -> anyhow::Result<Option<CorsLayer>> {
+    if settings.permissive {
+        tracing::warn!("CORS is permissive: any origin may call the control plane");
+        return Ok(Some(CorsLayer::very_permissive()));
+    }
+    if settings.allowed_origins.is_empty() {
+        return Ok(None);
+    }
+
+    let origins = settings
+        .allowed_origins
+        .iter()
+        .map(|origin| HeaderValue::from_str(origin).map_err(|e| anyhow::anyhow!("Invalid CORS origin '{}': {}", origin, e)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let methods = if settings.allowed_methods.is_empty() {
+        vec![Method::GET, Method::POST]
+    } else {
+        settings
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|e| anyhow::anyhow!("Invalid CORS method '{}': {}", method, e))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+    let headers = if settings.allowed_headers.is_empty() {
+        vec![header::AUTHORIZATION, header::CONTENT_TYPE]
+    } else {
+        settings
+            .allowed_headers
+            .iter()
+            .map(|name| HeaderName::try_from(name.as_str()).map_err(|e| anyhow::anyhow!("Invalid CORS header '{}': {}", name, e)))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(methods)
+            .allow_headers(headers),
+    ))
+}
+
+/// Runs the control plane HTTP server with WebSocket support, until the
+/// host's [shutdown](BrioHostState::shutdown) starts and open connections close.
+pub async fn run_server(
+    config: &Settings,
+    host: Arc<BrioHostState>,
+    log_level: Option<LogLevelHandle>,
+) -> anyhow::Result<()> {
+    let handle = prometheus_handle()?;
+
+    let protected = Router::new()
         .route("/metrics", get(move || std::future::ready(handle.render())))
-        .route("/debug/pprof/profile", get(pprof_profile));
+        .route("/debug/pprof/profile", get(pprof_profile))
+        .merge(health_sweep_router(
+            host.clone(),
+            Duration::from_secs(config.server.health_sweep_interval_secs),
+        ))
+        .merge(ws_router(host.broadcaster().clone()));
+    // Without the global subscriber there's no log filter to change
+    let protected = match log_level {
+        Some(log_level) => protected.merge(admin_router(log_level)),
+        None => protected,
+    };
+    let protected = match &config.server.auth_token {
+        Some(token) => with_bearer_auth(protected, token.clone()),
+        None => protected,
+    };
 
-    let app = control_plane.merge(ws_router(broadcaster));
+    // Probes stay open so orchestrators can check the kernel without the token
+    let app = probe_router(host.clone()).merge(protected);
+    // Outside auth, so preflight requests are answered without the token
+    let app = match cors_layer(&config.server.cors)? {
+        Some(cors) => app.layer(cors),
+        None => app,
+    };
 
     let addr_str = format!("{}:{}", config.server.host, config.server.port);
     let addr: SocketAddr = addr_str.parse()?;
@@ -77,7 +310,17 @@ pub async fn run_server(config: &Settings, broadcaster: Broadcaster) -> anyhow::
     tracing::info!("Control Plane listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    // Stops accepting connections once the host starts shutting down
+    let shutdown = host.shutdown_token().cancelled_owned();
+    if config.ws.handshake_timeout_secs > 0 {
+        let timeout = std::time::Duration::from_secs(config.ws.handshake_timeout_secs);
+        axum::serve(with_handshake_timeout(listener, timeout), app)
+            .with_graceful_shutdown(shutdown)
+            .await?;
+    } else {
+        axum::serve(listener, app).with_graceful_shutdown(shutdown).await?;
+    }
 
     Ok(())
 }
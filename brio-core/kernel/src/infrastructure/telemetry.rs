@@ -1,14 +1,128 @@
+use crate::infrastructure::audit::AuditFileLayer;
 use anyhow::{Context, Result};
+use axum::{Router, routing::get};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use opentelemetry_otlp::WithExportConfig;
-use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::Sampler};
+use opentelemetry_sdk::{
+    Resource,
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracer},
+};
 use opentelemetry_semantic_conventions::resource;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{Level, Subscriber};
+use tracing_opentelemetry::OpenTelemetryLayer;
 use tracing_subscriber::{
     EnvFilter, Layer, Registry,
+    filter::Targets,
     fmt::{self, format::FmtSpan},
     layer::SubscriberExt,
+    registry::LookupSpan,
+    reload,
     util::SubscriberInitExt,
 };
 
+/// How often histograms in the Prometheus recorder are compacted
+const PROMETHEUS_UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Installs the Prometheus recorder as the global metrics recorder, or
+/// returns the one already installed, so every `/metrics` endpoint renders
+/// the same registry.
+pub fn prometheus_handle() -> Result<PrometheusHandle> {
+    static HANDLE: Mutex<Option<PrometheusHandle>> = Mutex::new(None);
+    let mut handle = HANDLE.lock().expect("Mutex poisoned");
+    if let Some(handle) = handle.as_ref() {
+        return Ok(handle.clone());
+    }
+    let installed = PrometheusBuilder::new()
+        .install_recorder()
+        .context("Failed to install Prometheus recorder")?;
+    *handle = Some(installed.clone());
+    Ok(installed)
+}
+
+/// Spawns an HTTP server on `bind_addr` serving `/metrics` in the Prometheus
+/// text format. Must be called within a Tokio runtime.
+fn serve_prometheus(bind_addr: SocketAddr) -> Result<()> {
+    let runtime = tokio::runtime::Handle::try_current()
+        .context("Prometheus endpoint needs a Tokio runtime")?;
+    let handle = prometheus_handle()?;
+
+    let listener = std::net::TcpListener::bind(bind_addr)
+        .with_context(|| format!("Failed to bind Prometheus endpoint to {}", bind_addr))?;
+    listener.set_nonblocking(true)?;
+    let listener = {
+        let _guard = runtime.enter();
+        tokio::net::TcpListener::from_std(listener)?
+    };
+
+    let upkeep = handle.clone();
+    runtime.spawn(async move {
+        let mut interval = tokio::time::interval(PROMETHEUS_UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            upkeep.run_upkeep();
+        }
+    });
+
+    let app = Router::new().route("/metrics", get(move || std::future::ready(handle.render())));
+    runtime.spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!(error = %e, "Prometheus endpoint stopped");
+        }
+    });
+    Ok(())
+}
+
+/// Why the log filter couldn't be changed.
+#[derive(Debug, thiserror::Error)]
+pub enum LogLevelError {
+    #[error("Invalid log filter '{directives}': {reason}")]
+    InvalidDirective { directives: String, reason: String },
+    #[error("Failed to reload log filter: {0}")]
+    Reload(String),
+}
+
+/// Changes the log filter of a running subscriber without a restart.
+#[derive(Clone)]
+pub struct LogLevelHandle {
+    inner: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogLevelHandle {
+    /// Wraps `filter` in a layer, or per-layer filter, that the returned
+    /// handle can replace.
+    pub fn layer(filter: EnvFilter) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let (layer, inner) = reload::Layer::new(filter);
+        (layer, Self { inner })
+    }
+
+    /// Replaces the filter with `directives`, in `RUST_LOG` syntax such as
+    /// `info,brio_kernel::mesh=trace`. The current filter stays in place if
+    /// they don't parse.
+    pub fn set(&self, directives: &str) -> Result<(), LogLevelError> {
+        let invalid = |reason: String| LogLevelError::InvalidDirective {
+            directives: directives.to_string(),
+            reason,
+        };
+        if directives.trim().is_empty() {
+            return Err(invalid("no directives given".to_string()));
+        }
+        let filter = EnvFilter::try_new(directives).map_err(|e| invalid(e.to_string()))?;
+        self.inner.reload(filter).map_err(|e| LogLevelError::Reload(e.to_string()))?;
+        tracing::info!(directives = %directives, "Log filter changed");
+        Ok(())
+    }
+
+    /// The filter in effect, in `RUST_LOG` syntax.
+    pub fn current(&self) -> Option<String> {
+        self.inner.with_current(|filter| filter.to_string()).ok()
+    }
+}
+
 /// Builder for setting up telemetry (Logging, Tracing, Metrics).
 pub struct TelemetryBuilder {
     service_name: String,
@@ -18,6 +132,9 @@ pub struct TelemetryBuilder {
     otlp_endpoint: Option<String>,
     log_level: String,
     sampling_ratio: f64,
+    strict: bool,
+    audit_file: Option<(PathBuf, Vec<u8>)>,
+    prometheus_addr: Option<SocketAddr>,
 }
 
 impl TelemetryBuilder {
@@ -30,6 +147,9 @@ impl TelemetryBuilder {
             otlp_endpoint: None,
             log_level: "info".to_string(),
             sampling_ratio: 1.0,
+            strict: false,
+            audit_file: None,
+            prometheus_addr: None,
         }
     }
 
@@ -54,66 +174,212 @@ impl TelemetryBuilder {
         self
     }
 
-    pub fn init(self) -> Result<()> {
+    /// Makes [`init`](Self::init) fail if any part of telemetry can't be set
+    /// up. Otherwise failures are reported on stderr and the kernel carries on
+    /// with whatever could be installed, at worst local logging only.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Also appends audit events to `path`, HMAC-chained with `key`; see
+    /// [`AuditFileLayer`]. Audit events reach the file whatever the log
+    /// filter, and [`init`](Self::init) fails if it can't be opened, strict
+    /// or not.
+    pub fn with_audit_file(mut self, path: impl Into<PathBuf>, key: impl Into<Vec<u8>>) -> Self {
+        self.audit_file = Some((path.into(), key.into()));
+        self
+    }
+
+    /// Also serves metrics for Prometheus to scrape at `http://<bind_addr>/metrics`.
+    pub fn with_prometheus(mut self, bind_addr: SocketAddr) -> Self {
+        self.prometheus_addr = Some(bind_addr);
+        self
+    }
+
+    /// Installs the global subscriber, returning a handle that changes its
+    /// log filter at runtime; `None` if another subscriber was already
+    /// installed and, not being strict, this one was skipped.
+    pub fn init(self) -> Result<Option<LogLevelHandle>> {
         opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
 
+        if let Some(bind_addr) = self.prometheus_addr {
+            match serve_prometheus(bind_addr) {
+                Ok(()) => {}
+                Err(e) if !self.strict => {
+                    eprintln!("Telemetry: {:#}; continuing without the Prometheus endpoint", e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let (subscriber, log_level) = self.subscriber()?;
+        match subscriber.try_init().context("Failed to init subscriber") {
+            Ok(()) => Ok(Some(log_level)),
+            Err(e) if !self.strict => {
+                eprintln!("Telemetry: {:#}; continuing without it", e);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The subscriber [`init`](Self::init) installs, and the handle to its log filter.
+    fn subscriber(&self) -> Result<(impl Subscriber + Send + Sync + 'static, LogLevelHandle)> {
         let env_filter =
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&self.log_level));
 
         let fmt_layer = fmt::layer().json().with_span_events(FmtSpan::CLOSE).boxed();
 
-        let registry = Registry::default().with(env_filter).with(fmt_layer);
-
-        if self.enable_tracing {
-            if let Some(endpoint) = self.otlp_endpoint {
-                let resource = Resource::builder()
-                    .with_attributes(vec![
-                        opentelemetry::KeyValue::new(
-                            resource::SERVICE_NAME,
-                            self.service_name.clone(),
-                        ),
-                        opentelemetry::KeyValue::new(
-                            resource::SERVICE_VERSION,
-                            self.service_version.clone(),
-                        ),
-                    ])
-                    .build();
-
-                let exporter = opentelemetry_otlp::SpanExporter::builder()
-                    .with_tonic()
-                    .with_endpoint(endpoint)
-                    .build()
-                    .context("Failed to build OTLP span exporter")?;
-
-                let processor =
-                    opentelemetry_sdk::trace::BatchSpanProcessor::builder(exporter).build();
-
-                let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
-                    .with_span_processor(processor)
-                    .with_resource(resource)
-                    .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
-                        self.sampling_ratio,
-                    ))))
-                    .build();
-
-                opentelemetry::global::set_tracer_provider(provider.clone());
-
-                use opentelemetry::trace::TracerProvider;
-                let tracer = provider.tracer("brio-kernel");
-
-                let telemetry_layer = tracing_opentelemetry::layer().with_tracer(tracer);
-
-                registry
-                    .with(telemetry_layer)
-                    .try_init()
-                    .context("Failed to init subscriber")?;
-            } else {
-                registry.try_init().context("Failed to init subscriber")?;
+        // An audit trail with gaps is worse than none, so this is never skipped
+        let audit_layer = self.audit_layer()?;
+
+        let telemetry_layer = match self.otlp_layer() {
+            Ok(layer) => layer,
+            Err(e) if !self.strict => {
+                eprintln!("Telemetry: {:#}; continuing with local logging only", e);
+                None
             }
-        } else {
-            registry.try_init().context("Failed to init subscriber")?;
-        }
+            Err(e) => return Err(e),
+        };
 
-        Ok(())
+        // The log filter only applies to logs and traces; audit events are
+        // filtered on their own so changing the log level can't drop them
+        let (log_filter, log_level) = LogLevelHandle::layer(env_filter);
+        let subscriber = Registry::default()
+            .with(fmt_layer.and_then(telemetry_layer).with_filter(log_filter))
+            .with(audit_layer.with_filter(Targets::new().with_target("audit", Level::INFO)));
+        Ok((subscriber, log_level))
+    }
+
+    fn audit_layer(&self) -> Result<Option<AuditFileLayer>> {
+        let Some((path, key)) = &self.audit_file else {
+            return Ok(None);
+        };
+        AuditFileLayer::open(path, key.clone())
+            .map(Some)
+            .with_context(|| format!("Failed to open audit file {}", path.display()))
+    }
+
+    fn otlp_layer<S>(&self) -> Result<Option<OpenTelemetryLayer<S, SdkTracer>>>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let Some(endpoint) = self.otlp_endpoint.as_ref().filter(|_| self.enable_tracing) else {
+            return Ok(None);
+        };
+
+        let resource = Resource::builder()
+            .with_attributes(vec![
+                opentelemetry::KeyValue::new(resource::SERVICE_NAME, self.service_name.clone()),
+                opentelemetry::KeyValue::new(resource::SERVICE_VERSION, self.service_version.clone()),
+            ])
+            .build();
+
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+            .context("Failed to build OTLP span exporter")?;
+
+        let processor = opentelemetry_sdk::trace::BatchSpanProcessor::builder(exporter).build();
+
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .with_span_processor(processor)
+            .with_resource(resource)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                self.sampling_ratio,
+            ))))
+            .build();
+
+        opentelemetry::global::set_tracer_provider(provider.clone());
+
+        use opentelemetry::trace::TracerProvider;
+        let tracer = provider.tracer("brio-kernel");
+
+        Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_init_survives_bad_otlp_endpoint_unless_strict() {
+        let strict = TelemetryBuilder::new("brio-test", "0.0.0")
+            .with_log_level("error")
+            .with_tracing("not a uri")
+            .with_strict(true)
+            .init();
+        assert!(strict.is_err());
+
+        TelemetryBuilder::new("brio-test", "0.0.0")
+            .with_log_level("error")
+            .with_tracing("http://127.0.0.1:1")
+            .init()
+            .expect("non-strict init must not fail on an unreachable endpoint");
+    }
+
+    #[test]
+    fn test_skipped_init_gives_no_log_level_handle() {
+        let builder = || TelemetryBuilder::new("brio-test", "0.0.0").with_log_level("error");
+        // Whichever init installs the global subscriber, the second can't
+        let _ = builder().init();
+        assert!(builder().init().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_audit_events_bypass_the_log_filter() {
+        let path = std::env::temp_dir().join(format!("brio_audit_filter_{}.log", uuid::Uuid::new_v4()));
+        let builder = TelemetryBuilder::new("brio-test", "0.0.0")
+            .with_log_level("warn")
+            .with_audit_file(&path, b"key".to_vec());
+        let (subscriber, log_level) = builder.subscriber().unwrap();
+
+        tracing::subscriber::with_default(subscriber, || {
+            crate::infrastructure::audit::log_audit(crate::infrastructure::audit::AuditEvent::SystemStartup {
+                component: "test".into(),
+            });
+            log_level.set("error").unwrap();
+            crate::infrastructure::audit::log_audit(crate::infrastructure::audit::AuditEvent::SystemShutdown {
+                reason: "test".into(),
+            });
+            tracing::info!("not an audit event");
+        });
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written.lines().count(), 2, "{}", written);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_unopenable_audit_file_fails_init() {
+        let result = TelemetryBuilder::new("brio-test", "0.0.0")
+            .with_audit_file(std::env::temp_dir(), b"key".to_vec())
+            .init();
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_endpoint_serves_recorded_metrics() {
+        let free = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = free.local_addr().unwrap();
+        drop(free);
+
+        TelemetryBuilder::new("brio-test", "0.0.0")
+            .with_log_level("error")
+            .with_prometheus(addr)
+            .init()
+            .unwrap();
+        metrics::counter!("brio_test_scraped_total").increment(3);
+
+        let body = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("brio_test_scraped_total 3"), "{}", body);
     }
 }
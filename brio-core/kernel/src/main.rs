@@ -1,5 +1,10 @@
 use brio_kernel::host::BrioHostState;
-use brio_kernel::infrastructure::{audit, config::Settings, server, telemetry::TelemetryBuilder};
+use brio_kernel::infrastructure::{
+    audit,
+    config::{SessionSettings, Settings},
+    server,
+    telemetry::TelemetryBuilder,
+};
 use secrecy::ExposeSecret;
 use tokio::signal;
 use tracing::{error, info};
@@ -10,7 +15,8 @@ async fn main() -> anyhow::Result<()> {
 
     let mut telemetry_builder = TelemetryBuilder::new("brio-kernel", "0.1.0")
         .with_log_level("debug")
-        .with_sampling_ratio(config.telemetry.sampling_ratio);
+        .with_sampling_ratio(config.telemetry.sampling_ratio)
+        .with_strict(config.telemetry.strict);
 
     telemetry_builder = if let Some(ref endpoint) = config.telemetry.otlp_endpoint {
         telemetry_builder.with_tracing(endpoint)
@@ -18,7 +24,18 @@ async fn main() -> anyhow::Result<()> {
         telemetry_builder
     };
 
-    telemetry_builder
+    if let Some(ref path) = config.telemetry.audit_file {
+        let Some(ref key) = config.telemetry.audit_key else {
+            anyhow::bail!("telemetry.audit_file is set but telemetry.audit_key is not");
+        };
+        telemetry_builder = telemetry_builder.with_audit_file(path, key.expose_secret().as_bytes());
+    }
+
+    if let Some(addr) = config.telemetry.prometheus_addr {
+        telemetry_builder = telemetry_builder.with_prometheus(addr);
+    }
+
+    let log_level = telemetry_builder
         .with_metrics()
         .init()
         .expect("Failed to initialize telemetry");
@@ -31,35 +48,93 @@ async fn main() -> anyhow::Result<()> {
     let db_url = config.database.url.expose_secret();
 
 
-    // Clean Code: Configure Provider (DIP)
-    let openai_key = config.inference.as_ref()
-        .and_then(|i| i.openai_api_key.clone())
-        .unwrap_or_else(|| secrecy::SecretString::new("sk-placeholder".into()));
-
-    let openai_base = config.inference.as_ref()
-        .and_then(|i| i.openai_base_url.clone())
-        .unwrap_or("https://openrouter.ai/api/v1/".to_string());
-
-    let provider_config = brio_kernel::inference::OpenAIConfig::new(
-        openai_key,
-        reqwest::Url::parse(&openai_base).expect("Invalid OpenAI Base URL"),
-    );
-    let provider = brio_kernel::inference::OpenAIProvider::new(provider_config);
-    
-    // Create registry (common for both modes)
-    let registry = brio_kernel::inference::ProviderRegistry::new();
-    registry.register_arc("default", std::sync::Arc::new(provider));
-    registry.set_default("default");
+    let registry = build_registry(&config)?;
 
     // Check for distributed config
     let mesh_config = config.mesh.clone();
     let node_id = mesh_config.as_ref().and_then(|m| m.node_id.clone()).map(brio_kernel::mesh::types::NodeId::from);
-    let mesh_port = mesh_config.as_ref().and_then(|m| m.port).map(|p| p.to_string()).unwrap_or("50051".to_string());
+    let mesh_port = mesh_config.as_ref().and_then(|m| m.port).unwrap_or(50051);
+
+    let broadcaster =
+        brio_kernel::ws::Broadcaster::with_capacity(config.ws.broadcast_capacity.max(1));
+    let broadcaster = if config.ws.replay_capacity > 0 {
+        broadcaster.with_replay(config.ws.replay_capacity)
+    } else {
+        broadcaster
+    };
+    let broadcaster = broadcaster
+        .with_serialization(brio_kernel::ws::SerializationOptions {
+            pretty: config.ws.pretty_json,
+            skip_nulls: config.ws.skip_nulls,
+        })
+        .with_max_message_bytes(config.ws.max_message_bytes)
+        .with_max_clients((config.ws.max_clients > 0).then_some(config.ws.max_clients))
+        .with_stream_coalescing((config.ws.stream_coalesce_window_ms > 0).then(|| {
+            brio_kernel::ws::CoalesceOptions {
+                window: std::time::Duration::from_millis(config.ws.stream_coalesce_window_ms),
+                max_bytes: config.ws.stream_coalesce_max_bytes,
+            }
+        }))
+        .with_stream_heartbeat(
+            (config.ws.stream_heartbeat_interval_ms > 0)
+                .then(|| std::time::Duration::from_millis(config.ws.stream_heartbeat_interval_ms)),
+        )
+        .with_retained_topics(config.ws.retained_topics.clone())
+        .with_write_ahead_buffer(config.ws.write_ahead)
+        .with_connection_options(brio_kernel::ws::ConnectionOptions {
+            ping_interval: std::time::Duration::from_secs(config.ws.ping_interval_secs.max(1)),
+            idle_timeout: (config.ws.idle_timeout_secs > 0)
+                .then(|| std::time::Duration::from_secs(config.ws.idle_timeout_secs)),
+            pong_timeout: (config.ws.pong_timeout_secs > 0)
+                .then(|| std::time::Duration::from_secs(config.ws.pong_timeout_secs)),
+            inbound_rate_limit: (config.ws.inbound_messages_per_sec > 0).then_some(
+                brio_kernel::ws::InboundRateLimit {
+                    messages_per_sec: config.ws.inbound_messages_per_sec,
+                    burst: config.ws.inbound_burst,
+                    max_violations: config.ws.inbound_max_violations,
+                },
+            ),
+        });
+    let retry_budget = config.requests.retry_budget;
+    let selection = config
+        .inference
+        .as_ref()
+        .map(|i| i.selection.clone())
+        .unwrap_or_default();
 
     let state = if let Some(ref id) = node_id {
         info!("Initializing in Distributed Mode (Node ID: {})", id);
         match BrioHostState::new_distributed(db_url, registry, id.clone()).await {
-            Ok(s) => std::sync::Arc::new(s),
+            Ok(s) => {
+                let grace_period = mesh_config
+                    .as_ref()
+                    .and_then(|m| m.dead_node_grace_secs)
+                    .map(std::time::Duration::from_secs)
+                    .unwrap_or(brio_kernel::mesh::DEFAULT_DEAD_NODE_GRACE_PERIOD);
+                let s = s
+                    .with_broadcaster(broadcaster)
+                    .with_retry_budget(retry_budget)
+                    .with_busy_retries(config.database.busy_retries)
+                    .with_policy_failure_mode(config.database.policy_failure_mode)
+                    .with_value_size_limits(config.database.value_size_limits.clone())
+                    .with_dead_node_grace_period(grace_period)
+                    .with_gossip_ttl(
+                        mesh_config
+                            .as_ref()
+                            .and_then(|m| m.gossip_ttl_secs)
+                            .map(std::time::Duration::from_secs)
+                            .unwrap_or(brio_kernel::mesh::DEFAULT_GOSSIP_TTL),
+                    );
+                match mesh_config
+                    .as_ref()
+                    .and_then(|m| m.advertise_address.clone())
+                {
+                    Some(address) => s.with_mesh_advertise_address(
+                        brio_kernel::mesh::types::NodeAddress(address),
+                    ),
+                    None => s,
+                }
+            }
             Err(e) => {
                 error!("Failed to initialize distributed host state: {:?}", e);
                 std::process::exit(1);
@@ -68,47 +143,242 @@ async fn main() -> anyhow::Result<()> {
     } else {
         info!("Initializing in Standalone Mode");
         match BrioHostState::new(db_url, registry).await {
-            Ok(s) => std::sync::Arc::new(s),
+            Ok(s) => s
+                .with_broadcaster(broadcaster)
+                .with_retry_budget(retry_budget)
+                .with_busy_retries(config.database.busy_retries)
+                .with_policy_failure_mode(config.database.policy_failure_mode)
+                .with_value_size_limits(config.database.value_size_limits.clone()),
             Err(e) => {
                 error!("Failed to initialize host state: {:?}", e);
                 std::process::exit(1);
             }
         }
     };
-    
-    // Start gRPC server if distributed
-    if let Some(id) = node_id {
+
+    let state = match mesh_config.as_ref().and_then(|m| m.tls.as_ref()) {
+        Some(tls) => match brio_kernel::mesh::MeshTls::load(
+            &tls.cert_path,
+            &tls.key_path,
+            tls.ca_path.as_deref(),
+        ) {
+            Ok(mesh_tls) => match &tls.domain_name {
+                Some(name) => state.with_mesh_tls(mesh_tls.with_domain_name(name)),
+                None => state.with_mesh_tls(mesh_tls),
+            },
+            Err(e) => {
+                error!("Failed to load mesh TLS certificates: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => state,
+    };
+
+    let state = if config.ws.snapshot_scopes.is_empty() {
+        state
+    } else {
+        state.with_store_snapshots(&config.ws.snapshot_scopes)
+    };
+    let state = match config.sessions.idle_timeout_secs {
+        0 => state,
+        secs => state.with_session_idle_timeout(
+            std::time::Duration::from_secs(secs),
+            std::time::Duration::from_secs(config.sessions.reap_interval_secs.max(1)),
+        ),
+    };
+    let post_process = config
+        .inference
+        .as_ref()
+        .map(|i| i.post_process.clone())
+        .unwrap_or_default();
+    let state = state.with_post_processing(post_process);
+    let state = match config.inference.as_ref().filter(|i| i.record_requests) {
+        Some(inference) => {
+            use secrecy::ExposeSecret;
+            let mut redactions = inference.record_redactions.clone();
+            for key in [
+                &inference.openai_api_key,
+                &inference.anthropic_api_key,
+                &inference.embeddings_api_key,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                redactions.push(key.expose_secret().to_string());
+            }
+            state.with_request_recording(redactions)
+        }
+        None => state,
+    };
+    let state = match mesh_config.as_ref().and_then(|m| m.call_timeout_secs) {
+        Some(secs) => state.with_mesh_call_timeout(std::time::Duration::from_secs(secs)),
+        None => state,
+    };
+    let state = match mesh_config
+        .as_ref()
+        .and_then(|m| m.compression_threshold_bytes)
+    {
+        Some(threshold) => state.with_mesh_compression_threshold(threshold),
+        None => state,
+    };
+    let state = match mesh_config
+        .as_ref()
+        .filter(|m| m.circuit_breaker_failures.is_some())
+    {
+        Some(mesh) => state.with_circuit_breaker(
+            mesh.circuit_breaker_failures.unwrap_or_default(),
+            std::time::Duration::from_secs(mesh.circuit_breaker_cool_down_secs),
+        ),
+        None => state,
+    };
+
+    // An invalid strategy (e.g. naming a missing provider) must stop startup
+    let state = match state.with_selection_strategy(selection) {
+        Ok(s) => std::sync::Arc::new(s),
+        Err(e) => {
+            error!("Invalid inference selection strategy: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // A distributed node that can't serve the mesh must not come up half-registered
+    let mesh_server = if node_id.is_some() {
+        let addr = std::net::SocketAddr::from(([0, 0, 0, 0], mesh_port));
+        match state.start_mesh_server(addr).await {
+            Ok(server) => Some(server),
+            Err(e) => {
+                error!("{:#}. Shutting down.", e);
+                audit::log_audit(audit::AuditEvent::SystemShutdown {
+                    reason: "Mesh server failed to start".into(),
+                });
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(inference) = config.inference.as_ref() {
+        for (tenant, limits) in &inference.tenant_limits {
+            state.rate_limiter().set_limits(tenant.clone(), *limits);
+        }
+    }
+
+    if config.sessions.persist_on_shutdown {
+        match state.restore_sessions(std::path::Path::new(&config.sessions.persist_dir)) {
+            Ok(restored) if !restored.is_empty() => {
+                info!("Restored {} persisted sessions", restored.len())
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to restore persisted sessions: {}", e),
+        }
+    }
+
+    if let Some(mesh) = mesh_config.as_ref().filter(|_| node_id.is_some()) {
+        for (id, address) in &mesh.bootstrap_nodes {
+            state.register_remote_node(brio_kernel::mesh::types::NodeInfo {
+                id: brio_kernel::mesh::types::NodeId::from(id.clone()),
+                address: brio_kernel::mesh::types::NodeAddress(address.clone()),
+                capabilities: vec![],
+                last_seen: 0,
+            });
+        }
+    }
+
+    // Gossip known nodes if distributed and enabled
+    if let Some(interval) = mesh_config
+        .as_ref()
+        .and_then(|m| m.gossip_interval_secs)
+        .filter(|_| node_id.is_some())
+    {
         let state_clone = state.clone();
-        let port = mesh_port.clone();
+        let shutdown = state.shutdown_token();
         tokio::spawn(async move {
-             let addr = format!("0.0.0.0:{}", port).parse().expect("Invalid mesh address");
-             let service = brio_kernel::mesh::service::MeshService::new(state_clone, id);
-             
-             info!("Mesh gRPC server listening on {}", addr);
-             
-             if let Err(e) = tonic::transport::Server::builder()
-                .add_service(brio_kernel::mesh::grpc::mesh_transport_server::MeshTransportServer::new(service))
-                .serve(addr)
-                .await 
-             {
-                 error!("Mesh gRPC server failed: {:?}", e);
-             }
+            let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval.max(1)));
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        if let Err(e) = state_clone.gossip().await {
+                            tracing::warn!("Gossip round failed: {:#}", e);
+                        }
+                    }
+                    _ = shutdown.cancelled() => return,
+                }
+            }
         });
     }
 
-    let broadcaster = state.broadcaster().clone();
+    // Heartbeat known nodes if distributed
+    if node_id.is_some() {
+        let state_clone = state.clone();
+        let interval = mesh_config
+            .as_ref()
+            .and_then(|m| m.heartbeat_interval_secs)
+            .unwrap_or(5);
+        let shutdown = state.shutdown_token();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval.max(1)));
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => state_clone.heartbeat_remote_nodes().await,
+                    _ = shutdown.cancelled() => return,
+                }
+            }
+        });
+    }
+
+    let server_state = state.clone();
     let server_config = config.clone();
-    tokio::spawn(async move {
-        if let Err(e) = server::run_server(&server_config, broadcaster).await {
+    let control_plane = tokio::spawn(async move {
+        if let Err(e) = server::run_server(&server_config, server_state, log_level).await {
             error!("Control Plane failed: {:?}", e);
         }
     });
 
+    #[cfg(unix)]
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+                .expect("failed to install SIGHUP handler");
+            let shutdown = state.shutdown_token();
+            loop {
+                tokio::select! {
+                    received = hangup.recv() => match received {
+                        Some(()) => reload_providers(&state),
+                        None => return,
+                    },
+                    _ = shutdown.cancelled() => return,
+                }
+            }
+        });
+    }
+
     info!("Brio Kernel Initialized. Waiting for shutdown signal...");
 
     shutdown_signal().await;
 
     info!("Shutdown signal received, cleaning up...");
+    // Stops new connections and mesh calls, letting the ones running finish
+    let drain_timeout = std::time::Duration::from_secs(config.server.drain_timeout_secs);
+    state.shutdown(drain_timeout).await;
+    state
+        .broadcaster()
+        .drain(std::time::Duration::from_secs(
+            config.ws.shutdown_grace_secs,
+        ))
+        .await;
+    drain_sessions(&state, &config.sessions);
+    if let Some(server) = mesh_server {
+        server.shutdown().await;
+    }
+    // WebSocket connections closed with the broadcaster; other requests get the drain timeout
+    if tokio::time::timeout(drain_timeout, control_plane)
+        .await
+        .is_err()
+    {
+        error!("Control Plane did not stop within {:?}", drain_timeout);
+    }
     audit::log_audit(audit::AuditEvent::SystemShutdown {
         reason: "Signal received".into(),
     });
@@ -117,6 +387,188 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Persists or aborts in-flight VFS sessions according to config.
+fn drain_sessions(state: &BrioHostState, settings: &SessionSettings) {
+    let result = if settings.persist_on_shutdown {
+        state
+            .persist_sessions(std::path::Path::new(&settings.persist_dir))
+            .map(|n| info!("Persisted {} sessions to {}", n, settings.persist_dir))
+    } else {
+        state
+            .abort_sessions()
+            .map(|n| info!("Aborted {} uncommitted sessions", n))
+    };
+
+    if let Err(e) = result {
+        error!("Failed to drain sessions on shutdown: {}", e);
+    }
+}
+
+/// Re-reads configuration and swaps in the providers it describes.
+///
+/// A configuration that fails to load or validate leaves the running
+/// providers untouched.
+#[cfg(unix)]
+fn reload_providers(state: &BrioHostState) {
+    info!("Reload signal received, rebuilding inference providers");
+    let config = match Settings::new() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to reload configuration: {:?}", e);
+            return;
+        }
+    };
+    let registry = match build_registry(&config) {
+        Ok(registry) => registry,
+        Err(e) => {
+            error!(
+                "Failed to rebuild inference providers, keeping the current ones: {:?}",
+                e
+            );
+            return;
+        }
+    };
+    let selection = config
+        .inference
+        .as_ref()
+        .map(|i| i.selection.clone())
+        .unwrap_or_default();
+    if let Err(e) = state.reload_providers(registry, selection) {
+        error!("Rejected reloaded inference providers: {:?}", e);
+    }
+}
+
+/// Builds the provider registry from the inference settings.
+///
+/// Runs at startup and again on every reload signal. Fails on a base URL
+/// that doesn't parse.
+fn build_registry(config: &Settings) -> anyhow::Result<brio_kernel::inference::ProviderRegistry> {
+    // Clean Code: Configure Provider (DIP)
+    let openai_key = config
+        .inference
+        .as_ref()
+        .and_then(|i| i.openai_api_key.clone())
+        .unwrap_or_else(|| secrecy::SecretString::new("sk-placeholder".into()));
+
+    let openai_base = config
+        .inference
+        .as_ref()
+        .and_then(|i| i.openai_base_url.clone())
+        .unwrap_or("https://openrouter.ai/api/v1/".to_string());
+
+    let mut provider_config = brio_kernel::inference::OpenAIConfig::new(
+        openai_key.clone(),
+        reqwest::Url::parse(&openai_base)
+            .map_err(|e| anyhow::anyhow!("Invalid OpenAI base URL '{}': {}", openai_base, e))?,
+    );
+    if let Some(ms) = config
+        .inference
+        .as_ref()
+        .and_then(|i| i.slow_request_threshold_ms)
+    {
+        provider_config =
+            provider_config.with_slow_request_threshold(std::time::Duration::from_millis(ms));
+    }
+    if let Some(dimensions) = config
+        .inference
+        .as_ref()
+        .and_then(|i| i.embeddings_dimensions)
+    {
+        provider_config = provider_config.with_embedding_dimensions(dimensions);
+    }
+    // Create registry (common for both modes)
+    let registry = brio_kernel::inference::ProviderRegistry::new();
+    if let Some(inference) = config.inference.as_ref()
+        && inference.retry_attempts > 0
+    {
+        // The registry retries every provider, so the provider's own loop would only compound it
+        provider_config = provider_config.with_max_retries(0);
+        registry.set_retries(
+            inference.retry_attempts,
+            std::time::Duration::from_millis(inference.retry_base_delay_ms),
+        );
+    }
+    let provider = brio_kernel::inference::OpenAIProvider::new(provider_config);
+    let provider: std::sync::Arc<dyn brio_kernel::inference::LLMProvider> =
+        std::sync::Arc::new(provider);
+    let moderate = config.inference.as_ref().is_some_and(|i| i.moderation);
+    let provider: std::sync::Arc<dyn brio_kernel::inference::LLMProvider> = if moderate {
+        // Screen prompts and completions with the provider's own moderation endpoint
+        let moderator = brio_kernel::inference::ProviderModerator::new(provider.clone());
+        std::sync::Arc::new(brio_kernel::inference::ModeratedProvider::new(
+            provider,
+            std::sync::Arc::new(moderator),
+        ))
+    } else {
+        provider
+    };
+    let provider: std::sync::Arc<dyn brio_kernel::inference::LLMProvider> =
+        match config.inference.as_ref() {
+            Some(inference) if inference.validate_tool_arguments => std::sync::Arc::new(
+                brio_kernel::inference::ToolValidatingProvider::new(provider)
+                    .with_repair_attempts(inference.tool_repair_attempts),
+            ),
+            _ => provider,
+        };
+    let embedding_cache_capacity = config.inference.as_ref().map_or(
+        brio_kernel::inference::embedding_cache::DEFAULT_EMBEDDING_CACHE_CAPACITY,
+        |i| i.embedding_cache_capacity,
+    );
+    let provider = std::sync::Arc::new(
+        brio_kernel::inference::CachedEmbeddingProvider::new(provider, "default")
+            .with_capacity(embedding_cache_capacity),
+    );
+    registry.register_arc("default", provider);
+    registry.set_default("default");
+
+    // Embeddings may be served by a different endpoint than chat
+    if let Some(inference) = config.inference.as_ref()
+        && let Some(ref embeddings_base) = inference.embeddings_base_url
+    {
+        let mut embeddings_config = brio_kernel::inference::OpenAIConfig::new(
+            inference.embeddings_api_key.clone().unwrap_or(openai_key),
+            reqwest::Url::parse(embeddings_base).map_err(|e| {
+                anyhow::anyhow!("Invalid embeddings base URL '{}': {}", embeddings_base, e)
+            })?,
+        );
+        if let Some(ref model) = inference.embeddings_model {
+            embeddings_config = embeddings_config.with_embedding_model(model.clone());
+        }
+        if let Some(dimensions) = inference.embeddings_dimensions {
+            embeddings_config = embeddings_config.with_embedding_dimensions(dimensions);
+        }
+        if inference.retry_attempts > 0 {
+            embeddings_config = embeddings_config.with_max_retries(0);
+        }
+        let embeddings = brio_kernel::inference::CachedEmbeddingProvider::new(
+            std::sync::Arc::new(brio_kernel::inference::OpenAIProvider::new(
+                embeddings_config,
+            )),
+            inference
+                .embeddings_model
+                .clone()
+                .unwrap_or_else(|| "default".to_string()),
+        )
+        .with_capacity(inference.embedding_cache_capacity);
+        registry.register("embeddings", embeddings);
+        registry
+            .set_capability_default(brio_kernel::inference::Capability::Embeddings, "embeddings");
+    }
+
+    if let Some(inference) = config.inference.as_ref() {
+        for (name, policy) in &inference.history {
+            registry.set_history_policy(name.clone(), *policy);
+        }
+        for (name, region) in &inference.provider_regions {
+            registry.set_region(name.clone(), region.clone());
+        }
+        registry.set_local_region(inference.region.clone());
+        registry.set_fallback_chain(inference.fallback_chain.iter().map(String::as_str).collect());
+    }
+
+    Ok(registry)
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
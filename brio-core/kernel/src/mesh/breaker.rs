@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// A mesh call refused without being sent because its target kept failing.
+///
+/// Returned inside an `anyhow::Error`; downcast to tell it from a failed call.
+#[derive(Debug, thiserror::Error)]
+#[error("circuit open for '{target}' after repeated failures; retry in {retry_in:?}")]
+pub struct CircuitOpen {
+    pub target: String,
+    /// Time left until a call is let through to probe the target
+    pub retry_in: Duration,
+}
+
+/// Where a target's breaker stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through; consecutive failures are being counted
+    Closed,
+    /// Calls fail fast until the cool-down ends
+    Open,
+    /// One probe call is in flight to see if the target has recovered
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum Breaker {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    /// The probe may never report back if its caller gives up on it, so
+    /// another is let through once it has been out for a cool-down
+    HalfOpen { probe_until: Instant },
+}
+
+/// Per-target circuit breakers for mesh calls.
+///
+/// A target's breaker opens after `failure_threshold` calls in a row fail to
+/// reach it or time out, failing calls with [`CircuitOpen`] for `cool_down`.
+/// The first call after that is let through as a probe while others keep
+/// failing fast: if the target answers the breaker closes, otherwise it
+/// opens for another cool-down.
+#[derive(Debug)]
+pub struct CircuitBreakers {
+    failure_threshold: u32,
+    cool_down: Duration,
+    breakers: Mutex<HashMap<String, Breaker>>,
+}
+
+impl CircuitBreakers {
+    pub fn new(failure_threshold: u32, cool_down: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cool_down,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Lets a call to `target` through, or refuses it while the breaker is open.
+    ///
+    /// A call let through must be followed by [`record_success`](Self::record_success)
+    /// or [`record_failure`](Self::record_failure).
+    pub fn try_acquire(&self, target: &str) -> Result<(), CircuitOpen> {
+        let mut breakers = self.breakers.lock().expect("Mutex poisoned");
+        let Some(breaker) = breakers.get_mut(target) else {
+            return Ok(());
+        };
+        let until = match *breaker {
+            Breaker::Closed { .. } => return Ok(()),
+            Breaker::Open { until } | Breaker::HalfOpen { probe_until: until } => until,
+        };
+        let now = Instant::now();
+        if now < until {
+            return Err(CircuitOpen {
+                target: target.to_string(),
+                retry_in: until - now,
+            });
+        }
+        info!(target = %target, "Circuit half-open, probing target");
+        *breaker = Breaker::HalfOpen {
+            probe_until: now + self.cool_down,
+        };
+        Ok(())
+    }
+
+    pub fn record_success(&self, target: &str) {
+        let mut breakers = self.breakers.lock().expect("Mutex poisoned");
+        if let Some(Breaker::HalfOpen { .. }) = breakers.remove(target) {
+            info!(target = %target, "Circuit closed, target recovered");
+        }
+    }
+
+    pub fn record_failure(&self, target: &str) {
+        let mut breakers = self.breakers.lock().expect("Mutex poisoned");
+        let breaker = breakers
+            .entry(target.to_string())
+            .or_insert(Breaker::Closed { failures: 0 });
+        let failures = match *breaker {
+            Breaker::Closed { failures } => failures + 1,
+            // A failed probe, or a call let through before the breaker opened
+            Breaker::HalfOpen { .. } | Breaker::Open { .. } => self.failure_threshold,
+        };
+        if failures < self.failure_threshold {
+            *breaker = Breaker::Closed { failures };
+            return;
+        }
+        if !matches!(breaker, Breaker::Open { .. }) {
+            warn!(target = %target, cool_down = ?self.cool_down, "Circuit opened after repeated failures");
+        }
+        *breaker = Breaker::Open {
+            until: Instant::now() + self.cool_down,
+        };
+    }
+
+    pub fn state(&self, target: &str) -> CircuitState {
+        match self.breakers.lock().expect("Mutex poisoned").get(target) {
+            None | Some(Breaker::Closed { .. }) => CircuitState::Closed,
+            Some(Breaker::Open { .. }) => CircuitState::Open,
+            Some(Breaker::HalfOpen { .. }) => CircuitState::HalfOpen,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::mesh::types::NodeId;
+use crate::store::StoreChange;
+
+/// Event ids remembered for deduplication before the oldest are forgotten
+pub const DEFAULT_SEEN_CAPACITY: usize = 1024;
+
+/// Remote nodes' interest in this node's store changes, by scope and key prefix.
+#[derive(Debug, Default)]
+pub struct ChangeSubscriptions {
+    entries: Vec<(NodeId, String, String)>,
+}
+
+impl ChangeSubscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `node_id` wants changes to `scope` keys starting with `prefix`.
+    ///
+    /// Subscribing twice with the same prefix is a no-op.
+    pub fn subscribe(&mut self, node_id: NodeId, scope: String, prefix: String) {
+        let entry = (node_id, scope, prefix);
+        if !self.entries.contains(&entry) {
+            self.entries.push(entry);
+        }
+    }
+
+    /// Drops every subscription of `node_id`, e.g. once it has left the mesh.
+    pub fn unsubscribe_node(&mut self, node_id: &NodeId) {
+        self.entries.retain(|(id, _, _)| id != node_id);
+    }
+
+    /// Nodes interested in `change`, each listed once however many prefixes match.
+    pub fn interested(&self, change: &StoreChange) -> Vec<NodeId> {
+        let mut nodes: Vec<NodeId> = Vec::new();
+        for (node_id, scope, prefix) in &self.entries {
+            if *scope == change.scope && change.key.starts_with(prefix.as_str()) && !nodes.contains(node_id) {
+                nodes.push(node_id.clone());
+            }
+        }
+        nodes
+    }
+}
+
+/// Bounded memory of recently applied change events.
+#[derive(Debug)]
+pub struct SeenEvents {
+    capacity: usize,
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+}
+
+impl SeenEvents {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            ids: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Records `event_id`, returning `false` if it was already seen.
+    pub fn insert(&mut self, event_id: &str) -> bool {
+        if self.ids.contains(event_id) {
+            return false;
+        }
+        if self.order.len() == self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.ids.remove(&oldest);
+        }
+        self.order.push_back(event_id.to_string());
+        self.ids.insert(event_id.to_string());
+        true
+    }
+}
+
+impl Default for SeenEvents {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEEN_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(scope: &str, key: &str) -> StoreChange {
+        StoreChange {
+            scope: scope.to_string(),
+            key: key.to_string(),
+            value: None,
+        }
+    }
+
+    #[test]
+    fn interest_matches_scope_and_prefix_once_per_node() {
+        let node = NodeId::from("node-b".to_string());
+        let mut subs = ChangeSubscriptions::new();
+        subs.subscribe(node.clone(), "agent".to_string(), "job/".to_string());
+        subs.subscribe(node.clone(), "agent".to_string(), "".to_string());
+
+        assert_eq!(subs.interested(&change("agent", "job/1")), vec![node.clone()]);
+        assert_eq!(subs.interested(&change("agent", "other")), vec![node]);
+        assert!(subs.interested(&change("tasks", "job/1")).is_empty());
+    }
+
+    #[test]
+    fn unsubscribed_node_is_no_longer_interested() {
+        let (gone, staying) = (NodeId::from("node-b".to_string()), NodeId::from("node-c".to_string()));
+        let mut subs = ChangeSubscriptions::new();
+        subs.subscribe(gone.clone(), "agent".to_string(), "job/".to_string());
+        subs.subscribe(gone.clone(), "agent".to_string(), "".to_string());
+        subs.subscribe(staying.clone(), "agent".to_string(), "job/".to_string());
+
+        subs.unsubscribe_node(&gone);
+        assert_eq!(subs.interested(&change("agent", "job/1")), vec![staying]);
+        assert!(subs.interested(&change("agent", "other")).is_empty());
+    }
+
+    #[test]
+    fn seen_events_rejects_duplicates_and_forgets_oldest() {
+        let mut seen = SeenEvents::new(2);
+        assert!(seen.insert("a"));
+        assert!(!seen.insert("a"));
+        assert!(seen.insert("b"));
+        assert!(seen.insert("c"));
+        assert!(seen.insert("a"));
+    }
+}
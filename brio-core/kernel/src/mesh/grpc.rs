@@ -1,2 +1,216 @@
 // Include the generated protobuf code
 tonic::include_proto!("mesh");
+
+use std::io::{self, Read};
+
+use crate::mesh::Payload;
+
+/// Payloads at least this many bytes are compressed on the wire, by default
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 64 * 1024;
+/// Largest payload a compressed body may expand to, so a small message
+/// can't be used to exhaust memory
+pub const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Payload body as it crosses the wire, shared by requests and responses.
+/// Each kind has a compressed variant of its own, so a body says by itself
+/// whether it needs inflating.
+enum WireBody {
+    Json(String),
+    Binary(Vec<u8>),
+    MsgPack(Vec<u8>),
+    CompressedJson(Vec<u8>),
+    CompressedBinary(Vec<u8>),
+    CompressedMsgPack(Vec<u8>),
+}
+
+impl WireBody {
+    /// Compresses `payload` once it reaches `threshold` bytes.
+    async fn encode(payload: Payload, threshold: usize) -> io::Result<Self> {
+        if payload.len() < threshold {
+            return Ok(match payload {
+                Payload::Json(s) => Self::Json(s),
+                Payload::Binary(b) => Self::Binary(b),
+                Payload::MsgPack(b) => Self::MsgPack(b),
+            });
+        }
+        blocking(move || {
+            Ok(match payload {
+                Payload::Json(s) => Self::CompressedJson(compress(s.as_bytes())?),
+                Payload::Binary(b) => Self::CompressedBinary(compress(&b)?),
+                Payload::MsgPack(b) => Self::CompressedMsgPack(compress(&b)?),
+            })
+        })
+        .await
+    }
+
+    async fn decode(self) -> io::Result<Payload> {
+        match self {
+            Self::Json(s) => Ok(Payload::Json(s)),
+            Self::Binary(b) => Ok(Payload::Binary(b)),
+            Self::MsgPack(b) => Ok(Payload::MsgPack(b)),
+            Self::CompressedJson(b) => blocking(move || {
+                String::from_utf8(decompress(&b)?)
+                    .map(Payload::Json)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
+            .await,
+            Self::CompressedBinary(b) => blocking(move || decompress(&b).map(Payload::Binary)).await,
+            Self::CompressedMsgPack(b) => blocking(move || decompress(&b).map(Payload::MsgPack)).await,
+        }
+    }
+}
+
+/// Runs `f` on the blocking pool; compressing or inflating a large body
+/// would otherwise stall a runtime worker.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> io::Result<T> + Send + 'static) -> io::Result<T> {
+    tokio::task::spawn_blocking(f).await.map_err(io::Error::other)?
+}
+
+fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::encode_all(bytes, COMPRESSION_LEVEL)
+}
+
+fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    decompress_at_most(bytes, MAX_DECOMPRESSED_SIZE)
+}
+
+fn decompress_at_most(bytes: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    let mut inflated = Vec::new();
+    zstd::Decoder::new(bytes)?
+        .take(limit as u64 + 1)
+        .read_to_end(&mut inflated)?;
+    if inflated.len() > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Compressed payload expands past {} bytes", limit),
+        ));
+    }
+    Ok(inflated)
+}
+
+impl mesh_request::Payload {
+    /// Wire form of `payload`, compressed once it reaches `threshold` bytes.
+    pub async fn from_payload(payload: Payload, threshold: usize) -> io::Result<Self> {
+        Ok(match WireBody::encode(payload, threshold).await? {
+            WireBody::Json(s) => Self::Json(s),
+            WireBody::Binary(b) => Self::Binary(b),
+            WireBody::MsgPack(b) => Self::Msgpack(b),
+            WireBody::CompressedJson(b) => Self::CompressedJson(b),
+            WireBody::CompressedBinary(b) => Self::CompressedBinary(b),
+            WireBody::CompressedMsgPack(b) => Self::CompressedMsgpack(b),
+        })
+    }
+
+    /// Payload as sent, decompressed if it was compressed.
+    pub async fn into_payload(self) -> io::Result<Payload> {
+        let body = match self {
+            Self::Json(s) => WireBody::Json(s),
+            Self::Binary(b) => WireBody::Binary(b),
+            Self::Msgpack(b) => WireBody::MsgPack(b),
+            Self::CompressedJson(b) => WireBody::CompressedJson(b),
+            Self::CompressedBinary(b) => WireBody::CompressedBinary(b),
+            Self::CompressedMsgpack(b) => WireBody::CompressedMsgPack(b),
+        };
+        body.decode().await
+    }
+}
+
+impl mesh_response::Payload {
+    /// Wire form of `payload`, compressed once it reaches `threshold` bytes.
+    pub async fn from_payload(payload: Payload, threshold: usize) -> io::Result<Self> {
+        Ok(match WireBody::encode(payload, threshold).await? {
+            WireBody::Json(s) => Self::Json(s),
+            WireBody::Binary(b) => Self::Binary(b),
+            WireBody::MsgPack(b) => Self::Msgpack(b),
+            WireBody::CompressedJson(b) => Self::CompressedJson(b),
+            WireBody::CompressedBinary(b) => Self::CompressedBinary(b),
+            WireBody::CompressedMsgPack(b) => Self::CompressedMsgpack(b),
+        })
+    }
+
+    /// Payload as sent, decompressed if it was compressed; an `Error` reply
+    /// is returned as `Err` with its message.
+    pub async fn into_payload(self) -> io::Result<Result<Payload, String>> {
+        let body = match self {
+            Self::Json(s) => WireBody::Json(s),
+            Self::Binary(b) => WireBody::Binary(b),
+            Self::Msgpack(b) => WireBody::MsgPack(b),
+            Self::CompressedJson(b) => WireBody::CompressedJson(b),
+            Self::CompressedBinary(b) => WireBody::CompressedBinary(b),
+            Self::CompressedMsgpack(b) => WireBody::CompressedMsgPack(b),
+            Self::Error(e) => return Ok(Err(e)),
+        };
+        body.decode().await.map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+
+    async fn request(payload: Payload) -> MeshRequest {
+        let payload = mesh_request::Payload::from_payload(payload, DEFAULT_COMPRESSION_THRESHOLD).await.unwrap();
+        MeshRequest {
+            target: "echo".to_string(),
+            method: "ping".to_string(),
+            payload: Some(payload),
+            trace_context: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn large_payload_is_compressed_and_round_trips() {
+        let json = format!("[{}]", vec!["\"brio\""; 1024 * 1024 / 7].join(","));
+        assert!(json.len() >= 1024 * 1024 - 8);
+
+        let sent = request(Payload::Json(json.clone())).await;
+        assert!(matches!(sent.payload, Some(mesh_request::Payload::CompressedJson(_))));
+        assert!(sent.encoded_len() < json.len() / 10, "{} bytes on the wire", sent.encoded_len());
+
+        let received = MeshRequest::decode(sent.encode_to_vec().as_slice()).unwrap();
+        match received.payload.unwrap().into_payload().await.unwrap() {
+            Payload::Json(s) => assert_eq!(s, json),
+            other => panic!("Expected JSON, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn small_payload_is_sent_as_is() {
+        let sent = request(Payload::Binary(vec![7; 128])).await;
+        assert!(matches!(&sent.payload, Some(mesh_request::Payload::Binary(b)) if b.len() == 128));
+    }
+
+    #[tokio::test]
+    async fn compressed_response_round_trips_every_variant() {
+        let large = vec![1u8; DEFAULT_COMPRESSION_THRESHOLD * 2];
+        for payload in [Payload::Binary(large.clone()), Payload::MsgPack(large.clone())] {
+            let kind = std::mem::discriminant(&payload);
+            let wire = mesh_response::Payload::from_payload(payload, DEFAULT_COMPRESSION_THRESHOLD).await.unwrap();
+            assert!(matches!(
+                wire,
+                mesh_response::Payload::CompressedBinary(_) | mesh_response::Payload::CompressedMsgpack(_)
+            ));
+            let decoded = wire.into_payload().await.unwrap().unwrap();
+            assert_eq!(std::mem::discriminant(&decoded), kind);
+            assert_eq!(decoded.len(), large.len());
+        }
+    }
+
+    #[tokio::test]
+    async fn uncompressed_binary_is_not_inflated() {
+        // Without a flag to go stale, raw bytes that happen to be zstd stay as sent
+        let raw = compress(b"brio").unwrap();
+        let decoded = mesh_request::Payload::Binary(raw.clone()).into_payload().await.unwrap();
+        assert!(matches!(decoded, Payload::Binary(b) if b == raw));
+    }
+
+    #[test]
+    fn oversized_decompression_is_refused() {
+        let bomb = compress(&[0u8; 4096]).unwrap();
+        assert_eq!(decompress_at_most(&bomb, 4096).unwrap().len(), 4096);
+        let err = decompress_at_most(&bomb, 4095).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
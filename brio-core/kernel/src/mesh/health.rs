@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::mesh::types::NodeId;
+
+/// Default time a node must stay unreachable before it is removed from routing
+pub const DEFAULT_DEAD_NODE_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Consecutive missed heartbeats after which a node is evicted from the registry
+pub const MAX_MISSED_HEARTBEATS: u32 = 3;
+
+/// Tracks heartbeat outcomes and decides which nodes are routable.
+///
+/// A failed heartbeat only starts the clock: the node keeps receiving
+/// traffic until it has been unreachable for the whole grace period, so a
+/// brief blip doesn't reroute its traffic. A successful heartbeat restores
+/// the node immediately.
+#[derive(Debug)]
+pub struct FailureDetector {
+    grace_period: Duration,
+    unreachable_since: HashMap<NodeId, Instant>,
+    missed_heartbeats: HashMap<NodeId, u32>,
+}
+
+impl Default for FailureDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEAD_NODE_GRACE_PERIOD)
+    }
+}
+
+impl FailureDetector {
+    pub fn new(grace_period: Duration) -> Self {
+        Self {
+            grace_period,
+            unreachable_since: HashMap::new(),
+            missed_heartbeats: HashMap::new(),
+        }
+    }
+
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+
+    pub fn set_grace_period(&mut self, grace_period: Duration) {
+        self.grace_period = grace_period;
+    }
+
+    pub fn record_success(&mut self, node_id: &NodeId) {
+        self.unreachable_since.remove(node_id);
+        self.missed_heartbeats.remove(node_id);
+    }
+
+    /// Records a missed heartbeat, returning how many have been missed in a row.
+    ///
+    /// Only the first failure in a row starts the clock.
+    pub fn record_failure(&mut self, node_id: &NodeId) -> u32 {
+        self.unreachable_since
+            .entry(node_id.clone())
+            .or_insert_with(Instant::now);
+        let missed = self.missed_heartbeats.entry(node_id.clone()).or_insert(0);
+        *missed += 1;
+        *missed
+    }
+
+    /// Drops everything recorded about `node_id`, e.g. once it has been evicted.
+    pub fn forget(&mut self, node_id: &NodeId) {
+        self.unreachable_since.remove(node_id);
+        self.missed_heartbeats.remove(node_id);
+    }
+
+    pub fn is_routable(&self, node_id: &NodeId) -> bool {
+        match self.unreachable_since.get(node_id) {
+            Some(since) => since.elapsed() < self.grace_period,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_missed_heartbeat_keeps_node_routable() {
+        let mut detector = FailureDetector::new(Duration::from_secs(60));
+        let node = NodeId("node-1".to_string());
+
+        detector.record_failure(&node);
+        assert!(detector.is_routable(&node));
+
+        detector.record_success(&node);
+        assert!(detector.is_routable(&node));
+        assert!(detector.unreachable_since.is_empty());
+    }
+
+    #[test]
+    fn test_missed_heartbeats_are_counted_until_a_success() {
+        let mut detector = FailureDetector::default();
+        let node = NodeId("node-1".to_string());
+
+        assert_eq!(detector.record_failure(&node), 1);
+        assert_eq!(detector.record_failure(&node), 2);
+        detector.record_success(&node);
+        assert_eq!(detector.record_failure(&node), 1);
+    }
+
+    #[test]
+    fn test_node_removed_after_grace_period_and_restored_on_recovery() {
+        let mut detector = FailureDetector::new(Duration::ZERO);
+        let node = NodeId("node-1".to_string());
+
+        detector.record_failure(&node);
+        assert!(!detector.is_routable(&node));
+
+        detector.record_success(&node);
+        assert!(detector.is_routable(&node));
+    }
+
+    #[test]
+    fn test_repeated_failures_do_not_reset_the_clock() {
+        let mut detector = FailureDetector::new(Duration::from_millis(20));
+        let node = NodeId("node-1".to_string());
+
+        detector.record_failure(&node);
+        std::thread::sleep(Duration::from_millis(30));
+        detector.record_failure(&node);
+        assert!(!detector.is_routable(&node));
+    }
+}
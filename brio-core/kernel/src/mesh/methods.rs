@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::sync::mpsc::Receiver;
+
+use crate::inference::validate_arguments;
+use crate::mesh::{MeshError, MeshMessage, Payload};
+
+type Handler = Arc<dyn Fn(Payload) -> BoxFuture<'static, Result<Payload, String>> + Send + Sync>;
+
+/// Routes a component's incoming [`MeshMessage`]s to per-method handlers.
+///
+/// Handlers take and return typed values, so components don't match on
+/// `method` themselves. Payloads are decoded from JSON or MessagePack, and
+/// replies encoded as MessagePack for MessagePack calls and JSON otherwise.
+/// Methods can also be given a JSON Schema that payloads are checked against
+/// before their handler sees them.
+#[derive(Clone, Default)]
+pub struct MethodRouter {
+    handlers: HashMap<String, Handler>,
+    schemas: HashMap<String, Value>,
+}
+
+impl MethodRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes calls to `name` through `handler`, replacing any earlier one.
+    ///
+    /// A payload that doesn't decode as `Req` is answered with an error
+    /// without calling the handler.
+    pub fn register_method<Req, Resp, F, Fut>(mut self, name: impl Into<String>, handler: F) -> Self
+    where
+        Req: DeserializeOwned + Send + 'static,
+        Resp: Serialize + 'static,
+        F: Fn(Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, String>> + Send + 'static,
+    {
+        let name = name.into();
+        let method = name.clone();
+        let handler = Arc::new(handler);
+        let erased: Handler = Arc::new(move |payload| {
+            let msgpack = matches!(payload, Payload::MsgPack(_));
+            let decoded = decode::<Req>(&method, payload);
+            let handler = handler.clone();
+            Box::pin(async move {
+                let reply = handler(decoded?).await?;
+                if msgpack {
+                    return Payload::from_msgpack(&reply);
+                }
+                serde_json::to_string(&reply)
+                    .map(Payload::Json)
+                    .map_err(|e| format!("Failed to encode reply: {}", e))
+            })
+        });
+        self.handlers.insert(name, erased);
+        self
+    }
+
+    /// Rejects calls to `name` whose payload doesn't match `schema`, replacing
+    /// any earlier schema.
+    ///
+    /// Supports the same JSON Schema subset as tool argument validation.
+    pub fn with_schema(mut self, name: impl Into<String>, schema: Value) -> Self {
+        self.schemas.insert(name.into(), schema);
+        self
+    }
+
+    /// Checks `payload` against the schema registered for `method`, if any.
+    pub fn validate(&self, method: &str, payload: &Payload) -> Result<(), MeshError> {
+        let Some(schema) = self.schemas.get(method) else {
+            return Ok(());
+        };
+        let errors = match parse::<Value>(payload) {
+            Ok(value) => validate_arguments(schema, &value),
+            Err(e) => vec![e],
+        };
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(MeshError::InvalidPayload {
+                method: method.to_string(),
+                errors,
+            })
+        }
+    }
+
+    /// Registered method names, sorted.
+    pub fn methods(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.handlers.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Runs the handler for `method` against `payload`, once it passes the
+    /// method's schema.
+    pub async fn dispatch(&self, method: &str, payload: Payload) -> Result<Payload, String> {
+        let Some(handler) = self.handlers.get(method) else {
+            return Err(format!(
+                "Unknown method '{}'. Available methods: [{}]",
+                method,
+                self.methods().join(", ")
+            ));
+        };
+        self.validate(method, &payload).map_err(|e| e.to_string())?;
+        handler(payload).await
+    }
+
+    /// Answers messages from `rx` until every sender is dropped.
+    ///
+    /// Each call runs on its own task, so a slow method doesn't hold up the others.
+    pub async fn serve(self, mut rx: Receiver<MeshMessage>) {
+        let router = Arc::new(self);
+        while let Some(message) = rx.recv().await {
+            let router = router.clone();
+            tokio::spawn(async move {
+                let reply = router.dispatch(&message.method, message.payload).await;
+                let _ = message.reply_tx.send(reply);
+            });
+        }
+    }
+}
+
+fn decode<T: DeserializeOwned>(method: &str, payload: Payload) -> Result<T, String> {
+    parse(&payload).map_err(|e| format!("Invalid payload for method '{}': {}", method, e))
+}
+
+fn parse<T: DeserializeOwned>(payload: &Payload) -> Result<T, String> {
+    match payload {
+        Payload::Json(s) => serde_json::from_str(s).map_err(|e| e.to_string()),
+        Payload::Binary(b) => serde_json::from_slice(b).map_err(|e| e.to_string()),
+        Payload::MsgPack(_) => payload.decode_msgpack(),
+    }
+}
@@ -2,23 +2,134 @@ pub mod types;
 pub mod remote;
 pub mod grpc;
 pub mod service;
+pub mod health;
+pub mod changes;
+pub mod methods;
+pub mod tls;
+pub mod breaker;
+pub mod trace;
 
 pub use types::*;
 pub use remote::*;
 pub use service::*;
+pub use health::*;
+pub use methods::MethodRouter;
+pub use tls::MeshTls;
+pub use breaker::{CircuitBreakers, CircuitOpen, CircuitState};
 
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
 use tokio::sync::oneshot;
 
+/// Default time a mesh call may take, from queueing the message to the reply
+pub const DEFAULT_MESH_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A mesh call that gave up waiting on its target.
+///
+/// Returned inside an `anyhow::Error`; downcast to tell a timeout from a
+/// failed call.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "mesh call to '{target}' timed out after {timeout:?} ({})",
+    if *delivered { "no reply received" } else { "message was never delivered" }
+)]
+pub struct MeshCallTimeout {
+    pub target: String,
+    pub timeout: Duration,
+    /// Whether the message reached the target's queue before the deadline.
+    /// If not, it was dropped and the target will never see it.
+    pub delivered: bool,
+}
+
+/// A mesh call refused because the kernel is shutting down.
+///
+/// Returned inside an `anyhow::Error`, like [`MeshCallTimeout`].
+#[derive(Debug, thiserror::Error)]
+#[error("mesh call to '{target}' refused: kernel is shutting down")]
+pub struct ShuttingDown {
+    pub target: String,
+}
+
+/// A session transfer the target node refused, so the session never left.
+///
+/// Returned inside an `anyhow::Error`, like [`MeshCallTimeout`].
+#[derive(Debug, thiserror::Error)]
+#[error("node {node} rejected session transfer: {reason}")]
+pub struct SessionTransferRejected {
+    pub node: String,
+    pub reason: String,
+}
+
+/// A session transfer whose outcome is unknown: the request may have
+/// reached the target, but no answer came back.
+///
+/// The session is left detached, as the target may own it now. Check
+/// whether the target has it and, if not, re-attach `snapshot` with
+/// [`BrioHostState::attach_session`](crate::host::BrioHostState::attach_session).
+/// Returned inside an `anyhow::Error`, like [`MeshCallTimeout`].
+#[derive(Debug, thiserror::Error)]
+#[error("session {} may or may not have moved to node {node}, and is detached here: {reason}", snapshot.session_id)]
+pub struct SessionTransferUnconfirmed {
+    pub node: String,
+    pub reason: String,
+    pub snapshot: crate::vfs::manager::SessionSnapshot,
+}
+
+/// A call rejected at the mesh boundary before reaching its handler.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum MeshError {
+    /// The payload didn't match the schema registered for `method`
+    #[error("Invalid payload for method '{method}': {}", errors.join("; "))]
+    InvalidPayload { method: String, errors: Vec<String> },
+}
+
 #[derive(Debug, Clone)]
 pub enum Payload {
     Json(String),
     Binary(Vec<u8>),
+    /// Structured data encoded as MessagePack, more compact than JSON
+    MsgPack(Vec<u8>),
+}
+
+impl Payload {
+    /// Encodes `value` as a MessagePack payload, with structs as maps so
+    /// fields are matched by name rather than position.
+    pub fn from_msgpack<T: Serialize>(value: &T) -> Result<Self, String> {
+        rmp_serde::to_vec_named(value)
+            .map(Self::MsgPack)
+            .map_err(|e| format!("Failed to encode MessagePack payload: {}", e))
+    }
+
+    /// Decodes a MessagePack payload; other variants are an error.
+    pub fn decode_msgpack<T: DeserializeOwned>(&self) -> Result<T, String> {
+        match self {
+            Self::MsgPack(b) => {
+                rmp_serde::from_slice(b).map_err(|e| format!("Invalid MessagePack payload: {}", e))
+            }
+            _ => Err("Payload is not MessagePack".to_string()),
+        }
+    }
+
+    /// Size of the encoded payload in bytes.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Json(s) => s.len(),
+            Self::Binary(b) | Self::MsgPack(b) => b.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub struct MeshMessage {
     pub target: String,
     pub method: String,
     pub payload: Payload,
+    /// W3C `traceparent` of the caller's span, for linking the handler's spans to it
+    pub trace_context: Option<String>,
     // The return channel.
     // We start simple: A result containing a Payload or an Error string.
     pub reply_tx: oneshot::Sender<Result<Payload, String>>,
@@ -1,11 +1,31 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use anyhow::{Result, anyhow};
-use tonic::transport::Channel;
+use futures_util::future::join_all;
+use tonic::transport::{Channel, ClientTlsConfig};
+use tracing::{debug, info, warn};
 
+use crate::infrastructure::audit::{AuditEvent, log_audit};
+use crate::infrastructure::context;
+use crate::mesh::health::{FailureDetector, MAX_MISSED_HEARTBEATS};
 use crate::mesh::types::{NodeId, NodeInfo, NodeAddress};
+use crate::mesh::grpc::DEFAULT_COMPRESSION_THRESHOLD;
 use crate::mesh::grpc::mesh_transport_client::MeshTransportClient;
-use crate::mesh::{MeshMessage, Payload};
+use crate::mesh::{MeshMessage, Payload, SessionTransferRejected};
+use crate::vfs::manager::SessionSnapshot;
+
+/// Default time a node may go unheard of, directly or through gossip, before it is pruned
+pub const DEFAULT_GOSSIP_TTL: Duration = Duration::from_secs(60);
+
+/// Default time a node has to answer a heartbeat, connecting included
+pub const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default time a node has to confirm a session transfer
+pub const DEFAULT_TRANSFER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time allowed to open a connection to a node
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Router for dispatching mesh calls to remote nodes via gRPC.
 /// Handles connection pooling and payload serialization.
@@ -13,8 +33,22 @@ use crate::mesh::{MeshMessage, Payload};
 pub struct RemoteRouter {
     registry: Arc<RwLock<NodeRegistry>>,
     clients: Arc<RwLock<HashMap<NodeId, MeshTransportClient<Channel>>>>,
-    #[allow(dead_code)]
+    detector: Arc<RwLock<FailureDetector>>,
     local_node_id: NodeId,
+    /// Connect to nodes over TLS with this configuration instead of cleartext
+    tls: Option<ClientTlsConfig>,
+    /// Where other nodes reach this one, gossiped once known
+    advertise_address: Arc<RwLock<Option<NodeAddress>>>,
+    /// How long a node may go unheard of before it is pruned
+    node_ttl: Duration,
+    /// Call payloads at least this many bytes are compressed on the wire
+    compression_threshold: usize,
+    /// How long a heartbeat may wait for its answer before counting as missed
+    heartbeat_timeout: Duration,
+    /// How long a session transfer may wait for the target to confirm it
+    transfer_timeout: Duration,
+    /// Nodes evicted or pruned since the last [`take_removed_nodes`](Self::take_removed_nodes)
+    removed: Arc<RwLock<Vec<NodeId>>>,
 }
 
 impl RemoteRouter {
@@ -22,7 +56,289 @@ impl RemoteRouter {
         Self {
             registry: Arc::new(RwLock::new(NodeRegistry::new())),
             clients: Arc::new(RwLock::new(HashMap::new())),
+            detector: Arc::new(RwLock::new(FailureDetector::default())),
             local_node_id,
+            tls: None,
+            advertise_address: Arc::new(RwLock::new(None)),
+            node_ttl: DEFAULT_GOSSIP_TTL,
+            compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            heartbeat_timeout: DEFAULT_HEARTBEAT_TIMEOUT,
+            transfer_timeout: DEFAULT_TRANSFER_TIMEOUT,
+            removed: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Connects to other nodes over TLS; see [`MeshTls`](crate::mesh::MeshTls).
+    pub fn with_tls(mut self, tls: ClientTlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Sets how long a node must be unreachable before it is removed from routing
+    pub fn with_grace_period(self, grace_period: Duration) -> Self {
+        self.detector.write().expect("Detector lock poisoned").set_grace_period(grace_period);
+        self
+    }
+
+    /// Sets how long a node may go unheard of, directly or through gossip, before it is pruned
+    pub fn with_node_ttl(mut self, ttl: Duration) -> Self {
+        self.node_ttl = ttl;
+        self
+    }
+
+    /// Sets the payload size in bytes from which calls are compressed on the wire
+    pub fn with_compression_threshold(mut self, threshold: usize) -> Self {
+        self.compression_threshold = threshold;
+        self
+    }
+
+    /// Sets how long a node has to answer a heartbeat before it counts as missed
+    pub fn with_heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.heartbeat_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a node has to confirm a session transfer
+    pub fn with_transfer_timeout(mut self, timeout: Duration) -> Self {
+        self.transfer_timeout = timeout;
+        self
+    }
+
+    /// Sets the address this node tells others to reach it at.
+    pub fn set_advertise_address(&self, address: NodeAddress) {
+        *self.advertise_address.write().expect("Advertise address lock poisoned") = Some(address);
+    }
+
+    pub fn advertise_address(&self) -> Option<NodeAddress> {
+        self.advertise_address.read().expect("Advertise address lock poisoned").clone()
+    }
+
+    pub fn local_node_id(&self) -> &NodeId {
+        &self.local_node_id
+    }
+
+    pub fn is_routable(&self, node_id: &NodeId) -> bool {
+        self.detector.read().expect("Detector lock poisoned").is_routable(node_id)
+    }
+
+    /// Sends a heartbeat to `node_id` and records the outcome with the failure detector.
+    ///
+    /// A successful heartbeat refreshes the node's `last_seen`; one not
+    /// answered within the heartbeat timeout counts as missed. Once the node
+    /// has missed [`MAX_MISSED_HEARTBEATS`] in a row and been unreachable for
+    /// the whole grace period, so it is no longer routable, it is evicted
+    /// from the registry and must be registered again to receive traffic.
+    pub async fn heartbeat(&self, node_id: &NodeId) -> Result<()> {
+        let result = match tokio::time::timeout(self.heartbeat_timeout, self.ping(node_id)).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!(
+                "Node {} did not answer a heartbeat within {:?}",
+                node_id,
+                self.heartbeat_timeout
+            )),
+        };
+        match &result {
+            Ok(()) => {
+                self.detector.write().expect("Detector lock poisoned").record_success(node_id);
+                self.registry.write().expect("Registry lock poisoned").touch(node_id, unix_millis());
+            }
+            Err(_) => {
+                // Drop the cached channel so the next attempt reconnects
+                self.clients.write().expect("Clients lock poisoned").remove(node_id);
+                let (missed, routable) = {
+                    let mut detector = self.detector.write().expect("Detector lock poisoned");
+                    let missed = detector.record_failure(node_id);
+                    (missed, detector.is_routable(node_id))
+                };
+                if missed >= MAX_MISSED_HEARTBEATS && !routable {
+                    self.evict(node_id, missed);
+                }
+            }
+        }
+        result
+    }
+
+    fn evict(&self, node_id: &NodeId, missed_heartbeats: u32) {
+        if self.registry.write().expect("Registry lock poisoned").remove(node_id).is_none() {
+            return;
+        }
+        self.detector.write().expect("Detector lock poisoned").forget(node_id);
+        self.removed.write().expect("Removed nodes lock poisoned").push(node_id.clone());
+        warn!("Evicting node {} after {} missed heartbeats", node_id, missed_heartbeats);
+        log_audit(AuditEvent::NodeEvicted {
+            node_id: node_id.to_string(),
+            missed_heartbeats,
+        });
+    }
+
+    /// Registered nodes that answered a heartbeat within the last `max_age`, sorted.
+    pub fn live_nodes(&self, max_age: Duration) -> Vec<NodeId> {
+        let cutoff = unix_millis().saturating_sub(u64::try_from(max_age.as_millis()).unwrap_or(u64::MAX));
+        let registry = self.registry.read().expect("Registry lock poisoned");
+        let mut live: Vec<NodeId> = registry
+            .list()
+            .into_iter()
+            .filter(|info| info.last_seen > cutoff)
+            .map(|info| info.id)
+            .collect();
+        live.sort_by(|a, b| a.0.cmp(&b.0));
+        live
+    }
+
+    /// Ids of all registered nodes, sorted.
+    pub fn node_ids(&self) -> Vec<NodeId> {
+        let mut ids: Vec<NodeId> = self
+            .registry
+            .read()
+            .expect("Registry lock poisoned")
+            .list()
+            .into_iter()
+            .map(|info| info.id)
+            .collect();
+        ids.sort_by(|a, b| a.0.cmp(&b.0));
+        ids
+    }
+
+    /// Heartbeats every registered node at once, logging the ones that don't answer.
+    pub async fn heartbeat_all(&self) {
+        let nodes = self.registry.read().expect("Registry lock poisoned").list();
+        join_all(nodes.into_iter().map(|node| async move {
+            if let Err(e) = self.heartbeat(&node.id).await {
+                warn!("Heartbeat to node {} failed: {}", node.id, e);
+            }
+        }))
+        .await;
+    }
+
+    /// Known nodes to gossip, this one included with a fresh `last_seen`
+    /// once it has an advertise address.
+    pub fn gossip_snapshot(&self) -> Vec<NodeInfo> {
+        let mut nodes = self.registry.read().expect("Registry lock poisoned").list();
+        if let Some(address) = self.advertise_address() {
+            nodes.push(NodeInfo {
+                id: self.local_node_id.clone(),
+                address,
+                capabilities: vec![],
+                last_seen: unix_millis(),
+            });
+        }
+        nodes
+    }
+
+    /// Merges nodes learned through gossip, returning how many were new.
+    ///
+    /// Known nodes take the newer `last_seen` of the two, and the address
+    /// that came with it. Nodes unheard of for longer than the TTL are
+    /// ignored, so one that was pruned isn't revived by stale rumours.
+    /// `last_seen` is capped at the local clock, and rumours further ahead
+    /// of it than [`MAX_GOSSIP_CLOCK_SKEW`] are dropped, so a peer with a
+    /// fast clock can't keep a node alive past its TTL.
+    pub fn merge_gossip(&self, nodes: Vec<NodeInfo>) -> usize {
+        let now = unix_millis();
+        let skew = u64::try_from(MAX_GOSSIP_CLOCK_SKEW.as_millis()).unwrap_or(u64::MAX);
+        let cutoff = self.ttl_cutoff();
+        let mut learned = 0;
+        let mut registry = self.registry.write().expect("Registry lock poisoned");
+        for mut node in nodes {
+            if node.last_seen > now.saturating_add(skew) {
+                warn!("Ignoring gossip about node {} seen {}ms in the future", node.id, node.last_seen - now);
+                continue;
+            }
+            node.last_seen = node.last_seen.min(now);
+            if node.id == self.local_node_id || node.last_seen <= cutoff {
+                continue;
+            }
+            match registry.get(&node.id) {
+                None => {
+                    info!("Learned of node {} at {} through gossip", node.id, node.address);
+                    registry.register(node);
+                    learned += 1;
+                }
+                Some(known) if node.last_seen > known.last_seen => {
+                    if known.address != node.address {
+                        // The cached channel points at the old address
+                        self.clients.write().expect("Clients lock poisoned").remove(&node.id);
+                    }
+                    registry.register(node);
+                }
+                Some(_) => {}
+            }
+        }
+        learned
+    }
+
+    /// Removes nodes that have gone unheard of for longer than the TTL,
+    /// returning their ids. Nodes never heard from are left to heartbeats
+    /// to evict.
+    pub fn prune_stale(&self) -> Vec<NodeId> {
+        let cutoff = self.ttl_cutoff();
+        let stale: Vec<NodeId> = {
+            let mut registry = self.registry.write().expect("Registry lock poisoned");
+            let stale: Vec<NodeId> = registry
+                .list()
+                .into_iter()
+                .filter(|info| info.last_seen > 0 && info.last_seen <= cutoff)
+                .map(|info| info.id)
+                .collect();
+            for id in &stale {
+                registry.remove(id);
+            }
+            stale
+        };
+        for id in &stale {
+            self.clients.write().expect("Clients lock poisoned").remove(id);
+            self.detector.write().expect("Detector lock poisoned").forget(id);
+            info!("Pruned node {}, unheard of for over {:?}", id, self.node_ttl);
+        }
+        self.removed.write().expect("Removed nodes lock poisoned").extend(stale.iter().cloned());
+        stale
+    }
+
+    /// Nodes evicted by heartbeats or pruned as stale since the last call,
+    /// so state kept about them elsewhere can be dropped too.
+    pub fn take_removed_nodes(&self) -> Vec<NodeId> {
+        std::mem::take(&mut *self.removed.write().expect("Removed nodes lock poisoned"))
+    }
+
+    /// Prunes stale nodes, then swaps known nodes with one random routable
+    /// peer, returning how many nodes were newly learned from its reply.
+    pub async fn gossip_round(&self) -> Result<usize> {
+        self.prune_stale();
+        let peers: Vec<NodeId> = self
+            .node_ids()
+            .into_iter()
+            .filter(|id| self.is_routable(id))
+            .collect();
+        if peers.is_empty() {
+            return Ok(0);
+        }
+        let peer = &peers[rand::random_range(0..peers.len())];
+
+        let mut client = self.get_or_connect(peer).await?;
+        let request = tonic::Request::new(crate::mesh::grpc::GossipRequest {
+            node_id: self.local_node_id.to_string(),
+            nodes: self.gossip_snapshot().into_iter().map(Into::into).collect(),
+        });
+        let response = client.gossip(request).await?.into_inner();
+        let learned = self.merge_gossip(response.nodes.into_iter().map(Into::into).collect());
+        debug!(peer = %peer, learned, "Gossip round complete");
+        Ok(learned)
+    }
+
+    fn ttl_cutoff(&self) -> u64 {
+        unix_millis().saturating_sub(u64::try_from(self.node_ttl.as_millis()).unwrap_or(u64::MAX))
+    }
+
+    async fn ping(&self, node_id: &NodeId) -> Result<()> {
+        let mut client = self.get_or_connect(node_id).await?;
+        let request = tonic::Request::new(crate::mesh::grpc::HeartbeatRequest {
+            node_id: self.local_node_id.to_string(),
+        });
+        let response = client.heartbeat(request).await?.into_inner();
+        if response.ready {
+            Ok(())
+        } else {
+            Err(anyhow!("Node {} is not ready", node_id))
         }
     }
 
@@ -37,30 +353,125 @@ impl RemoteRouter {
     }
 
     pub async fn send(&self, target_node: &NodeId, message: MeshMessage) -> Result<Payload> {
-        let client = self.get_or_connect(target_node).await?;
-        
-        let request = tonic::Request::new(crate::mesh::grpc::MeshRequest {
+        if !self.is_routable(target_node) {
+            return Err(anyhow!("Node {} is unreachable and has been removed from routing", target_node));
+        }
+        let payload =
+            crate::mesh::grpc::mesh_request::Payload::from_payload(message.payload, self.compression_threshold).await?;
+        let request = crate::mesh::grpc::MeshRequest {
             target: message.target,
             method: message.method,
-            payload: Some(match message.payload {
-                Payload::Json(s) => crate::mesh::grpc::mesh_request::Payload::Json(s),
-                Payload::Binary(b) => crate::mesh::grpc::mesh_request::Payload::Binary(b),
-            }),
-        });
+            payload: Some(payload),
+            trace_context: message.trace_context,
+        };
+
+        // A call that never reached the node is retried once, on a fresh
+        // connection, if the request's retry budget allows
+        let mut retried = false;
+        let response = loop {
+            let attempt = match self.get_or_connect(target_node).await {
+                Ok(mut client) => client
+                    .call(tonic::Request::new(request.clone()))
+                    .await
+                    .map_err(anyhow::Error::new),
+                Err(e) => Err(e),
+            };
+            match attempt {
+                Ok(response) => break response.into_inner(),
+                Err(e) if !retried && is_transport_failure(&e) && context::try_consume_retry() => {
+                    self.clients.write().expect("Clients lock poisoned").remove(target_node);
+                    debug!(node = %target_node, error = %e, "Mesh call did not reach node, retrying");
+                    retried = true;
+                }
+                Err(e) => return Err(e),
+            }
+        };
 
-        // We need a mutable client for the call, so we clone the channel which is cheap
-        let mut client = client.clone();
-        
-        let response = client.call(request).await?.into_inner();
-        
         match response.payload {
-            Some(crate::mesh::grpc::mesh_response::Payload::Json(s)) => Ok(Payload::Json(s)),
-            Some(crate::mesh::grpc::mesh_response::Payload::Binary(b)) => Ok(Payload::Binary(b)),
-            Some(crate::mesh::grpc::mesh_response::Payload::Error(e)) => Err(anyhow!("Remote error: {}", e)),
+            Some(payload) => payload
+                .into_payload()
+                .await?
+                .map_err(|e| anyhow!("Remote error: {}", e)),
             None => Err(anyhow!("Empty response payload")),
         }
     }
 
+    /// Hands a detached session to `target_node`, which restores it and takes ownership.
+    ///
+    /// Fails with [`SessionTransferRejected`] if the target refused the
+    /// session, and with an error for which [`is_transport_failure`] holds if
+    /// it never reached the target. Any other error, such as no answer within
+    /// the transfer timeout, leaves it unknown whether the target took it.
+    pub async fn transfer_session(&self, target_node: &NodeId, snapshot: SessionSnapshot) -> Result<()> {
+        let mut client = self.get_or_connect(target_node).await?;
+
+        let request = tonic::Request::new(crate::mesh::grpc::TransferSessionRequest {
+            session_id: snapshot.session_id,
+            base_path: snapshot.base_path.to_string_lossy().into_owned(),
+            base_snapshot_hash: snapshot.base_snapshot_hash,
+            files: snapshot
+                .files
+                .into_iter()
+                .map(|(path, content)| crate::mesh::grpc::SessionFile {
+                    path: path.to_string_lossy().into_owned(),
+                    content,
+                })
+                .collect(),
+        });
+
+        let response = match tokio::time::timeout(self.transfer_timeout, client.transfer_session(request)).await {
+            Ok(response) => response?.into_inner(),
+            Err(_) => {
+                return Err(anyhow!(
+                    "Node {} did not confirm the session transfer within {:?}",
+                    target_node,
+                    self.transfer_timeout
+                ));
+            }
+        };
+        if response.accepted {
+            Ok(())
+        } else {
+            Err(SessionTransferRejected {
+                node: target_node.to_string(),
+                reason: response.error,
+            }
+            .into())
+        }
+    }
+
+    /// Asks `target_node` to forward its changes to `scope` keys under `prefix` here.
+    pub async fn subscribe_changes(&self, target_node: &NodeId, scope: &str, prefix: &str) -> Result<()> {
+        let mut client = self.get_or_connect(target_node).await?;
+
+        let request = tonic::Request::new(crate::mesh::grpc::SubscribeChangesRequest {
+            node_id: self.local_node_id.to_string(),
+            scope: scope.to_string(),
+            prefix: prefix.to_string(),
+        });
+
+        let response = client.subscribe_changes(request).await?.into_inner();
+        if response.accepted {
+            Ok(())
+        } else {
+            Err(anyhow!("Node {} rejected change subscription", target_node))
+        }
+    }
+
+    /// Delivers a store change event, returning whether `target_node` applied it.
+    pub async fn publish_change(
+        &self,
+        target_node: &NodeId,
+        event: crate::mesh::grpc::StoreChangeEvent,
+    ) -> Result<bool> {
+        if !self.is_routable(target_node) {
+            return Err(anyhow!("Node {} is unreachable and has been removed from routing", target_node));
+        }
+        let mut client = self.get_or_connect(target_node).await?;
+        let response = client.publish_change(tonic::Request::new(event)).await?.into_inner();
+        Ok(response.applied)
+    }
+
     async fn get_or_connect(&self, node_id: &NodeId) -> Result<MeshTransportClient<Channel>> {
         // Fast path: check if connected
         {
@@ -74,9 +485,12 @@ impl RemoteRouter {
         let address = self.get_node_address(node_id)
             .ok_or_else(|| anyhow!("Node {} not found in registry", node_id))?;
 
-        // Format as http URL for tonic
-        let url = format!("http://{}", address); // Assuming HTTP/2 over cleartext for now
-        let endpoint = Channel::from_shared(url)?;
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        let mut endpoint =
+            Channel::from_shared(format!("{}://{}", scheme, address))?.connect_timeout(CONNECT_TIMEOUT);
+        if let Some(tls) = &self.tls {
+            endpoint = endpoint.tls_config(tls.clone())?;
+        }
         let channel = endpoint.connect().await?;
         let client = MeshTransportClient::new(channel);
 
@@ -89,6 +503,48 @@ impl RemoteRouter {
     }
 }
 
+impl From<NodeInfo> for crate::mesh::grpc::GossipNode {
+    fn from(info: NodeInfo) -> Self {
+        Self {
+            node_id: info.id.0,
+            address: info.address.0,
+            capabilities: info.capabilities,
+            last_seen: info.last_seen,
+        }
+    }
+}
+
+impl From<crate::mesh::grpc::GossipNode> for NodeInfo {
+    fn from(node: crate::mesh::grpc::GossipNode) -> Self {
+        Self {
+            id: NodeId(node.node_id),
+            address: NodeAddress(node.address),
+            capabilities: node.capabilities,
+            last_seen: node.last_seen,
+        }
+    }
+}
+
+/// Whether `error` means a call never reached its node: the connection
+/// failed, or the transport reported the node unavailable.
+pub(crate) fn is_transport_failure(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<tonic::transport::Error>().is_some()
+        || error
+            .downcast_ref::<tonic::Status>()
+            .is_some_and(|status| status.code() == tonic::Code::Unavailable)
+}
+
+/// How far ahead of the local clock a gossiped `last_seen` may be before
+/// the rumour is dropped
+pub const MAX_GOSSIP_CLOCK_SKEW: Duration = Duration::from_secs(5);
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
 pub struct NodeRegistry {
     nodes: HashMap<NodeId, NodeInfo>,
 }
@@ -113,6 +569,17 @@ impl NodeRegistry {
     pub fn get(&self, id: &NodeId) -> Option<&NodeInfo> {
         self.nodes.get(id)
     }
+
+    pub fn remove(&mut self, id: &NodeId) -> Option<NodeInfo> {
+        self.nodes.remove(id)
+    }
+
+    /// Sets `last_seen` of a registered node, in milliseconds since the Unix epoch.
+    pub fn touch(&mut self, id: &NodeId, last_seen: u64) {
+        if let Some(info) = self.nodes.get_mut(id) {
+            info.last_seen = last_seen;
+        }
+    }
     
     pub fn list(&self) -> Vec<NodeInfo> {
         self.nodes.values().cloned().collect()
@@ -141,4 +608,120 @@ mod tests {
         assert_eq!(list.len(), 1);
         assert_eq!(list[0].id, id);
     }
+
+    fn node(id: &str, last_seen: u64) -> NodeInfo {
+        NodeInfo {
+            id: NodeId(id.to_string()),
+            address: NodeAddress(format!("{}:50051", id)),
+            capabilities: vec![],
+            last_seen,
+        }
+    }
+
+    #[test]
+    fn test_gossip_merges_fresh_nodes_and_prunes_stale_ones() {
+        let router = RemoteRouter::new(NodeId("local".to_string())).with_node_ttl(Duration::from_secs(60));
+        let now = unix_millis();
+
+        let learned = router.merge_gossip(vec![
+            node("local", now),
+            node("fresh", now - 1_000),
+            node("stale", now - 120_000),
+        ]);
+        assert_eq!(learned, 1);
+        assert_eq!(router.node_ids(), vec![NodeId("fresh".to_string())]);
+
+        // A newer rumour moves the node
+        let mut moved = node("fresh", now);
+        moved.address = NodeAddress("10.0.0.9:50051".to_string());
+        assert_eq!(router.merge_gossip(vec![moved]), 0);
+        assert_eq!(router.get_node_address(&NodeId("fresh".to_string())).unwrap().0, "10.0.0.9:50051");
+
+        router.register_node(node("aging", now - 120_000));
+        router.register_node(node("bootstrap", 0));
+        assert_eq!(router.prune_stale(), vec![NodeId("aging".to_string())]);
+        assert_eq!(router.node_ids().len(), 2);
+    }
+
+    #[test]
+    fn test_gossip_from_fast_clocks_is_capped_or_dropped() {
+        let router = RemoteRouter::new(NodeId("local".to_string())).with_node_ttl(Duration::from_secs(60));
+        let now = unix_millis();
+        let skew = MAX_GOSSIP_CLOCK_SKEW.as_millis() as u64;
+
+        let learned = router.merge_gossip(vec![node("ahead", now + skew / 2), node("far-future", now + 3_600_000)]);
+        assert_eq!(learned, 1);
+        assert_eq!(router.node_ids(), vec![NodeId("ahead".to_string())]);
+
+        let registry = router.registry.read().unwrap();
+        let ahead = registry.get(&NodeId("ahead".to_string())).unwrap();
+        assert!(ahead.last_seen <= unix_millis(), "last_seen must not be in the future");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_node_is_retried_once_within_budget() {
+        use crate::infrastructure::context::RequestContext;
+
+        let router = RemoteRouter::new(NodeId("local".to_string()));
+        // Nothing listens on port 1, so every connection is refused
+        router.register_node(NodeInfo {
+            address: NodeAddress("127.0.0.1:1".to_string()),
+            ..node("down", 0)
+        });
+        let message = || MeshMessage {
+            target: "echo".to_string(),
+            method: "ping".to_string(),
+            payload: Payload::Json("{}".to_string()),
+            trace_context: None,
+            reply_tx: tokio::sync::oneshot::channel().0,
+        };
+        let down = NodeId("down".to_string());
+
+        let context = RequestContext::new(3);
+        let result = context.clone().scope(router.send(&down, message())).await;
+        assert!(result.is_err_and(|e| is_transport_failure(&e)));
+        assert_eq!(context.retry_budget().remaining(), 2);
+
+        let spent = RequestContext::new(0);
+        assert!(spent.clone().scope(router.send(&down, message())).await.is_err());
+        assert_eq!(spent.retry_budget().remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_missed_heartbeats_evict_only_after_grace_period() {
+        let router = RemoteRouter::new(NodeId("local".to_string())).with_grace_period(Duration::from_secs(60));
+        router.register_node(NodeInfo {
+            address: NodeAddress("127.0.0.1:1".to_string()),
+            ..node("down", 0)
+        });
+
+        for _ in 0..MAX_MISSED_HEARTBEATS + 1 {
+            router.heartbeat_all().await;
+        }
+        // Still within the grace period, so still routable and registered
+        assert_eq!(router.node_ids(), vec![NodeId("down".to_string())]);
+
+        let router = router.with_grace_period(Duration::ZERO);
+        router.heartbeat_all().await;
+        assert!(router.node_ids().is_empty());
+        assert_eq!(router.take_removed_nodes(), vec![NodeId("down".to_string())]);
+        assert!(router.take_removed_nodes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_to_silent_node_times_out() {
+        // Accepts connections but never answers them
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let router = RemoteRouter::new(NodeId("local".to_string())).with_heartbeat_timeout(Duration::from_millis(100));
+        router.register_node(NodeInfo {
+            address: NodeAddress(listener.local_addr().unwrap().to_string()),
+            ..node("silent", 0)
+        });
+
+        let result = tokio::time::timeout(Duration::from_secs(5), router.heartbeat(&NodeId("silent".to_string())))
+            .await
+            .expect("heartbeat must give up on its own");
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("did not answer a heartbeat"), "{}", err);
+    }
 }
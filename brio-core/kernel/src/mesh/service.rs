@@ -1,15 +1,25 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tonic::transport::ServerTlsConfig;
+use tonic::transport::server::TcpIncoming;
 use tonic::{Request, Response, Status};
 
 use crate::host::BrioHostState;
 use crate::mesh::grpc::{
-    mesh_transport_server::MeshTransport,
+    mesh_transport_server::{MeshTransport, MeshTransportServer},
     MeshRequest, MeshResponse, HeartbeatRequest, HeartbeatResponse,
+    TransferSessionRequest, TransferSessionResponse,
+    SubscribeChangesRequest, SubscribeChangesResponse,
+    StoreChangeEvent, PublishChangeResponse,
+    GossipRequest, GossipResponse,
     mesh_response::Payload as ResponsePayload,
-    mesh_request::Payload as RequestPayload,
 };
-use crate::mesh::Payload;
 use crate::mesh::types::NodeId;
+use crate::store::StoreChange;
+use crate::vfs::manager::SessionSnapshot;
 
 /// gRPC Service Implementation for MeshTransport.
 /// Handles incoming RPC calls and routes them to local components via `BrioHostState`.
@@ -24,32 +34,168 @@ impl MeshService {
     }
 }
 
+/// A running mesh gRPC server; dropping the handle leaves it serving.
+#[derive(Debug)]
+pub struct MeshServer {
+    local_addr: SocketAddr,
+    shutdown: CancellationToken,
+    task: JoinHandle<Result<(), tonic::transport::Error>>,
+}
+
+impl MeshServer {
+    /// Binds `addr` and starts serving `host` on it, over TLS if `tls` is given.
+    ///
+    /// The listener is bound before this returns, so a taken or unusable
+    /// address is reported here instead of from a background task.
+    pub async fn bind(
+        host: Arc<BrioHostState>,
+        node_id: NodeId,
+        addr: SocketAddr,
+        tls: Option<ServerTlsConfig>,
+    ) -> std::io::Result<Self> {
+        let mut builder = tonic::transport::Server::builder();
+        if let Some(tls) = tls {
+            builder = builder.tls_config(tls).map_err(std::io::Error::other)?;
+        }
+
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let incoming = TcpIncoming::from_listener(listener, true, None).map_err(std::io::Error::other)?;
+
+        let shutdown = CancellationToken::new();
+        let service = MeshService::new(host, node_id);
+        let task = tokio::spawn(
+            builder
+                .add_service(MeshTransportServer::new(service))
+                .serve_with_incoming_shutdown(incoming, shutdown.clone().cancelled_owned()),
+        );
+
+        Ok(Self {
+            local_addr,
+            shutdown,
+            task,
+        })
+    }
+
+    /// The address actually bound, e.g. the port picked for `:0`.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops accepting calls and waits for the server to finish.
+    pub async fn shutdown(self) {
+        self.shutdown.cancel();
+        match self.task.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::error!("Mesh gRPC server failed: {:?}", e),
+            Err(e) => tracing::error!("Mesh gRPC server task failed: {}", e),
+        }
+    }
+}
+
 #[tonic::async_trait]
 impl MeshTransport for MeshService {
     async fn call(&self, request: Request<MeshRequest>) -> Result<Response<MeshResponse>, Status> {
         let req = request.into_inner();
         
         let payload = match req.payload {
-            Some(RequestPayload::Json(s)) => Payload::Json(s),
-            Some(RequestPayload::Binary(b)) => Payload::Binary(b),
+            Some(payload) => payload
+                .into_payload()
+                .await
+                .map_err(|e| Status::invalid_argument(format!("Undecodable payload: {}", e)))?,
             None => return Err(Status::invalid_argument("Missing payload")),
         };
 
         // Execute call against local host
         // Note: We use the raw component ID as the target, assuming incoming requests are for this node
-        match self.host.mesh_call(&req.target, &req.method, payload).await {
-            Ok(Payload::Json(s)) => Ok(Response::new(MeshResponse {
-                payload: Some(ResponsePayload::Json(s)),
-            })),
-            Ok(Payload::Binary(b)) => Ok(Response::new(MeshResponse {
-                payload: Some(ResponsePayload::Binary(b)),
-            })),
+        match self.host.mesh_call_from_remote(&req.target, &req.method, payload, req.trace_context).await {
+            Ok(payload) => {
+                let payload = ResponsePayload::from_payload(payload, self.host.mesh_compression_threshold())
+                    .await
+                    .map_err(|e| Status::internal(format!("Failed to compress reply: {}", e)))?;
+                Ok(Response::new(MeshResponse { payload: Some(payload) }))
+            }
             Err(e) => Ok(Response::new(MeshResponse {
                 payload: Some(ResponsePayload::Error(e.to_string())),
             })),
         }
     }
 
+    async fn transfer_session(
+        &self,
+        request: Request<TransferSessionRequest>,
+    ) -> Result<Response<TransferSessionResponse>, Status> {
+        let req = request.into_inner();
+        let snapshot = SessionSnapshot {
+            session_id: req.session_id,
+            base_path: req.base_path.into(),
+            base_snapshot_hash: req.base_snapshot_hash,
+            files: req
+                .files
+                .into_iter()
+                .map(|f| (f.path.into(), f.content))
+                .collect(),
+        };
+
+        Ok(Response::new(match self.host.attach_session(snapshot) {
+            Ok(()) => TransferSessionResponse {
+                accepted: true,
+                error: String::new(),
+            },
+            Err(e) => TransferSessionResponse {
+                accepted: false,
+                error: e,
+            },
+        }))
+    }
+
+    async fn subscribe_changes(
+        &self,
+        request: Request<SubscribeChangesRequest>,
+    ) -> Result<Response<SubscribeChangesResponse>, Status> {
+        let req = request.into_inner();
+        if req.node_id.is_empty() || req.scope.is_empty() {
+            return Err(Status::invalid_argument("Missing node_id or scope"));
+        }
+        self.host
+            .add_change_subscriber(NodeId::from(req.node_id), req.scope, req.prefix);
+        Ok(Response::new(SubscribeChangesResponse { accepted: true }))
+    }
+
+    async fn publish_change(
+        &self,
+        request: Request<StoreChangeEvent>,
+    ) -> Result<Response<PublishChangeResponse>, Status> {
+        let event = request.into_inner();
+        let value = if event.deleted {
+            None
+        } else {
+            Some(
+                serde_json::from_str(&event.value_json)
+                    .map_err(|e| Status::invalid_argument(format!("Invalid value_json: {}", e)))?,
+            )
+        };
+        let change = StoreChange {
+            scope: event.scope,
+            key: event.key,
+            value,
+        };
+
+        let applied = self
+            .host
+            .apply_remote_change(&event.event_id, &NodeId::from(event.origin_node), &change)
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(PublishChangeResponse { applied }))
+    }
+
+    async fn gossip(&self, request: Request<GossipRequest>) -> Result<Response<GossipResponse>, Status> {
+        let req = request.into_inner();
+        let known = self.host.exchange_gossip(req.nodes.into_iter().map(Into::into).collect());
+        Ok(Response::new(GossipResponse {
+            nodes: known.into_iter().map(Into::into).collect(),
+        }))
+    }
+
     async fn heartbeat(&self, _request: Request<HeartbeatRequest>) -> Result<Response<HeartbeatResponse>, Status> {
         Ok(Response::new(HeartbeatResponse {
             node_id: self.node_id.to_string(),
@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use tonic::transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig};
+
+/// TLS settings for mesh traffic, shared by a node's server and its clients.
+///
+/// Every node presents the same kind of certificate both ways. With a CA the
+/// mesh runs mutual TLS: servers only accept clients whose certificate the
+/// CA signed, and clients only trust servers the CA signed. Without one,
+/// servers accept any client and clients check servers against the public
+/// web PKI roots.
+#[derive(Debug, Clone)]
+pub struct MeshTls {
+    server: ServerTlsConfig,
+    client: ClientTlsConfig,
+}
+
+impl MeshTls {
+    /// Builds the configuration from PEM-encoded certificate chain, private
+    /// key and, for mutual TLS, CA certificate.
+    pub fn from_pem(cert: impl AsRef<[u8]>, key: impl AsRef<[u8]>, ca: Option<&[u8]>) -> Self {
+        // Other dependencies enable both rustls backends, which leaves it
+        // unable to pick one by itself; an already installed one wins
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+
+        let identity = Identity::from_pem(cert, key);
+        let server = ServerTlsConfig::new().identity(identity.clone());
+        match ca {
+            Some(ca) => Self {
+                server: server.client_ca_root(Certificate::from_pem(ca)),
+                client: ClientTlsConfig::new()
+                    .ca_certificate(Certificate::from_pem(ca))
+                    .identity(identity),
+            },
+            None => Self {
+                server,
+                client: ClientTlsConfig::new().with_webpki_roots(),
+            },
+        }
+    }
+
+    /// Reads the PEM files for [`from_pem`](Self::from_pem).
+    pub fn load(cert_path: &Path, key_path: &Path, ca_path: Option<&Path>) -> std::io::Result<Self> {
+        let read = |path: &Path| {
+            std::fs::read(path).map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+        };
+        let ca = ca_path.map(read).transpose()?;
+        Ok(Self::from_pem(read(cert_path)?, read(key_path)?, ca.as_deref()))
+    }
+
+    /// Name servers' certificates are checked against, instead of the host
+    /// in the node's address. Useful when nodes are addressed by IP.
+    pub fn with_domain_name(mut self, domain_name: impl Into<String>) -> Self {
+        self.client = self.client.domain_name(domain_name);
+        self
+    }
+
+    pub fn server_config(&self) -> ServerTlsConfig {
+        self.server.clone()
+    }
+
+    pub fn client_config(&self) -> ClientTlsConfig {
+        self.client.clone()
+    }
+}
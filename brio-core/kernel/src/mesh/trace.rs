@@ -0,0 +1,30 @@
+//! W3C trace context carried by mesh calls, so spans on both ends of a call
+//! end up in the same trace.
+
+use std::collections::HashMap;
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+const TRACEPARENT: &str = "traceparent";
+
+/// The `traceparent` of the current span, if it belongs to a trace.
+///
+/// Spans only belong to a trace when an OpenTelemetry layer is installed,
+/// so this is `None` without tracing enabled.
+pub fn current_traceparent() -> Option<String> {
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&Span::current().context(), &mut carrier);
+    carrier.remove(TRACEPARENT)
+}
+
+/// Makes `span` a child of the remote span `traceparent` names.
+///
+/// Malformed values are ignored, leaving `span` where it was.
+pub fn set_remote_parent(span: &Span, traceparent: &str) {
+    let carrier = HashMap::from([(TRACEPARENT.to_string(), traceparent.to_string())]);
+    let cx = TraceContextPropagator::new().extract(&carrier);
+    let _ = span.set_parent(cx);
+}
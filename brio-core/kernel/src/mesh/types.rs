@@ -45,16 +45,10 @@ pub struct NodeInfo {
     pub id: NodeId,
     pub address: NodeAddress,
     pub capabilities: Vec<String>,
+    /// Milliseconds since the Unix epoch of the last successful heartbeat
     pub last_seen: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MeshConfig {
-    pub node_id: String,
-    pub listen_address: String,
-    pub bootstrap_nodes: Vec<String>,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::store::typed::TypedStore;
+use crate::ws::{SnapshotSource, WsError, WsPatch};
+
+/// A write to one key of a scope's key-value table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoreChange {
+    pub scope: String,
+    pub key: String,
+    /// The new value as JSON, or `None` when the key was deleted
+    pub value: Option<serde_json::Value>,
+}
+
+impl StoreChange {
+    /// Renders the change as a patch against `/store/{scope}/{key}`.
+    ///
+    /// Writes are `add`s, which RFC 6902 treats as a replace when the member
+    /// already exists.
+    pub fn to_patch(&self) -> WsPatch {
+        let path = format!("/store/{}/{}", escape_pointer(&self.scope), escape_pointer(&self.key));
+        let op = match &self.value {
+            Some(value) => serde_json::json!({"op": "add", "path": path, "value": value}),
+            None => serde_json::json!({"op": "remove", "path": path}),
+        };
+        let patch = serde_json::from_value(serde_json::Value::Array(vec![op]))
+            .expect("Store change patch is well-formed");
+        WsPatch::new(patch)
+    }
+}
+
+fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Receives the changes made through a [`TypedStore`](crate::store::TypedStore).
+///
+/// Called after the write has succeeded, so a failing sink never loses data.
+#[async_trait]
+pub trait ChangeSink: Send + Sync {
+    async fn publish(&self, change: StoreChange);
+}
+
+/// The key-value entries of a set of scopes, as the document that
+/// [`StoreChange::to_patch`] patches apply to: `{"store": {scope: {key: value}}}`.
+///
+/// Lets lagged WebSocket clients resync from the store. Scopes must use the
+/// JSON codec.
+pub struct StoreSnapshot {
+    stores: Vec<TypedStore<serde_json::Value>>,
+}
+
+impl StoreSnapshot {
+    pub fn new(stores: Vec<TypedStore<serde_json::Value>>) -> Self {
+        Self { stores }
+    }
+}
+
+#[async_trait]
+impl SnapshotSource for StoreSnapshot {
+    async fn snapshot(&self) -> Result<serde_json::Value, WsError> {
+        let mut scopes = serde_json::Map::new();
+        for store in &self.stores {
+            let entries = store
+                .entries()
+                .await
+                .map_err(|e| WsError::Snapshot(format!("scope '{}': {}", store.scope(), e)))?;
+            scopes.insert(store.scope().to_string(), entries.into_iter().collect());
+        }
+        Ok(serde_json::json!({ "store": scopes }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_becomes_add_with_escaped_path() {
+        let change = StoreChange {
+            scope: "agent_1".to_string(),
+            key: "job/1~a".to_string(),
+            value: Some(serde_json::json!({"state": "done"})),
+        };
+        assert_eq!(
+            change.to_patch().to_json().unwrap(),
+            r#"[{"op":"add","path":"/store/agent_1/job~11~0a","value":{"state":"done"}}]"#
+        );
+    }
+
+    #[test]
+    fn delete_becomes_remove() {
+        let change = StoreChange {
+            scope: "agent_1".to_string(),
+            key: "job".to_string(),
+            value: None,
+        };
+        assert_eq!(
+            change.to_patch().to_json().unwrap(),
+            r#"[{"op":"remove","path":"/store/agent_1/job"}]"#
+        );
+    }
+}
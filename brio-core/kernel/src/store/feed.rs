@@ -0,0 +1,219 @@
+use futures_util::Stream;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+
+use crate::store::changes::StoreChange;
+use crate::store::r#impl::{GenericRow, SqlStore, StoreError, StoreTransaction};
+
+/// Events fetched per query while a feed catches up
+const FEED_BATCH_SIZE: usize = 256;
+
+/// How often an idle feed re-checks the table for entries appended elsewhere,
+/// e.g. by another node sharing the database
+const FEED_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn changelog_table(scope: &str) -> String {
+    format!("{}_changelog", scope)
+}
+
+/// One entry of a [`ChangeLog`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    /// Position in the log; strictly increasing, never reused
+    pub sequence: u64,
+    pub change: StoreChange,
+    pub recorded_at: SystemTime,
+}
+
+/// Append-only log of store changes, read back in order as a change feed.
+///
+/// Entries live in the `{scope}_changelog` table, numbered by an
+/// autoincrement sequence. Attach the log to a [`TypedStore`](crate::store::TypedStore)
+/// with [`with_change_log`](crate::store::TypedStore::with_change_log) to
+/// record every put and delete in the same transaction as the write itself,
+/// so the log holds exactly the writes that committed. The log must live in
+/// the same database as the store.
+///
+/// Only writes made through a `TypedStore` are logged. Statements run
+/// directly with [`SqlStore::execute`] or [`SqlStore::transaction`] bypass
+/// the log, as they bypass key watchers.
+pub struct ChangeLog {
+    store: SqlStore,
+    scope: String,
+    /// Bumped on every local append to wake idle feeds early
+    appended: watch::Sender<u64>,
+}
+
+impl ChangeLog {
+    pub fn new(store: SqlStore, scope: impl Into<String>) -> Self {
+        Self {
+            store,
+            scope: scope.into(),
+            appended: watch::Sender::new(0),
+        }
+    }
+
+    /// Creates the log table if it doesn't exist yet.
+    pub async fn ensure_table(&self) -> Result<(), StoreError> {
+        // Neither backend accepts the other's spelling of a never-reused sequence
+        let seq = if self.store.is_postgres() {
+            "BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY"
+        } else {
+            "INTEGER PRIMARY KEY AUTOINCREMENT"
+        };
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} (seq {}, change_scope TEXT NOT NULL, \
+             id TEXT NOT NULL, value TEXT NOT NULL, deleted INTEGER NOT NULL, recorded_at BIGINT NOT NULL)",
+            changelog_table(&self.scope),
+            seq
+        );
+        self.store.execute(&self.scope, &sql, vec![]).await?;
+        Ok(())
+    }
+
+    /// Records `change` on its own, returning its sequence number.
+    pub async fn append(&self, change: &StoreChange) -> Result<u64, StoreError> {
+        let (sql, params) = self.insert(change);
+        let rows = self.store.query(&self.scope, &sql, params).await?;
+        let sequence = inserted_sequence(rows)?;
+        self.notify_appended();
+        Ok(sequence)
+    }
+
+    /// Records `change` as part of `tx`, returning its sequence number.
+    ///
+    /// Call [`notify_appended`](Self::notify_appended) once `tx` commits.
+    pub(crate) async fn append_in(&self, tx: &mut StoreTransaction<'_>, change: &StoreChange) -> Result<u64, StoreError> {
+        let (sql, params) = self.insert(change);
+        inserted_sequence(tx.query(&self.scope, &sql, params).await?)
+    }
+
+    /// Wakes idle feeds to read what was just appended.
+    pub(crate) fn notify_appended(&self) {
+        self.appended.send_modify(|count| *count += 1);
+    }
+
+    fn insert(&self, change: &StoreChange) -> (String, Vec<String>) {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let sql = format!(
+            "INSERT INTO {} (change_scope, id, value, deleted, recorded_at) \
+             VALUES (?, ?, ?, CAST(? AS INTEGER), CAST(? AS BIGINT)) RETURNING seq",
+            changelog_table(&self.scope)
+        );
+        let params = vec![
+            change.scope.clone(),
+            change.key.clone(),
+            change.value.as_ref().map(|v| v.to_string()).unwrap_or_default(),
+            u8::from(change.value.is_none()).to_string(),
+            now_ms.to_string(),
+        ];
+        (sql, params)
+    }
+
+    /// Up to `limit` entries after `after_sequence` whose key starts with `prefix`, oldest first.
+    pub async fn read(&self, after_sequence: u64, prefix: &str, limit: usize) -> Result<Vec<ChangeEvent>, StoreError> {
+        let pattern = format!(
+            "{}%",
+            prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+        let sql = format!(
+            "SELECT seq, change_scope, id, value, deleted, recorded_at FROM {} \
+             WHERE seq > CAST(? AS BIGINT) AND id LIKE ? ESCAPE '\\' ORDER BY seq LIMIT {}",
+            changelog_table(&self.scope),
+            limit
+        );
+        let rows = self
+            .store
+            .query(&self.scope, &sql, vec![after_sequence.to_string(), pattern])
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let [seq, scope, key, value, deleted, recorded_at] = <[String; 6]>::try_from(row.values)
+                    .map_err(|_| StoreError::Internal(anyhow::anyhow!("Malformed changelog row")))?;
+                let parse_int = |s: &str| {
+                    s.parse::<u64>()
+                        .map_err(|e| StoreError::Internal(anyhow::anyhow!("Malformed changelog row: {}", e)))
+                };
+                let value = if deleted == "1" {
+                    None
+                } else {
+                    Some(serde_json::from_str(&value).map_err(|e| StoreError::Codec(e.to_string()))?)
+                };
+                Ok(ChangeEvent {
+                    sequence: parse_int(&seq)?,
+                    change: StoreChange { scope, key, value },
+                    recorded_at: UNIX_EPOCH + Duration::from_millis(parse_int(&recorded_at)?),
+                })
+            })
+            .collect()
+    }
+
+    /// Streams entries after `from_sequence` whose key starts with `prefix_filter`,
+    /// then keeps following the log as it grows.
+    ///
+    /// Pass the last sequence a consumer processed to resume where it left
+    /// off, or 0 to read from the beginning. A failed read is yielded as an
+    /// error and retried after a pause if the consumer keeps polling.
+    pub fn change_feed(
+        self: &Arc<Self>,
+        from_sequence: u64,
+        prefix_filter: &str,
+    ) -> impl Stream<Item = Result<ChangeEvent, StoreError>> + Send + use<> {
+        struct Feed {
+            log: Arc<ChangeLog>,
+            last: u64,
+            prefix: String,
+            pending: VecDeque<ChangeEvent>,
+            appended: watch::Receiver<u64>,
+            failed: bool,
+        }
+
+        let feed = Feed {
+            log: self.clone(),
+            last: from_sequence,
+            prefix: prefix_filter.to_string(),
+            pending: VecDeque::new(),
+            appended: self.appended.subscribe(),
+            failed: false,
+        };
+
+        futures_util::stream::unfold(feed, |mut feed| async move {
+            loop {
+                if let Some(event) = feed.pending.pop_front() {
+                    feed.last = event.sequence;
+                    return Some((Ok(event), feed));
+                }
+
+                if std::mem::take(&mut feed.failed) {
+                    tokio::time::sleep(FEED_POLL_INTERVAL).await;
+                }
+
+                // Marked seen before reading, so an append racing the read still wakes us
+                feed.appended.borrow_and_update();
+                match feed.log.read(feed.last, &feed.prefix, FEED_BATCH_SIZE).await {
+                    Ok(events) if !events.is_empty() => feed.pending.extend(events),
+                    Ok(_) => {
+                        let _ = tokio::time::timeout(FEED_POLL_INTERVAL, feed.appended.changed()).await;
+                    }
+                    Err(e) => {
+                        feed.failed = true;
+                        return Some((Err(e), feed));
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn inserted_sequence(rows: Vec<GenericRow>) -> Result<u64, StoreError> {
+    rows.first()
+        .and_then(|row| row.values.first())
+        .and_then(|seq| seq.parse().ok())
+        .ok_or_else(|| StoreError::Internal(anyhow::anyhow!("Changelog insert returned no sequence")))
+}
@@ -1,11 +1,33 @@
 use anyhow::Result;
+use futures_util::future::BoxFuture;
 use sqlx::{
-    Column, Row, TypeInfo, ValueRef,
-    sqlite::{SqlitePool, SqliteRow},
+    Column, Executor, Row, Sqlite, TypeInfo, ValueRef,
+    sqlite::{SqlitePool, SqlitePoolOptions, SqliteRow},
 };
-use tracing::instrument;
+use futures_util::Stream;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, instrument, warn};
 
-use crate::store::policy::{PolicyError, QueryPolicy};
+use crate::infrastructure::audit::{AuditEvent, log_audit};
+use crate::infrastructure::context;
+use crate::store::policy::{
+    PolicyError, PolicyFailureMode, QueryPolicy, namespace_tables, tenant_scope, validate_tenant,
+};
+#[cfg(feature = "postgres")]
+use crate::store::postgres;
+use crate::store::typed::{UNEXPIRED, kv_table, now_ms};
+use crate::store::watch::{KeyChange, KeyWatchers};
+
+/// Default number of retries for writes that hit a busy/locked database
+pub const DEFAULT_BUSY_RETRIES: u32 = 5;
+/// Backoff before the first busy retry; doubles on each further attempt
+const BUSY_BASE_DELAY: Duration = Duration::from_millis(10);
+
+// Primary SQLite result codes for contention (extended codes share the low byte)
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
 
 #[derive(Debug, thiserror::Error)]
 pub enum StoreError {
@@ -15,6 +37,140 @@ pub enum StoreError {
     PolicyError(#[from] PolicyError),
     #[error("Internal Error: {0}")]
     Internal(#[from] anyhow::Error),
+    #[error("Database busy after {attempts} attempts")]
+    Busy { attempts: u32 },
+    #[error("Codec Error: {0}")]
+    Codec(String),
+    #[error("Value of {size} bytes exceeds the {limit} byte limit")]
+    ValueTooLarge { size: usize, limit: usize },
+    #[error("Key '{key}' is no longer at version {expected}")]
+    VersionConflict { key: String, expected: u64 },
+}
+
+/// Returns true if the error is SQLite reporting lock contention
+/// (`SQLITE_BUSY`/`SQLITE_LOCKED` or any of their extended codes).
+fn is_busy(err: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db_err) = err else {
+        return false;
+    };
+    db_err
+        .code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| matches!(code & 0xff, SQLITE_BUSY | SQLITE_LOCKED))
+}
+
+/// Aggregate figures for the key-value entries under a prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    pub key_count: u64,
+    /// Encoded size of the values, excluding keys
+    pub total_bytes: u64,
+    /// Write time of the least recently written entry
+    pub oldest: Option<SystemTime>,
+    /// Write time of the most recently written entry
+    pub newest: Option<SystemTime>,
+}
+
+/// Largest encoded value a store accepts, overridable per scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct ValueSizeLimits {
+    /// Limit for scopes without their own; `None` accepts any size
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+    /// Limits for scopes that hold larger (or must hold smaller) values
+    #[serde(default)]
+    pub per_scope: HashMap<String, usize>,
+}
+
+impl ValueSizeLimits {
+    /// The limit applying to `scope`, if any.
+    pub fn limit_for(&self, scope: &str) -> Option<usize> {
+        self.per_scope.get(scope).copied().or(self.max_bytes)
+    }
+}
+
+/// Connection pool of the database behind a [`SqlStore`].
+///
+/// SQLite is always available; PostgreSQL needs the `postgres` feature and
+/// lets several nodes share one database. Statements are written once,
+/// with `?` placeholders, and numbered for PostgreSQL on the way out.
+/// Parameters are bound as text, so statements compare or store them in
+/// integer columns through `CAST(? AS BIGINT)`, which both backends accept.
+#[derive(Debug, Clone)]
+pub enum DbPool {
+    Sqlite(SqlitePool),
+    #[cfg(feature = "postgres")]
+    Postgres(sqlx::PgPool),
+}
+
+impl DbPool {
+    /// Connects to `url`, choosing the backend by its scheme: `postgres://`
+    /// and `postgresql://` URLs open PostgreSQL, anything else SQLite.
+    pub async fn connect(url: &str) -> Result<Self, StoreError> {
+        if Self::is_postgres_url(url) {
+            #[cfg(feature = "postgres")]
+            return Ok(Self::Postgres(sqlx::postgres::PgPoolOptions::new().connect(url).await?));
+            #[cfg(not(feature = "postgres"))]
+            return Err(StoreError::Internal(anyhow::anyhow!(
+                "'{}' is a PostgreSQL URL, but the kernel was built without the `postgres` feature",
+                url.split('@').next_back().unwrap_or_default()
+            )));
+        }
+        Ok(Self::Sqlite(SqlitePoolOptions::new().connect(url).await?))
+    }
+
+    /// Whether [`connect`](Self::connect) would open PostgreSQL for `url`.
+    pub fn is_postgres_url(url: &str) -> bool {
+        url.starts_with("postgres://") || url.starts_with("postgresql://")
+    }
+
+    /// The SQLite pool, unless the store is backed by another database.
+    pub fn as_sqlite(&self) -> Option<&SqlitePool> {
+        match self {
+            Self::Sqlite(pool) => Some(pool),
+            #[cfg(feature = "postgres")]
+            Self::Postgres(_) => None,
+        }
+    }
+
+    /// Runs `SELECT 1`, failing if the database can't be reached.
+    pub async fn ping(&self) -> Result<(), StoreError> {
+        match self {
+            Self::Sqlite(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ())?,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ())?,
+        }
+        Ok(())
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Self::Sqlite(pool) => pool.close().await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(pool) => pool.close().await,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Self::Sqlite(pool) => pool.is_closed(),
+            #[cfg(feature = "postgres")]
+            Self::Postgres(pool) => pool.is_closed(),
+        }
+    }
+}
+
+impl From<SqlitePool> for DbPool {
+    fn from(pool: SqlitePool) -> Self {
+        Self::Sqlite(pool)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<sqlx::PgPool> for DbPool {
+    fn from(pool: sqlx::PgPool) -> Self {
+        Self::Postgres(pool)
+    }
 }
 
 /// A generic Row representation matching the WIT definition.
@@ -25,13 +181,145 @@ pub struct GenericRow {
 }
 
 pub struct SqlStore {
-    pool: SqlitePool,
+    pool: DbPool,
     policy: Box<dyn QueryPolicy>,
+    policy_failure_mode: PolicyFailureMode,
+    busy_retries: u32,
+    value_limits: ValueSizeLimits,
+    /// Namespace every table is moved into; `None` uses names as written
+    tenant: Option<String>,
+    watchers: Arc<KeyWatchers>,
 }
 
 impl SqlStore {
-    pub fn new(pool: SqlitePool, policy: Box<dyn QueryPolicy>) -> Self {
-        Self { pool, policy }
+    pub fn new(pool: impl Into<DbPool>, policy: Box<dyn QueryPolicy>) -> Self {
+        Self {
+            pool: pool.into(),
+            policy,
+            policy_failure_mode: PolicyFailureMode::default(),
+            busy_retries: DEFAULT_BUSY_RETRIES,
+            value_limits: ValueSizeLimits::default(),
+            tenant: None,
+            watchers: Arc::new(KeyWatchers::new()),
+        }
+    }
+
+    /// Shares `watchers` with other stores over the same database, so
+    /// [`watch`](Self::watch) sees writes made through any of them.
+    pub fn with_watchers(mut self, watchers: Arc<KeyWatchers>) -> Self {
+        self.watchers = watchers;
+        self
+    }
+
+    /// Streams every write and delete of a key starting with `prefix`, in
+    /// any scope, from now on.
+    ///
+    /// Covers changes made through a [`TypedStore`](crate::store::TypedStore)
+    /// over this store or one sharing its [watchers](Self::with_watchers);
+    /// raw SQL writes aren't seen. A tenant store only sees its own tenant's keys.
+    pub fn watch(&self, prefix: &str) -> impl Stream<Item = KeyChange> + Send + use<> {
+        self.watchers.watch(self.tenant.as_deref(), prefix)
+    }
+
+    /// True if a watcher would receive a change to `key`.
+    pub(crate) fn is_watched(&self, key: &str) -> bool {
+        self.watchers.is_watched(self.tenant.as_deref(), key)
+    }
+
+    /// Tells watchers that `key` in `scope` now holds `value`, or was deleted.
+    pub(crate) fn publish_change(&self, scope: &str, key: &str, value: Option<serde_json::Value>) {
+        self.watchers.publish(self.tenant.as_deref(), scope, key, value);
+    }
+
+    /// Isolates this store in `tenant`'s namespace.
+    ///
+    /// Callers keep using logical table names such as `agent_1_data`; each
+    /// query is rewritten to the tenant's physical tables (`{tenant}__agent_1_data`)
+    /// and authorized against the tenant's scope, so no SQL can reach the
+    /// tables of another tenant or of the shared, un-namespaced store.
+    pub fn with_tenant(mut self, tenant: &str) -> Result<Self, StoreError> {
+        validate_tenant(tenant)?;
+        self.tenant = Some(tenant.to_string());
+        Ok(self)
+    }
+
+    pub fn tenant(&self) -> Option<&str> {
+        self.tenant.as_deref()
+    }
+
+    /// Whether statements run against PostgreSQL rather than SQLite, for the
+    /// little DDL that can't be written for both.
+    pub(crate) fn is_postgres(&self) -> bool {
+        self.pool.as_sqlite().is_none()
+    }
+
+    /// Applies the tenant namespace, if any, and enforces the policy.
+    fn prepare<'a>(&self, scope: &str, sql: &'a str) -> Result<std::borrow::Cow<'a, str>, StoreError> {
+        match &self.tenant {
+            Some(tenant) => {
+                let sql = namespace_tables(tenant, sql)?;
+                self.authorize(&tenant_scope(tenant, scope), &sql)?;
+                Ok(sql.into())
+            }
+            None => {
+                self.authorize(scope, sql)?;
+                Ok(sql.into())
+            }
+        }
+    }
+
+    /// Runs the policy, applying the failure mode if it can't reach a decision.
+    ///
+    /// Denials are returned as-is; evaluation errors are always logged and
+    /// audited, whether or not the query then goes ahead.
+    fn authorize(&self, scope: &str, sql: &str) -> Result<(), PolicyError> {
+        match self.policy.authorize(scope, sql) {
+            Err(e) if e.is_evaluation_error() => {
+                let allowed = self.policy_failure_mode == PolicyFailureMode::Open;
+                if allowed {
+                    warn!(scope = %scope, "Policy evaluation failed, allowing query (fail-open): {}", e);
+                } else {
+                    error!(scope = %scope, "Policy evaluation failed, denying query: {}", e);
+                }
+                log_audit(AuditEvent::PolicyEvaluationFailed {
+                    scope: scope.to_string(),
+                    error: e.to_string(),
+                    allowed,
+                });
+                if allowed { Ok(()) } else { Err(e) }
+            }
+            result => result,
+        }
+    }
+
+    /// Sets whether queries are denied (the default) or allowed when the
+    /// policy errors instead of deciding. Denials are enforced either way.
+    pub fn with_policy_failure_mode(mut self, mode: PolicyFailureMode) -> Self {
+        self.policy_failure_mode = mode;
+        self
+    }
+
+    /// Sets how many times a write is retried when the database is busy.
+    ///
+    /// Complements SQLite's `busy_timeout`: the timeout waits inside one
+    /// statement, while this retries statements that still fail with a lock.
+    pub fn with_busy_retries(mut self, retries: u32) -> Self {
+        self.busy_retries = retries;
+        self
+    }
+
+    /// Sets the largest values [`TypedStore`](crate::store::TypedStore) writes accept.
+    pub fn with_value_size_limits(mut self, limits: ValueSizeLimits) -> Self {
+        self.value_limits = limits;
+        self
+    }
+
+    /// Rejects a value of `size` encoded bytes if it's over `scope`'s limit.
+    pub fn check_value_size(&self, scope: &str, size: usize) -> Result<(), StoreError> {
+        match self.value_limits.limit_for(scope) {
+            Some(limit) if size > limit => Err(StoreError::ValueTooLarge { size, limit }),
+            _ => Ok(()),
+        }
     }
 
     /// Execute a query that returns rows (SELECT).
@@ -44,29 +332,14 @@ impl SqlStore {
         params: Vec<String>,
     ) -> Result<Vec<GenericRow>, StoreError> {
         // 1. Enforce Policy
-        self.policy.authorize(scope, sql)?;
+        let sql = self.prepare(scope, sql)?;
 
-        // 2. Prepare Query
-        let mut query_builder = sqlx::query(sql);
-        for param in params {
-            query_builder = query_builder.bind(param);
+        // 2. Execute and map results
+        match &self.pool {
+            DbPool::Sqlite(pool) => sqlite_query(pool, &sql, params).await,
+            #[cfg(feature = "postgres")]
+            DbPool::Postgres(pool) => postgres::query(pool, &sql, params).await,
         }
-
-        // 3. Execute
-        let rows: Vec<SqliteRow> = query_builder.fetch_all(&self.pool).await?;
-
-        // 4. Map Results
-        let mut results = Vec::new();
-        for row in rows {
-            let columns: Vec<String> = row.columns().iter().map(|c| c.name().to_string()).collect();
-            let mut values = Vec::new();
-            for (i, col) in row.columns().iter().enumerate() {
-                values.push(convert_cell(&row, i, col));
-            }
-            results.push(GenericRow { columns, values });
-        }
-
-        Ok(results)
     }
 
     /// Execute a statement that modifies state (INSERT, UPDATE, DELETE).
@@ -79,19 +352,219 @@ impl SqlStore {
         params: Vec<String>,
     ) -> Result<u32, StoreError> {
         // 1. Enforce Policy
-        self.policy.authorize(scope, sql)?;
+        let sql = self.prepare(scope, sql)?;
+
+        // 2. Execute
+        match &self.pool {
+            DbPool::Sqlite(pool) => self.execute_with_busy_retries(pool, &sql, params).await,
+            // PostgreSQL waits for row locks itself rather than failing busy
+            #[cfg(feature = "postgres")]
+            DbPool::Postgres(pool) => postgres::execute(pool, &sql, params).await,
+        }
+    }
+
+    /// Runs a SQLite write, retrying while the database is locked by another writer.
+    async fn execute_with_busy_retries(
+        &self,
+        pool: &SqlitePool,
+        sql: &str,
+        params: Vec<String>,
+    ) -> Result<u32, StoreError> {
+        let mut attempt = 0;
+        loop {
+            match sqlite_execute(pool, sql, params.clone()).await {
+                Ok(rows_affected) => return Ok(rows_affected),
+                Err(StoreError::DbError(e)) if is_busy(&e) => {
+                    if attempt >= self.busy_retries || !context::try_consume_retry() {
+                        return Err(StoreError::Busy {
+                            attempts: attempt + 1,
+                        });
+                    }
+                    let delay = BUSY_BASE_DELAY * 2u32.saturating_pow(attempt);
+                    warn!(
+                        attempt = attempt + 1,
+                        delay_ms = delay.as_millis() as u64,
+                        "Database busy, retrying write"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
 
-        // 2. Prepare Query
-        let mut query_builder = sqlx::query(sql);
-        for param in params {
-            query_builder = query_builder.bind(param);
+    /// Runs `f` inside one database transaction, committing if it returns
+    /// `Ok` and rolling back if it returns `Err`.
+    ///
+    /// Statements go through the [`StoreTransaction`] handed to `f`, which
+    /// enforces the policy and tenant namespace on each one just like the
+    /// store itself. Busy statements aren't retried inside a transaction;
+    /// the whole transaction fails and can be run again.
+    ///
+    /// ```
+    /// # async fn move_row(store: &brio_kernel::store::SqlStore) -> Result<(), brio_kernel::store::StoreError> {
+    /// store
+    ///     .transaction(|tx| Box::pin(async move {
+    ///         tx.execute("agent_1", "INSERT INTO agent_1_done (content) VALUES (?)", vec!["a".into()]).await?;
+    ///         tx.execute("agent_1", "DELETE FROM agent_1_todo WHERE content = ?", vec!["a".into()]).await
+    ///     }))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn transaction<T, F>(&self, f: F) -> Result<T, StoreError>
+    where
+        F: for<'t> FnOnce(&'t mut StoreTransaction<'_>) -> BoxFuture<'t, Result<T, StoreError>>,
+    {
+        let tx = match &self.pool {
+            DbPool::Sqlite(pool) => DbTransaction::Sqlite(pool.begin().await?),
+            #[cfg(feature = "postgres")]
+            DbPool::Postgres(pool) => DbTransaction::Postgres(pool.begin().await?),
+        };
+        let mut tx = StoreTransaction { store: self, tx };
+        match f(&mut tx).await {
+            Ok(value) => {
+                tx.tx.commit().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback) = tx.tx.rollback().await {
+                    warn!("Failed to roll back transaction: {}", rollback);
+                }
+                Err(e)
+            }
         }
+    }
+
+    /// Counts the unexpired key-value entries in `scope` whose keys start
+    /// with `prefix`, as written by [`TypedStore`](crate::store::TypedStore);
+    /// the same entries its `list` returns.
+    ///
+    /// Computed by a single aggregate query, authorized by the policy like
+    /// any other read.
+    #[instrument(skip(self), fields(scope = %scope))]
+    pub async fn stats(&self, scope: &str, prefix: &str) -> Result<StoreStats, StoreError> {
+        let sql = format!(
+            "SELECT COUNT(*), COALESCE(SUM(LENGTH(value)), 0) / 2, \
+             CAST(MIN(updated_at) AS BIGINT), CAST(MAX(updated_at) AS BIGINT) \
+             FROM {} WHERE substr(id, 1, length(?)) = ? AND {}",
+            kv_table(scope),
+            UNEXPIRED
+        );
+        let sql = self.prepare(scope, &sql)?;
 
-        // 3. Execute
-        let result = query_builder.execute(&self.pool).await?;
+        let now = now_ms().to_string();
+        // Aggregate columns carry no declared type, so read them typed rather than via `query`
+        let (count, bytes, oldest, newest): (i64, i64, Option<i64>, Option<i64>) = match &self.pool {
+            DbPool::Sqlite(pool) => sqlx::query_as(&sql).bind(prefix).bind(prefix).bind(now).fetch_one(pool).await?,
+            #[cfg(feature = "postgres")]
+            DbPool::Postgres(pool) => {
+                sqlx::query_as(&postgres::numbered_placeholders(&sql))
+                    .bind(prefix)
+                    .bind(prefix)
+                    .bind(now)
+                    .fetch_one(pool)
+                    .await?
+            }
+        };
+
+        let time = |ms: i64| UNIX_EPOCH + Duration::from_millis(ms.max(0) as u64);
+        Ok(StoreStats {
+            key_count: count.max(0) as u64,
+            total_bytes: bytes.max(0) as u64,
+            oldest: oldest.map(time),
+            newest: newest.map(time),
+        })
+    }
+}
+
+/// Handle for the statements of one [`SqlStore::transaction`].
+pub struct StoreTransaction<'s> {
+    store: &'s SqlStore,
+    tx: DbTransaction,
+}
+
+enum DbTransaction {
+    Sqlite(sqlx::Transaction<'static, Sqlite>),
+    #[cfg(feature = "postgres")]
+    Postgres(sqlx::Transaction<'static, sqlx::Postgres>),
+}
+
+impl DbTransaction {
+    async fn commit(self) -> Result<(), sqlx::Error> {
+        match self {
+            Self::Sqlite(tx) => tx.commit().await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(tx) => tx.commit().await,
+        }
+    }
+
+    async fn rollback(self) -> Result<(), sqlx::Error> {
+        match self {
+            Self::Sqlite(tx) => tx.rollback().await,
+            #[cfg(feature = "postgres")]
+            Self::Postgres(tx) => tx.rollback().await,
+        }
+    }
+}
+
+impl StoreTransaction<'_> {
+    /// Like [`SqlStore::query`], seeing the transaction's uncommitted writes.
+    pub async fn query(&mut self, scope: &str, sql: &str, params: Vec<String>) -> Result<Vec<GenericRow>, StoreError> {
+        let sql = self.store.prepare(scope, sql)?;
+        match &mut self.tx {
+            DbTransaction::Sqlite(tx) => sqlite_query(&mut **tx, &sql, params).await,
+            #[cfg(feature = "postgres")]
+            DbTransaction::Postgres(tx) => postgres::query(&mut **tx, &sql, params).await,
+        }
+    }
+
+    /// Like [`SqlStore::execute`], without busy retries.
+    pub async fn execute(&mut self, scope: &str, sql: &str, params: Vec<String>) -> Result<u32, StoreError> {
+        let sql = self.store.prepare(scope, sql)?;
+        match &mut self.tx {
+            DbTransaction::Sqlite(tx) => sqlite_execute(&mut **tx, &sql, params).await,
+            #[cfg(feature = "postgres")]
+            DbTransaction::Postgres(tx) => postgres::execute(&mut **tx, &sql, params).await,
+        }
+    }
+}
+
+async fn sqlite_query<'e, E>(executor: E, sql: &str, params: Vec<String>) -> Result<Vec<GenericRow>, StoreError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let mut query_builder = sqlx::query(sql);
+    for param in params {
+        query_builder = query_builder.bind(param);
+    }
+    let rows: Vec<SqliteRow> = query_builder.fetch_all(executor).await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let columns = row.columns().iter().map(|c| c.name().to_string()).collect();
+            let values = row
+                .columns()
+                .iter()
+                .enumerate()
+                .map(|(i, col)| convert_cell(&row, i, col))
+                .collect();
+            GenericRow { columns, values }
+        })
+        .collect())
+}
 
-        Ok(result.rows_affected() as u32)
+async fn sqlite_execute<'e, E>(executor: E, sql: &str, params: Vec<String>) -> Result<u32, StoreError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let mut query_builder = sqlx::query(sql);
+    for param in params {
+        query_builder = query_builder.bind(param);
     }
+    let result = query_builder.execute(executor).await?;
+    Ok(result.rows_affected() as u32)
 }
 
 /// Helper to convert a single cell to string using best-effort strategy.
@@ -0,0 +1,215 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::store::r#impl::{SqlStore, StoreError};
+
+/// Scope owning the lock table shared by every node
+pub const LOCK_SCOPE: &str = "mesh";
+
+fn lock_table(scope: &str) -> String {
+    format!("{}_locks", scope)
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// Acquires named locks that are exclusive across every node sharing the store.
+///
+/// Each lock is a row holding its holder's token and an expiry. Every
+/// acquisition gets a token of its own, so two tasks of one owner exclude
+/// each other just as two nodes do. A held lock is renewed in the background,
+/// so it only lapses once its holder stops renewing it, e.g. because the node
+/// crashed or lost the database.
+pub struct LockManager {
+    store: Arc<SqlStore>,
+    scope: String,
+    owner: String,
+}
+
+impl LockManager {
+    /// Creates a manager acquiring locks on behalf of `owner`, usually the node id.
+    pub fn new(store: SqlStore, owner: impl Into<String>) -> Self {
+        Self {
+            store: Arc::new(store),
+            scope: LOCK_SCOPE.to_string(),
+            owner: owner.into(),
+        }
+    }
+
+    /// Creates the lock table if it does not exist yet.
+    pub async fn ensure_table(&self) -> Result<(), StoreError> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, holder TEXT NOT NULL, expires_at BIGINT NOT NULL)",
+            lock_table(&self.scope)
+        );
+        self.store.execute(&self.scope, &sql, Vec::new()).await?;
+        Ok(())
+    }
+
+    /// Tries to take the lock `name` for `ttl`, returning `None` if it is held,
+    /// by this owner or another, and hasn't expired.
+    ///
+    /// The returned guard renews the lock every third of `ttl` and releases it
+    /// when dropped.
+    pub async fn acquire_lock(&self, name: &str, ttl: Duration) -> Result<Option<LockGuard>, StoreError> {
+        let now = now_ms();
+        let expires_at = now + ttl.as_millis();
+        let token = format!("{}:{}", self.owner, Uuid::new_v4());
+        // Takes a free or expired lock in one statement, so two holders can't both win
+        let sql = format!(
+            "INSERT INTO {table} (id, holder, expires_at) VALUES (?, ?, CAST(? AS BIGINT)) \
+             ON CONFLICT(id) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at \
+             WHERE {table}.expires_at <= CAST(? AS BIGINT)",
+            table = lock_table(&self.scope)
+        );
+        let taken = self
+            .store
+            .execute(
+                &self.scope,
+                &sql,
+                vec![name.to_string(), token.clone(), expires_at.to_string(), now.to_string()],
+            )
+            .await?;
+        if taken == 0 {
+            return Ok(None);
+        }
+
+        debug!(lock = name, holder = %token, "Acquired lock");
+        let held = Arc::new(AtomicBool::new(true));
+        let renewal = tokio::spawn(renew_periodically(
+            self.store.clone(),
+            self.scope.clone(),
+            name.to_string(),
+            token.clone(),
+            ttl,
+            held.clone(),
+        ));
+        Ok(Some(LockGuard {
+            store: self.store.clone(),
+            scope: self.scope.clone(),
+            name: name.to_string(),
+            token,
+            ttl,
+            held,
+            renewal,
+            released: false,
+        }))
+    }
+}
+
+/// Extends the expiry of the lock `name` held with `token` by `ttl`,
+/// returning false if it is no longer held with that token.
+async fn renew(store: &SqlStore, scope: &str, name: &str, token: &str, ttl: Duration) -> Result<bool, StoreError> {
+    let sql = format!(
+        "UPDATE {} SET expires_at = CAST(? AS BIGINT) WHERE id = ? AND holder = ?",
+        lock_table(scope)
+    );
+    let expires_at = now_ms() + ttl.as_millis();
+    let params = vec![expires_at.to_string(), name.to_string(), token.to_string()];
+    Ok(store.execute(scope, &sql, params).await? > 0)
+}
+
+/// Renews the lock until it is lost or the task is aborted.
+async fn renew_periodically(
+    store: Arc<SqlStore>,
+    scope: String,
+    name: String,
+    token: String,
+    ttl: Duration,
+    held: Arc<AtomicBool>,
+) {
+    let mut interval = tokio::time::interval((ttl / 3).max(Duration::from_millis(1)));
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        match renew(&store, &scope, &name, &token, ttl).await {
+            Ok(false) => {
+                warn!(lock = %name, "Lock was taken over by another holder");
+                held.store(false, Ordering::SeqCst);
+                return;
+            }
+            Ok(true) => {}
+            // Keep trying; the lock only lapses once the expiry passes
+            Err(e) => warn!(lock = %name, error = %e, "Failed to renew lock"),
+        }
+    }
+}
+
+/// Proof of holding a lock from [`LockManager::acquire_lock`].
+///
+/// Dropping the guard stops renewal and releases the lock in the background;
+/// use [`release`](Self::release) to wait for the release to complete.
+pub struct LockGuard {
+    store: Arc<SqlStore>,
+    scope: String,
+    name: String,
+    /// Identifies this acquisition as the lock's holder
+    token: String,
+    ttl: Duration,
+    held: Arc<AtomicBool>,
+    renewal: JoinHandle<()>,
+    released: bool,
+}
+
+impl LockGuard {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// False once a renewal found the lock expired and taken by another holder.
+    pub fn is_held(&self) -> bool {
+        self.held.load(Ordering::SeqCst)
+    }
+
+    /// Extends the lock by its ttl now, on top of the background renewal.
+    /// Returns false, and stops counting the lock as held, if it was lost.
+    pub async fn renew(&self) -> Result<bool, StoreError> {
+        let renewed = renew(&self.store, &self.scope, &self.name, &self.token, self.ttl).await?;
+        if !renewed {
+            self.held.store(false, Ordering::SeqCst);
+        }
+        Ok(renewed)
+    }
+
+    /// Releases the lock, letting another owner acquire it immediately.
+    pub async fn release(mut self) -> Result<(), StoreError> {
+        self.renewal.abort();
+        self.released = true;
+        release(&self.store, &self.scope, &self.name, &self.token).await
+    }
+}
+
+async fn release(store: &SqlStore, scope: &str, name: &str, token: &str) -> Result<(), StoreError> {
+    let sql = format!("DELETE FROM {} WHERE id = ? AND holder = ?", lock_table(scope));
+    store
+        .execute(scope, &sql, vec![name.to_string(), token.to_string()])
+        .await?;
+    Ok(())
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        self.renewal.abort();
+        if self.released || !self.is_held() {
+            return;
+        }
+        // Without a runtime the lock is left to expire
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let store = self.store.clone();
+            let (scope, name, token) = (self.scope.clone(), self.name.clone(), self.token.clone());
+            handle.spawn(async move {
+                if let Err(e) = release(&store, &scope, &name, &token).await {
+                    warn!(lock = %name, error = %e, "Failed to release lock");
+                }
+            });
+        }
+    }
+}
@@ -1,8 +1,20 @@
+pub mod changes;
+pub mod feed;
 pub mod r#impl;
+pub mod lock;
 pub mod policy;
+#[cfg(feature = "postgres")]
+mod postgres;
+pub mod typed;
+pub mod watch;
 
-pub use r#impl::{SqlStore, StoreError};
-pub use policy::{PolicyError, PrefixPolicy, QueryPolicy};
+pub use changes::{ChangeSink, StoreChange, StoreSnapshot};
+pub use feed::{ChangeEvent, ChangeLog};
+pub use r#impl::{DEFAULT_BUSY_RETRIES, DbPool, SqlStore, StoreError, StoreStats, StoreTransaction, ValueSizeLimits};
+pub use lock::{LOCK_SCOPE, LockGuard, LockManager};
+pub use policy::{PolicyError, PolicyFailureMode, PrefixPolicy, QueryPolicy};
+pub use typed::{Codec, JsonCodec, PostcardCodec, TypedStore};
+pub use watch::{KeyChange, KeyWatchers};
 
 #[cfg(test)]
 mod integration_tests;
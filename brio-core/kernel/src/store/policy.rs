@@ -1,8 +1,9 @@
 use sqlparser::{
-    ast::{TableFactor, Visit, Visitor},
+    ast::{Expr, Ident, ObjectNamePart, TableFactor, Visit, Visitor, visit_expressions_mut, visit_relations_mut},
     dialect::GenericDialect,
     parser::Parser,
 };
+use serde::Deserialize;
 use std::ops::ControlFlow;
 use thiserror::Error;
 
@@ -14,6 +15,29 @@ pub enum PolicyError {
     ScopeViolation(String, String),
     #[error("Policy Violation: {0}")]
     Violation(String),
+    /// The policy could not reach a decision, e.g. its rules are misconfigured.
+    /// Unlike the other variants this is not a denial of the query.
+    #[error("Policy Evaluation Failed: {0}")]
+    Evaluation(String),
+}
+
+impl PolicyError {
+    /// True if the policy failed to decide rather than denying the query.
+    pub fn is_evaluation_error(&self) -> bool {
+        matches!(self, Self::Evaluation(_))
+    }
+}
+
+/// What a store does with a query when its policy fails to evaluate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyFailureMode {
+    /// Reject the query, as if the policy had denied it
+    #[default]
+    Closed,
+    /// Run the query anyway, logging a warning; for deployments that put
+    /// availability ahead of enforcement
+    Open,
 }
 
 /// Defines the authorization contract for SQL execution.
@@ -43,6 +67,69 @@ impl QueryPolicy for PrefixPolicy {
     }
 }
 
+/// Separates a tenant namespace from the logical table name
+pub const TENANT_SEPARATOR: &str = "__";
+
+/// Checks that `tenant` can't collide with or escape another tenant's namespace.
+pub fn validate_tenant(tenant: &str) -> Result<(), PolicyError> {
+    if tenant.is_empty() || !tenant.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(PolicyError::Violation(format!(
+            "Tenant id '{}' must be non-empty and alphanumeric",
+            tenant
+        )));
+    }
+    Ok(())
+}
+
+/// The scope a tenant's logical `scope` maps to physically.
+pub fn tenant_scope(tenant: &str, scope: &str) -> String {
+    format!("{}{}{}", tenant, TENANT_SEPARATOR, scope)
+}
+
+/// Rewrites every table name in `sql` into `tenant`'s namespace.
+///
+/// Applied to names as written, so a caller spelling out another tenant's
+/// physical name only reaches a table nested inside its own namespace.
+/// Schema-qualified names are rejected, as they could reach attached databases.
+/// Columns qualified by a renamed table, like `{table}.version`, follow it.
+pub fn namespace_tables(tenant: &str, sql: &str) -> Result<String, PolicyError> {
+    let dialect = GenericDialect {};
+    let mut ast =
+        Parser::parse_sql(&dialect, sql).map_err(|e| PolicyError::ParseError(e.to_string()))?;
+
+    let mut renamed = Vec::new();
+    let rewritten = visit_relations_mut(&mut ast, |name| {
+        let [ObjectNamePart::Identifier(ident)] = name.0.as_mut_slice() else {
+            return ControlFlow::Break(PolicyError::Violation(format!(
+                "Qualified table name '{}' is not allowed for tenants",
+                name
+            )));
+        };
+        renamed.push(ident.value.clone());
+        *ident = Ident::new(tenant_scope(tenant, &ident.value));
+        ControlFlow::Continue(())
+    });
+    if let ControlFlow::Break(err) = rewritten {
+        return Err(err);
+    }
+
+    let _ = visit_expressions_mut(&mut ast, |expr| {
+        if let Expr::CompoundIdentifier(parts) = expr
+            && let [table, _column] = parts.as_mut_slice()
+            && renamed.contains(&table.value)
+        {
+            *table = Ident::new(tenant_scope(tenant, &table.value));
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    Ok(ast
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
 struct TableVisitor<'a> {
     scope: &'a str,
 }
@@ -73,6 +160,20 @@ impl<'a> Visitor for TableVisitor<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_namespace_tables_renames_column_qualifiers() {
+        let sql = namespace_tables(
+            "acme",
+            "UPDATE agent_1_kv SET version = agent_1_kv.version + 1 WHERE other.id = 1",
+        )
+        .unwrap();
+        let expected = format!(
+            "UPDATE {table} SET version = {table}.version + 1 WHERE other.id = 1",
+            table = tenant_scope("acme", "agent_1_kv")
+        );
+        assert_eq!(sql, expected);
+    }
+
     #[test]
     fn test_valid_scope_query() {
         let policy = PrefixPolicy;
@@ -0,0 +1,102 @@
+//! PostgreSQL side of [`SqlStore`](crate::store::SqlStore), built with the `postgres` feature.
+
+use sqlx::postgres::PgRow;
+use sqlx::{Column, Executor, Postgres, Row, TypeInfo, ValueRef};
+
+use crate::store::r#impl::{GenericRow, StoreError};
+
+/// Rewrites `?` placeholders to PostgreSQL's `$1, $2, ...`, leaving
+/// question marks inside quoted strings and identifiers alone.
+pub(crate) fn numbered_placeholders(sql: &str) -> String {
+    let mut numbered = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut next = 0;
+    for c in sql.chars() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '?') => {
+                next += 1;
+                numbered.push_str(&format!("${}", next));
+                continue;
+            }
+            _ => {}
+        }
+        numbered.push(c);
+    }
+    numbered
+}
+
+/// Runs a row-returning statement.
+///
+/// Parameters are bound as text, so compare them with non-text columns
+/// through a cast, e.g. `seq > CAST(? AS BIGINT)`.
+pub(crate) async fn query<'e, E>(executor: E, sql: &str, params: Vec<String>) -> Result<Vec<GenericRow>, StoreError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let sql = numbered_placeholders(sql);
+    let mut query_builder = sqlx::query(&sql);
+    for param in params {
+        query_builder = query_builder.bind(param);
+    }
+    let rows: Vec<PgRow> = query_builder.fetch_all(executor).await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let columns = row.columns().iter().map(|c| c.name().to_string()).collect();
+            let values = (0..row.columns().len()).map(|i| convert_cell(&row, i)).collect();
+            GenericRow { columns, values }
+        })
+        .collect())
+}
+
+/// Runs a modifying statement, returning the number of rows affected.
+pub(crate) async fn execute<'e, E>(executor: E, sql: &str, params: Vec<String>) -> Result<u32, StoreError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let sql = numbered_placeholders(sql);
+    let mut query_builder = sqlx::query(&sql);
+    for param in params {
+        query_builder = query_builder.bind(param);
+    }
+    let result = query_builder.execute(executor).await?;
+    Ok(result.rows_affected() as u32)
+}
+
+/// Renders a cell as SQLite rows are rendered, so callers see the same
+/// strings from either backend (booleans as `1`/`0`).
+fn convert_cell(row: &PgRow, index: usize) -> String {
+    if row.try_get_raw(index).map(|r| r.is_null()).unwrap_or(true) {
+        return "NULL".to_string();
+    }
+
+    match row.columns()[index].type_info().name() {
+        "BOOL" => row
+            .try_get::<bool, _>(index)
+            .map(|b| u8::from(b).to_string())
+            .unwrap_or_default(),
+        "INT2" => row.try_get::<i16, _>(index).map(|n| n.to_string()).unwrap_or_default(),
+        "INT4" => row.try_get::<i32, _>(index).map(|n| n.to_string()).unwrap_or_default(),
+        "INT8" => row.try_get::<i64, _>(index).map(|n| n.to_string()).unwrap_or_default(),
+        "FLOAT4" => row.try_get::<f32, _>(index).map(|f| f.to_string()).unwrap_or_default(),
+        "FLOAT8" => row.try_get::<f64, _>(index).map(|f| f.to_string()).unwrap_or_default(),
+        _ => row
+            .try_get::<String, _>(index)
+            .unwrap_or_else(|_| "UNSUPPORTED_TYPE".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_numbered_outside_quotes() {
+        assert_eq!(
+            numbered_placeholders("SELECT '?' FROM t WHERE a = ? AND \"b?\" = ?"),
+            "SELECT '?' FROM t WHERE a = $1 AND \"b?\" = $2"
+        );
+    }
+}
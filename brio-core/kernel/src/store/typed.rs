@@ -0,0 +1,471 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::store::changes::{ChangeSink, StoreChange};
+use crate::store::feed::ChangeLog;
+use crate::store::r#impl::{GenericRow, SqlStore, StoreError};
+
+/// Converts values to and from bytes for a [`TypedStore`].
+pub trait Codec: Send + Sync {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, StoreError>;
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, StoreError>;
+}
+
+/// Human-readable JSON; the default codec.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, StoreError> {
+        serde_json::to_vec(value).map_err(|e| StoreError::Codec(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, StoreError> {
+        serde_json::from_slice(bytes).map_err(|e| StoreError::Codec(e.to_string()))
+    }
+}
+
+/// Compact binary encoding via `postcard`, for large or hot values.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostcardCodec;
+
+impl Codec for PostcardCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, StoreError> {
+        postcard::to_allocvec(value).map_err(|e| StoreError::Codec(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, StoreError> {
+        postcard::from_bytes(bytes).map_err(|e| StoreError::Codec(e.to_string()))
+    }
+}
+
+pub(crate) fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// Condition matching entries that haven't expired, given the current time
+/// in milliseconds as its one parameter
+pub(crate) const UNEXPIRED: &str = "(expires_at IS NULL OR expires_at > CAST(? AS BIGINT))";
+
+/// Name of the table holding a scope's key-value entries.
+pub(crate) fn kv_table(scope: &str) -> String {
+    format!("{}_kv", scope)
+}
+
+/// Returns true if the error is the database rejecting an unknown column:
+/// SQLite only says so in its message, PostgreSQL with `undefined_column`.
+fn is_missing_column(err: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db_err) = err else {
+        return false;
+    };
+    db_err.code().as_deref() == Some("42703") || db_err.message().contains("no such column")
+}
+
+/// Key-value access to values of type `T`, layered over [`SqlStore`].
+///
+/// Entries live in the `{scope}_kv` table, so the store's scope policy still
+/// applies. Encoded values are stored hex-encoded, as the store binds and
+/// returns text, alongside the time they were last written, a version that
+/// every write bumps and an optional expiry. Expired entries read as absent
+/// until [`sweep_expired`](Self::sweep_expired) removes them.
+///
+/// Tables created by earlier versions are given the columns they lack on
+/// first use.
+pub struct TypedStore<T, C = JsonCodec> {
+    store: SqlStore,
+    scope: String,
+    codec: C,
+    changes: Option<Arc<dyn ChangeSink>>,
+    change_log: Option<Arc<ChangeLog>>,
+    /// Set once the table is known to have every column
+    migrated: OnceCell<()>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedStore<T, JsonCodec> {
+    pub fn new(store: SqlStore, scope: impl Into<String>) -> Self {
+        Self {
+            store,
+            scope: scope.into(),
+            codec: JsonCodec,
+            changes: None,
+            change_log: None,
+            migrated: OnceCell::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, C> TypedStore<T, C>
+where
+    T: Serialize + DeserializeOwned,
+    C: Codec,
+{
+    /// Replaces the codec used for values.
+    ///
+    /// Values written with one codec can't be read back with another.
+    pub fn with_codec<C2: Codec>(self, codec: C2) -> TypedStore<T, C2> {
+        TypedStore {
+            store: self.store,
+            scope: self.scope,
+            codec,
+            changes: self.changes,
+            change_log: self.change_log,
+            migrated: self.migrated,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Reports every successful put and delete to `sink`.
+    pub fn with_change_sink(mut self, sink: Arc<dyn ChangeSink>) -> Self {
+        self.changes = Some(sink);
+        self
+    }
+
+    /// Records every successful put and delete in `log`, in the same
+    /// transaction as the write.
+    pub fn with_change_log(mut self, log: Arc<ChangeLog>) -> Self {
+        self.change_log = Some(log);
+        self
+    }
+
+    /// Runs a statement writing `key`, returning the rows it affected.
+    ///
+    /// With a change log attached, the statement and the log entry for
+    /// `value` commit together, and nothing is logged if no row changed.
+    async fn execute_write(
+        &self,
+        key: &str,
+        sql: String,
+        params: Vec<String>,
+        value: Option<serde_json::Value>,
+    ) -> Result<u32, StoreError> {
+        let Some(log) = &self.change_log else {
+            return self.store.execute(&self.scope, &sql, params).await;
+        };
+        let change = StoreChange {
+            scope: self.scope.clone(),
+            key: key.to_string(),
+            value,
+        };
+        let (scope, tx_log) = (self.scope.clone(), Arc::clone(log));
+        let affected = self
+            .store
+            .transaction(|tx| {
+                Box::pin(async move {
+                    let affected = tx.execute(&scope, &sql, params).await?;
+                    if affected > 0 {
+                        tx_log.append_in(tx, &change).await?;
+                    }
+                    Ok(affected)
+                })
+            })
+            .await?;
+        if affected > 0 {
+            log.notify_appended();
+        }
+        Ok(affected)
+    }
+
+    async fn notify(&self, key: &str, value: Option<serde_json::Value>) {
+        if self.store.is_watched(key) {
+            self.store.publish_change(&self.scope, key, value.clone());
+        }
+        if let Some(sink) = &self.changes {
+            sink.publish(StoreChange {
+                scope: self.scope.clone(),
+                key: key.to_string(),
+                value,
+            })
+            .await;
+        }
+    }
+
+    fn table(&self) -> String {
+        kv_table(&self.scope)
+    }
+
+    pub fn scope(&self) -> &str {
+        &self.scope
+    }
+
+    /// Creates the backing table if it doesn't exist yet, adding columns
+    /// that tables created by earlier versions lack.
+    pub async fn ensure_table(&self) -> Result<(), StoreError> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, value TEXT NOT NULL, updated_at BIGINT NOT NULL, \
+             version BIGINT NOT NULL DEFAULT 1, expires_at BIGINT)",
+            self.table()
+        );
+        self.store.execute(&self.scope, &sql, vec![]).await?;
+        self.migrate().await
+    }
+
+    /// Adds the columns that tables created by earlier versions lack, the
+    /// first time it's called.
+    async fn migrate(&self) -> Result<(), StoreError> {
+        self.migrated
+            .get_or_try_init(|| async {
+                // Rows written before versioning start at 1, as 0 means absent
+                self.add_column_if_missing("version", "BIGINT NOT NULL DEFAULT 1").await?;
+                self.add_column_if_missing("expires_at", "BIGINT").await
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn add_column_if_missing(&self, column: &str, definition: &str) -> Result<(), StoreError> {
+        let probe = format!("SELECT {} FROM {} LIMIT 0", column, self.table());
+        match self.store.query(&self.scope, &probe, vec![]).await {
+            Ok(_) => Ok(()),
+            Err(StoreError::DbError(e)) if is_missing_column(&e) => {
+                let sql = format!("ALTER TABLE {} ADD COLUMN {} {}", self.table(), column, definition);
+                self.store.execute(&self.scope, &sql, vec![]).await?;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Encodes `value` for writing under `key`, along with the change to
+    /// report once written, if anyone is listening.
+    fn encode_for_write(&self, key: &str, value: &T) -> Result<(String, Option<serde_json::Value>), StoreError> {
+        let change = if self.changes.is_some() || self.change_log.is_some() || self.store.is_watched(key) {
+            Some(serde_json::to_value(value).map_err(|e| StoreError::Codec(e.to_string()))?)
+        } else {
+            None
+        };
+        let encoded = self.codec.encode(value)?;
+        self.store.check_value_size(&self.scope, encoded.len())?;
+        Ok((hex::encode(encoded), change))
+    }
+
+    /// Writes `value` under `key`, clearing any expiry it had.
+    pub async fn put(&self, key: &str, value: &T) -> Result<(), StoreError> {
+        self.write(key, value, None).await
+    }
+
+    /// Writes `value` under `key`, to read as absent once `ttl` has passed.
+    pub async fn set_with_ttl(&self, key: &str, value: &T, ttl: Duration) -> Result<(), StoreError> {
+        self.write(key, value, Some(now_ms().saturating_add(ttl.as_millis()))).await
+    }
+
+    async fn write(&self, key: &str, value: &T, expires_at: Option<u128>) -> Result<(), StoreError> {
+        let (encoded, change) = self.encode_for_write(key, value)?;
+        self.migrate().await?;
+        // Parameters are bound as text, so no expiry is spelled out as NULL
+        let sql = format!(
+            "INSERT INTO {table} (id, value, updated_at, version, expires_at) \
+             VALUES (?, ?, CAST(? AS BIGINT), 1, {expiry}) \
+             ON CONFLICT(id) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at, \
+             version = {table}.version + 1, expires_at = excluded.expires_at",
+            table = self.table(),
+            expiry = if expires_at.is_some() { "CAST(? AS BIGINT)" } else { "NULL" }
+        );
+        let mut params = vec![key.to_string(), encoded, now_ms().to_string()];
+        params.extend(expires_at.map(|at| at.to_string()));
+        self.execute_write(key, sql, params, change.clone()).await?;
+        if change.is_some() {
+            self.notify(key, change).await;
+        }
+        Ok(())
+    }
+
+    /// Writes `value` only if `key` is still at `expected_version`, returning
+    /// the new version.
+    ///
+    /// A version of 0 expects the key to be absent, or expired. Like
+    /// [`put`](Self::put), clears any expiry. Fails with
+    /// [`StoreError::VersionConflict`] if another write got there first;
+    /// read the key again with [`get_versioned`](Self::get_versioned) and retry.
+    pub async fn compare_and_swap(&self, key: &str, expected_version: u64, value: &T) -> Result<u64, StoreError> {
+        let (encoded, change) = self.encode_for_write(key, value)?;
+        self.migrate().await?;
+        let now = now_ms().to_string();
+        let (sql, params) = if expected_version == 0 {
+            (
+                format!(
+                    "INSERT INTO {table} (id, value, updated_at, version) VALUES (?, ?, CAST(? AS BIGINT), 1) \
+                     ON CONFLICT(id) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at, \
+                     version = 1, expires_at = NULL WHERE {table}.expires_at <= CAST(? AS BIGINT)",
+                    table = self.table()
+                ),
+                vec![key.to_string(), encoded, now.clone(), now],
+            )
+        } else {
+            (
+                format!(
+                    "UPDATE {} SET value = ?, updated_at = CAST(? AS BIGINT), version = version + 1, expires_at = NULL \
+                     WHERE id = ? AND version = CAST(? AS BIGINT) AND {}",
+                    self.table(),
+                    UNEXPIRED
+                ),
+                vec![encoded, now.clone(), key.to_string(), expected_version.to_string(), now],
+            )
+        };
+        if self.execute_write(key, sql, params, change.clone()).await? == 0 {
+            return Err(StoreError::VersionConflict {
+                key: key.to_string(),
+                expected: expected_version,
+            });
+        }
+        if change.is_some() {
+            self.notify(key, change).await;
+        }
+        Ok(expected_version + 1)
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<T>, StoreError> {
+        self.migrate().await?;
+        let sql = format!("SELECT value FROM {} WHERE id = ? AND {}", self.table(), UNEXPIRED);
+        let rows = self
+            .store
+            .query(&self.scope, &sql, vec![key.to_string(), now_ms().to_string()])
+            .await?;
+
+        let Some(row) = rows.first() else {
+            return Ok(None);
+        };
+        let bytes = hex::decode(&row.values[0]).map_err(|e| StoreError::Codec(e.to_string()))?;
+        self.codec.decode(&bytes).map(Some)
+    }
+
+    /// The value under `key` and its version, for a later
+    /// [`compare_and_swap`](Self::compare_and_swap).
+    pub async fn get_versioned(&self, key: &str) -> Result<Option<(T, u64)>, StoreError> {
+        self.migrate().await?;
+        let sql = format!("SELECT value, version FROM {} WHERE id = ? AND {}", self.table(), UNEXPIRED);
+        let rows = self
+            .store
+            .query(&self.scope, &sql, vec![key.to_string(), now_ms().to_string()])
+            .await?;
+
+        let Some(row) = rows.first() else {
+            return Ok(None);
+        };
+        let bytes = hex::decode(&row.values[0]).map_err(|e| StoreError::Codec(e.to_string()))?;
+        let version = row.values[1]
+            .parse()
+            .map_err(|_| StoreError::Internal(anyhow::anyhow!("Malformed version '{}'", row.values[1])))?;
+        Ok(Some((self.codec.decode(&bytes)?, version)))
+    }
+
+    /// Every unexpired entry in the scope, ordered by key.
+    pub async fn entries(&self) -> Result<Vec<(String, T)>, StoreError> {
+        self.list("").await
+    }
+
+    /// Unexpired entries whose keys start with `prefix`, ordered by key.
+    ///
+    /// The prefix matches exactly, case and all.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<(String, T)>, StoreError> {
+        self.migrate().await?;
+        let sql = format!(
+            "SELECT id, value FROM {} WHERE substr(id, 1, length(?)) = ? AND {} ORDER BY id",
+            self.table(),
+            UNEXPIRED
+        );
+        let params = vec![prefix.to_string(), prefix.to_string(), now_ms().to_string()];
+        let rows = self.store.query(&self.scope, &sql, params).await?;
+        self.decode_entries(rows)
+    }
+
+    /// Up to `limit` entries of [`list`](Self::list), starting after the key
+    /// `cursor`, along with the cursor for the next page; `None` once there
+    /// are no more.
+    ///
+    /// Pass `None` as the cursor for the first page.
+    pub async fn list_paged(
+        &self,
+        prefix: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<(String, T)>, Option<String>), StoreError> {
+        self.migrate().await?;
+        let limit = limit.max(1);
+        let sql = format!(
+            "SELECT id, value FROM {} WHERE substr(id, 1, length(?)) = ? AND id > ? AND {} ORDER BY id LIMIT CAST(? AS BIGINT)",
+            self.table(),
+            UNEXPIRED
+        );
+        // One past the page shows whether another follows
+        let params = vec![
+            prefix.to_string(),
+            prefix.to_string(),
+            cursor.unwrap_or_default().to_string(),
+            now_ms().to_string(),
+            (limit + 1).to_string(),
+        ];
+        let mut rows = self.store.query(&self.scope, &sql, params).await?;
+        let more = rows.len() > limit;
+        rows.truncate(limit);
+        let entries = self.decode_entries(rows)?;
+        let next = if more { entries.last().map(|(key, _)| key.clone()) } else { None };
+        Ok((entries, next))
+    }
+
+    fn decode_entries(&self, rows: Vec<GenericRow>) -> Result<Vec<(String, T)>, StoreError> {
+        rows.into_iter()
+            .map(|row| {
+                let [key, value] = <[String; 2]>::try_from(row.values)
+                    .map_err(|_| StoreError::Internal(anyhow::anyhow!("Malformed key-value row")))?;
+                let bytes = hex::decode(&value).map_err(|e| StoreError::Codec(e.to_string()))?;
+                Ok((key, self.codec.decode(&bytes)?))
+            })
+            .collect()
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub async fn delete(&self, key: &str) -> Result<bool, StoreError> {
+        let sql = format!("DELETE FROM {} WHERE id = ?", self.table());
+        let affected = self.execute_write(key, sql, vec![key.to_string()], None).await?;
+        if affected > 0 {
+            self.notify(key, None).await;
+        }
+        Ok(affected > 0)
+    }
+
+    /// Deletes expired entries, returning how many there were.
+    ///
+    /// They already read as absent, so watchers and change sinks aren't told.
+    pub async fn sweep_expired(&self) -> Result<u32, StoreError> {
+        self.migrate().await?;
+        let sql = format!("DELETE FROM {} WHERE expires_at <= CAST(? AS BIGINT)", self.table());
+        self.store.execute(&self.scope, &sql, vec![now_ms().to_string()]).await
+    }
+}
+
+impl<T, C> TypedStore<T, C>
+where
+    T: Serialize + DeserializeOwned + 'static,
+    C: Codec + 'static,
+{
+    /// Runs [`sweep_expired`](Self::sweep_expired) every `interval` on the
+    /// current Tokio runtime, until the store is dropped or the task aborted.
+    pub fn spawn_expiry_sweeper(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let store = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(1)));
+            loop {
+                ticker.tick().await;
+                let Some(store) = store.upgrade() else {
+                    return;
+                };
+                match store.sweep_expired().await {
+                    Ok(0) => {}
+                    Ok(swept) => debug!(scope = %store.scope, swept, "Swept expired entries"),
+                    Err(e) => warn!(scope = %store.scope, "Failed to sweep expired entries: {}", e),
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use futures_util::Stream;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// Changes buffered per watched prefix before a slow watcher starts missing them
+const WATCH_CHANNEL_CAPACITY: usize = 256;
+
+/// Tenant (`None` for the shared store) and key prefix a channel serves
+type WatchKey = (Option<String>, String);
+
+/// A key written or deleted through a [`TypedStore`](crate::store::TypedStore),
+/// as seen by [`SqlStore::watch`](crate::store::SqlStore::watch).
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyChange {
+    pub scope: String,
+    pub key: String,
+    /// The new value as JSON, or `None` for a tombstone
+    pub value: Option<serde_json::Value>,
+    /// Increases with every change published through the same watchers
+    pub version: u64,
+}
+
+/// Channels that carry key changes to watchers, one per watched prefix.
+///
+/// Shared by every [`SqlStore`](crate::store::SqlStore) over the same
+/// database so that a write through any of them reaches all watchers.
+/// Channels are keyed by tenant as well as prefix, so a tenant's watchers
+/// only see that tenant's keys.
+#[derive(Debug, Default)]
+pub struct KeyWatchers {
+    channels: Mutex<HashMap<WatchKey, broadcast::Sender<KeyChange>>>,
+    version: AtomicU64,
+}
+
+impl KeyWatchers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True if some watcher would receive a change to `key`, so callers can
+    /// skip building the change otherwise.
+    pub fn is_watched(&self, tenant: Option<&str>, key: &str) -> bool {
+        let channels = self.channels.lock().expect("Mutex poisoned");
+        channels
+            .iter()
+            .any(|((t, prefix), tx)| t.as_deref() == tenant && key.starts_with(prefix) && tx.receiver_count() > 0)
+    }
+
+    /// Sends a change to every watcher whose prefix covers `key`.
+    pub fn publish(&self, tenant: Option<&str>, scope: &str, key: &str, value: Option<serde_json::Value>) {
+        let mut channels = self.channels.lock().expect("Mutex poisoned");
+        // Versions are taken under the lock so watchers see them in order
+        let change = KeyChange {
+            scope: scope.to_string(),
+            key: key.to_string(),
+            value,
+            version: self.version.fetch_add(1, Ordering::Relaxed) + 1,
+        };
+        channels.retain(|(t, prefix), tx| {
+            if t.as_deref() == tenant && key.starts_with(prefix.as_str()) {
+                return tx.send(change.clone()).is_ok();
+            }
+            // Drop channels whose watchers have all gone away
+            tx.receiver_count() > 0
+        });
+    }
+
+    /// Streams changes to keys under `prefix`, from now on.
+    ///
+    /// A watcher that falls more than the channel capacity behind skips the
+    /// changes it missed, which shows up as a gap in the versions.
+    pub fn watch(
+        &self,
+        tenant: Option<&str>,
+        prefix: &str,
+    ) -> impl Stream<Item = KeyChange> + Send + use<> {
+        let rx = {
+            let mut channels = self.channels.lock().expect("Mutex poisoned");
+            channels
+                .entry((tenant.map(str::to_string), prefix.to_string()))
+                .or_insert_with(|| broadcast::channel(WATCH_CHANNEL_CAPACITY).0)
+                .subscribe()
+        };
+
+        futures_util::stream::unfold(rx, |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(change) => return Some((change, rx)),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(missed, "Store watcher lagged, skipping changes");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
@@ -13,6 +13,16 @@ pub enum FileChange {
     Deleted(PathBuf),
 }
 
+/// Counts of the changes actually applied to the base directory by a commit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommitSummary {
+    pub files_created: usize,
+    pub files_modified: usize,
+    pub files_deleted: usize,
+    /// Total bytes copied into the base for created and modified files
+    pub bytes_written: u64,
+}
+
 /// Computes SHA256 hash of a file
 fn compute_hash(path: &Path) -> io::Result<String> {
     let mut file = fs::File::open(path)?;
@@ -79,12 +89,14 @@ pub fn compute_diff(session_path: &Path, base_path: &Path) -> io::Result<Vec<Fil
     Ok(changes)
 }
 
-/// Applies changes from session to base
+/// Applies changes from session to base, returning what was actually applied
 pub fn apply_changes(
     session_path: &Path,
     base_path: &Path,
     changes: &[FileChange],
-) -> io::Result<()> {
+) -> io::Result<CommitSummary> {
+    let mut summary = CommitSummary::default();
+
     for change in changes {
         match change {
             FileChange::Added(rel) | FileChange::Modified(rel) => {
@@ -96,13 +108,18 @@ pub fn apply_changes(
                 }
 
                 // Safest is copy.
-                fs::copy(&src, &dst)?;
+                summary.bytes_written += fs::copy(&src, &dst)?;
+                match change {
+                    FileChange::Added(_) => summary.files_created += 1,
+                    _ => summary.files_modified += 1,
+                }
                 debug!("Applied {:?}: {:?}", change, dst);
             }
             FileChange::Deleted(rel) => {
                 let target = base_path.join(rel);
                 if target.exists() {
                     fs::remove_file(&target)?;
+                    summary.files_deleted += 1;
                     debug!("Applied Delete: {:?}", target);
                 }
             }
@@ -110,5 +127,5 @@ pub fn apply_changes(
     }
 
     info!("Applied {} changes to Base", changes.len());
-    Ok(())
+    Ok(summary)
 }
@@ -1,24 +1,163 @@
+use super::diff::CommitSummary;
 use super::{diff, reflink};
+use crate::infrastructure::audit::{AuditEvent, log_audit};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
 use walkdir::WalkDir;
 
+/// Suffix of the manifest file written next to each persisted session directory.
+const MANIFEST_SUFFIX: &str = ".session.json";
+
+/// A self-contained copy of a session, used to move it between nodes.
+#[derive(Debug, Clone)]
+pub struct SessionSnapshot {
+    pub session_id: String,
+    pub base_path: PathBuf,
+    /// Hash of the base directory at session start (for conflict detection)
+    pub base_snapshot_hash: String,
+    /// Working copy files as (path relative to the session root, contents)
+    pub files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// An active session as reported by [`SessionManager::list_sessions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub base_path: PathBuf,
+    /// When the session was begun, or attached if it came from another node
+    pub created_at: SystemTime,
+    /// Files added, modified or deleted in the working copy so far
+    pub pending_changes: usize,
+}
+
+/// A session captured by [`SessionManager::active_sessions`], to be
+/// described without holding the manager.
+#[derive(Debug, Clone)]
+pub struct ActiveSession {
+    pub session_id: String,
+    base_path: PathBuf,
+    session_path: PathBuf,
+    created_at: SystemTime,
+}
+
+impl ActiveSession {
+    /// Describes the session, diffing the working copy against its base. A
+    /// session whose diff fails is described with no pending changes.
+    pub fn describe(self) -> SessionInfo {
+        let pending_changes = diff::compute_diff(&self.session_path, &self.base_path)
+            .map(|changes| changes.len())
+            .unwrap_or_else(|e| {
+                warn!("Failed to diff session {}: {}", self.session_id, e);
+                0
+            });
+        SessionInfo {
+            session_id: self.session_id,
+            base_path: self.base_path,
+            created_at: self.created_at,
+            pending_changes,
+        }
+    }
+}
+
+/// Describes `sessions`, oldest first; see [`ActiveSession::describe`].
+pub fn describe_sessions(sessions: Vec<ActiveSession>) -> Vec<SessionInfo> {
+    let mut sessions: Vec<SessionInfo> = sessions.into_iter().map(ActiveSession::describe).collect();
+    sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.session_id.cmp(&b.session_id)));
+    sessions
+}
+
 /// Represents a session with its base path and snapshot hash
-struct SessionInfo {
+#[derive(Serialize, Deserialize)]
+struct SessionEntry {
     base_path: PathBuf,
     /// Hash of the base directory at session start (for conflict detection)
     base_snapshot_hash: String,
+    /// Manifests persisted before this was tracked restore as new
+    #[serde(default = "SystemTime::now")]
+    created_at: SystemTime,
+    /// Last time the session was used; reset when a session is restored
+    #[serde(skip, default = "SystemTime::now")]
+    last_activity: SystemTime,
+}
+
+/// Checks that a session id names a single directory under the temp root,
+/// as ids from manifests or other nodes end up joined onto it.
+fn validate_session_id(session_id: &str) -> Result<(), String> {
+    if Uuid::parse_str(session_id).is_ok() {
+        return Ok(());
+    }
+    let mut components = Path::new(session_id).components();
+    match (components.next(), components.next()) {
+        (Some(std::path::Component::Normal(_)), None) => Ok(()),
+        _ => Err(format!("Invalid session id: {:?}", session_id)),
+    }
+}
+
+/// A session found idle by [`SessionManager::idle_sessions`].
+#[derive(Debug)]
+pub struct IdleSession {
+    pub session_id: String,
+    session_path: PathBuf,
+    last_activity: SystemTime,
+}
+
+impl IdleSession {
+    /// When a file in the working copy was last modified, walking the whole
+    /// tree. Doesn't need the manager, so can run without holding it.
+    pub fn last_edit(&self) -> Option<SystemTime> {
+        WalkDir::new(&self.session_path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+            .max()
+    }
+}
+
+/// A session rolled back for being idle, whose working copy is still to be
+/// removed.
+#[derive(Debug)]
+pub struct ExpiredSession {
+    pub session_id: String,
+    session_path: PathBuf,
+    base_path: PathBuf,
+    idle_for: Duration,
 }
 
+impl ExpiredSession {
+    /// Removes the working copy and audits the rollback, returning the
+    /// session id. A failed removal is logged.
+    pub fn remove(self) -> String {
+        warn!("Session {} idle for {:?}, rolling back", self.session_id, self.idle_for);
+        if let Err(e) = fs::remove_dir_all(&self.session_path) {
+            warn!("Failed to remove idle session {} at {:?}: {}", self.session_id, self.session_path, e);
+        }
+        log_audit(AuditEvent::SessionExpired {
+            session_id: self.session_id.clone(),
+            base_path: self.base_path.display().to_string(),
+            idle_secs: self.idle_for.as_secs(),
+        });
+        self.session_id
+    }
+}
+
+/// Source of the current time, swappable so tests can move it forward
+pub type Clock = Arc<dyn Fn() -> SystemTime + Send + Sync>;
+
 pub struct SessionManager {
-    // Map SessionID -> SessionInfo
-    sessions: HashMap<String, SessionInfo>,
+    // Map SessionID -> SessionEntry
+    sessions: HashMap<String, SessionEntry>,
     root_temp_dir: PathBuf,
+    /// Sessions unused for this long are rolled back by [`Self::reap_idle_sessions`]
+    idle_timeout: Option<Duration>,
+    clock: Clock,
 }
 
 impl SessionManager {
@@ -28,9 +167,102 @@ impl SessionManager {
         Self {
             sessions: HashMap::new(),
             root_temp_dir: temp,
+            idle_timeout: None,
+            clock: Arc::new(SystemTime::now),
+        }
+    }
+
+    /// Rolls back sessions left unused for `timeout` whenever
+    /// [`Self::reap_idle_sessions`] runs. `None` keeps sessions until they end.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Replaces the clock used for session timestamps.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Marks a session as in use, postponing its idle timeout.
+    /// Returns `false` if there is no such session.
+    pub fn touch_session(&mut self, session_id: &str) -> bool {
+        let now = (self.clock)();
+        match self.sessions.get_mut(session_id) {
+            Some(entry) => {
+                entry.last_activity = now;
+                true
+            }
+            None => false,
         }
     }
 
+    /// Rolls back every session idle for longer than the idle timeout,
+    /// auditing each one. Returns the ids of the sessions rolled back.
+    ///
+    /// Guests edit working copies directly, so a session is only idle once
+    /// its files are too. A working copy that can't be removed is logged and
+    /// left for [`Self::cleanup_orphaned_sessions`].
+    #[instrument(skip(self))]
+    pub fn reap_idle_sessions(&mut self) -> Vec<String> {
+        let expired: Vec<ExpiredSession> = self
+            .idle_sessions()
+            .into_iter()
+            .filter_map(|idle| {
+                let last_edit = idle.last_edit();
+                self.expire_idle(idle, last_edit)
+            })
+            .collect();
+        expired.into_iter().map(ExpiredSession::remove).collect()
+    }
+
+    /// Sessions not used for longer than the idle timeout, going by the
+    /// activity recorded here; [`IdleSession::last_edit`] has the final say.
+    pub fn idle_sessions(&self) -> Vec<IdleSession> {
+        let Some(timeout) = self.idle_timeout else {
+            return Vec::new();
+        };
+        let now = (self.clock)();
+        self.sessions
+            .iter()
+            .filter(|(_, entry)| now.duration_since(entry.last_activity).is_ok_and(|idle_for| idle_for > timeout))
+            .map(|(session_id, entry)| IdleSession {
+                session_id: session_id.clone(),
+                session_path: self.root_temp_dir.join(session_id),
+                last_activity: entry.last_activity,
+            })
+            .collect()
+    }
+
+    /// Stops tracking an idle session unless it was used since it was found
+    /// idle, or `last_edit` shows its files were changed within the idle
+    /// timeout. Returns the session to remove.
+    pub fn expire_idle(&mut self, idle: IdleSession, last_edit: Option<SystemTime>) -> Option<ExpiredSession> {
+        let timeout = self.idle_timeout?;
+        let now = (self.clock)();
+        let entry = self.sessions.get_mut(&idle.session_id)?;
+        if entry.last_activity != idle.last_activity {
+            return None;
+        }
+        if let Some(edited) = last_edit
+            && edited > entry.last_activity
+        {
+            entry.last_activity = edited;
+        }
+        let idle_for = now.duration_since(entry.last_activity).ok()?;
+        if idle_for <= timeout {
+            return None;
+        }
+
+        let entry = self.sessions.remove(&idle.session_id)?;
+        Some(ExpiredSession {
+            session_id: idle.session_id,
+            session_path: idle.session_path,
+            base_path: entry.base_path,
+            idle_for,
+        })
+    }
+
     /// Computes a combined hash of all files in a directory for conflict detection.
     fn compute_directory_hash(path: &Path) -> Result<String, String> {
         let mut hasher = Sha256::new();
@@ -83,6 +315,7 @@ impl SessionManager {
 
     /// Returns the path to the session's working directory.
     /// Useful for agents that need to know where to make changes.
+    /// Doesn't count as activity; see [`Self::touch_session`].
     pub fn get_session_path(&self, session_id: &str) -> Option<PathBuf> {
         if self.sessions.contains_key(session_id) {
             Some(self.root_temp_dir.join(session_id))
@@ -112,11 +345,14 @@ impl SessionManager {
             .map_err(|e| format!("Failed to create session copy: {}", e))?;
 
         // Store session mapping with snapshot
+        let now = (self.clock)();
         self.sessions.insert(
             session_id.clone(),
-            SessionInfo {
+            SessionEntry {
                 base_path: base,
                 base_snapshot_hash,
+                created_at: now,
+                last_activity: now,
             },
         );
 
@@ -126,8 +362,11 @@ impl SessionManager {
     /// Commits changes from the session back to the base directory.
     /// Returns an error if the base directory has been modified since session start.
     /// Automatically cleans up the session directory after successful commit.
+    /// Returns counts of the changes that were applied.
     #[instrument(skip(self))]
-    pub fn commit_session(&mut self, session_id: String) -> Result<(), String> {
+    pub fn commit_session(&mut self, session_id: String) -> Result<CommitSummary, String> {
+        // A failed commit (e.g. a conflict) still counts as using the session
+        self.touch_session(&session_id);
         let session_info = self
             .sessions
             .get(&session_id)
@@ -169,19 +408,26 @@ impl SessionManager {
             // Still cleanup even if no changes
             self.sessions.remove(&session_id);
             self.cleanup_session_dir(&session_id)?;
-            return Ok(());
+            return Ok(CommitSummary::default());
         }
 
         // 2. Apply Changes
-        diff::apply_changes(&session_path, &base_path, &changes)
+        let summary = diff::apply_changes(&session_path, &base_path, &changes)
             .map_err(|e| format!("Failed to apply changes: {}", e))?;
 
         // 3. Cleanup session from map and filesystem
         self.sessions.remove(&session_id);
         self.cleanup_session_dir(&session_id)?;
 
-        info!("Session {} committed and cleaned up successfully", session_id);
-        Ok(())
+        info!(
+            created = summary.files_created,
+            modified = summary.files_modified,
+            deleted = summary.files_deleted,
+            bytes = summary.bytes_written,
+            "Session {} committed and cleaned up successfully",
+            session_id
+        );
+        Ok(summary)
     }
 
     /// Rolls back a session, discarding all changes without applying them.
@@ -204,11 +450,216 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Describes every active session, oldest first.
+    ///
+    /// Counting pending changes diffs each working copy against its base, so
+    /// this walks every session's files; to do that without holding the
+    /// manager, take [`active_sessions`](Self::active_sessions) and pass them
+    /// to [`describe_sessions`].
+    pub fn list_sessions(&self) -> Vec<SessionInfo> {
+        describe_sessions(self.active_sessions())
+    }
+
+    /// Captures every active session, for [`describe_sessions`].
+    pub fn active_sessions(&self) -> Vec<ActiveSession> {
+        self.sessions
+            .iter()
+            .map(|(session_id, entry)| ActiveSession {
+                session_id: session_id.clone(),
+                base_path: entry.base_path.clone(),
+                session_path: self.root_temp_dir.join(session_id),
+                created_at: entry.created_at,
+            })
+            .collect()
+    }
+
     /// Returns the number of active sessions.
     pub fn active_session_count(&self) -> usize {
         self.sessions.len()
     }
 
+    /// Snapshots every active session into `dir` and stops tracking them.
+    ///
+    /// Each session is written as `<dir>/<id>/` (the working copy) plus a
+    /// `<dir>/<id>.session.json` manifest. Returns the number of sessions persisted.
+    /// Used on planned shutdown so uncommitted edits survive a restart.
+    #[instrument(skip(self))]
+    pub fn persist_sessions(&mut self, dir: &Path) -> Result<usize, String> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create persist directory {:?}: {}", dir, e))?;
+
+        let session_ids: Vec<String> = self.sessions.keys().cloned().collect();
+        for session_id in &session_ids {
+            let session_path = self.root_temp_dir.join(session_id);
+            let target = dir.join(session_id);
+
+            reflink::copy_dir_reflink(&session_path, &target)
+                .map_err(|e| format!("Failed to persist session {}: {}", session_id, e))?;
+
+            let manifest = serde_json::to_string(&self.sessions[session_id])
+                .map_err(|e| format!("Failed to serialize session {}: {}", session_id, e))?;
+            fs::write(dir.join(format!("{}{}", session_id, MANIFEST_SUFFIX)), manifest)
+                .map_err(|e| format!("Failed to write manifest for {}: {}", session_id, e))?;
+
+            self.sessions.remove(session_id);
+            self.cleanup_session_dir(session_id)?;
+            debug!("Persisted session {} to {:?}", session_id, target);
+        }
+
+        info!("Persisted {} sessions to {:?}", session_ids.len(), dir);
+        Ok(session_ids.len())
+    }
+
+    /// Restores sessions previously written by [`Self::persist_sessions`].
+    ///
+    /// Restored sessions keep their original id and base snapshot hash, so
+    /// conflict detection still applies on commit. The persisted copies are
+    /// removed once restored. Returns the restored session ids.
+    #[instrument(skip(self))]
+    pub fn restore_sessions(&mut self, dir: &Path) -> Result<Vec<String>, String> {
+        let mut restored = Vec::new();
+
+        if !dir.exists() {
+            return Ok(restored);
+        }
+
+        let entries = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read persist directory {:?}: {}", dir, e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let file_name = entry.file_name();
+            let Some(session_id) = file_name
+                .to_str()
+                .and_then(|n| n.strip_suffix(MANIFEST_SUFFIX))
+            else {
+                continue;
+            };
+            validate_session_id(session_id)?;
+
+            let manifest = fs::read_to_string(entry.path())
+                .map_err(|e| format!("Failed to read manifest {:?}: {}", entry.path(), e))?;
+            let mut info: SessionEntry = serde_json::from_str(&manifest)
+                .map_err(|e| format!("Invalid manifest {:?}: {}", entry.path(), e))?;
+            // Time spent persisted doesn't count towards the idle timeout
+            info.last_activity = (self.clock)();
+
+            let persisted_path = dir.join(session_id);
+            reflink::copy_dir_reflink(&persisted_path, &self.root_temp_dir.join(session_id))
+                .map_err(|e| format!("Failed to restore session {}: {}", session_id, e))?;
+
+            fs::remove_dir_all(&persisted_path)
+                .map_err(|e| format!("Failed to remove persisted copy {:?}: {}", persisted_path, e))?;
+            fs::remove_file(entry.path())
+                .map_err(|e| format!("Failed to remove manifest {:?}: {}", entry.path(), e))?;
+
+            self.sessions.insert(session_id.to_string(), info);
+            restored.push(session_id.to_string());
+        }
+
+        info!("Restored {} sessions from {:?}", restored.len(), dir);
+        Ok(restored)
+    }
+
+    /// Captures a session into a [`SessionSnapshot`] and stops tracking it locally.
+    ///
+    /// The local working copy is removed, so at most one owner exists once the
+    /// snapshot is handed elsewhere. Use [`Self::attach_session`] to undo.
+    #[instrument(skip(self))]
+    pub fn detach_session(&mut self, session_id: &str) -> Result<SessionSnapshot, String> {
+        let info = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        let session_path = self.root_temp_dir.join(session_id);
+
+        let mut files = Vec::new();
+        for entry in WalkDir::new(&session_path).sort_by_file_name() {
+            let entry = entry.map_err(|e| format!("Failed to walk session: {}", e))?;
+            if entry.path().is_file() {
+                let relative = entry
+                    .path()
+                    .strip_prefix(&session_path)
+                    .map_err(|e| format!("Failed to strip prefix: {}", e))?;
+                let content = fs::read(entry.path())
+                    .map_err(|e| format!("Failed to read {:?}: {}", entry.path(), e))?;
+                files.push((relative.to_path_buf(), content));
+            }
+        }
+
+        let snapshot = SessionSnapshot {
+            session_id: session_id.to_string(),
+            base_path: info.base_path.clone(),
+            base_snapshot_hash: info.base_snapshot_hash.clone(),
+            files,
+        };
+
+        self.sessions.remove(session_id);
+        self.cleanup_session_dir(session_id)?;
+        info!("Detached session {} ({} files)", session_id, snapshot.files.len());
+        Ok(snapshot)
+    }
+
+    /// Takes ownership of a session captured by [`Self::detach_session`].
+    #[instrument(skip(self, snapshot), fields(session_id = %snapshot.session_id))]
+    pub fn attach_session(&mut self, snapshot: SessionSnapshot) -> Result<(), String> {
+        // Snapshots may come from another node; never write outside the session dir
+        validate_session_id(&snapshot.session_id)?;
+        if self.sessions.contains_key(&snapshot.session_id) {
+            return Err(format!("Session already exists: {}", snapshot.session_id));
+        }
+
+        if let Err(e) = self.write_snapshot_files(&snapshot) {
+            let _ = self.cleanup_session_dir(&snapshot.session_id);
+            return Err(e);
+        }
+
+        info!("Attached session {}", snapshot.session_id);
+        let now = (self.clock)();
+        self.sessions.insert(
+            snapshot.session_id,
+            SessionEntry {
+                base_path: snapshot.base_path,
+                base_snapshot_hash: snapshot.base_snapshot_hash,
+                created_at: now,
+                last_activity: now,
+            },
+        );
+        Ok(())
+    }
+
+    fn write_snapshot_files(&self, snapshot: &SessionSnapshot) -> Result<(), String> {
+        let session_path = self.root_temp_dir.join(&snapshot.session_id);
+        for (relative, content) in &snapshot.files {
+            if relative.is_absolute()
+                || relative
+                    .components()
+                    .any(|c| !matches!(c, std::path::Component::Normal(_)))
+            {
+                return Err(format!("Invalid path in session snapshot: {:?}", relative));
+            }
+
+            let target = session_path.join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+            }
+            fs::write(&target, content)
+                .map_err(|e| format!("Failed to write {:?}: {}", target, e))?;
+        }
+        fs::create_dir_all(&session_path)
+            .map_err(|e| format!("Failed to create session directory: {}", e))
+    }
+
+    /// Rolls back every active session. Returns the number of sessions aborted.
+    pub fn abort_all_sessions(&mut self) -> Result<usize, String> {
+        let session_ids: Vec<String> = self.sessions.keys().cloned().collect();
+        for session_id in &session_ids {
+            self.rollback_session(session_id.clone())?;
+        }
+        Ok(session_ids.len())
+    }
+
     /// Cleans up all orphaned session directories that are not being tracked.
     /// This can be called on startup to recover from crashes.
     #[instrument(skip(self))]
@@ -1,4 +1,5 @@
-use super::manager::SessionManager;
+use super::diff::CommitSummary;
+use super::manager::{SessionManager, SessionSnapshot};
 use std::fs;
 
 #[test]
@@ -55,3 +56,148 @@ fn test_session_lifecycle() {
     let _ = fs::remove_dir_all(&base_dir);
     let _ = fs::remove_dir_all(&session_path);
 }
+
+#[test]
+fn test_commit_summary_counts_applied_changes() {
+    let base_dir = std::env::temp_dir().join("brio_tests_summary");
+    if base_dir.exists() {
+        fs::remove_dir_all(&base_dir).unwrap();
+    }
+    fs::create_dir_all(&base_dir).unwrap();
+    fs::write(base_dir.join("keep.txt"), "unchanged").unwrap();
+    fs::write(base_dir.join("edit.txt"), "old").unwrap();
+    fs::write(base_dir.join("gone.txt"), "bye").unwrap();
+
+    let mut manager = SessionManager::new();
+    let session_id = manager
+        .begin_session(base_dir.to_str().unwrap().to_string())
+        .unwrap();
+    let session_path = manager.get_session_path(&session_id).unwrap();
+
+    fs::write(session_path.join("edit.txt"), "new content").unwrap();
+    fs::write(session_path.join("a.txt"), "12345").unwrap();
+    fs::create_dir(session_path.join("nested")).unwrap();
+    fs::write(session_path.join("nested/b.txt"), "xy").unwrap();
+    fs::remove_file(session_path.join("gone.txt")).unwrap();
+
+    let summary = manager.commit_session(session_id).unwrap();
+    assert_eq!(
+        summary,
+        CommitSummary {
+            files_created: 2,
+            files_modified: 1,
+            files_deleted: 1,
+            bytes_written: ("new content".len() + "12345".len() + "xy".len()) as u64,
+        }
+    );
+
+    let _ = fs::remove_dir_all(&base_dir);
+}
+
+#[test]
+fn test_persisted_session_survives_restart() {
+    let temp_dir = std::env::temp_dir().join("brio_tests_persist");
+    let base_dir = temp_dir.join("base");
+    let persist_dir = temp_dir.join("persisted");
+
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    fs::create_dir_all(&base_dir).unwrap();
+    fs::write(base_dir.join("file.txt"), "original").unwrap();
+
+    // Stage an edit, then "shut down" by persisting
+    let mut manager = SessionManager::new();
+    let session_id = manager
+        .begin_session(base_dir.to_str().unwrap().to_string())
+        .unwrap();
+    let session_path = manager.get_session_path(&session_id).unwrap();
+    fs::write(session_path.join("file.txt"), "staged").unwrap();
+
+    assert_eq!(manager.persist_sessions(&persist_dir).unwrap(), 1);
+    assert_eq!(manager.active_session_count(), 0);
+    assert!(!session_path.exists());
+
+    // "Restart" with a fresh manager
+    let mut restarted = SessionManager::new();
+    let restored = restarted.restore_sessions(&persist_dir).unwrap();
+    assert_eq!(restored, vec![session_id.clone()]);
+    assert_eq!(
+        fs::read_to_string(restarted.get_session_path(&session_id).unwrap().join("file.txt"))
+            .unwrap(),
+        "staged"
+    );
+
+    restarted.commit_session(session_id).unwrap();
+    assert_eq!(
+        fs::read_to_string(base_dir.join("file.txt")).unwrap(),
+        "staged"
+    );
+    assert_eq!(fs::read_dir(&persist_dir).unwrap().count(), 0);
+
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_attach_rejects_path_traversal() {
+    let mut manager = SessionManager::new();
+    let snapshot = SessionSnapshot {
+        session_id: uuid::Uuid::new_v4().to_string(),
+        base_path: std::env::temp_dir(),
+        base_snapshot_hash: String::new(),
+        files: vec![("../escape.txt".into(), b"nope".to_vec())],
+    };
+
+    assert!(manager.attach_session(snapshot).is_err());
+    assert_eq!(manager.active_session_count(), 0);
+}
+
+#[test]
+fn test_attach_rejects_session_id_outside_temp_root() {
+    let mut manager = SessionManager::new();
+    for session_id in ["../../escape", "/tmp/escape", "a/b", ".."] {
+        let snapshot = SessionSnapshot {
+            session_id: session_id.to_string(),
+            base_path: std::env::temp_dir(),
+            base_snapshot_hash: String::new(),
+            files: vec![("file.txt".into(), b"nope".to_vec())],
+        };
+        assert!(manager.attach_session(snapshot).is_err(), "accepted {:?}", session_id);
+    }
+    assert_eq!(manager.active_session_count(), 0);
+}
+
+#[test]
+fn test_failed_attach_removes_partial_session() {
+    let mut manager = SessionManager::new();
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let snapshot = SessionSnapshot {
+        session_id: session_id.clone(),
+        base_path: std::env::temp_dir(),
+        base_snapshot_hash: String::new(),
+        // The second file can't be written under the first, which is a file
+        files: vec![
+            ("file.txt".into(), b"written".to_vec()),
+            ("file.txt/nested.txt".into(), b"fails".to_vec()),
+        ],
+    };
+
+    assert!(manager.attach_session(snapshot).is_err());
+    assert!(!std::env::temp_dir().join("brio").join(&session_id).exists());
+}
+
+#[test]
+fn test_restore_rejects_manifest_with_traversal_id() {
+    let persist_dir = std::env::temp_dir().join("brio_tests_restore_traversal");
+    if persist_dir.exists() {
+        fs::remove_dir_all(&persist_dir).unwrap();
+    }
+    fs::create_dir_all(&persist_dir).unwrap();
+    fs::write(persist_dir.join("...session.json"), r#"{"base_path":"/tmp","base_snapshot_hash":""}"#).unwrap();
+
+    let mut manager = SessionManager::new();
+    assert!(manager.restore_sessions(&persist_dir).is_err());
+    assert_eq!(manager.active_session_count(), 0);
+
+    let _ = fs::remove_dir_all(&persist_dir);
+}
@@ -1,55 +1,437 @@
 //! Broadcaster service for JSON Patch distribution.
 
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use tokio::sync::broadcast;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::Instant;
 use tracing::{debug, warn};
 
-use crate::ws::types::{BroadcastMessage, WsError};
+use crate::ws::buffer::{WriteAheadBuffer, WriteAheadOptions};
+use crate::ws::connection::ConnectionOptions;
+use crate::ws::replay::ReplayLog;
+use crate::ws::snapshot::SnapshotSource;
+use crate::ws::stream::CoalesceOptions;
+use crate::ws::types::{
+    BroadcastMessage, ClientId, ClientInfo, SerializationOptions, WsError, WsPatch,
+};
 
-const BROADCAST_CAPACITY: usize = 256;
+/// Messages the channel holds before the slowest subscriber starts lagging
+pub const DEFAULT_BROADCAST_CAPACITY: usize = 256;
+/// Counter of receivers falling behind the channel and skipping messages
+pub const LAG_EVENTS_METRIC: &str = "brio_ws_receiver_lag_events_total";
+/// Histogram of how many messages each lag event skipped
+pub const LAG_SKIPPED_METRIC: &str = "brio_ws_receiver_lag_skipped_messages";
+/// Gauge of receivers currently subscribed, across all broadcasters
+pub const ACTIVE_CLIENTS_METRIC: &str = "brio_ws_active_clients";
+/// Default upper bound on a single serialized frame
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+/// How often [`Broadcaster::drain`] checks whether subscribers have caught up
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Messages sent to one client with [`Broadcaster::send_to`] that may wait for it
+const DIRECT_CHANNEL_CAPACITY: usize = 64;
+/// Topic subscription that accepts patches on every topic
+pub const TOPIC_WILDCARD: &str = "*";
+
+type DirectSenders = Arc<RwLock<HashMap<ClientId, mpsc::Sender<BroadcastMessage>>>>;
 
 #[derive(Clone)]
 pub struct Broadcaster {
     sender: broadcast::Sender<BroadcastMessage>,
     client_count: Arc<AtomicUsize>,
+    /// Subscribers allowed at once; `None` is unbounded
+    max_clients: Option<usize>,
+    serialization: SerializationOptions,
+    max_message_bytes: usize,
+    stream_coalescing: Option<CoalesceOptions>,
+    stream_heartbeat: Option<Duration>,
+    retained_topics: Arc<HashSet<String>>,
+    /// Last message per retained topic; the lock also orders publishes against subscribes
+    retained: Arc<RwLock<HashMap<String, BroadcastMessage>>>,
+    write_ahead: Option<Arc<WriteAheadBuffer>>,
+    connection_options: ConnectionOptions,
+    /// Set once [`Broadcaster::drain`] closes the channel to subscribers
+    closed: Arc<watch::Sender<bool>>,
+    lag: Arc<RwLock<HashMap<ClientId, ClientLag>>>,
+    /// Per-client channels for [`Broadcaster::send_to`], one per attached receiver
+    direct: DirectSenders,
+    /// Recent patches by sequence number; the lock also orders broadcasts against resumes
+    replay: Option<Arc<Mutex<ReplayLog>>>,
+    snapshot_source: Option<Arc<dyn SnapshotSource>>,
+}
+
+/// Lag history of one connected client, for spotting chronically slow consumers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientLag {
+    pub client_id: ClientId,
+    pub subject: Option<String>,
+    /// Times the client fell behind and had to resync
+    pub events: u64,
+    /// Messages skipped across all of those events
+    pub skipped: u64,
 }
 
 impl Broadcaster {
     pub fn new() -> Self {
-        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+        Self::with_capacity(DEFAULT_BROADCAST_CAPACITY)
+    }
+
+    /// Creates a broadcaster whose channel holds `capacity` messages.
+    ///
+    /// A subscriber more than `capacity` messages behind skips ahead and
+    /// sees [`WsError::Lagged`]. Panics if `capacity` is zero.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
         Self {
             sender,
             client_count: Arc::new(AtomicUsize::new(0)),
+            max_clients: None,
+            serialization: SerializationOptions::default(),
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            stream_coalescing: None,
+            stream_heartbeat: None,
+            retained_topics: Arc::new(HashSet::new()),
+            retained: Arc::new(RwLock::new(HashMap::new())),
+            write_ahead: None,
+            connection_options: ConnectionOptions::default(),
+            closed: Arc::new(watch::Sender::new(false)),
+            lag: Arc::new(RwLock::new(HashMap::new())),
+            direct: Arc::new(RwLock::new(HashMap::new())),
+            replay: None,
+            snapshot_source: None,
         }
     }
 
-    pub fn subscribe(&self) -> BroadcastReceiver {
-        self.client_count.fetch_add(1, Ordering::SeqCst);
-        debug!(client_count = self.client_count(), "Client subscribed");
-        BroadcastReceiver {
-            inner: self.sender.subscribe(),
+    /// Sets how messages are rendered into frames for subscribers.
+    pub fn with_serialization(mut self, options: SerializationOptions) -> Self {
+        self.serialization = options;
+        self
+    }
+
+    pub fn serialization(&self) -> &SerializationOptions {
+        &self.serialization
+    }
+
+    /// Sets the largest serialized frame that will be broadcast.
+    ///
+    /// Every subscriber receives every message, so one huge frame lags all
+    /// clients at once; producers should chunk or snapshot large updates.
+    pub fn with_max_message_bytes(mut self, limit: usize) -> Self {
+        self.max_message_bytes = limit;
+        self
+    }
+
+    pub fn max_message_bytes(&self) -> usize {
+        self.max_message_bytes
+    }
+
+    /// Caps concurrent subscribers, so a connection storm can't exhaust memory
+    /// (unbounded by default). Subscribing past the cap fails with
+    /// [`WsError::CapacityExceeded`].
+    pub fn with_max_clients(mut self, max_clients: Option<usize>) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+
+    pub fn max_clients(&self) -> Option<usize> {
+        self.max_clients
+    }
+
+    /// Enables batching of streamed tokens into fewer frames (off by default).
+    pub fn with_stream_coalescing(mut self, options: Option<CoalesceOptions>) -> Self {
+        self.stream_coalescing = options;
+        self
+    }
+
+    pub fn stream_coalescing(&self) -> Option<CoalesceOptions> {
+        self.stream_coalescing
+    }
+
+    /// Sends a heartbeat frame whenever a forwarded stream waits this long
+    /// for its next token (off by default).
+    pub fn with_stream_heartbeat(mut self, interval: Option<Duration>) -> Self {
+        self.stream_heartbeat = interval;
+        self
+    }
+
+    pub fn stream_heartbeat(&self) -> Option<Duration> {
+        self.stream_heartbeat
+    }
+
+    /// Retains the last patch on each of these topics and replays it to
+    /// new subscribers, like MQTT retained messages. Suits state-like topics.
+    ///
+    /// Applies to every patch tagged with a retained topic, whether sent with
+    /// [`publish`](Self::publish) or [`broadcast`](Self::broadcast).
+    pub fn with_retained_topics<I, T>(mut self, topics: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.retained_topics = Arc::new(topics.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets keepalive and idle-timeout behaviour for connections served from this broadcaster.
+    pub fn with_connection_options(mut self, options: ConnectionOptions) -> Self {
+        self.connection_options = options;
+        self
+    }
+
+    pub fn connection_options(&self) -> ConnectionOptions {
+        self.connection_options
+    }
+
+    /// Queues broadcasts in a bounded buffer drained at a steady rate, so short
+    /// bursts don't make clients lag (off by default). Must be called within a
+    /// Tokio runtime, as it spawns the drain task.
+    pub fn with_write_ahead_buffer(mut self, options: Option<WriteAheadOptions>) -> Self {
+        self.write_ahead =
+            options.map(|options| WriteAheadBuffer::spawn(options, self.sender.clone()));
+        self
+    }
+
+    /// Numbers patches and keeps the last `capacity` of them, so clients can
+    /// resume with [`subscribe_since`](Self::subscribe_since) after a drop.
+    ///
+    /// Patch frames are then wrapped as `{"seq":N,"frame":...}`.
+    pub fn with_replay(mut self, capacity: usize) -> Self {
+        self.replay = Some(Arc::new(Mutex::new(ReplayLog::new(capacity))));
+        self
+    }
+
+    /// Resyncs receivers that lag with a snapshot from `source`, instead of
+    /// only telling their client that patches were lost.
+    pub fn with_snapshot_source(mut self, source: Arc<dyn SnapshotSource>) -> Self {
+        self.snapshot_source = Some(source);
+        self
+    }
+
+    pub fn subscribe(&self) -> Result<BroadcastReceiver, WsError> {
+        self.subscribe_from(None)
+    }
+
+    /// Subscribes, first delivering the patches numbered after `seq`.
+    ///
+    /// If some of them are no longer retained (or replay is disabled), the
+    /// receiver starts with [`WsError::Lagged`] so the client resyncs.
+    /// Patches buffered by a write-ahead buffer may arrive twice; clients
+    /// should ignore sequence numbers they have already seen.
+    pub fn subscribe_since(&self, seq: u64) -> Result<BroadcastReceiver, WsError> {
+        self.subscribe_from(Some(seq))
+    }
+
+    fn subscribe_from(&self, since: Option<u64>) -> Result<BroadcastReceiver, WsError> {
+        // Checked and counted in one step so concurrent subscribes can't overshoot the cap
+        let limit = self.max_clients.unwrap_or(usize::MAX);
+        self.client_count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < limit).then_some(count + 1)
+            })
+            .map_err(|count| {
+                warn!(
+                    client_count = count,
+                    limit, "Rejected subscriber over client cap"
+                );
+                WsError::CapacityExceeded { limit }
+            })?;
+        metrics::gauge!(ACTIVE_CLIENTS_METRIC).increment(1.0);
+
+        // Subscribe under the locks so no publish lands between snapshot and subscription
+        let retained = self.retained.read().expect("RwLock poisoned");
+        let replay = self
+            .replay
+            .as_ref()
+            .map(|log| log.lock().expect("Mutex poisoned"));
+        let inner = self.sender.subscribe();
+        let mut pending: VecDeque<BroadcastMessage> = retained.values().cloned().collect();
+        let missed = match (since, &replay) {
+            (None, _) => None,
+            (Some(seq), Some(log)) => match log.since(seq) {
+                Ok(messages) => {
+                    pending.extend(messages);
+                    None
+                }
+                Err(missed) => Some(missed),
+            },
+            (Some(_), None) => Some(0),
+        };
+        drop(replay);
+        drop(retained);
+
+        debug!(
+            client_count = self.client_count(),
+            retained = pending.len(),
+            "Client subscribed"
+        );
+        Ok(BroadcastReceiver {
+            inner,
+            closed: self.closed.subscribe(),
+            pending,
             client_count: Arc::clone(&self.client_count),
-        }
+            serialization: self.serialization,
+            lag: Arc::clone(&self.lag),
+            direct: Arc::clone(&self.direct),
+            direct_rx: None,
+            topics: RwLock::new(None),
+            client: None,
+            missed,
+            snapshot_source: self.snapshot_source.clone(),
+        })
+    }
+
+    /// Broadcasts `patch` tagged with `topic`, retaining it if the topic is
+    /// configured to.
+    pub fn publish(&self, topic: impl Into<String>, patch: WsPatch) -> Result<(), WsError> {
+        self.broadcast(BroadcastMessage::Patch(patch.with_topic(topic)))
     }
 
     pub fn broadcast(&self, message: BroadcastMessage) -> Result<(), WsError> {
-        match self.sender.send(message) {
-            Ok(receiver_count) => {
-                debug!(receiver_count, "Broadcast sent");
-                Ok(())
+        if self.is_closed() {
+            return Err(WsError::ChannelClosed);
+        }
+
+        let size = message.to_frame_payload_with(&self.serialization)?.len();
+        if size > self.max_message_bytes {
+            warn!(
+                size,
+                limit = self.max_message_bytes,
+                "Rejected oversized broadcast"
+            );
+            return Err(WsError::MessageTooLarge {
+                size,
+                limit: self.max_message_bytes,
+            });
+        }
+
+        // Taken before the replay lock, in the order subscribers take them
+        let mut retained = match message.topic() {
+            Some(topic) if self.retained_topics.contains(topic) => Some((
+                topic.to_string(),
+                self.retained.write().expect("RwLock poisoned"),
+            )),
+            _ => None,
+        };
+        // Held through the send so sequence numbers reach the channel in order
+        let mut replay = self
+            .replay
+            .as_ref()
+            .map(|log| log.lock().expect("Mutex poisoned"));
+        let message = match (&mut replay, message) {
+            (Some(log), message @ BroadcastMessage::Patch(_)) => log.append(message),
+            (_, message) => message,
+        };
+        // Retained as sent, sequence number included, so it replays like the original
+        let retain = retained.as_ref().map(|_| message.clone());
+
+        if let Some(buffer) = &self.write_ahead {
+            buffer.push(message)?;
+        } else {
+            match self.sender.send(message) {
+                Ok(receiver_count) => debug!(receiver_count, "Broadcast sent"),
+                Err(_) => warn!("Broadcast sent but no clients connected"),
             }
-            Err(_) => {
-                warn!("Broadcast sent but no clients connected");
-                Ok(())
+        }
+
+        if let (Some((topic, retained)), Some(message)) = (&mut retained, retain) {
+            retained.insert(std::mem::take(topic), message);
+        }
+        Ok(())
+    }
+
+    /// Sends `message` to the one client with id `client`, alongside broadcasts.
+    ///
+    /// Only receivers attached with [`BroadcastReceiver::for_client`] can be
+    /// reached. Direct messages skip replay, retention and the write-ahead
+    /// buffer, and are delivered ahead of broadcasts the client has yet to
+    /// read. A client with 64 of them still
+    /// unread gets [`WsError::BufferFull`] instead of another.
+    pub fn send_to(&self, client: ClientId, message: BroadcastMessage) -> Result<(), WsError> {
+        if self.is_closed() {
+            return Err(WsError::ChannelClosed);
+        }
+
+        let size = message.to_frame_payload_with(&self.serialization)?.len();
+        if size > self.max_message_bytes {
+            warn!(size, limit = self.max_message_bytes, client_id = %client, "Rejected oversized message");
+            return Err(WsError::MessageTooLarge {
+                size,
+                limit: self.max_message_bytes,
+            });
+        }
+
+        let sender = self
+            .direct
+            .read()
+            .expect("RwLock poisoned")
+            .get(&client)
+            .cloned()
+            .ok_or(WsError::ClientNotFound(client))?;
+        sender.try_send(message).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => WsError::BufferFull {
+                capacity: DIRECT_CHANNEL_CAPACITY,
+            },
+            mpsc::error::TrySendError::Closed(_) => WsError::ClientNotFound(client),
+        })
+    }
+
+    /// Broadcasts [`BroadcastMessage::Shutdown`], then closes the channel once
+    /// subscribers have consumed everything queued or `grace` has passed.
+    ///
+    /// Subscribers see [`WsError::ChannelClosed`] after their last message, so
+    /// connections close cleanly. Later broadcasts fail with the same error.
+    /// Returns true if everything was consumed within the grace period.
+    pub async fn drain(&self, grace: Duration) -> bool {
+        if let Err(e) = self.broadcast(BroadcastMessage::Shutdown) {
+            warn!(error = %e, "Failed to broadcast shutdown");
+        }
+
+        let deadline = Instant::now() + grace;
+        let drained = loop {
+            let buffered = self.write_ahead.as_ref().map_or(0, |b| b.len());
+            if self.sender.is_empty() && buffered == 0 {
+                break true;
+            }
+            if Instant::now() >= deadline {
+                break false;
             }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        };
+
+        if !drained {
+            warn!(
+                queued = self.sender.len(),
+                grace_ms = grace.as_millis() as u64,
+                "Closing broadcaster with undelivered messages"
+            );
         }
+        self.closed.send_replace(true);
+        debug!(drained, "Broadcaster closed");
+        drained
+    }
+
+    pub fn is_closed(&self) -> bool {
+        *self.closed.borrow()
     }
 
     pub fn client_count(&self) -> usize {
         self.client_count.load(Ordering::SeqCst)
     }
 
+    /// Connected clients that have lagged at least once, slowest first.
+    pub fn lag_stats(&self) -> Vec<ClientLag> {
+        let mut stats: Vec<ClientLag> = self
+            .lag
+            .read()
+            .expect("RwLock poisoned")
+            .values()
+            .cloned()
+            .collect();
+        stats.sort_by_key(|s| std::cmp::Reverse(s.skipped));
+        stats
+    }
+
     pub fn sender(&self) -> &broadcast::Sender<BroadcastMessage> {
         &self.sender
     }
@@ -63,24 +445,173 @@ impl Default for Broadcaster {
 
 pub struct BroadcastReceiver {
     inner: broadcast::Receiver<BroadcastMessage>,
+    closed: watch::Receiver<bool>,
+    /// Retained messages delivered before anything from the channel
+    pending: VecDeque<BroadcastMessage>,
     client_count: Arc<AtomicUsize>,
+    serialization: SerializationOptions,
+    lag: Arc<RwLock<HashMap<ClientId, ClientLag>>>,
+    direct: DirectSenders,
+    /// Messages for this client alone, once attached to one
+    direct_rx: Option<mpsc::Receiver<BroadcastMessage>>,
+    /// Topics whose patches are delivered; `None` until the first subscription, accepting all
+    topics: RwLock<Option<HashSet<String>>>,
+    /// Client whose lag is tracked, once attached to a connection
+    client: Option<ClientInfo>,
+    /// Messages a resume could not replay, reported by the first `recv`
+    missed: Option<u64>,
+    snapshot_source: Option<Arc<dyn SnapshotSource>>,
 }
 
 impl BroadcastReceiver {
+    /// Attributes this receiver's lag to `client` in [`Broadcaster::lag_stats`]
+    /// and makes it the one [`Broadcaster::send_to`] reaches for that client.
+    pub fn for_client(mut self, client: &ClientInfo) -> Self {
+        let (tx, rx) = mpsc::channel(DIRECT_CHANNEL_CAPACITY);
+        self.direct
+            .write()
+            .expect("RwLock poisoned")
+            .insert(client.id, tx);
+        self.direct_rx = Some(rx);
+        self.client = Some(client.clone());
+        self
+    }
+
+    /// Narrows delivery to patches tagged with `topic`, plus untagged ones.
+    ///
+    /// Each call adds another topic. Until the first call every topic is
+    /// delivered, as it is again after subscribing to [`TOPIC_WILDCARD`].
+    pub fn subscribe_topic(&self, topic: &str) {
+        self.topics
+            .write()
+            .expect("RwLock poisoned")
+            .get_or_insert_with(HashSet::new)
+            .insert(topic.to_string());
+    }
+
+    /// Stops delivering patches tagged with `topic`.
+    ///
+    /// A receiver that never subscribed still gets every topic, as there is
+    /// no subscription to narrow.
+    pub fn unsubscribe_topic(&self, topic: &str) {
+        if let Some(topics) = self.topics.write().expect("RwLock poisoned").as_mut() {
+            topics.remove(topic);
+        }
+    }
+
+    fn accepts(&self, message: &BroadcastMessage) -> bool {
+        let Some(topic) = message.topic() else {
+            return true;
+        };
+        self.topics
+            .read()
+            .expect("RwLock poisoned")
+            .as_ref()
+            .is_none_or(|topics| topics.contains(TOPIC_WILDCARD) || topics.contains(topic))
+    }
+
+    fn record_lag(&self, skipped: u64) {
+        metrics::counter!(LAG_EVENTS_METRIC).increment(1);
+        metrics::histogram!(LAG_SKIPPED_METRIC).record(skipped as f64);
+
+        let Some(client) = &self.client else {
+            warn!(skipped, "Receiver lagged");
+            return;
+        };
+        warn!(client_id = %client.id, skipped, "Receiver lagged");
+        let mut lag = self.lag.write().expect("RwLock poisoned");
+        let entry = lag.entry(client.id).or_insert_with(|| ClientLag {
+            client_id: client.id,
+            subject: client.subject.clone(),
+            events: 0,
+            skipped: 0,
+        });
+        entry.events += 1;
+        entry.skipped += skipped;
+    }
+
+    /// Serialization options inherited from the broadcaster at subscribe time.
+    pub fn serialization(&self) -> &SerializationOptions {
+        &self.serialization
+    }
+
+    /// Fetches a [`BroadcastMessage::Snapshot`] of the current state, to send
+    /// after [`recv`](Self::recv) reports [`WsError::Lagged`].
+    ///
+    /// `None` if the broadcaster has no snapshot source.
+    pub async fn snapshot(&self) -> Option<Result<BroadcastMessage, WsError>> {
+        let source = self.snapshot_source.as_ref()?;
+        Some(
+            source
+                .snapshot()
+                .await
+                .map(|state| BroadcastMessage::Snapshot { state }),
+        )
+    }
+
     pub async fn recv(&mut self) -> Result<BroadcastMessage, WsError> {
-        self.inner.recv().await.map_err(|e| match e {
-            broadcast::error::RecvError::Closed => WsError::ChannelClosed,
-            broadcast::error::RecvError::Lagged(count) => {
-                warn!(skipped = count, "Receiver lagged");
-                WsError::ChannelClosed
+        if let Some(skipped) = self.missed.take() {
+            return Err(WsError::Lagged { skipped });
+        }
+        while let Some(message) = self.pending.pop_front() {
+            if self.accepts(&message) {
+                return Ok(message);
             }
-        })
+        }
+
+        loop {
+            let result = tokio::select! {
+                biased;
+                // Sent to this client by id, so never filtered by topic
+                Some(message) = recv_direct(&mut self.direct_rx) => return Ok(message),
+                result = self.inner.recv() => result,
+                // Once drained, deliver whatever is still queued and then report closure
+                _ = self.closed.wait_for(|closed| *closed) => self.inner.try_recv().map_err(|_| {
+                    broadcast::error::RecvError::Closed
+                }),
+            };
+
+            match result {
+                Ok(message) if self.accepts(&message) => return Ok(message),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Err(WsError::ChannelClosed),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    self.record_lag(skipped);
+                    return Err(WsError::Lagged { skipped });
+                }
+            }
+        }
+    }
+}
+
+/// Waits for a direct message, forever if the receiver has no direct channel
+async fn recv_direct(
+    rx: &mut Option<mpsc::Receiver<BroadcastMessage>>,
+) -> Option<BroadcastMessage> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
     }
 }
 
 impl Drop for BroadcastReceiver {
     fn drop(&mut self) {
+        if let Some(client) = &self.client {
+            self.lag
+                .write()
+                .expect("RwLock poisoned")
+                .remove(&client.id);
+            if let Some(rx) = &mut self.direct_rx {
+                rx.close();
+            }
+            let mut direct = self.direct.write().expect("RwLock poisoned");
+            // A newer receiver may have taken over the client's id
+            if direct.get(&client.id).is_some_and(|tx| tx.is_closed()) {
+                direct.remove(&client.id);
+            }
+        }
         self.client_count.fetch_sub(1, Ordering::SeqCst);
+        metrics::gauge!(ACTIVE_CLIENTS_METRIC).decrement(1.0);
         debug!(
             client_count = self.client_count.load(Ordering::SeqCst),
             "Client unsubscribed"
@@ -97,10 +628,10 @@ mod tests {
         let broadcaster = Broadcaster::new();
         assert_eq!(broadcaster.client_count(), 0);
 
-        let _rx1 = broadcaster.subscribe();
+        let _rx1 = broadcaster.subscribe().unwrap();
         assert_eq!(broadcaster.client_count(), 1);
 
-        let _rx2 = broadcaster.subscribe();
+        let _rx2 = broadcaster.subscribe().unwrap();
         assert_eq!(broadcaster.client_count(), 2);
 
         drop(_rx1);
@@ -110,7 +641,7 @@ mod tests {
     #[tokio::test]
     async fn broadcast_reaches_subscribers() {
         let broadcaster = Broadcaster::new();
-        let mut rx = broadcaster.subscribe();
+        let mut rx = broadcaster.subscribe().unwrap();
 
         broadcaster.broadcast(BroadcastMessage::Shutdown).unwrap();
 
@@ -125,10 +656,374 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn oversized_broadcast_is_rejected_without_affecting_subscribers() {
+        let broadcaster = Broadcaster::new().with_max_message_bytes(64);
+        let mut rx = broadcaster.subscribe().unwrap();
+
+        let large: json_patch::Patch = serde_json::from_value(serde_json::json!([
+            { "op": "add", "path": "/blob", "value": "x".repeat(256) }
+        ]))
+        .unwrap();
+        let result = broadcaster.broadcast(BroadcastMessage::Patch(WsPatch::new(large)));
+        assert!(matches!(
+            result,
+            Err(WsError::MessageTooLarge { limit: 64, .. })
+        ));
+
+        // Subscriber sees only the next message, with no lag
+        broadcaster.broadcast(BroadcastMessage::Shutdown).unwrap();
+        let msg = rx.recv().await.unwrap();
+        assert!(matches!(msg, BroadcastMessage::Shutdown));
+        assert_eq!(broadcaster.client_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn drain_delivers_queued_messages_and_shutdown_before_closing() {
+        let broadcaster = Broadcaster::new();
+        let mut rx = broadcaster.subscribe().unwrap();
+        broadcaster
+            .broadcast(BroadcastMessage::Patch(status_patch("last")))
+            .unwrap();
+
+        let consumer = tokio::spawn(async move {
+            let mut received = Vec::new();
+            loop {
+                match rx.recv().await {
+                    Ok(msg) => received.push(msg),
+                    Err(WsError::ChannelClosed) => return received,
+                    Err(e) => panic!("Unexpected error: {}", e),
+                }
+            }
+        });
+
+        assert!(broadcaster.drain(Duration::from_secs(5)).await);
+        let received = tokio::time::timeout(Duration::from_secs(5), consumer)
+            .await
+            .expect("Subscriber never saw the channel close")
+            .unwrap();
+
+        assert_eq!(received.len(), 2);
+        assert!(matches!(received[0], BroadcastMessage::Patch(_)));
+        assert!(matches!(received[1], BroadcastMessage::Shutdown));
+        assert!(matches!(
+            broadcaster.broadcast(BroadcastMessage::Shutdown),
+            Err(WsError::ChannelClosed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn drain_gives_up_on_stalled_subscribers_after_grace() {
+        let broadcaster = Broadcaster::new();
+        let _stalled = broadcaster.subscribe().unwrap();
+
+        let start = Instant::now();
+        assert!(!broadcaster.drain(Duration::from_millis(50)).await);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(broadcaster.is_closed());
+    }
+
+    fn status_patch(status: &str) -> WsPatch {
+        WsPatch::new(
+            serde_json::from_value(serde_json::json!([
+                { "op": "replace", "path": "/status", "value": status }
+            ]))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn late_subscriber_receives_retained_topic_value() {
+        let broadcaster = Broadcaster::new().with_retained_topics(["status"]);
+        let _early = broadcaster.subscribe().unwrap();
+
+        broadcaster
+            .publish("status", status_patch("starting"))
+            .unwrap();
+        broadcaster
+            .publish("status", status_patch("ready"))
+            .unwrap();
+        broadcaster
+            .publish("events", status_patch("not retained"))
+            .unwrap();
+
+        let mut late = broadcaster.subscribe().unwrap();
+        let BroadcastMessage::Patch(patch) = late.recv().await.unwrap() else {
+            panic!("Expected retained topic message");
+        };
+        assert_eq!(patch.topic(), Some("status"));
+        assert!(patch.to_json().unwrap().contains("ready"));
+
+        // Nothing else was retained; the next message is live
+        broadcaster.broadcast(BroadcastMessage::Shutdown).unwrap();
+        assert!(matches!(
+            late.recv().await.unwrap(),
+            BroadcastMessage::Shutdown
+        ));
+    }
+
+    #[tokio::test]
+    async fn retained_topic_keeps_its_sequence_number() {
+        let broadcaster = Broadcaster::new()
+            .with_replay(8)
+            .with_retained_topics(["status"]);
+        broadcaster
+            .broadcast(BroadcastMessage::Patch(
+                status_patch("ready").with_topic("status"),
+            ))
+            .unwrap();
+
+        let mut late = broadcaster.subscribe().unwrap();
+        let BroadcastMessage::Sequenced { seq, message } = late.recv().await.unwrap() else {
+            panic!("Expected the retained message as sequenced");
+        };
+        assert_eq!(seq, 1);
+        assert_eq!(message.topic(), Some("status"));
+    }
+
+    #[tokio::test]
+    async fn topics_are_not_retained_by_default() {
+        let broadcaster = Broadcaster::new();
+        broadcaster
+            .publish("status", status_patch("ready"))
+            .unwrap();
+
+        let mut rx = broadcaster.subscribe().unwrap();
+        broadcaster.broadcast(BroadcastMessage::Shutdown).unwrap();
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            BroadcastMessage::Shutdown
+        ));
+    }
+
+    #[test]
+    fn subscribers_inherit_serialization_options() {
+        let options = SerializationOptions {
+            pretty: true,
+            skip_nulls: false,
+        };
+        let broadcaster = Broadcaster::new().with_serialization(options);
+        let rx = broadcaster.subscribe().unwrap();
+        assert_eq!(*rx.serialization(), options);
+    }
+
     #[test]
     fn broadcaster_is_clone() {
         let broadcaster = Broadcaster::new();
         let cloned = broadcaster.clone();
         assert_eq!(broadcaster.client_count(), cloned.client_count());
     }
+
+    #[test]
+    fn forced_lag_is_counted_and_attributed_to_client() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let metrics = recorder.handle();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let broadcaster = Broadcaster::new();
+        let client = ClientInfo::new(Some("slow-consumer".into()), None);
+        let mut rx = broadcaster.subscribe().unwrap().for_client(&client);
+        for _ in 0..DEFAULT_BROADCAST_CAPACITY + 10 {
+            broadcaster
+                .broadcast(BroadcastMessage::Patch(status_patch("busy")))
+                .unwrap();
+        }
+
+        let result = metrics::with_local_recorder(&recorder, || runtime.block_on(rx.recv()));
+        assert!(matches!(result, Err(WsError::Lagged { skipped: 10 })));
+        let rendered = metrics.render();
+        assert!(
+            rendered.contains(&format!("{} 1", LAG_EVENTS_METRIC)),
+            "{}",
+            rendered
+        );
+        assert!(rendered.contains(LAG_SKIPPED_METRIC), "{}", rendered);
+
+        // The receiver recovers at the oldest retained message
+        assert!(runtime.block_on(rx.recv()).is_ok());
+        let stats = broadcaster.lag_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].client_id, client.id);
+        assert_eq!(stats[0].subject.as_deref(), Some("slow-consumer"));
+        assert_eq!((stats[0].events, stats[0].skipped), (1, 10));
+
+        drop(rx);
+        assert!(broadcaster.lag_stats().is_empty());
+    }
+
+    #[tokio::test]
+    async fn small_capacity_lags_subscriber_past_oldest_retained_message() {
+        let broadcaster = Broadcaster::with_capacity(2);
+        let mut rx = broadcaster.subscribe().unwrap();
+        for status in ["a", "b", "c", "d", "e"] {
+            broadcaster
+                .broadcast(BroadcastMessage::Patch(status_patch(status)))
+                .unwrap();
+        }
+
+        assert!(matches!(
+            rx.recv().await,
+            Err(WsError::Lagged { skipped: 3 })
+        ));
+        for expected in ["d", "e"] {
+            let BroadcastMessage::Patch(patch) = rx.recv().await.unwrap() else {
+                panic!("Expected patch");
+            };
+            assert!(patch.to_json().unwrap().contains(expected));
+        }
+    }
+
+    struct FixedSnapshot;
+
+    #[async_trait::async_trait]
+    impl SnapshotSource for FixedSnapshot {
+        async fn snapshot(&self) -> Result<serde_json::Value, WsError> {
+            Ok(serde_json::json!({"status": "e"}))
+        }
+    }
+
+    #[tokio::test]
+    async fn overflowed_receiver_is_lagged_not_closed_and_can_resync() {
+        let broadcaster =
+            Broadcaster::with_capacity(2).with_snapshot_source(Arc::new(FixedSnapshot));
+        let mut slow = broadcaster.subscribe().unwrap();
+        for status in ["a", "b", "c", "d", "e"] {
+            broadcaster
+                .broadcast(BroadcastMessage::Patch(status_patch(status)))
+                .unwrap();
+        }
+
+        let result = slow.recv().await;
+        assert!(
+            matches!(result, Err(WsError::Lagged { skipped: 3 })),
+            "{:?}",
+            result.err()
+        );
+        let snapshot = slow
+            .snapshot()
+            .await
+            .expect("snapshot source is configured")
+            .unwrap();
+        assert_eq!(
+            snapshot.to_frame_payload().unwrap(),
+            r#"{"state":{"status":"e"},"type":"snapshot"}"#
+        );
+        assert!(!broadcaster.is_closed());
+        assert!(
+            Broadcaster::new()
+                .subscribe()
+                .unwrap()
+                .snapshot()
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn send_to_reaches_only_the_target_client() {
+        let broadcaster = Broadcaster::new();
+        let alice = ClientInfo::new(Some("alice".into()), None);
+        let bob = ClientInfo::new(Some("bob".into()), None);
+        let mut alice_rx = broadcaster.subscribe().unwrap().for_client(&alice);
+        let mut bob_rx = broadcaster.subscribe().unwrap().for_client(&bob);
+
+        broadcaster
+            .send_to(alice.id, BroadcastMessage::Patch(status_patch("for-alice")))
+            .unwrap();
+        broadcaster
+            .broadcast(BroadcastMessage::Patch(status_patch("for-all")))
+            .unwrap();
+
+        let BroadcastMessage::Patch(patch) = alice_rx.recv().await.unwrap() else {
+            panic!("Expected patch");
+        };
+        assert!(patch.to_json().unwrap().contains("for-alice"));
+        assert!(alice_rx.recv().await.is_ok());
+        let BroadcastMessage::Patch(patch) = bob_rx.recv().await.unwrap() else {
+            panic!("Expected patch");
+        };
+        assert!(patch.to_json().unwrap().contains("for-all"));
+
+        let stranger = ClientInfo::new(None, None);
+        let result =
+            broadcaster.send_to(stranger.id, BroadcastMessage::Patch(status_patch("lost")));
+        assert!(matches!(result, Err(WsError::ClientNotFound(id)) if id == stranger.id));
+
+        drop(bob_rx);
+        let result = broadcaster.send_to(bob.id, BroadcastMessage::Patch(status_patch("gone")));
+        assert!(matches!(result, Err(WsError::ClientNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn topic_subscriber_skips_patches_for_other_topics() {
+        let broadcaster = Broadcaster::new();
+        let mut only_a = broadcaster.subscribe().unwrap();
+        only_a.subscribe_topic("a");
+        let mut everything = broadcaster.subscribe().unwrap();
+
+        broadcaster
+            .broadcast(BroadcastMessage::Patch(
+                status_patch("for-b").with_topic("b"),
+            ))
+            .unwrap();
+        broadcaster
+            .broadcast(BroadcastMessage::Patch(
+                status_patch("for-a").with_topic("a"),
+            ))
+            .unwrap();
+        broadcaster
+            .publish("b", status_patch("published-b"))
+            .unwrap();
+        broadcaster
+            .broadcast(BroadcastMessage::Patch(status_patch("untagged")))
+            .unwrap();
+
+        for expected in ["for-a", "untagged"] {
+            let BroadcastMessage::Patch(patch) = only_a.recv().await.unwrap() else {
+                panic!("Expected patch");
+            };
+            assert!(patch.to_json().unwrap().contains(expected));
+        }
+        for _ in 0..4 {
+            assert!(everything.recv().await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn wildcard_subscription_receives_every_topic() {
+        let broadcaster = Broadcaster::new().with_retained_topics(["b"]);
+        broadcaster
+            .publish("b", status_patch("retained-b"))
+            .unwrap();
+
+        let mut rx = broadcaster.subscribe().unwrap();
+        rx.subscribe_topic("a");
+        rx.subscribe_topic(TOPIC_WILDCARD);
+        broadcaster
+            .broadcast(BroadcastMessage::Patch(
+                status_patch("for-c").with_topic("c"),
+            ))
+            .unwrap();
+
+        assert_eq!(rx.recv().await.unwrap().topic(), Some("b"));
+        assert_eq!(rx.recv().await.unwrap().topic(), Some("c"));
+    }
+
+    #[tokio::test]
+    async fn subscribe_past_client_cap_fails_without_counting() {
+        let broadcaster = Broadcaster::new().with_max_clients(Some(1));
+        let first = broadcaster.subscribe().unwrap();
+
+        let result = broadcaster.subscribe();
+        assert!(matches!(
+            result,
+            Err(WsError::CapacityExceeded { limit: 1 })
+        ));
+        assert_eq!(broadcaster.client_count(), 1);
+
+        drop(first);
+        assert!(broadcaster.subscribe_since(0).is_ok());
+    }
 }
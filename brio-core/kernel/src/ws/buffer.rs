@@ -0,0 +1,192 @@
+//! Write-ahead buffer smoothing bursts in front of the broadcast channel.
+
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::{Notify, broadcast};
+use tokio::time::MissedTickBehavior;
+use tracing::warn;
+
+use crate::ws::types::{BroadcastMessage, WsError};
+
+/// What happens to a message broadcast while the buffer is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Evict the oldest buffered message to make room
+    #[default]
+    DropOldest,
+    /// Discard the incoming message
+    DropNewest,
+    /// Fail the broadcast with [`WsError::BufferFull`]
+    Reject,
+}
+
+/// Sizing of the write-ahead buffer.
+///
+/// Broadcasts are queued and forwarded to subscribers at `drain_rate_per_sec`,
+/// so a burst shorter than `capacity` reaches slow clients without overrunning
+/// their channel, at the cost of some latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct WriteAheadOptions {
+    pub capacity: usize,
+    pub drain_rate_per_sec: u32,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for WriteAheadOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 4096,
+            drain_rate_per_sec: 1000,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+/// Queue drained into the broadcast channel by a background task.
+///
+/// The task only holds a weak reference, and exits once the last
+/// broadcaster sharing the buffer is dropped.
+pub(crate) struct WriteAheadBuffer {
+    queue: Mutex<VecDeque<BroadcastMessage>>,
+    wake: Arc<Notify>,
+    options: WriteAheadOptions,
+}
+
+impl WriteAheadBuffer {
+    /// Creates the buffer and spawns its drain task; must be called within a Tokio runtime.
+    pub(crate) fn spawn(options: WriteAheadOptions, sender: broadcast::Sender<BroadcastMessage>) -> Arc<Self> {
+        let buffer = Arc::new(Self {
+            queue: Mutex::new(VecDeque::with_capacity(options.capacity)),
+            wake: Arc::new(Notify::new()),
+            options,
+        });
+        tokio::spawn(drain(Arc::downgrade(&buffer), Arc::clone(&buffer.wake), sender));
+        buffer
+    }
+
+    pub(crate) fn push(&self, message: BroadcastMessage) -> Result<(), WsError> {
+        {
+            let mut queue = self.queue.lock().expect("Mutex poisoned");
+            if queue.len() >= self.options.capacity {
+                match self.options.overflow {
+                    OverflowPolicy::DropOldest => {
+                        queue.pop_front();
+                        warn!(capacity = self.options.capacity, "Write-ahead buffer full, dropped oldest message");
+                    }
+                    OverflowPolicy::DropNewest => {
+                        warn!(capacity = self.options.capacity, "Write-ahead buffer full, dropped new message");
+                        return Ok(());
+                    }
+                    OverflowPolicy::Reject => {
+                        return Err(WsError::BufferFull {
+                            capacity: self.options.capacity,
+                        });
+                    }
+                }
+            }
+            queue.push_back(message);
+        }
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.queue.lock().expect("Mutex poisoned").len()
+    }
+
+    fn pop(&self) -> Option<BroadcastMessage> {
+        self.queue.lock().expect("Mutex poisoned").pop_front()
+    }
+}
+
+impl Drop for WriteAheadBuffer {
+    fn drop(&mut self) {
+        // Lets the drain task notice the buffer is gone
+        self.wake.notify_one();
+    }
+}
+
+async fn drain(buffer: Weak<WriteAheadBuffer>, wake: Arc<Notify>, sender: broadcast::Sender<BroadcastMessage>) {
+    let rate = buffer.upgrade().map_or(1, |b| b.options.drain_rate_per_sec.max(1));
+    let mut ticker = tokio::time::interval(Duration::from_secs(1) / rate);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        let Some(next) = buffer.upgrade().map(|b| b.pop()) else {
+            return;
+        };
+        match next {
+            Some(message) => {
+                // No subscribers just means nobody is listening yet
+                let _ = sender.send(message);
+                ticker.tick().await;
+            }
+            None => wake.notified().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::Broadcaster;
+
+    #[tokio::test]
+    async fn burst_within_capacity_loses_no_messages() {
+        let broadcaster = Broadcaster::new().with_write_ahead_buffer(Some(WriteAheadOptions {
+            capacity: 2048,
+            drain_rate_per_sec: 20_000,
+            overflow: OverflowPolicy::Reject,
+        }));
+        let mut rx = broadcaster.subscribe().unwrap();
+
+        // Several times the channel capacity, sent without yielding
+        let burst = 1000;
+        for _ in 0..burst {
+            broadcaster.broadcast(BroadcastMessage::Shutdown).unwrap();
+        }
+
+        for _ in 0..burst {
+            let msg = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .expect("Timed out draining buffer")
+                .expect("Message lost");
+            assert!(matches!(msg, BroadcastMessage::Shutdown));
+        }
+    }
+
+    #[test]
+    fn overflow_policies() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let _guard = runtime.enter();
+        let (sender, _) = broadcast::channel(16);
+
+        let options = |overflow| WriteAheadOptions {
+            capacity: 1,
+            drain_rate_per_sec: 1,
+            overflow,
+        };
+
+        // The drain task never runs here, so the buffer stays full
+        let reject = WriteAheadBuffer::spawn(options(OverflowPolicy::Reject), sender.clone());
+        reject.push(BroadcastMessage::Shutdown).unwrap();
+        assert!(matches!(
+            reject.push(BroadcastMessage::Shutdown),
+            Err(WsError::BufferFull { capacity: 1 })
+        ));
+
+        let drop_newest = WriteAheadBuffer::spawn(options(OverflowPolicy::DropNewest), sender.clone());
+        drop_newest.push(BroadcastMessage::Shutdown).unwrap();
+        drop_newest.push(BroadcastMessage::Patch(crate::ws::WsPatch::new(json_patch::Patch(vec![])))).unwrap();
+        assert!(matches!(drop_newest.pop(), Some(BroadcastMessage::Shutdown)));
+
+        let drop_oldest = WriteAheadBuffer::spawn(options(OverflowPolicy::DropOldest), sender);
+        drop_oldest.push(BroadcastMessage::Shutdown).unwrap();
+        drop_oldest.push(BroadcastMessage::Patch(crate::ws::WsPatch::new(json_patch::Patch(vec![])))).unwrap();
+        assert!(matches!(drop_oldest.pop(), Some(BroadcastMessage::Patch(_))));
+    }
+}
@@ -0,0 +1,336 @@
+//! Client-side helper that keeps a WebSocket subscription alive across drops.
+
+use futures_util::StreamExt;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::{HeaderValue, header};
+use tokio_tungstenite::tungstenite::{Error as TungsteniteError, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+use tracing::{debug, warn};
+
+use crate::ws::types::WsError;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Backoff schedule for [`ReconnectingClient`].
+///
+/// The delay before attempt `n` is `initial_backoff * multiplier^(n-1)`,
+/// capped at `max_backoff` and randomised by up to `jitter` (as a fraction)
+/// in either direction so clients dropped together don't reconnect together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectOptions {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    pub jitter: f64,
+    /// Consecutive failed attempts before giving up; `None` retries forever
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectOptions {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: Some(10),
+        }
+    }
+}
+
+impl ReconnectOptions {
+    fn delay(&self, attempt: u32) -> Duration {
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let capped = base.min(self.max_backoff.as_secs_f64());
+        let unit = rand::random_range(-1.0..1.0);
+        Duration::from_secs_f64((capped * (1.0 + self.jitter * unit)).max(0.0))
+    }
+}
+
+/// A frame delivered by [`ReconnectingClient::next`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    /// A broadcast frame; `seq` is set when the server numbers patches
+    Frame {
+        seq: Option<u64>,
+        data: serde_json::Value,
+    },
+    /// Messages were lost (the client lagged, or resumed too late); refetch state
+    Resync { skipped: u64 },
+    /// The server is shutting down; the client will reconnect once it returns
+    Shutdown,
+}
+
+/// Subscribes to a kernel's `/ws` endpoint and transparently reconnects.
+///
+/// After a drop, the client reconnects with exponential backoff and resumes
+/// after the last sequence number it saw, so no numbered frame is lost or
+/// delivered twice. This relies on the server enabling
+/// [`Broadcaster::with_replay`](crate::ws::Broadcaster::with_replay); if the
+/// server can't replay far enough back, the client gets [`ClientEvent::Resync`].
+pub struct ReconnectingClient {
+    url: String,
+    /// Sent as `Authorization: Bearer <token>` on every handshake
+    auth_token: Option<String>,
+    options: ReconnectOptions,
+    socket: Option<Socket>,
+    last_seq: Option<u64>,
+}
+
+impl ReconnectingClient {
+    /// Connects to `url` (e.g. `ws://host:9090/ws`), retrying per `options`.
+    pub async fn connect(url: impl Into<String>, options: ReconnectOptions) -> Result<Self, WsError> {
+        Self::open(url.into(), None, options).await
+    }
+
+    /// Like [`connect`](Self::connect), for a server that requires the
+    /// control plane's bearer token.
+    pub async fn connect_with_token(
+        url: impl Into<String>,
+        token: impl Into<String>,
+        options: ReconnectOptions,
+    ) -> Result<Self, WsError> {
+        Self::open(url.into(), Some(token.into()), options).await
+    }
+
+    async fn open(url: String, auth_token: Option<String>, options: ReconnectOptions) -> Result<Self, WsError> {
+        let mut client = Self {
+            url,
+            auth_token,
+            options,
+            socket: None,
+            last_seq: None,
+        };
+        client.reconnect().await?;
+        Ok(client)
+    }
+
+    /// Sequence number of the last frame delivered.
+    pub fn last_seq(&self) -> Option<u64> {
+        self.last_seq
+    }
+
+    /// Waits for the next event, reconnecting as often as needed.
+    ///
+    /// Fails only once reconnection has used up its attempts.
+    pub async fn next(&mut self) -> Result<ClientEvent, WsError> {
+        loop {
+            let Some(socket) = self.socket.as_mut() else {
+                self.reconnect().await?;
+                continue;
+            };
+
+            let text = match socket.next().await {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => {
+                    debug!(url = %self.url, "Connection closed by server");
+                    self.socket = None;
+                    continue;
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    warn!(url = %self.url, error = %e, "Connection dropped");
+                    self.socket = None;
+                    continue;
+                }
+            };
+
+            let value: serde_json::Value = serde_json::from_str(&text).map_err(WsError::Serialization)?;
+            if let Some(event) = self.accept(value) {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Turns a frame into an event, dropping numbered frames already delivered.
+    fn accept(&mut self, value: serde_json::Value) -> Option<ClientEvent> {
+        match value.get("type").and_then(|t| t.as_str()) {
+            Some("resync") => {
+                // The server may have restarted and begun numbering afresh
+                self.last_seq = None;
+                let skipped = value.get("skipped").and_then(|s| s.as_u64()).unwrap_or(0);
+                return Some(ClientEvent::Resync { skipped });
+            }
+            Some("shutdown") => return Some(ClientEvent::Shutdown),
+            // Keepalive only; carries no data
+            Some("heartbeat") => return None,
+            _ => {}
+        }
+
+        let Some(seq) = value.get("seq").and_then(|s| s.as_u64()) else {
+            return Some(ClientEvent::Frame { seq: None, data: value });
+        };
+        if self.last_seq.is_some_and(|last| seq <= last) {
+            return None;
+        }
+        self.last_seq = Some(seq);
+        let data = value.get("frame").cloned().unwrap_or_default();
+        Some(ClientEvent::Frame { seq: Some(seq), data })
+    }
+
+    fn resume_url(&self) -> String {
+        match self.last_seq {
+            Some(seq) => {
+                let separator = if self.url.contains('?') { '&' } else { '?' };
+                format!("{}{}since={}", self.url, separator, seq)
+            }
+            None => self.url.clone(),
+        }
+    }
+
+    fn handshake(&self, url: &str) -> Result<Request, TungsteniteError> {
+        let mut request = url.into_client_request()?;
+        if let Some(token) = &self.auth_token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| TungsteniteError::HttpFormat(e.into()))?;
+            request.headers_mut().insert(header::AUTHORIZATION, value);
+        }
+        Ok(request)
+    }
+
+    async fn reconnect(&mut self) -> Result<(), WsError> {
+        let mut attempt = 0;
+        loop {
+            let url = self.resume_url();
+            let connected = match self.handshake(&url) {
+                Ok(request) => connect_async(request).await,
+                Err(e) => Err(e),
+            };
+            let error = match connected {
+                Ok((socket, _)) => {
+                    debug!(url = %url, attempt, "Connected");
+                    self.socket = Some(socket);
+                    return Ok(());
+                }
+                Err(e) => e,
+            };
+
+            attempt += 1;
+            if self.options.max_attempts.is_some_and(|max| attempt >= max) {
+                return Err(WsError::ReconnectFailed {
+                    attempts: attempt,
+                    reason: error.to_string(),
+                });
+            }
+            let delay = self.options.delay(attempt);
+            warn!(url = %url, attempt, delay_ms = delay.as_millis() as u64, error = %error, "Reconnect failed, backing off");
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::handler::ws_router;
+    use crate::ws::{BroadcastMessage, Broadcaster, WsPatch};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    fn patch(n: u64) -> BroadcastMessage {
+        BroadcastMessage::Patch(WsPatch::new(
+            serde_json::from_value(serde_json::json!([
+                { "op": "replace", "path": "/n", "value": n }
+            ]))
+            .unwrap(),
+        ))
+    }
+
+    /// Forwards connections to `upstream`, keeping each relay's handle so the
+    /// test can cut it like a network failure would.
+    async fn spawn_proxy(upstream: std::net::SocketAddr) -> (std::net::SocketAddr, tokio::sync::mpsc::UnboundedReceiver<JoinHandle<()>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                let relay = tokio::spawn(async move {
+                    let mut server = TcpStream::connect(upstream).await.unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+                });
+                let _ = tx.send(relay);
+            }
+        });
+        (addr, rx)
+    }
+
+    async fn next_frame(client: &mut ReconnectingClient) -> (Option<u64>, serde_json::Value) {
+        match tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap().unwrap() {
+            ClientEvent::Frame { seq, data } => (seq, data),
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_after_last_seen_sequence_following_a_drop() {
+        let broadcaster = Broadcaster::new().with_replay(64);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let app = ws_router(broadcaster.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let (proxy_addr, mut relays) = spawn_proxy(server_addr).await;
+
+        let options = ReconnectOptions {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let mut client = ReconnectingClient::connect(format!("ws://{}/ws", proxy_addr), options)
+            .await
+            .unwrap();
+        while broadcaster.client_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        broadcaster.broadcast(patch(1)).unwrap();
+        broadcaster.broadcast(patch(2)).unwrap();
+        assert_eq!(next_frame(&mut client).await.0, Some(1));
+        assert_eq!(next_frame(&mut client).await.0, Some(2));
+
+        // Cut the connection, then publish while the client is away
+        relays.recv().await.unwrap().abort();
+        broadcaster.broadcast(patch(3)).unwrap();
+        broadcaster.broadcast(patch(4)).unwrap();
+
+        let (seq, data) = next_frame(&mut client).await;
+        assert_eq!(seq, Some(3));
+        assert_eq!(data[0]["value"], 3);
+        assert_eq!(next_frame(&mut client).await.0, Some(4));
+        assert_eq!(client.last_seq(), Some(4));
+    }
+
+    #[test]
+    fn duplicate_sequences_are_dropped_and_resync_resets() {
+        let mut client = ReconnectingClient {
+            url: "ws://localhost/ws".to_string(),
+            auth_token: None,
+            options: ReconnectOptions::default(),
+            socket: None,
+            last_seq: Some(5),
+        };
+        assert_eq!(client.resume_url(), "ws://localhost/ws?since=5");
+        assert!(client.accept(serde_json::json!({ "seq": 5, "frame": [] })).is_none());
+        assert!(client.accept(serde_json::json!({ "seq": 6, "frame": [] })).is_some());
+
+        let resync = client.accept(serde_json::json!({ "type": "resync", "skipped": 3 }));
+        assert_eq!(resync, Some(ClientEvent::Resync { skipped: 3 }));
+        assert_eq!(client.last_seq(), None);
+    }
+
+    #[test]
+    fn backoff_grows_to_the_cap_within_jitter() {
+        let options = ReconnectOptions {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+            multiplier: 2.0,
+            jitter: 0.1,
+            max_attempts: None,
+        };
+        for (attempt, expected_ms) in [(1, 100.0), (2, 200.0), (3, 400.0), (10, 1000.0)] {
+            let delay = options.delay(attempt).as_secs_f64() * 1000.0;
+            assert!((delay - expected_ms).abs() <= expected_ms * 0.1 + 0.001, "{} -> {}", attempt, delay);
+        }
+    }
+}
@@ -1,46 +1,145 @@
 //! WebSocket connection lifecycle management.
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
 use bytes::Bytes;
 use futures_util::StreamExt;
 use std::time::Duration;
-use tokio::time::interval;
+use tokio::time::{Instant, interval, sleep_until};
 use tracing::{debug, error, info, warn};
 
+use crate::infrastructure::audit::{self, AuditEvent};
 use crate::ws::broadcaster::BroadcastReceiver;
-use crate::ws::types::{BroadcastMessage, ClientId, WsError};
+use crate::ws::handler::{ClientRateLimiter, ClientRequest, InboundRateLimit};
+use crate::ws::types::{BroadcastMessage, ClientId, ClientInfo, WsError};
 
 const PING_INTERVAL: Duration = Duration::from_secs(30);
+const PONG_TIMEOUT: Duration = Duration::from_secs(10);
+/// Consecutive unanswered pings after which a client is considered dead
+const MAX_MISSED_PONGS: u32 = 2;
+
+/// Keepalive and idle-timeout behaviour of a connection.
+///
+/// Any frame from the client, including pings and pongs, counts as activity.
+/// Since clients answer the server's pings, a connection silently dropped by
+/// a load balancer stops producing pongs and is closed by the idle timeout,
+/// or sooner by the pong timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionOptions {
+    pub ping_interval: Duration,
+    /// Close after this long without activity; `None` keeps idle connections open
+    pub idle_timeout: Option<Duration>,
+    /// A ping without a pong this long after it counts as missed; two misses
+    /// in a row close the connection. `None` never reaps on missing pongs
+    pub pong_timeout: Option<Duration>,
+    /// Drops client messages beyond this rate; `None` accepts any rate
+    pub inbound_rate_limit: Option<InboundRateLimit>,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            ping_interval: PING_INTERVAL,
+            idle_timeout: None,
+            pong_timeout: Some(PONG_TIMEOUT),
+            inbound_rate_limit: Some(InboundRateLimit::default()),
+        }
+    }
+}
 
 pub struct Connection {
     client_id: ClientId,
+    info: ClientInfo,
     stream: WebSocket,
     receiver: BroadcastReceiver,
+    options: ConnectionOptions,
 }
 
 impl Connection {
-    pub fn new(stream: WebSocket, receiver: BroadcastReceiver) -> Self {
-        let client_id = ClientId::generate();
-        info!(client_id = %client_id, "WebSocket connection established");
+    pub fn new(stream: WebSocket, receiver: BroadcastReceiver, info: ClientInfo) -> Self {
+        info!(
+            client_id = %info.id,
+            subject = info.subject.as_deref().unwrap_or("anonymous"),
+            "WebSocket connection established"
+        );
+        audit::log_audit(AuditEvent::client_connected(&info));
         Self {
-            client_id,
+            client_id: info.id,
+            receiver: receiver.for_client(&info),
+            info,
             stream,
-            receiver,
+            options: ConnectionOptions::default(),
         }
     }
 
+    pub fn with_options(mut self, options: ConnectionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     pub fn client_id(&self) -> ClientId {
         self.client_id
     }
 
+    pub fn info(&self) -> &ClientInfo {
+        &self.info
+    }
+
     pub async fn run(mut self) -> Result<(), WsError> {
-        let mut ping_interval = interval(PING_INTERVAL);
+        let result = self.run_loop().await;
+        audit::log_audit(AuditEvent::client_disconnected(&self.info));
+        match result {
+            Ok(close_frame) => self.graceful_close(close_frame).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Runs until the connection ends, returning the close frame to send, if any.
+    async fn run_loop(&mut self) -> Result<Option<CloseFrame>, WsError> {
+        let mut ping_interval = interval(self.options.ping_interval);
+        let idle_timeout = self.options.idle_timeout;
+        let pong_timeout = self.options.pong_timeout;
+        let mut last_activity = Instant::now();
+        // When the oldest ping still waiting for its pong was sent
+        let mut ping_outstanding: Option<Instant> = None;
+        let mut missed_pongs = 0;
+        let mut rate_limiter = self
+            .options
+            .inbound_rate_limit
+            .map(|limit| ClientRateLimiter::new(self.client_id, limit));
 
         loop {
+            let idle_deadline = idle_timeout.map(|timeout| last_activity + timeout);
+            let pong_deadline = pong_timeout.zip(ping_outstanding).map(|(timeout, sent)| sent + timeout);
+
             tokio::select! {
                 incoming = self.stream.next() => {
+                    last_activity = Instant::now();
                     match incoming {
                         Some(Ok(msg)) => {
+                            if matches!(msg, Message::Pong(_)) {
+                                ping_outstanding = None;
+                                missed_pongs = 0;
+                            }
+                            // Pongs answer our own pings and closes must always get through
+                            if let Some(limiter) = rate_limiter.as_mut()
+                                && !matches!(msg, Message::Pong(_) | Message::Close(_))
+                                && let Err(e) = limiter.check()
+                            {
+                                debug!(client_id = %self.client_id, error = %e, "Dropped inbound message");
+                                if limiter.is_abusive() {
+                                    warn!(
+                                        client_id = %self.client_id,
+                                        dropped = limiter.violations(),
+                                        "Closing connection for exceeding inbound rate limit"
+                                    );
+                                    audit::log_audit(AuditEvent::client_rate_limited(&self.info, limiter.violations()));
+                                    return Ok(Some(CloseFrame {
+                                        code: close_code::POLICY,
+                                        reason: "rate limit exceeded".into(),
+                                    }));
+                                }
+                                continue;
+                            }
                             if self.handle_incoming_message(msg).await? {
                                 break;
                             }
@@ -65,6 +164,17 @@ impl Connection {
                             info!(client_id = %self.client_id, "Broadcast channel closed");
                             break;
                         }
+                        Err(WsError::Lagged { skipped }) => {
+                            // Patches were lost; hand the client the full state if we can
+                            match self.receiver.snapshot().await {
+                                Some(Ok(snapshot)) => self.send_broadcast_message(snapshot).await?,
+                                Some(Err(e)) => {
+                                    warn!(client_id = %self.client_id, error = %e, "Snapshot for lagged client failed");
+                                    self.send_resync(skipped).await?;
+                                }
+                                None => self.send_resync(skipped).await?,
+                            }
+                        }
                         Err(e) => {
                             warn!(client_id = %self.client_id, error = %e, "Broadcast error");
                         }
@@ -73,18 +183,50 @@ impl Connection {
 
                 _ = ping_interval.tick() => {
                     self.send_ping().await?;
+                    ping_outstanding.get_or_insert_with(Instant::now);
+                }
+
+                _ = sleep_until(pong_deadline.unwrap_or_else(Instant::now)), if pong_deadline.is_some() => {
+                    ping_outstanding = None;
+                    missed_pongs += 1;
+                    warn!(client_id = %self.client_id, missed_pongs, "Ping went unanswered");
+                    if missed_pongs >= MAX_MISSED_PONGS {
+                        info!(client_id = %self.client_id, "Closing unresponsive connection");
+                        return Ok(Some(CloseFrame {
+                            code: close_code::AWAY,
+                            reason: "pong timeout".into(),
+                        }));
+                    }
+                }
+
+                _ = sleep_until(idle_deadline.unwrap_or_else(Instant::now)), if idle_deadline.is_some() => {
+                    info!(
+                        client_id = %self.client_id,
+                        idle_secs = last_activity.elapsed().as_secs(),
+                        "Closing idle connection"
+                    );
+                    return Ok(Some(CloseFrame {
+                        code: close_code::AWAY,
+                        reason: "idle timeout".into(),
+                    }));
                 }
             }
         }
 
-        self.graceful_close().await
+        Ok(None)
     }
 
     async fn handle_incoming_message(&mut self, message: Message) -> Result<bool, WsError> {
         match message {
             Message::Text(text) => {
                 debug!(client_id = %self.client_id, len = text.len(), "Received text");
-                warn!(client_id = %self.client_id, "Unexpected text from client");
+                match serde_json::from_str::<ClientRequest>(&text) {
+                    Ok(request) => {
+                        debug!(client_id = %self.client_id, ?request, "Client request");
+                        request.apply(&self.receiver);
+                    }
+                    Err(e) => warn!(client_id = %self.client_id, error = %e, "Unexpected text from client"),
+                }
                 Ok(false)
             }
             Message::Binary(data) => {
@@ -113,7 +255,7 @@ impl Connection {
 
     async fn send_broadcast_message(&mut self, message: BroadcastMessage) -> Result<(), WsError> {
         let should_close = matches!(message, BroadcastMessage::Shutdown);
-        let payload = message.to_frame_payload()?;
+        let payload = message.to_frame_payload_with(self.receiver.serialization())?;
 
         self.stream
             .send(Message::Text(payload.into()))
@@ -127,6 +269,14 @@ impl Connection {
         Ok(())
     }
 
+    async fn send_resync(&mut self, skipped: u64) -> Result<(), WsError> {
+        let payload = format!(r#"{{"type":"resync","skipped":{}}}"#, skipped);
+        self.stream
+            .send(Message::Text(payload.into()))
+            .await
+            .map_err(WsError::AxumWs)
+    }
+
     async fn send_ping(&mut self) -> Result<(), WsError> {
         debug!(client_id = %self.client_id, "Sending ping");
         self.stream
@@ -135,13 +285,18 @@ impl Connection {
             .map_err(WsError::AxumWs)
     }
 
-    async fn graceful_close(mut self) -> Result<(), WsError> {
+    async fn graceful_close(mut self, close_frame: Option<CloseFrame>) -> Result<(), WsError> {
         debug!(client_id = %self.client_id, "Closing gracefully");
+        let reason = close_frame.as_ref().map(|f| f.reason.to_string());
         self.stream
-            .send(Message::Close(None))
+            .send(Message::Close(close_frame))
             .await
             .map_err(WsError::AxumWs)?;
-        info!(client_id = %self.client_id, "Connection closed");
+        info!(
+            client_id = %self.client_id,
+            reason = reason.as_deref().unwrap_or("normal"),
+            "Connection closed"
+        );
         Ok(())
     }
 }
@@ -156,4 +311,223 @@ mod tests {
         let id2 = ClientId::generate();
         assert_ne!(id1, id2);
     }
+
+    #[tokio::test]
+    async fn idle_connection_is_closed_after_timeout() {
+        use crate::ws::Broadcaster;
+        use crate::ws::handler::ws_router;
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let broadcaster = Broadcaster::new().with_connection_options(ConnectionOptions {
+            ping_interval: Duration::from_secs(60),
+            idle_timeout: Some(Duration::from_millis(200)),
+            pong_timeout: None,
+            inbound_rate_limit: None,
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, ws_router(broadcaster)).await.unwrap();
+        });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+        let start = std::time::Instant::now();
+
+        // Skip the initial keepalive ping; the client answers it automatically
+        let close = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match client.next().await.unwrap().unwrap() {
+                    ClientMessage::Close(frame) => break frame.expect("Close frame has no reason"),
+                    ClientMessage::Ping(_) => continue,
+                    other => panic!("Unexpected frame: {:?}", other),
+                }
+            }
+        })
+        .await
+        .expect("Idle connection was not closed");
+        assert_eq!(close.reason.as_str(), "idle timeout");
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn client_that_stops_answering_pings_is_reaped() {
+        use crate::ws::Broadcaster;
+        use crate::ws::handler::ws_router;
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let broadcaster = Broadcaster::new().with_connection_options(ConnectionOptions {
+            ping_interval: Duration::from_millis(50),
+            idle_timeout: None,
+            pong_timeout: Some(Duration::from_millis(50)),
+            inbound_rate_limit: None,
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = ws_router(broadcaster.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while broadcaster.client_count() == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("Client never subscribed");
+
+        // Not reading the socket means the client never answers the pings
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while broadcaster.client_count() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Unresponsive client was not reaped");
+
+        // Reading now answers the queued pings, which may hit the closed socket first
+        let close = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match client.next().await {
+                    Some(Ok(ClientMessage::Close(frame))) => break frame,
+                    Some(Ok(ClientMessage::Ping(_))) => continue,
+                    Some(Ok(other)) => panic!("Unexpected frame: {:?}", other),
+                    Some(Err(_)) | None => break None,
+                }
+            }
+        })
+        .await
+        .expect("Connection was not closed");
+        if let Some(close) = close {
+            assert_eq!(close.reason.as_str(), "pong timeout");
+        }
+    }
+
+    #[tokio::test]
+    async fn client_chooses_its_topics_over_the_socket() {
+        use crate::ws::Broadcaster;
+        use crate::ws::handler::ws_router;
+        use crate::ws::types::WsPatch;
+        use futures_util::SinkExt;
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let broadcaster = Broadcaster::new().with_connection_options(ConnectionOptions {
+            ping_interval: Duration::from_secs(60),
+            idle_timeout: None,
+            pong_timeout: None,
+            inbound_rate_limit: None,
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = ws_router(broadcaster.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+        let patch = |value: &str| {
+            WsPatch::from_value(serde_json::json!([{ "op": "replace", "path": "/status", "value": value }]))
+                .unwrap()
+        };
+
+        for (requests, expected) in [
+            (vec![r#"{"type":"subscribe","topic":"a"}"#], "on a"),
+            (
+                vec![r#"{"type":"unsubscribe","topic":"a"}"#, r#"{"type":"subscribe","topic":"b"}"#],
+                "on b",
+            ),
+        ] {
+            for request in requests {
+                client.send(ClientMessage::Text(request.into())).await.unwrap();
+            }
+            // Frames are handled in order, so the pong means the requests were applied
+            client.send(ClientMessage::Ping(Bytes::new())).await.unwrap();
+            loop {
+                match client.next().await.unwrap().unwrap() {
+                    ClientMessage::Pong(_) => break,
+                    ClientMessage::Ping(_) => continue,
+                    other => panic!("Unexpected frame: {:?}", other),
+                }
+            }
+
+            broadcaster.publish("a", patch("on a")).unwrap();
+            broadcaster.publish("b", patch("on b")).unwrap();
+            let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("No patch delivered")
+                .unwrap()
+                .unwrap();
+            let ClientMessage::Text(text) = frame else {
+                panic!("Expected a patch, got {:?}", frame);
+            };
+            assert!(text.contains(expected), "{} should carry {}", text, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn flooding_client_is_throttled_then_disconnected() {
+        use crate::ws::Broadcaster;
+        use crate::ws::handler::ws_router;
+        use futures_util::SinkExt;
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let broadcaster = Broadcaster::new().with_connection_options(ConnectionOptions {
+            ping_interval: Duration::from_secs(60),
+            idle_timeout: None,
+            pong_timeout: None,
+            inbound_rate_limit: Some(InboundRateLimit {
+                messages_per_sec: 1,
+                burst: 5,
+                max_violations: 10,
+            }),
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, ws_router(broadcaster)).await.unwrap();
+        });
+        let mut audit_events = audit::subscribe_audit();
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+        // Far more than the burst plus the violations allowed on top of it
+        for i in 0..50 {
+            if client.send(ClientMessage::Text(format!("msg {}", i).into())).await.is_err() {
+                break;
+            }
+        }
+
+        let close = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match client.next().await {
+                    Some(Ok(ClientMessage::Close(frame))) => break frame,
+                    Some(Ok(ClientMessage::Ping(_))) => continue,
+                    Some(Ok(other)) => panic!("Unexpected frame: {:?}", other),
+                    Some(Err(_)) | None => break None,
+                }
+            }
+        })
+        .await
+        .expect("Flooding client was not disconnected");
+        assert_eq!(close.expect("No close frame").reason.as_str(), "rate limit exceeded");
+
+        let dropped = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(AuditEvent::ClientRateLimited { dropped_messages, .. }) = audit_events.recv().await {
+                    break dropped_messages;
+                }
+            }
+        })
+        .await
+        .expect("No rate limit audit event");
+        assert_eq!(dropped, 10);
+    }
 }
@@ -1,22 +1,81 @@
 //! WebSocket upgrade handler.
 
 use axum::{
-    extract::{State, ws::WebSocketUpgrade},
-    response::Response,
+    Extension,
+    extract::{ConnectInfo, Query, State, ws::WebSocketUpgrade},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    serve::{Listener, ListenerExt, TapIo},
 };
-use tracing::info;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{Instant, Sleep};
+use tracing::{info, warn};
 
-use crate::ws::broadcaster::Broadcaster;
+use crate::ws::broadcaster::{BroadcastReceiver, Broadcaster};
 use crate::ws::connection::Connection;
+use crate::ws::types::{AuthenticatedSubject, ClientId, ClientInfo, WsError};
+
+/// Query parameters accepted on the upgrade request.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct UpgradeParams {
+    /// Last sequence number the client saw, to resume from the replay log
+    pub since: Option<u64>,
+}
+
+/// Control messages a client sends as JSON text frames.
+#[derive(Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientRequest {
+    /// `{"type":"subscribe","topic":"..."}`
+    Subscribe { topic: String },
+    /// `{"type":"unsubscribe","topic":"..."}`
+    Unsubscribe { topic: String },
+}
+
+impl ClientRequest {
+    /// Applies the request to the client's receiver.
+    pub fn apply(self, receiver: &BroadcastReceiver) {
+        match self {
+            Self::Subscribe { topic } => receiver.subscribe_topic(&topic),
+            Self::Unsubscribe { topic } => receiver.unsubscribe_topic(&topic),
+        }
+    }
+}
 
 pub async fn handle_ws_upgrade(
     ws: WebSocketUpgrade,
     State(broadcaster): State<Broadcaster>,
+    Query(params): Query<UpgradeParams>,
+    subject: Option<Extension<AuthenticatedSubject>>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
 ) -> Response {
-    info!("WebSocket upgrade requested");
+    info!(since = params.since, "WebSocket upgrade requested");
+    let client_info = ClientInfo::new(
+        subject.map(|Extension(AuthenticatedSubject(s))| s),
+        connect_info.map(|Extension(ConnectInfo(addr))| addr),
+    );
+    // Subscribed before upgrading so a full server can refuse with a plain HTTP error
+    let receiver = match params.since {
+        Some(seq) => broadcaster.subscribe_since(seq),
+        None => broadcaster.subscribe(),
+    };
+    let receiver = match receiver {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            warn!(error = %e, "Refusing WebSocket upgrade");
+            return (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response();
+        }
+    };
     ws.on_upgrade(move |socket| async move {
-        let receiver = broadcaster.subscribe();
-        let connection = Connection::new(socket, receiver);
+        let connection = Connection::new(socket, receiver, client_info)
+            .with_options(broadcaster.connection_options());
 
         if let Err(e) = connection.run().await {
             tracing::error!(error = %e, "WebSocket connection error");
@@ -30,9 +89,364 @@ pub fn ws_router(broadcaster: Broadcaster) -> axum::Router {
         .with_state(broadcaster)
 }
 
+/// Rate of inbound messages each client may send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InboundRateLimit {
+    pub messages_per_sec: u32,
+    /// Messages a client may send back to back before being throttled
+    pub burst: u32,
+    /// Dropped messages, less one forgiven per [`VIOLATION_DECAY`], after
+    /// which the connection is closed; 0 never closes
+    pub max_violations: u32,
+}
+
+impl Default for InboundRateLimit {
+    fn default() -> Self {
+        Self {
+            messages_per_sec: 20,
+            burst: 40,
+            max_violations: 100,
+        }
+    }
+}
+
+/// How long a client must go without another dropped message for one
+/// violation to be forgiven
+pub const VIOLATION_DECAY: Duration = Duration::from_secs(1);
+
+/// Token bucket over one client's inbound messages.
+///
+/// Messages over the limit are dropped rather than delayed, so a flooding
+/// client can't back up the connection's broadcast delivery.
+#[derive(Debug)]
+pub struct ClientRateLimiter {
+    client_id: ClientId,
+    limit: InboundRateLimit,
+    available: f64,
+    last_refill: Instant,
+    /// Dropped messages not yet forgiven
+    violations: u32,
+    /// When the next violation starts being forgiven
+    last_forgiven: Instant,
+}
+
+impl ClientRateLimiter {
+    pub fn new(client_id: ClientId, limit: InboundRateLimit) -> Self {
+        Self {
+            client_id,
+            limit,
+            available: f64::from(limit.burst.max(1)),
+            last_refill: Instant::now(),
+            violations: 0,
+            last_forgiven: Instant::now(),
+        }
+    }
+
+    /// Admits one message, or fails with [`WsError::RateLimited`] if it should be dropped.
+    pub fn check(&mut self) -> Result<(), WsError> {
+        let now = Instant::now();
+        let rate = f64::from(self.limit.messages_per_sec.max(1));
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.available = (self.available + elapsed * rate).min(f64::from(self.limit.burst.max(1)));
+        self.last_refill = now;
+        self.forgive(now);
+
+        if self.available >= 1.0 {
+            self.available -= 1.0;
+            return Ok(());
+        }
+        self.violations += 1;
+        Err(WsError::RateLimited {
+            client_id: self.client_id,
+            retry_after: Duration::from_secs_f64((1.0 - self.available) / rate),
+        })
+    }
+
+    /// Forgives one violation per [`VIOLATION_DECAY`] passed, so a client
+    /// that only now and then overruns its limit isn't disconnected, while
+    /// admitting a message between drops doesn't clear a steady flood.
+    fn forgive(&mut self, now: Instant) {
+        if self.violations == 0 {
+            self.last_forgiven = now;
+            return;
+        }
+        let elapsed = now.saturating_duration_since(self.last_forgiven);
+        let forgiven = u32::try_from(elapsed.as_nanos() / VIOLATION_DECAY.as_nanos()).unwrap_or(u32::MAX);
+        self.violations = self.violations.saturating_sub(forgiven);
+        self.last_forgiven = if self.violations == 0 {
+            now
+        } else {
+            self.last_forgiven + VIOLATION_DECAY * forgiven
+        };
+    }
+
+    /// Dropped messages not yet forgiven.
+    pub fn violations(&self) -> u32 {
+        self.violations
+    }
+
+    /// Whether the client has kept flooding long enough to be disconnected.
+    pub fn is_abusive(&self) -> bool {
+        self.limit.max_violations > 0 && self.violations >= self.limit.max_violations
+    }
+}
+
+/// Default time a client has to send its complete upgrade request
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Marks the end of an HTTP request head
+const HEAD_TERMINATOR: &[u8] = b"\r\n\r\n";
+
+/// Listener whose connections are closed if the request head doesn't arrive in time.
+///
+/// axum reads request headers without a deadline, so a client that opens a
+/// socket and trickles (or never sends) its upgrade request would hold the
+/// connection forever.
+pub struct HandshakeTimeoutListener {
+    inner: TcpListener,
+    timeout: Duration,
+}
+
+/// Wraps `listener` so stalled handshakes are dropped after `timeout`.
+///
+/// Returned as a `TapIo` (with a no-op tap) because that is how axum provides
+/// `ConnectInfo<SocketAddr>` for listeners other than `TcpListener`.
+pub fn with_handshake_timeout(
+    listener: TcpListener,
+    timeout: Duration,
+) -> TapIo<HandshakeTimeoutListener, fn(&mut HandshakeStream)> {
+    HandshakeTimeoutListener {
+        inner: listener,
+        timeout,
+    }
+    .tap_io(|_| {})
+}
+
+impl Listener for HandshakeTimeoutListener {
+    type Io = HandshakeStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let (io, addr) = Listener::accept(&mut self.inner).await;
+        (HandshakeStream::new(io, addr, self.timeout), addr)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Listener::local_addr(&self.inner)
+    }
+}
+
+/// TCP stream that fails its reads once the handshake deadline passes.
+///
+/// The deadline is disarmed as soon as a full request head has been read;
+/// after that the connection behaves like a plain `TcpStream`.
+pub struct HandshakeStream {
+    inner: TcpStream,
+    peer: SocketAddr,
+    deadline: Option<Pin<Box<Sleep>>>,
+    /// Trailing bytes of the last read, for terminators split across reads
+    tail: Vec<u8>,
+}
+
+impl HandshakeStream {
+    fn new(inner: TcpStream, peer: SocketAddr, timeout: Duration) -> Self {
+        Self {
+            inner,
+            peer,
+            deadline: Some(Box::pin(tokio::time::sleep(timeout))),
+            tail: Vec::new(),
+        }
+    }
+
+    fn observe(&mut self, bytes: &[u8]) {
+        let mut window = std::mem::take(&mut self.tail);
+        window.extend_from_slice(bytes);
+        if window.windows(HEAD_TERMINATOR.len()).any(|w| w == HEAD_TERMINATOR) {
+            self.deadline = None;
+            return;
+        }
+        let keep = window.len().min(HEAD_TERMINATOR.len() - 1);
+        self.tail = window.split_off(window.len() - keep);
+    }
+}
+
+impl AsyncRead for HandshakeStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(deadline) = this.deadline.as_mut()
+            && deadline.as_mut().poll(cx).is_ready()
+        {
+            warn!(remote_addr = %this.peer, "WebSocket handshake timed out, closing connection");
+            this.deadline = None;
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "handshake timed out",
+            )));
+        }
+
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if this.deadline.is_some() && matches!(result, Poll::Ready(Ok(()))) {
+            this.observe(&buf.filled()[filled..]);
+        }
+        result
+    }
+}
+
+impl AsyncWrite for HandshakeStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn serve_with_timeout(timeout: Duration) -> (Broadcaster, SocketAddr) {
+        let broadcaster = Broadcaster::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = ws_router(broadcaster.clone());
+        tokio::spawn(async move {
+            axum::serve(
+                with_handshake_timeout(listener, timeout),
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+        (broadcaster, addr)
+    }
+
+    #[tokio::test]
+    async fn stalled_handshake_is_closed_after_timeout() {
+        let (_broadcaster, addr) = serve_with_timeout(Duration::from_millis(200)).await;
+
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        // An incomplete request head, never finished
+        socket
+            .write_all(b"GET /ws HTTP/1.1\r\nHost: localhost\r\n")
+            .await
+            .unwrap();
+        let start = std::time::Instant::now();
+
+        let mut buf = [0u8; 64];
+        let read = tokio::time::timeout(Duration::from_secs(5), socket.read(&mut buf))
+            .await
+            .expect("Stalled handshake was not closed");
+        assert!(matches!(read, Ok(0) | Err(_)));
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn completed_handshake_outlives_timeout() {
+        let (broadcaster, addr) = serve_with_timeout(Duration::from_millis(100)).await;
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        broadcaster
+            .broadcast(crate::ws::BroadcastMessage::Patch(crate::ws::WsPatch::new(
+                json_patch::Patch(Vec::new()),
+            )))
+            .unwrap();
+        let frame = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match client.next().await.unwrap().unwrap() {
+                    tokio_tungstenite::tungstenite::Message::Ping(_) => continue,
+                    other => break other,
+                }
+            }
+        })
+        .await
+        .expect("Connection did not survive the handshake timeout");
+        assert!(matches!(frame, tokio_tungstenite::tungstenite::Message::Text(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limiter_throttles_past_burst_and_recovers() {
+        let client_id = ClientId::generate();
+        let mut limiter = ClientRateLimiter::new(
+            client_id,
+            InboundRateLimit {
+                messages_per_sec: 10,
+                burst: 3,
+                max_violations: 2,
+            },
+        );
+
+        for _ in 0..3 {
+            limiter.check().unwrap();
+        }
+        assert!(matches!(
+            limiter.check(),
+            Err(WsError::RateLimited { client_id: id, .. }) if id == client_id
+        ));
+        assert!(!limiter.is_abusive());
+        assert!(limiter.check().is_err());
+        assert!(limiter.is_abusive());
+
+        tokio::time::advance(Duration::from_millis(150)).await;
+        limiter.check().unwrap();
+        assert_eq!(limiter.violations(), 2, "admitting a message forgives nothing");
+
+        tokio::time::advance(VIOLATION_DECAY * 2).await;
+        limiter.check().unwrap();
+        assert_eq!(limiter.violations(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limiter_catches_flood_between_admitted_messages() {
+        let mut limiter = ClientRateLimiter::new(
+            ClientId::generate(),
+            InboundRateLimit {
+                messages_per_sec: 10,
+                burst: 1,
+                max_violations: 5,
+            },
+        );
+
+        // Each token admits one message and the rest of the burst is dropped
+        for _ in 0..5 {
+            limiter.check().unwrap();
+            assert!(limiter.check().is_err());
+            tokio::time::advance(Duration::from_millis(100)).await;
+        }
+        assert!(limiter.is_abusive());
+    }
 
     #[test]
     fn ws_router_creates_valid_router() {
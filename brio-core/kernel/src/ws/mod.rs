@@ -1,9 +1,23 @@
 //! WebSocket module for JSON Patch broadcasting.
 
 pub mod broadcaster;
+pub mod buffer;
+pub mod client;
 pub mod connection;
 pub mod handler;
+pub mod replay;
+pub mod snapshot;
+pub mod stream;
 pub mod types;
 
-pub use broadcaster::Broadcaster;
-pub use types::{BroadcastMessage, ClientId, WsError, WsPatch};
+pub use broadcaster::{Broadcaster, ClientLag};
+pub use buffer::{OverflowPolicy, WriteAheadOptions};
+pub use client::{ClientEvent, ReconnectOptions, ReconnectingClient};
+pub use connection::ConnectionOptions;
+pub use handler::{ClientRequest, InboundRateLimit, VIOLATION_DECAY};
+pub use snapshot::SnapshotSource;
+pub use stream::CoalesceOptions;
+pub use types::{
+    AuthenticatedSubject, BroadcastMessage, ClientId, ClientInfo, SerializationOptions, WsError,
+    WsPatch,
+};
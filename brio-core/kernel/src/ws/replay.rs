@@ -0,0 +1,100 @@
+//! Sequence-numbered history of recent broadcasts, for resuming clients.
+
+use std::collections::VecDeque;
+
+use crate::ws::types::BroadcastMessage;
+
+/// Bounded log of the most recent sequenced messages.
+///
+/// Sequence numbers start at 1 and increase by one per message, so a client
+/// that remembers the last one it saw can ask for exactly what it missed.
+#[derive(Debug)]
+pub(crate) struct ReplayLog {
+    capacity: usize,
+    next_seq: u64,
+    entries: VecDeque<BroadcastMessage>,
+}
+
+impl ReplayLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            next_seq: 1,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Numbers `message` and records it, evicting the oldest entry when full.
+    pub(crate) fn append(&mut self, message: BroadcastMessage) -> BroadcastMessage {
+        let sequenced = BroadcastMessage::Sequenced {
+            seq: self.next_seq,
+            message: Box::new(message),
+        };
+        self.next_seq += 1;
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(sequenced.clone());
+        sequenced
+    }
+
+    fn oldest_seq(&self) -> u64 {
+        self.next_seq - self.entries.len() as u64
+    }
+
+    /// Messages after `seq`, or the number of messages that can no longer be
+    /// replayed if the log has moved past it.
+    ///
+    /// A `seq` ahead of the log (e.g. from before a server restart) also
+    /// fails, with nothing missing, so the client knows to resync.
+    pub(crate) fn since(&self, seq: u64) -> Result<Vec<BroadcastMessage>, u64> {
+        if seq >= self.next_seq {
+            return Err(0);
+        }
+        if seq + 1 < self.oldest_seq() {
+            return Err(self.oldest_seq() - seq - 1);
+        }
+        let skip = (seq + 1 - self.oldest_seq()) as usize;
+        Ok(self.entries.iter().skip(skip).cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seqs(messages: &[BroadcastMessage]) -> Vec<u64> {
+        messages
+            .iter()
+            .map(|m| match m {
+                BroadcastMessage::Sequenced { seq, .. } => *seq,
+                other => panic!("Unsequenced message: {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replays_messages_after_sequence() {
+        let mut log = ReplayLog::new(8);
+        for _ in 0..5 {
+            log.append(BroadcastMessage::Shutdown);
+        }
+
+        assert_eq!(seqs(&log.since(2).unwrap()), vec![3, 4, 5]);
+        assert_eq!(seqs(&log.since(0).unwrap()), vec![1, 2, 3, 4, 5]);
+        assert!(log.since(5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_evicted_and_unknown_sequences() {
+        let mut log = ReplayLog::new(3);
+        for _ in 0..6 {
+            log.append(BroadcastMessage::Shutdown);
+        }
+
+        // Entries 1..=3 were evicted; resuming after 1 misses 2 and 3
+        assert_eq!(log.since(1).unwrap_err(), 2);
+        assert_eq!(seqs(&log.since(3).unwrap()), vec![4, 5, 6]);
+        assert_eq!(log.since(42).unwrap_err(), 0);
+    }
+}
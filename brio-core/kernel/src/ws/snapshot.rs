@@ -0,0 +1,15 @@
+//! Full-state snapshots for clients that fell too far behind to catch up.
+
+use async_trait::async_trait;
+
+use crate::ws::types::WsError;
+
+/// Produces the current state that broadcast patches apply to.
+///
+/// A receiver that lagged has missed patches for good; rather than
+/// disconnecting, its connection sends a [`BroadcastMessage::Snapshot`](crate::ws::BroadcastMessage::Snapshot)
+/// built from this so the client can replace its state and carry on.
+#[async_trait]
+pub trait SnapshotSource: Send + Sync {
+    async fn snapshot(&self) -> Result<serde_json::Value, WsError>;
+}
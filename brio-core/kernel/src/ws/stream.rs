@@ -0,0 +1,214 @@
+//! Bridge from token streams (e.g. LLM output) to WebSocket broadcasts.
+
+use futures_util::{Stream, StreamExt};
+use std::time::Duration;
+use tokio::time::{Instant, timeout_at};
+
+use crate::ws::broadcaster::Broadcaster;
+use crate::ws::types::{BroadcastMessage, WsError, WsPatch};
+
+/// How tokens are batched into frames before broadcasting.
+///
+/// A chunk is sent once `window` has passed since its first token or it
+/// reaches `max_bytes`, whichever comes first. The tail is flushed as soon
+/// as the stream ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoalesceOptions {
+    pub window: Duration,
+    pub max_bytes: usize,
+}
+
+impl Default for CoalesceOptions {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(50),
+            max_bytes: 4096,
+        }
+    }
+}
+
+/// Merges consecutive tokens into larger chunks according to `options`.
+pub fn coalesce<S>(tokens: S, options: CoalesceOptions) -> impl Stream<Item = String>
+where
+    S: Stream<Item = String> + Unpin,
+{
+    futures_util::stream::unfold((tokens, false), move |(mut tokens, ended)| async move {
+        if ended {
+            return None;
+        }
+
+        // The window starts at the first token, so idle gaps don't delay it
+        let mut chunk = tokens.next().await?;
+        let deadline = Instant::now() + options.window;
+        let mut ended = false;
+
+        while chunk.len() < options.max_bytes {
+            match timeout_at(deadline, tokens.next()).await {
+                Ok(Some(token)) => chunk.push_str(&token),
+                Ok(None) => {
+                    ended = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        Some((chunk, (tokens, ended)))
+    })
+}
+
+/// Builds the patch appending one chunk to the stream's array at `/streams/{stream_id}`.
+fn chunk_patch(stream_id: &str, chunk: String) -> Result<WsPatch, WsError> {
+    let path = format!("/streams/{}/-", stream_id.replace('~', "~0").replace('/', "~1"));
+    let patch = serde_json::from_value(serde_json::json!([
+        { "op": "add", "path": path, "value": chunk }
+    ]))
+    .map_err(WsError::Serialization)?;
+    Ok(WsPatch::new(patch))
+}
+
+impl Broadcaster {
+    /// Broadcasts a token stream, one patch per chunk.
+    ///
+    /// Tokens are coalesced when the broadcaster was configured with
+    /// [`Broadcaster::with_stream_coalescing`], otherwise each token is sent
+    /// as its own frame. With [`Broadcaster::with_stream_heartbeat`], a
+    /// [`BroadcastMessage::Heartbeat`] is sent each time the stream stays
+    /// quiet for the interval, e.g. while the model thinks before its first
+    /// token. Returns the number of chunk frames broadcast, heartbeats excluded.
+    pub async fn forward_stream<S>(&self, stream_id: &str, tokens: S) -> Result<usize, WsError>
+    where
+        S: Stream<Item = String> + Unpin,
+    {
+        let mut chunks = match self.stream_coalescing() {
+            Some(options) => coalesce(tokens, options).boxed_local(),
+            None => tokens.boxed_local(),
+        };
+
+        let mut frames = 0;
+        loop {
+            let next = match self.stream_heartbeat() {
+                Some(interval) => match tokio::time::timeout(interval, chunks.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        self.broadcast(BroadcastMessage::Heartbeat {
+                            stream_id: stream_id.to_string(),
+                        })?;
+                        continue;
+                    }
+                },
+                None => chunks.next().await,
+            };
+            let Some(chunk) = next else {
+                break;
+            };
+            self.broadcast(BroadcastMessage::Patch(chunk_patch(stream_id, chunk)?))?;
+            frames += 1;
+        }
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Emits each token after `gap`, like a provider producing tokens over time.
+    fn timed_tokens(tokens: &[&str], gap: Duration) -> impl Stream<Item = String> + Unpin {
+        let tokens: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+        Box::pin(futures_util::stream::iter(tokens).then(move |t| async move {
+            tokio::time::sleep(gap).await;
+            t
+        }))
+    }
+
+    #[tokio::test]
+    async fn coalescing_preserves_output_and_flushes_tail() {
+        let tokens = ["Hel", "lo", ", ", "wor", "ld", "!"];
+        let options = CoalesceOptions {
+            window: Duration::from_secs(2),
+            max_bytes: 1024,
+        };
+
+        let start = Instant::now();
+        let chunks: Vec<String> = coalesce(timed_tokens(&tokens, Duration::from_millis(5)), options)
+            .collect()
+            .await;
+
+        assert_eq!(chunks.concat(), "Hello, world!");
+        assert!(chunks.len() < tokens.len());
+        // Tail is flushed when the stream ends, not when its window would close
+        assert!(start.elapsed() < options.window);
+    }
+
+    #[tokio::test]
+    async fn coalescing_respects_byte_threshold() {
+        let tokens = futures_util::stream::iter(["ab", "cd", "ef", "gh", "ij"].map(String::from));
+        let options = CoalesceOptions {
+            window: Duration::from_secs(60),
+            max_bytes: 4,
+        };
+
+        let chunks: Vec<String> = coalesce(tokens, options).collect().await;
+        assert_eq!(chunks, ["abcd", "efgh", "ij"]);
+    }
+
+    #[tokio::test]
+    async fn forward_stream_broadcasts_coalesced_frames() {
+        let broadcaster = Broadcaster::new().with_stream_coalescing(Some(CoalesceOptions {
+            window: Duration::from_secs(60),
+            max_bytes: 1024,
+        }));
+        let mut rx = broadcaster.subscribe().unwrap();
+
+        let tokens = futures_util::stream::iter(["a", "b", "c"].map(String::from));
+        let frames = broadcaster.forward_stream("chat/1", tokens).await.unwrap();
+        assert_eq!(frames, 1);
+
+        let BroadcastMessage::Patch(patch) = rx.recv().await.unwrap() else {
+            panic!("Expected patch");
+        };
+        assert_eq!(
+            patch.to_json().unwrap(),
+            r#"[{"op":"add","path":"/streams/chat~11/-","value":"abc"}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_stream_without_coalescing_sends_each_token() {
+        let broadcaster = Broadcaster::new();
+        let _rx = broadcaster.subscribe().unwrap();
+
+        let tokens = futures_util::stream::iter(["a", "b", "c"].map(String::from));
+        assert_eq!(broadcaster.forward_stream("s", tokens).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn forward_stream_sends_heartbeats_before_first_token() {
+        let broadcaster = Broadcaster::new().with_stream_heartbeat(Some(Duration::from_millis(40)));
+        let mut rx = broadcaster.subscribe().unwrap();
+
+        let tokens = timed_tokens(&["Hi", "!"], Duration::from_millis(200));
+        let frames = broadcaster.forward_stream("s", tokens).await.unwrap();
+        assert_eq!(frames, 2);
+
+        let mut heartbeats_before_first = 0;
+        let mut chunks = Vec::new();
+        while chunks.len() < 2 {
+            match rx.recv().await.unwrap() {
+                BroadcastMessage::Heartbeat { stream_id } => {
+                    assert_eq!(stream_id, "s");
+                    if chunks.is_empty() {
+                        heartbeats_before_first += 1;
+                    }
+                }
+                BroadcastMessage::Patch(patch) => chunks.push(patch.to_json().unwrap()),
+                other => panic!("Unexpected message: {:?}", other),
+            }
+        }
+        assert!(heartbeats_before_first >= 2, "only {} heartbeats", heartbeats_before_first);
+        // Chunk frames are unchanged by the heartbeats around them
+        assert_eq!(chunks[0], r#"[{"op":"add","path":"/streams/s/-","value":"Hi"}]"#);
+        assert_eq!(chunks[1], r#"[{"op":"add","path":"/streams/s/-","value":"!"}]"#);
+    }
+}
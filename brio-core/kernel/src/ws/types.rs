@@ -1,6 +1,8 @@
 //! Domain types for WebSocket broadcasting.
 
 use std::fmt;
+use std::net::SocketAddr;
+use std::time::SystemTime;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -23,36 +25,240 @@ impl fmt::Display for ClientId {
     }
 }
 
+/// Identity established by whatever authenticated the WebSocket handshake.
+///
+/// Auth layers insert this into the upgrade request's extensions; the
+/// handler copies it into the connection's [`ClientInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedSubject(pub String);
+
+/// Provenance of a connected client, captured once at connection time.
+///
+/// Kept alongside the [`ClientId`] rather than inside it so the id stays
+/// `Copy` and cheap to compare.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub id: ClientId,
+    /// Authenticated subject, if the handshake carried one
+    pub subject: Option<String>,
+    pub connected_at: SystemTime,
+    pub remote_addr: Option<SocketAddr>,
+}
+
+impl ClientInfo {
+    pub fn new(subject: Option<String>, remote_addr: Option<SocketAddr>) -> Self {
+        Self {
+            id: ClientId::generate(),
+            subject,
+            connected_at: SystemTime::now(),
+            remote_addr,
+        }
+    }
+}
+
+/// Controls how patches are rendered into WebSocket frames.
+///
+/// Object keys are always emitted in sorted order so frames are stable
+/// across runs regardless of how the patch was built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializationOptions {
+    /// Indent frames for readability instead of emitting compact JSON.
+    pub pretty: bool,
+    /// Drop `null` object members nested inside operation values.
+    ///
+    /// An operation's own `value` is left alone since a `null` there is
+    /// meaningful in RFC 6902.
+    pub skip_nulls: bool,
+}
+
+impl Default for SerializationOptions {
+    fn default() -> Self {
+        Self {
+            pretty: false,
+            skip_nulls: true,
+        }
+    }
+}
+
+fn strip_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+/// Checks that `value` is a well-formed RFC 6902 patch document.
+fn validate_patch_value(value: &serde_json::Value) -> Result<(), WsError> {
+    let invalid = |reason: String| WsError::InvalidPatch { reason };
+    let ops = value
+        .as_array()
+        .ok_or_else(|| invalid("patch must be an array of operations".to_string()))?;
+
+    for (i, op) in ops.iter().enumerate() {
+        let op = op
+            .as_object()
+            .ok_or_else(|| invalid(format!("operation {} is not an object", i)))?;
+        let name = op
+            .get("op")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| invalid(format!("operation {} has no \"op\"", i)))?;
+        let path = op
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| invalid(format!("operation {} has no \"path\"", i)))?;
+        validate_pointer(path).map_err(|reason| invalid(format!("operation {} path: {}", i, reason)))?;
+
+        match name {
+            "add" | "replace" | "test" if !op.contains_key("value") => {
+                return Err(invalid(format!("{} operation {} has no \"value\"", name, i)));
+            }
+            "add" | "replace" | "test" | "remove" => {}
+            "move" | "copy" => {
+                let from = op
+                    .get("from")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| invalid(format!("{} operation {} has no \"from\"", name, i)))?;
+                validate_pointer(from)
+                    .map_err(|reason| invalid(format!("operation {} from: {}", i, reason)))?;
+                // RFC 6902 section 4.4: a value can't be moved into one of its own children
+                if name == "move" && path.strip_prefix(from).is_some_and(|rest| rest.starts_with('/')) {
+                    return Err(invalid(format!("move operation {} moves {} into its own child", i, from)));
+                }
+            }
+            other => return Err(invalid(format!("operation {} has unknown op \"{}\"", i, other))),
+        }
+    }
+    Ok(())
+}
+
+/// Checks RFC 6901 JSON Pointer syntax.
+fn validate_pointer(pointer: &str) -> Result<(), String> {
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return Err(format!("\"{}\" does not start with '/'", pointer));
+    }
+    let mut chars = pointer.chars();
+    while let Some(c) = chars.next() {
+        if c == '~' && !matches!(chars.next(), Some('0' | '1')) {
+            return Err(format!("\"{}\" has an invalid '~' escape", pointer));
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct WsPatch {
     inner: json_patch::Patch,
+    /// Topic routing the patch; untagged patches reach every subscriber
+    topic: Option<String>,
 }
 
 impl WsPatch {
     pub fn new(patch: json_patch::Patch) -> Self {
-        Self { inner: patch }
+        Self { inner: patch, topic: None }
+    }
+
+    /// Parses a patch from raw JSON, rejecting anything [`validate`](Self::validate) would.
+    pub fn from_value(value: serde_json::Value) -> Result<Self, WsError> {
+        validate_patch_value(&value)?;
+        let patch = serde_json::from_value(value).map_err(|e| WsError::InvalidPatch {
+            reason: e.to_string(),
+        })?;
+        Ok(Self::new(patch))
+    }
+
+    /// Checks that every operation is well-formed per RFC 6902: a known `op`,
+    /// a valid `path`, and the `value` or `from` that op requires.
+    pub fn validate(&self) -> Result<(), WsError> {
+        let value = serde_json::to_value(&self.inner).map_err(WsError::Serialization)?;
+        validate_patch_value(&value)
+    }
+
+    /// Tags the patch so only receivers subscribed to `topic` get it.
+    pub fn with_topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Some(topic.into());
+        self
     }
 
     pub fn inner(&self) -> &json_patch::Patch {
         &self.inner
     }
 
+    pub fn topic(&self) -> Option<&str> {
+        self.topic.as_deref()
+    }
+
     pub fn to_json(&self) -> Result<String, WsError> {
-        serde_json::to_string(&self.inner).map_err(WsError::Serialization)
+        self.to_json_with(&SerializationOptions::default())
+    }
+
+    pub fn to_json_with(&self, options: &SerializationOptions) -> Result<String, WsError> {
+        let mut value = serde_json::to_value(&self.inner).map_err(WsError::Serialization)?;
+
+        if options.skip_nulls
+            && let serde_json::Value::Array(ops) = &mut value
+        {
+            // Only members *inside* each value are stripped; a null value itself is kept
+            ops.iter_mut()
+                .filter_map(|op| op.get_mut("value"))
+                .for_each(strip_nulls);
+        }
+
+        if options.pretty {
+            serde_json::to_string_pretty(&value).map_err(WsError::Serialization)
+        } else {
+            serde_json::to_string(&value).map_err(WsError::Serialization)
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum BroadcastMessage {
+    /// A patch, delivered by its [topic](WsPatch::topic) if it has one
     Patch(WsPatch),
     Shutdown,
+    /// Keepalive for a token stream that is waiting on its next token
+    Heartbeat { stream_id: String },
+    /// A message numbered by the broadcaster's replay log
+    Sequenced { seq: u64, message: Box<BroadcastMessage> },
+    /// Full current state, sent to a client that lagged in place of the patches it missed
+    Snapshot { state: serde_json::Value },
 }
 
 impl BroadcastMessage {
+    /// Topic the message is routed by, if any.
+    pub fn topic(&self) -> Option<&str> {
+        match self {
+            Self::Patch(patch) => patch.topic(),
+            Self::Sequenced { message, .. } => message.topic(),
+            Self::Shutdown | Self::Heartbeat { .. } | Self::Snapshot { .. } => None,
+        }
+    }
+
     pub fn to_frame_payload(&self) -> Result<String, WsError> {
+        self.to_frame_payload_with(&SerializationOptions::default())
+    }
+
+    pub fn to_frame_payload_with(&self, options: &SerializationOptions) -> Result<String, WsError> {
         match self {
-            Self::Patch(patch) => patch.to_json(),
+            Self::Patch(patch) => patch.to_json_with(options),
             Self::Shutdown => Ok(r#"{"type":"shutdown"}"#.to_string()),
+            Self::Heartbeat { stream_id } => {
+                serde_json::to_string(&serde_json::json!({"type": "heartbeat", "stream": stream_id}))
+                    .map_err(WsError::Serialization)
+            }
+            Self::Snapshot { state } => {
+                serde_json::to_string(&serde_json::json!({"type": "snapshot", "state": state}))
+                    .map_err(WsError::Serialization)
+            }
+            Self::Sequenced { seq, message } => Ok(format!(
+                r#"{{"seq":{},"frame":{}}}"#,
+                seq,
+                message.to_frame_payload_with(options)?
+            )),
         }
     }
 }
@@ -70,6 +276,37 @@ pub enum WsError {
 
     #[error("Connection closed by client")]
     ClientDisconnected,
+
+    #[error("Message of {size} bytes exceeds broadcast limit of {limit} bytes")]
+    MessageTooLarge { size: usize, limit: usize },
+
+    #[error("Write-ahead buffer full ({capacity} messages)")]
+    BufferFull { capacity: usize },
+
+    /// The receiver fell behind; the next message follows the skipped ones
+    #[error("Receiver lagged and skipped {skipped} messages")]
+    Lagged { skipped: u64 },
+
+    #[error("Failed to build state snapshot: {0}")]
+    Snapshot(String),
+
+    #[error("Reconnection failed after {attempts} attempts: {reason}")]
+    ReconnectFailed { attempts: u32, reason: String },
+
+    #[error("Client limit of {limit} reached")]
+    CapacityExceeded { limit: usize },
+
+    #[error("Invalid JSON Patch: {reason}")]
+    InvalidPatch { reason: String },
+
+    #[error("No connected client with id {0}")]
+    ClientNotFound(ClientId),
+
+    #[error("Client {client_id} is sending too fast; retry after {retry_after:?}")]
+    RateLimited {
+        client_id: ClientId,
+        retry_after: std::time::Duration,
+    },
 }
 
 #[cfg(test)]
@@ -90,10 +327,116 @@ mod tests {
         assert!(!display.is_empty());
     }
 
+    fn patch_with_nulls() -> WsPatch {
+        let patch: json_patch::Patch = serde_json::from_value(serde_json::json!([
+            {"op": "add", "path": "/task", "value": {"zeta": 1, "alpha": null, "nested": {"b": null, "a": 2}}},
+            {"op": "replace", "path": "/status", "value": null}
+        ]))
+        .unwrap();
+        WsPatch::new(patch)
+    }
+
+    #[test]
+    fn patch_default_is_compact_sorted_and_skips_nulls() {
+        let json = patch_with_nulls().to_json().unwrap();
+        assert_eq!(
+            json,
+            r#"[{"op":"add","path":"/task","value":{"nested":{"a":2},"zeta":1}},{"op":"replace","path":"/status","value":null}]"#
+        );
+    }
+
+    #[test]
+    fn patch_keeps_nulls_when_disabled() {
+        let options = SerializationOptions {
+            pretty: false,
+            skip_nulls: false,
+        };
+        let json = patch_with_nulls().to_json_with(&options).unwrap();
+        assert!(json.contains(r#""alpha":null"#));
+        assert!(json.contains(r#""b":null"#));
+        assert!(!json.contains('\n'));
+    }
+
+    #[test]
+    fn patch_pretty_prints_when_enabled() {
+        let options = SerializationOptions {
+            pretty: true,
+            skip_nulls: true,
+        };
+        let json = patch_with_nulls().to_json_with(&options).unwrap();
+        assert!(json.contains('\n'));
+        let roundtrip: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let compact: serde_json::Value =
+            serde_json::from_str(&patch_with_nulls().to_json().unwrap()).unwrap();
+        assert_eq!(roundtrip, compact);
+    }
+
+    #[test]
+    fn well_formed_patch_validates() {
+        let patch = WsPatch::from_value(serde_json::json!([
+            {"op": "add", "path": "/a~1b", "value": 1},
+            {"op": "move", "from": "/a~1b", "path": "/c"},
+            {"op": "remove", "path": "/c"}
+        ]))
+        .unwrap();
+        assert!(patch.validate().is_ok());
+        assert!(patch_with_nulls().validate().is_ok());
+    }
+
+    #[test]
+    fn patch_missing_path_is_invalid() {
+        let result = WsPatch::from_value(serde_json::json!([{"op": "add", "value": 1}]));
+        assert!(
+            matches!(&result, Err(WsError::InvalidPatch { reason }) if reason.contains("path")),
+            "{:?}",
+            result.err()
+        );
+    }
+
+    #[test]
+    fn patch_with_unknown_op_is_invalid() {
+        let result = WsPatch::from_value(serde_json::json!([{"op": "merge", "path": "/a", "value": 1}]));
+        assert!(
+            matches!(&result, Err(WsError::InvalidPatch { reason }) if reason.contains("merge")),
+            "{:?}",
+            result.err()
+        );
+    }
+
+    #[test]
+    fn move_into_own_child_is_invalid() {
+        let patch: json_patch::Patch =
+            serde_json::from_value(serde_json::json!([{"op": "move", "from": "/a", "path": "/a/b"}])).unwrap();
+        assert!(matches!(WsPatch::new(patch).validate(), Err(WsError::InvalidPatch { .. })));
+    }
+
+    #[test]
+    fn message_topic_comes_from_its_patch() {
+        let tagged = BroadcastMessage::Patch(patch_with_nulls().with_topic("a"));
+        assert_eq!(tagged.topic(), Some("a"));
+        assert_eq!(BroadcastMessage::Patch(patch_with_nulls()).topic(), None);
+
+        let sequenced = BroadcastMessage::Sequenced {
+            seq: 1,
+            message: Box::new(BroadcastMessage::Patch(patch_with_nulls().with_topic("b"))),
+        };
+        assert_eq!(sequenced.topic(), Some("b"));
+        assert_eq!(BroadcastMessage::Shutdown.topic(), None);
+    }
+
     #[test]
     fn broadcast_message_shutdown_serializes() {
         let msg = BroadcastMessage::Shutdown;
         let payload = msg.to_frame_payload().unwrap();
         assert_eq!(payload, r#"{"type":"shutdown"}"#);
     }
+
+    #[test]
+    fn broadcast_message_heartbeat_serializes() {
+        let msg = BroadcastMessage::Heartbeat {
+            stream_id: "chat/\"1\"".to_string(),
+        };
+        let payload = msg.to_frame_payload().unwrap();
+        assert_eq!(payload, r#"{"stream":"chat/\"1\"","type":"heartbeat"}"#);
+    }
 }
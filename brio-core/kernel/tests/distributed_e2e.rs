@@ -1,8 +1,8 @@
 use brio_kernel::host::BrioHostState;
 use brio_kernel::inference::ProviderRegistry;
-use brio_kernel::mesh::service::MeshService;
 use brio_kernel::mesh::types::{NodeId, NodeInfo, NodeAddress};
 use brio_kernel::mesh::Payload;
+use brio_kernel::store::{StoreChange, TypedStore};
 use tokio::sync::mpsc;
 use std::sync::Arc;
 use std::time::Duration;
@@ -23,21 +23,11 @@ async fn spawn_node(id: &str, port: u16) -> (Arc<BrioHostState>, String) {
         .expect("Failed to create host state");
     let state = Arc::new(state);
     
-    // Spawn server
-    let state_clone = state.clone();
-    let addr = addr_str.parse().unwrap();
-    let service = MeshService::new(state_clone, node_id);
-    
-    tokio::spawn(async move {
-        tonic::transport::Server::builder()
-            .add_service(brio_kernel::mesh::grpc::mesh_transport_server::MeshTransportServer::new(service))
-            .serve(addr)
-            .await
-            .unwrap();
-    });
-    
-    // Give server a moment to start
-    tokio::time::sleep(Duration::from_millis(100)).await;
+    // Bound before start_mesh_server returns, and kept serving for the rest of the test
+    state
+        .start_mesh_server(addr_str.parse().unwrap())
+        .await
+        .expect("Failed to start mesh server");
     
     (state, addr_str)
 }
@@ -84,3 +74,561 @@ async fn test_distributed_call() {
         _ => panic!("Unexpected payload type"),
     }
 }
+
+#[tokio::test]
+async fn test_large_payload_round_trips_compressed() {
+    let node_a = BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-a-zstd".to_string()))
+        .await
+        .expect("Failed to create host state");
+    let node_b = Arc::new(
+        BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-b-zstd".to_string()))
+            .await
+            .expect("Failed to create host state"),
+    );
+    let server = node_b
+        .start_mesh_server("127.0.0.1:0".parse().unwrap())
+        .await
+        .expect("Failed to start mesh server");
+
+    let (tx, mut rx) = mpsc::channel(1);
+    node_b.register_component("echo".to_string(), tx);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            msg.reply_tx.send(Ok(msg.payload)).unwrap();
+        }
+    });
+    node_a.register_remote_node(NodeInfo {
+        id: NodeId::from("node-b-zstd".to_string()),
+        address: NodeAddress(server.local_addr().to_string()),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+
+    // 1MB both ways, over the 64KB default threshold
+    let json = format!("{{\"data\":\"{}\"}}", "a".repeat(1024 * 1024));
+    let response = node_a
+        .mesh_call("node-b-zstd/echo", "ping", Payload::Json(json.clone()))
+        .await
+        .expect("Mesh call failed");
+    match response {
+        Payload::Json(s) => assert_eq!(s, json),
+        other => panic!("Unexpected payload {:?}", other.len()),
+    }
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_session_transfer_preserves_staged_changes() {
+    let (node_a, _addr_a) = spawn_node("node-transfer-a", 50057).await;
+    let (node_b, addr_b) = spawn_node("node-transfer-b", 50058).await;
+
+    node_a.register_remote_node(NodeInfo {
+        id: NodeId::from("node-transfer-b".to_string()),
+        address: NodeAddress(addr_b),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+
+    let base = std::env::temp_dir().join("brio_transfer_base");
+    if base.exists() {
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+    std::fs::create_dir_all(base.join("src")).unwrap();
+    std::fs::write(base.join("src/lib.rs"), "original").unwrap();
+
+    // Stage an edit on node A
+    let session_id = node_a.begin_session(base.to_str().unwrap().to_string()).unwrap();
+    let path_a = node_a.session_path(&session_id).unwrap();
+    std::fs::write(path_a.join("src/lib.rs"), "staged").unwrap();
+    std::fs::write(path_a.join("new.txt"), "added").unwrap();
+
+    node_a
+        .transfer_session(&session_id, &NodeId::from("node-transfer-b".to_string()))
+        .await
+        .expect("Transfer failed");
+
+    // A has given up ownership
+    assert!(node_a.session_path(&session_id).is_none());
+    assert!(node_a.commit_session(session_id.clone()).is_err());
+
+    // B owns the staged changes and can commit them
+    let path_b = node_b.session_path(&session_id).unwrap();
+    assert_eq!(std::fs::read_to_string(path_b.join("src/lib.rs")).unwrap(), "staged");
+    node_b.commit_session(session_id).unwrap();
+    assert_eq!(std::fs::read_to_string(base.join("src/lib.rs")).unwrap(), "staged");
+    assert_eq!(std::fs::read_to_string(base.join("new.txt")).unwrap(), "added");
+
+    let _ = std::fs::remove_dir_all(&base);
+}
+
+#[tokio::test]
+async fn test_failed_session_transfer_keeps_or_hands_back_the_session() {
+    let node_a = BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-unconfirmed-a".to_string()))
+        .await
+        .expect("Failed to create host state")
+        .with_session_transfer_timeout(Duration::from_millis(200));
+    // Nothing listens on port 1; the silent node accepts connections but never answers
+    let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    for (id, address) in [("node-down", "127.0.0.1:1".to_string()), ("node-silent", silent.local_addr().unwrap().to_string())] {
+        node_a.register_remote_node(NodeInfo {
+            id: NodeId::from(id.to_string()),
+            address: NodeAddress(address),
+            capabilities: vec![],
+            last_seen: 0,
+        });
+    }
+
+    let base = std::env::temp_dir().join(format!("brio_unconfirmed_base_{}", std::process::id()));
+    std::fs::create_dir_all(&base).unwrap();
+    let session_id = node_a.begin_session(base.to_str().unwrap().to_string()).unwrap();
+
+    // Never reached the target, so the session is still ours
+    assert!(node_a.transfer_session(&session_id, &NodeId::from("node-down".to_string())).await.is_err());
+    assert!(node_a.session_path(&session_id).is_some());
+
+    // The target may have taken it, so it stays detached and is handed back to reconcile
+    let err = node_a
+        .transfer_session(&session_id, &NodeId::from("node-silent".to_string()))
+        .await
+        .unwrap_err();
+    let unconfirmed = err
+        .downcast::<brio_kernel::mesh::SessionTransferUnconfirmed>()
+        .expect("an unconfirmed transfer");
+    assert_eq!(unconfirmed.node, "node-silent");
+    assert!(node_a.session_path(&session_id).is_none());
+
+    node_a.attach_session(unconfirmed.snapshot).unwrap();
+    assert!(node_a.session_path(&session_id).is_some());
+    node_a.rollback_session(session_id).unwrap();
+    let _ = std::fs::remove_dir_all(&base);
+}
+
+#[tokio::test]
+async fn test_missed_heartbeat_within_grace_period_keeps_node_routable() {
+    // Node A only makes outgoing heartbeats, so it doesn't need a server
+    let node_a = BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-a-hb".to_string()))
+        .await
+        .expect("Failed to create host state")
+        .with_dead_node_grace_period(Duration::from_secs(60));
+
+    // Node C isn't listening yet, so the first heartbeat fails
+    let node_c_id = NodeId::from("node-c-hb".to_string());
+    node_a.register_remote_node(NodeInfo {
+        id: node_c_id.clone(),
+        address: NodeAddress("127.0.0.1:50059".to_string()),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+    node_a.heartbeat_remote_nodes().await;
+    assert!(node_a.is_node_routable(&node_c_id));
+
+    // Node C comes up and the next heartbeat succeeds
+    let (_node_c, _addr_c) = spawn_node("node-c-hb", 50059).await;
+    node_a.heartbeat_remote_nodes().await;
+    assert!(node_a.is_node_routable(&node_c_id));
+}
+
+#[tokio::test]
+async fn test_store_change_propagates_to_subscribed_node() {
+    let (node_a, addr_a) = spawn_node("node-changes-a", 50060).await;
+    let (node_b, addr_b) = spawn_node("node-changes-b", 50061).await;
+    let node_a_id = NodeId::from("node-changes-a".to_string());
+
+    node_a.register_remote_node(NodeInfo {
+        id: NodeId::from("node-changes-b".to_string()),
+        address: NodeAddress(addr_b),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+    node_b.register_remote_node(NodeInfo {
+        id: node_a_id.clone(),
+        address: NodeAddress(addr_a),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+
+    // A client attached to node B, interested in node A's jobs
+    let mut receiver = node_b.broadcaster().subscribe().unwrap();
+    node_b
+        .subscribe_remote_changes(&node_a_id, "agent", "job/")
+        .await
+        .expect("Subscription failed");
+
+    let store: TypedStore<serde_json::Value> =
+        TypedStore::new(node_a.get_store("agent"), "agent").with_change_sink(node_a.clone());
+    store.ensure_table().await.unwrap();
+    store.put("notes", &serde_json::json!("not forwarded")).await.unwrap();
+    store.put("job/1", &serde_json::json!({"state": "done"})).await.unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+        .await
+        .expect("Timed out waiting for patch")
+        .unwrap();
+    assert_eq!(
+        message.to_frame_payload().unwrap(),
+        r#"[{"op":"add","path":"/store/agent/job~11","value":{"state":"done"}}]"#
+    );
+
+    // Redelivered events and events claiming to come from B itself are dropped
+    let change = StoreChange {
+        scope: "agent".to_string(),
+        key: "job/2".to_string(),
+        value: None,
+    };
+    assert!(node_b.apply_remote_change("evt-1", &node_a_id, &change).unwrap());
+    assert!(!node_b.apply_remote_change("evt-1", &node_a_id, &change).unwrap());
+    let node_b_id = NodeId::from("node-changes-b".to_string());
+    assert!(!node_b.apply_remote_change("evt-2", &node_b_id, &change).unwrap());
+}
+
+#[tokio::test]
+async fn test_silent_subscribers_hold_up_a_write_only_until_the_deadline() {
+    let node_a = Arc::new(
+        BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-silent-subs-a".to_string()))
+            .await
+            .expect("Failed to create host state"),
+    );
+    // Both accept connections but never answer
+    let mut listeners = Vec::new();
+    for id in ["node-silent-sub-1", "node-silent-sub-2"] {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        node_a.register_remote_node(NodeInfo {
+            id: NodeId::from(id.to_string()),
+            address: NodeAddress(listener.local_addr().unwrap().to_string()),
+            capabilities: vec![],
+            last_seen: 0,
+        });
+        node_a.add_change_subscriber(NodeId::from(id.to_string()), "agent".to_string(), String::new());
+        listeners.push(listener);
+    }
+
+    let store: TypedStore<serde_json::Value> =
+        TypedStore::new(node_a.get_store("agent"), "agent").with_change_sink(node_a.clone());
+    store.ensure_table().await.unwrap();
+    let started = std::time::Instant::now();
+    store.put("job/1", &serde_json::json!("done")).await.unwrap();
+    // Forwarded to both at once, so one deadline's worth of waiting, not two
+    let deadline = brio_kernel::host::CHANGE_FORWARD_TIMEOUT;
+    assert!(started.elapsed() < deadline + deadline / 2, "took {:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn test_mesh_bind_failure_tears_down_host() {
+    // Hold the port so the node can't bind it
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = taken.local_addr().unwrap();
+
+    let state = BrioHostState::new_distributed(
+        "sqlite::memory:",
+        ProviderRegistry::new(),
+        NodeId::from("node-a".to_string()),
+    )
+    .await
+    .expect("Failed to create host state");
+    let state = Arc::new(state);
+
+    let err = state.start_mesh_server(addr).await.unwrap_err();
+    assert!(err.to_string().contains(&addr.to_string()), "unexpected error: {}", err);
+    assert!(state.db().is_closed(), "a failed startup must not leave the host usable");
+}
+
+#[tokio::test]
+async fn test_mesh_server_is_reachable_once_started() {
+    let node_b = Arc::new(
+        BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-b".to_string()))
+            .await
+            .expect("Failed to create host state"),
+    );
+    let server = node_b
+        .start_mesh_server("127.0.0.1:0".parse().unwrap())
+        .await
+        .expect("Failed to start mesh server");
+    let addr = server.local_addr();
+
+    // Reachable immediately, with no grace period to let it come up
+    let mut client = brio_kernel::mesh::grpc::mesh_transport_client::MeshTransportClient::connect(format!("http://{}", addr))
+        .await
+        .expect("Mesh server is not reachable");
+    client
+        .heartbeat(brio_kernel::mesh::grpc::HeartbeatRequest::default())
+        .await
+        .expect("Heartbeat failed");
+
+    server.shutdown().await;
+    assert!(std::net::TcpListener::bind(addr).is_ok(), "shutdown must release the port");
+}
+
+#[tokio::test]
+async fn test_silent_node_leaves_live_nodes_and_is_evicted() {
+    // No grace period, so three misses are enough to evict
+    let node_a = BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-a-live".to_string()))
+        .await
+        .expect("Failed to create host state")
+        .with_dead_node_grace_period(Duration::ZERO);
+    let node_b = Arc::new(
+        BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-b-live".to_string()))
+            .await
+            .expect("Failed to create host state"),
+    );
+    let server = node_b
+        .start_mesh_server("127.0.0.1:0".parse().unwrap())
+        .await
+        .expect("Failed to start mesh server");
+
+    let node_b_id = NodeId::from("node-b-live".to_string());
+    node_a.register_remote_node(NodeInfo {
+        id: node_b_id.clone(),
+        address: NodeAddress(server.local_addr().to_string()),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+    // Never heard from yet
+    assert!(node_a.live_nodes(Duration::from_secs(60)).is_empty());
+
+    node_a.heartbeat_remote_nodes().await;
+    assert_eq!(node_a.live_nodes(Duration::from_secs(60)), vec![node_b_id.clone()]);
+
+    // Node B goes silent: stale once max_age has passed, evicted after three misses
+    server.shutdown().await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(node_a.live_nodes(Duration::from_millis(10)).is_empty());
+
+    let mut audit = brio_kernel::infrastructure::audit::subscribe_audit();
+    for _ in 0..3 {
+        node_a.heartbeat_remote_nodes().await;
+    }
+    assert!(node_a.live_nodes(Duration::from_secs(60)).is_empty());
+    assert!(node_a.mesh_call("node-b-live/echo", "ping", Payload::Json("{}".to_string())).await.is_err());
+
+    loop {
+        match audit.try_recv().expect("No eviction was audited") {
+            brio_kernel::infrastructure::audit::AuditEvent::NodeEvicted { node_id, missed_heartbeats }
+                if node_id == "node-b-live" =>
+            {
+                assert_eq!(missed_heartbeats, 3);
+                break;
+            }
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_node_learns_of_unbootstrapped_node_through_gossip() {
+    let mut nodes = Vec::new();
+    let mut servers = Vec::new();
+    for name in ["node-a-gossip", "node-b-gossip", "node-c-gossip"] {
+        let node = Arc::new(
+            BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from(name.to_string()))
+                .await
+                .expect("Failed to create host state"),
+        );
+        let server = node
+            .start_mesh_server("127.0.0.1:0".parse().unwrap())
+            .await
+            .expect("Failed to start mesh server");
+        nodes.push(node);
+        servers.push(server);
+    }
+    let bootstrap = |from: usize, to: usize, id: &str| {
+        nodes[from].register_remote_node(NodeInfo {
+            id: NodeId::from(id.to_string()),
+            address: NodeAddress(servers[to].local_addr().to_string()),
+            capabilities: vec![],
+            last_seen: 0,
+        });
+    };
+    // A and B know each other; C only knows B
+    bootstrap(0, 1, "node-b-gossip");
+    bootstrap(1, 0, "node-a-gossip");
+    bootstrap(2, 1, "node-b-gossip");
+
+    let node_a_id = NodeId::from("node-a-gossip".to_string());
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !nodes[2].live_nodes(Duration::from_secs(60)).contains(&node_a_id) {
+            for node in &nodes {
+                node.heartbeat_remote_nodes().await;
+                node.gossip().await.expect("Gossip round failed");
+            }
+        }
+    })
+    .await
+    .expect("Node C never learned of node A");
+
+    // The gossiped address is one C can reach A at directly
+    nodes[2].heartbeat_remote_nodes().await;
+    let mut live = nodes[2].live_nodes(Duration::from_secs(60));
+    live.sort_by_key(ToString::to_string);
+    assert_eq!(live, vec![node_a_id, NodeId::from("node-b-gossip".to_string())]);
+
+    for server in servers {
+        server.shutdown().await;
+    }
+}
+
+struct StubProvider {
+    down: bool,
+}
+
+#[async_trait::async_trait]
+impl brio_kernel::inference::LLMProvider for StubProvider {
+    async fn chat(
+        &self,
+        _request: brio_kernel::inference::ChatRequest,
+    ) -> Result<brio_kernel::inference::ChatResponse, brio_kernel::inference::InferenceError> {
+        Err(brio_kernel::inference::InferenceError::ProviderError("not used".to_string()))
+    }
+
+    async fn health_check(&self) -> Result<(), brio_kernel::inference::InferenceError> {
+        if self.down {
+            Err(brio_kernel::inference::InferenceError::NetworkError("connection refused".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_health_sweep_endpoint_reports_nodes_and_providers() {
+    let registry = ProviderRegistry::new();
+    registry.register("primary", StubProvider { down: false });
+    registry.register("backup", StubProvider { down: true });
+    let node_a = Arc::new(
+        BrioHostState::new_distributed("sqlite::memory:", registry, NodeId::from("node-a-sweep".to_string()))
+            .await
+            .expect("Failed to create host state"),
+    );
+
+    let node_b = Arc::new(
+        BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from("node-b-sweep".to_string()))
+            .await
+            .expect("Failed to create host state"),
+    );
+    let server_b = node_b
+        .start_mesh_server("127.0.0.1:0".parse().unwrap())
+        .await
+        .expect("Failed to start mesh server");
+    node_a.register_remote_node(NodeInfo {
+        id: NodeId::from("node-b-sweep".to_string()),
+        address: NodeAddress(server_b.local_addr().to_string()),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+
+    // Node C is down: nothing listens on its port
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let closed_addr = closed.local_addr().unwrap();
+    drop(closed);
+    node_a.register_remote_node(NodeInfo {
+        id: NodeId::from("node-c-sweep".to_string()),
+        address: NodeAddress(closed_addr.to_string()),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+
+    let app = brio_kernel::infrastructure::server::health_sweep_router(node_a, Duration::from_secs(60));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/health/sweep", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let client = reqwest::Client::new();
+    let response = client.post(&url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["healthy"], false);
+
+    let healthy = |section: &str, name: &str| {
+        report[section]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["name"] == name)
+            .unwrap_or_else(|| panic!("{} missing from {}: {}", name, section, report))["healthy"]
+            .as_bool()
+            .unwrap()
+    };
+    assert!(healthy("nodes", "node-b-sweep"));
+    assert!(!healthy("nodes", "node-c-sweep"));
+    assert!(healthy("providers", "primary"));
+    assert!(!healthy("providers", "backup"));
+    assert!(report["providers"][0]["error"].as_str().unwrap().contains("connection refused"));
+
+    // A second sweep straight away is refused
+    let response = client.post(&url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("retry-after"));
+
+    server_b.shutdown().await;
+}
+
+/// Forwards the trace context of each call `node` receives for `component`
+fn capture_trace_context(node: &BrioHostState, component: &str) -> mpsc::UnboundedReceiver<Option<String>> {
+    let (tx, mut rx) = mpsc::channel::<brio_kernel::mesh::MeshMessage>(1);
+    node.register_component(component.to_string(), tx);
+    let (seen_tx, seen_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let _ = seen_tx.send(msg.trace_context);
+            let _ = msg.reply_tx.send(Ok(Payload::Json("ok".to_string())));
+        }
+    });
+    seen_rx
+}
+
+#[tokio::test]
+async fn test_traceparent_survives_grpc_round_trip() {
+    use brio_kernel::mesh::grpc::{mesh_request, mesh_transport_client::MeshTransportClient, MeshRequest};
+
+    let (node, addr) = spawn_node("node-trace-grpc", 50062).await;
+    let mut seen = capture_trace_context(&node, "traced");
+
+    let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    let mut client = MeshTransportClient::connect(format!("http://{}", addr)).await.unwrap();
+    client
+        .call(MeshRequest {
+            target: "traced".to_string(),
+            method: "ping".to_string(),
+            payload: Some(mesh_request::Payload::Json("{}".to_string())),
+            trace_context: Some(traceparent.to_string()),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(seen.recv().await.unwrap().as_deref(), Some(traceparent));
+}
+
+#[tokio::test]
+async fn test_mesh_call_forwards_active_trace() {
+    use opentelemetry::trace::{TraceContextExt, TracerProvider};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    // Both nodes run on this test's thread, so they share the subscriber
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("distributed-e2e")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (node_a, _addr_a) = spawn_node("node-trace-a", 50063).await;
+    let (node_b, addr_b) = spawn_node("node-trace-b", 50064).await;
+    let mut seen = capture_trace_context(&node_b, "traced");
+    node_a.register_remote_node(NodeInfo {
+        id: NodeId::from("node-trace-b".to_string()),
+        address: NodeAddress(addr_b),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+
+    let span = tracing::info_span!("caller");
+    let trace_id = span.context().span().span_context().trace_id();
+    tracing::Instrument::instrument(
+        node_a.mesh_call("node-trace-b/traced", "ping", Payload::Json("{}".to_string())),
+        span,
+    )
+    .await
+    .unwrap();
+
+    let traceparent = seen.recv().await.unwrap().expect("No trace context reached node B");
+    assert_eq!(traceparent.split('-').nth(1), Some(trace_id.to_string().as_str()));
+}
@@ -36,7 +36,7 @@ fn test_engine_config_enables_async() {
     let config = create_engine_config();
     let engine = wasmtime::Engine::new(&config).unwrap();
     // Engine should support async
-    assert!(engine.config().async_support());
+    assert!(engine.is_async());
 }
 
 // =============================================================================
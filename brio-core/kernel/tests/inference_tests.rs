@@ -21,10 +21,7 @@ fn test_role_variants() {
 
 #[test]
 fn test_message_construction() {
-    let msg = Message {
-        role: Role::User,
-        content: "Hello, world!".to_string(),
-    };
+    let msg = Message::new(Role::User, "Hello, world!");
 
     assert!(matches!(msg.role, Role::User));
     assert_eq!(msg.content, "Hello, world!");
@@ -48,15 +45,10 @@ fn test_chat_request_construction() {
     let request = ChatRequest {
         model: "gpt-4".to_string(),
         messages: vec![
-            Message {
-                role: Role::System,
-                content: "You are helpful.".to_string(),
-            },
-            Message {
-                role: Role::User,
-                content: "Hi!".to_string(),
-            },
+            Message::new(Role::System, "You are helpful."),
+            Message::new(Role::User, "Hi!"),
         ],
+        ..Default::default()
     };
 
     assert_eq!(request.model, "gpt-4");
@@ -72,6 +64,7 @@ fn test_chat_response_with_usage() {
             completion_tokens: 1,
             total_tokens: 6,
         }),
+        tool_calls: Vec::new(),
     };
 
     assert_eq!(response.content, "Hello!");
@@ -84,6 +77,7 @@ fn test_chat_response_without_usage() {
     let response = ChatResponse {
         content: "Response".to_string(),
         usage: None,
+        tool_calls: Vec::new(),
     };
 
     assert!(response.usage.is_none());
@@ -110,7 +104,7 @@ fn test_inference_error_rate_limit_display() {
 
 #[test]
 fn test_inference_error_context_length_display() {
-    let err = InferenceError::ContextLengthExceeded;
+    let err = InferenceError::ContextLengthExceeded { limit: None, requested: None };
     let display = format!("{}", err);
     assert!(display.contains("Context Length"));
 }
@@ -153,10 +147,7 @@ fn test_role_serialization() {
 
 #[test]
 fn test_message_serialization() {
-    let msg = Message {
-        role: Role::User,
-        content: "Test message".to_string(),
-    };
+    let msg = Message::new(Role::User, "Test message");
 
     let json = serde_json::to_string(&msg).unwrap();
     assert!(json.contains(r#""role":"user""#));
@@ -202,6 +193,7 @@ impl LLMProvider for TestMockProvider {
         Ok(ChatResponse {
             content: self.response.clone(),
             usage: None,
+            tool_calls: Vec::new(),
         })
     }
 }
@@ -214,10 +206,8 @@ async fn test_mock_provider_returns_configured_response() {
 
     let request = ChatRequest {
         model: "test-model".to_string(),
-        messages: vec![Message {
-            role: Role::User,
-            content: "Hello".to_string(),
-        }],
+        messages: vec![Message::new(Role::User, "Hello")],
+        ..Default::default()
     };
 
     let response = provider.chat(request).await.unwrap();
@@ -240,6 +230,7 @@ async fn test_failing_provider_returns_error() {
     let request = ChatRequest {
         model: "test-model".to_string(),
         messages: vec![],
+        ..Default::default()
     };
 
     let result = provider.chat(request).await;
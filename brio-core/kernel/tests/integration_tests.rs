@@ -11,6 +11,7 @@ impl LLMProvider for MockProvider {
         Ok(ChatResponse {
             content: "Mock response".to_string(),
             usage: None,
+            tool_calls: Vec::new(),
         })
     }
 }
@@ -33,7 +34,7 @@ async fn test_basic_host_interaction() -> Result<()> {
     let component = wasmtime::component::Component::new(&engine, r#"(component)"#)?;
     let instance = wasm_engine.linker().instantiate_async(&mut store, &component).await?;
     
-    assert!(instance.exports(&mut store).root().into_iter().count() >= 0);
+    assert!(instance.get_export(&mut store, None, "run").is_none());
 
     Ok(())
 }
@@ -56,7 +56,7 @@ impl TestEnvironment {
             );
             "#,
         )
-        .execute(self.host.db())
+        .execute(self.host.db().as_sqlite().unwrap())
         .await?;
         Ok(())
     }
@@ -79,6 +79,7 @@ impl LLMProvider for MockProvider {
         Ok(ChatResponse {
             content: "Mock".to_string(),
             usage: None,
+            tool_calls: Vec::new(),
         })
     }
 }
@@ -95,7 +96,7 @@ impl TaskRepository for TestTaskRepository {
                 let rows = sqlx::query(
                     "SELECT * FROM tasks WHERE status = 'pending' ORDER BY priority DESC",
                 )
-                .fetch_all(self.host.db())
+                .fetch_all(self.host.db().as_sqlite().unwrap())
                 .await
                 .map_err(|e| RepositoryError::SqlError(e.to_string()))?;
 
@@ -127,7 +128,7 @@ impl TaskRepository for TestTaskRepository {
                 )
                 .bind(agent.as_str())
                 .bind(task_id.inner() as i64)
-                .execute(self.host.db())
+                .execute(self.host.db().as_sqlite().unwrap())
                 .await
                 .map_err(|e| RepositoryError::SqlError(e.to_string()))?;
                 Ok(())
@@ -207,7 +208,7 @@ async fn create_bug_fix_task(host: &BrioHostState) -> Result<()> {
         .bind("fix bug")
         .bind(10)
         .bind("pending")
-        .execute(host.db())
+        .execute(host.db().as_sqlite().unwrap())
         .await?;
     Ok(())
 }
@@ -254,15 +255,10 @@ async fn smart_agent_logic(
             let request = ChatRequest {
                 model: "mistralai/devstral-2512:free".to_string(), // Reliable free model
                 messages: vec![
-                    Message {
-                        role: Role::System,
-                        content: "You are a precise code editor.".into(),
-                    },
-                    Message {
-                        role: Role::User,
-                        content: prompt,
-                    },
+                    Message::new(Role::System, "You are a precise code editor."),
+                    Message::new(Role::User, prompt),
                 ],
+                ..Default::default()
             };
 
             let response = host
@@ -13,6 +13,7 @@ impl brio_kernel::inference::LLMProvider for DummyProvider {
         Ok(brio_kernel::inference::ChatResponse {
             content: "".to_string(),
             usage: None,
+            tool_calls: Vec::new(),
         })
     }
 }
@@ -85,3 +86,303 @@ async fn test_mesh_routing() {
             .contains("not found")
     );
 }
+
+#[derive(serde::Deserialize)]
+struct AddRequest {
+    a: i64,
+    b: i64,
+}
+
+#[tokio::test]
+async fn test_typed_methods_route_by_name() {
+    let state = BrioHostState::with_provider("sqlite::memory:", Box::new(DummyProvider))
+        .await
+        .expect("Failed to create host");
+
+    let methods = brio_kernel::mesh::MethodRouter::new()
+        .register_method("add", |req: AddRequest| async move { Ok(req.a + req.b) })
+        .register_method("shout", |text: String| async move { Ok(text.to_uppercase()) });
+    state.register_methods("calc".to_string(), methods);
+
+    let sum = state
+        .mesh_call("calc", "add", Payload::Json(r#"{"a":2,"b":3}"#.to_string()))
+        .await
+        .expect("add failed");
+    assert!(matches!(sum, Payload::Json(ref s) if s == "5"));
+
+    let shouted = state
+        .mesh_call("calc", "shout", Payload::Json(r#""hi""#.to_string()))
+        .await
+        .expect("shout failed");
+    assert!(matches!(shouted, Payload::Json(ref s) if s == r#""HI""#));
+
+    let err = state
+        .mesh_call("calc", "divide", Payload::Json("{}".to_string()))
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("Unknown method 'divide'. Available methods: [add, shout]"),
+        "unexpected error: {}",
+        err
+    );
+
+    let err = state
+        .mesh_call("calc", "add", Payload::Json(r#"{"a":"two"}"#.to_string()))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Invalid payload for method 'add'"));
+}
+
+type SpanFields = std::collections::HashMap<String, String>;
+
+/// Collects the fields of every `mesh_call` span, in creation order
+#[derive(Clone, Default)]
+struct MeshSpans(std::sync::Arc<std::sync::Mutex<Vec<SpanFields>>>);
+
+struct FieldVisitor<'a>(&'a mut SpanFields);
+
+impl tracing::field::Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+/// Index of a span's entry in the collected list
+struct SpanIndex(usize);
+
+impl<S> tracing_subscriber::Layer<S> for MeshSpans
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::span::Id,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        if attrs.metadata().name() != "mesh_call" {
+            return;
+        }
+        let mut fields = SpanFields::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let mut spans = self.0.lock().unwrap();
+        spans.push(fields);
+        ctx.span(id).unwrap().extensions_mut().insert(SpanIndex(spans.len() - 1));
+    }
+
+    fn on_record(
+        &self,
+        id: &tracing::span::Id,
+        values: &tracing::span::Record<'_>,
+        ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let span = ctx.span(id).unwrap();
+        if let Some(SpanIndex(index)) = span.extensions().get::<SpanIndex>() {
+            values.record(&mut FieldVisitor(&mut self.0.lock().unwrap()[*index]));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_mesh_call_records_span_fields() {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let spans = MeshSpans::default();
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+
+    let state = BrioHostState::with_provider("sqlite::memory:", Box::new(DummyProvider))
+        .await
+        .expect("Failed to create host");
+    let methods = brio_kernel::mesh::MethodRouter::new()
+        .register_method("shout", |text: String| async move { Ok(text.to_uppercase()) });
+    state.register_methods("calc".to_string(), methods);
+
+    state
+        .mesh_call("calc", "shout", Payload::Json(r#""hi""#.to_string()))
+        .await
+        .expect("shout failed");
+    state
+        .mesh_call("calc", "divide", Payload::Binary(vec![0; 7]))
+        .await
+        .unwrap_err();
+
+    let spans = spans.0.lock().unwrap();
+    assert_eq!(spans.len(), 2);
+
+    let ok = &spans[0];
+    assert_eq!(ok["target"], "calc");
+    assert_eq!(ok["method"], "shout");
+    assert_eq!(ok["payload_bytes"], "4");
+    assert_eq!(ok["outcome"], "ok");
+    assert!(!ok.contains_key("error"));
+
+    let failed = &spans[1];
+    assert_eq!(failed["method"], "divide");
+    assert_eq!(failed["payload_bytes"], "7");
+    assert_eq!(failed["outcome"], "error");
+    assert!(failed["error"].contains("Unknown method 'divide'"), "unexpected error: {}", failed["error"]);
+}
+
+#[tokio::test]
+async fn test_mesh_call_times_out_on_hung_component() {
+    let state = BrioHostState::with_provider("sqlite::memory:", Box::new(DummyProvider))
+        .await
+        .expect("Failed to create host");
+    let methods = brio_kernel::mesh::MethodRouter::new().register_method("hang", |_: ()| async move {
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        Ok(())
+    });
+    state.register_methods("slow".to_string(), methods);
+
+    let err = state
+        .mesh_call_timeout(
+            "slow",
+            "hang",
+            Payload::Json("null".to_string()),
+            std::time::Duration::from_millis(50),
+        )
+        .await
+        .unwrap_err();
+
+    assert!(
+        err.to_string().starts_with("mesh call to 'slow' timed out after 50ms"),
+        "unexpected error: {}",
+        err
+    );
+    let timeout = err.downcast_ref::<brio_kernel::mesh::MeshCallTimeout>().expect("not a timeout");
+    assert!(timeout.delivered);
+}
+
+#[tokio::test]
+async fn test_mesh_call_timeout_reports_undelivered_message() {
+    let state = BrioHostState::with_provider("sqlite::memory:", Box::new(DummyProvider))
+        .await
+        .expect("Failed to create host");
+    // Never drained, so the queue stays full after the first message
+    let (tx, _rx) = mpsc::channel::<MeshMessage>(1);
+    state.register_component("stuck".to_string(), tx);
+    let timeout = std::time::Duration::from_millis(20);
+
+    let first = state
+        .mesh_call_timeout("stuck", "ping", Payload::Json("1".to_string()), timeout)
+        .await
+        .unwrap_err();
+    let second = state
+        .mesh_call_timeout("stuck", "ping", Payload::Json("2".to_string()), timeout)
+        .await
+        .unwrap_err();
+
+    let delivered = |e: &anyhow::Error| e.downcast_ref::<brio_kernel::mesh::MeshCallTimeout>().unwrap().delivered;
+    assert!(delivered(&first));
+    assert!(!delivered(&second));
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Reading {
+    sensor: String,
+    values: Vec<f64>,
+    calibrated: bool,
+}
+
+#[test]
+fn test_msgpack_payload_round_trips() {
+    let reading = Reading {
+        sensor: "thermo-1".to_string(),
+        values: vec![20.5, 21.0],
+        calibrated: true,
+    };
+
+    let payload = Payload::from_msgpack(&reading).unwrap();
+    assert!(matches!(payload, Payload::MsgPack(_)));
+    assert_eq!(payload.decode_msgpack::<Reading>().unwrap(), reading);
+    assert!(Payload::Json("{}".to_string()).decode_msgpack::<Reading>().is_err());
+}
+
+#[tokio::test]
+async fn test_msgpack_payload_through_mesh_handler() {
+    let state = BrioHostState::with_provider("sqlite::memory:", Box::new(DummyProvider))
+        .await
+        .expect("Failed to create host");
+
+    // A raw handler that decodes, edits and re-encodes the reading
+    let (tx, mut rx) = mpsc::channel::<MeshMessage>(10);
+    state.register_component("sensors".to_string(), tx);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let reply = msg.payload.decode_msgpack::<Reading>().and_then(|mut reading| {
+                reading.calibrated = true;
+                Payload::from_msgpack(&reading)
+            });
+            let _ = msg.reply_tx.send(reply);
+        }
+    });
+    // Typed methods answer MessagePack calls in MessagePack
+    let methods = brio_kernel::mesh::MethodRouter::new()
+        .register_method("average", |reading: Reading| async move {
+            Ok(reading.values.iter().sum::<f64>() / reading.values.len() as f64)
+        });
+    state.register_methods("stats".to_string(), methods);
+
+    let reading = Reading {
+        sensor: "thermo-1".to_string(),
+        values: vec![20.0, 22.0],
+        calibrated: false,
+    };
+    let reply = state
+        .mesh_call("sensors", "calibrate", Payload::from_msgpack(&reading).unwrap())
+        .await
+        .expect("calibrate failed");
+    let calibrated: Reading = reply.decode_msgpack().unwrap();
+    assert!(calibrated.calibrated);
+    assert_eq!(calibrated.values, reading.values);
+
+    let average = state
+        .mesh_call("stats", "average", Payload::from_msgpack(&reading).unwrap())
+        .await
+        .expect("average failed");
+    assert_eq!(average.decode_msgpack::<f64>().unwrap(), 21.0);
+}
+
+#[tokio::test]
+async fn test_payload_violating_method_schema_is_rejected() {
+    let state = BrioHostState::with_provider("sqlite::memory:", Box::new(DummyProvider))
+        .await
+        .expect("Failed to create host");
+
+    let methods = brio_kernel::mesh::MethodRouter::new()
+        .register_method("add", |req: AddRequest| async move { Ok(req.a + req.b) })
+        .with_schema(
+            "add",
+            serde_json::json!({
+                "type": "object",
+                "properties": {"a": {"type": "integer"}, "b": {"type": "integer"}},
+                "required": ["a", "b"],
+            }),
+        );
+
+    let err = methods
+        .validate("add", &Payload::Json(r#"{"a":"two"}"#.to_string()))
+        .unwrap_err();
+    let brio_kernel::mesh::MeshError::InvalidPayload { method, errors } = &err;
+    assert_eq!(method, "add");
+    assert_eq!(errors.len(), 2, "unexpected errors: {:?}", errors);
+    assert!(methods.validate("add", &Payload::Json(r#"{"a":2,"b":3}"#.to_string())).is_ok());
+
+    state.register_methods("calc".to_string(), methods);
+    let err = state
+        .mesh_call("calc", "add", Payload::Json(r#"{"a":"two"}"#.to_string()))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Invalid payload for method 'add'"), "unexpected error: {}", err);
+    assert!(err.to_string().contains("missing required property 'b'"), "unexpected error: {}", err);
+
+    let sum = state
+        .mesh_call("calc", "add", Payload::Json(r#"{"a":2,"b":3}"#.to_string()))
+        .await
+        .expect("add failed");
+    assert!(matches!(sum, Payload::Json(ref s) if s == "5"));
+}
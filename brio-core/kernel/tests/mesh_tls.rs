@@ -0,0 +1,114 @@
+//! Mesh calls between nodes over TLS, with certificates generated per run.
+
+use brio_kernel::host::BrioHostState;
+use brio_kernel::inference::ProviderRegistry;
+use brio_kernel::mesh::types::{NodeAddress, NodeId, NodeInfo};
+use brio_kernel::mesh::{MeshTls, Payload};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+struct Ca {
+    cert: Certificate,
+    key: KeyPair,
+}
+
+impl Ca {
+    fn new(name: &str) -> Self {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.distinguished_name.push(DnType::CommonName, name);
+        let cert = params.self_signed(&key).unwrap();
+        Self { cert, key }
+    }
+
+    /// TLS settings for a node whose certificate this CA signed, trusting only this CA
+    fn node_tls(&self) -> MeshTls {
+        let key = KeyPair::generate().unwrap();
+        let params = CertificateParams::new(vec!["127.0.0.1".to_string()]).unwrap();
+        let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+        MeshTls::from_pem(cert.pem(), key.serialize_pem(), Some(self.cert.pem().as_bytes()))
+    }
+}
+
+async fn spawn_node(id: &str, tls: Option<MeshTls>) -> (Arc<BrioHostState>, String) {
+    let state = BrioHostState::new_distributed("sqlite::memory:", ProviderRegistry::new(), NodeId::from(id.to_string()))
+        .await
+        .expect("Failed to create host state")
+        .with_mesh_call_timeout(Duration::from_secs(5));
+    let state = Arc::new(match tls {
+        Some(tls) => state.with_mesh_tls(tls),
+        None => state,
+    });
+
+    let server = state
+        .start_mesh_server("127.0.0.1:0".parse().unwrap())
+        .await
+        .expect("Failed to start mesh server");
+    let addr = server.local_addr().to_string();
+    (state, addr)
+}
+
+fn register(caller: &BrioHostState, id: &str, addr: &str) {
+    caller.register_remote_node(NodeInfo {
+        id: NodeId::from(id.to_string()),
+        address: NodeAddress(addr.to_string()),
+        capabilities: vec![],
+        last_seen: 0,
+    });
+}
+
+fn serve_echo(node: &BrioHostState) {
+    let (tx, mut rx) = mpsc::channel(1);
+    node.register_component("echo".to_string(), tx);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let _ = msg.reply_tx.send(Ok(msg.payload));
+        }
+    });
+}
+
+#[tokio::test]
+async fn test_mesh_calls_require_tls_from_trusted_ca() {
+    let ca = Ca::new("brio mesh ca");
+    let (server, server_addr) = spawn_node("tls-server", Some(ca.node_tls())).await;
+    serve_echo(&server);
+
+    let (trusted, _) = spawn_node("tls-client", Some(ca.node_tls())).await;
+    register(&trusted, "tls-server", &server_addr);
+    let reply = trusted
+        .mesh_call("tls-server/echo", "ping", Payload::Json("hello".to_string()))
+        .await
+        .expect("TLS call between nodes of the same CA failed");
+    assert!(matches!(reply, Payload::Json(s) if s == "hello"));
+
+    let (plaintext, _) = spawn_node("plaintext-client", None).await;
+    register(&plaintext, "tls-server", &server_addr);
+    assert!(
+        plaintext
+            .mesh_call("tls-server/echo", "ping", Payload::Json("hello".to_string()))
+            .await
+            .is_err(),
+        "Plaintext client reached a TLS server"
+    );
+
+    // Trusts the server's CA, but presents a certificate the server doesn't
+    let rogue_ca = Ca::new("rogue ca");
+    let rogue_key = KeyPair::generate().unwrap();
+    let rogue_cert = CertificateParams::new(vec!["127.0.0.1".to_string()])
+        .unwrap()
+        .signed_by(&rogue_key, &rogue_ca.cert, &rogue_ca.key)
+        .unwrap();
+    let rogue_tls = MeshTls::from_pem(rogue_cert.pem(), rogue_key.serialize_pem(), Some(ca.cert.pem().as_bytes()));
+    let (rogue, _) = spawn_node("rogue-client", Some(rogue_tls)).await;
+    register(&rogue, "tls-server", &server_addr);
+    assert!(
+        rogue
+            .mesh_call("tls-server/echo", "ping", Payload::Json("hello".to_string()))
+            .await
+            .is_err(),
+        "Client with a certificate from another CA passed mutual TLS"
+    );
+}
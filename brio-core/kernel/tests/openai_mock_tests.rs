@@ -2,10 +2,14 @@
 //!
 //! Uses wiremock to simulate various HTTP responses from the OpenAI API.
 
-use brio_kernel::inference::{ChatRequest, InferenceError, LLMProvider, Message, OpenAIConfig, OpenAIProvider, Role};
+use brio_kernel::inference::{
+    Capability, ChatRequest, CompletionRequestBuilder, InferenceError, LLMProvider, Message, OpenAIConfig, OpenAIProvider,
+    ProviderRegistry, Role, Tool, ToolValidatingProvider,
+};
+use brio_kernel::infrastructure::context::RequestContext;
 use reqwest::Url;
 use secrecy::SecretString;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{body_partial_json, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 async fn create_provider_with_mock_server(server: &MockServer) -> OpenAIProvider {
@@ -20,10 +24,8 @@ async fn create_provider_with_mock_server(server: &MockServer) -> OpenAIProvider
 fn create_test_request() -> ChatRequest {
     ChatRequest {
         model: "gpt-4".to_string(),
-        messages: vec![Message {
-            role: Role::User,
-            content: "Hello".to_string(),
-        }],
+        messages: vec![Message::new(Role::User, "Hello")],
+        ..Default::default()
     }
 }
 
@@ -50,12 +52,29 @@ async fn test_rate_limit_returns_rate_limit_error() {
     assert!(matches!(result.unwrap_err(), InferenceError::RateLimit));
 }
 
+#[tokio::test]
+async fn test_retry_after_header_is_reported() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "7"))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let err = provider.chat(create_test_request()).await.unwrap_err();
+
+    assert!(matches!(err, InferenceError::Throttled { status: 503, .. }), "{:?}", err);
+    assert_eq!(err.retry_after(), Some(std::time::Duration::from_secs(7)));
+}
+
 // =============================================================================
 // Server Error Tests
 // =============================================================================
 
 #[tokio::test]
-async fn test_server_error_returns_provider_error() {
+async fn test_server_error_returns_upstream_error() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
@@ -70,15 +89,15 @@ async fn test_server_error_returns_provider_error() {
     let result = provider.chat(request).await;
 
     assert!(result.is_err());
-    if let InferenceError::ProviderError(msg) = result.unwrap_err() {
-        assert!(msg.contains("500"));
-    } else {
-        panic!("Expected ProviderError");
-    }
+    // Plain-text bodies aren't API errors, so the raw body is kept
+    assert!(matches!(
+        result.unwrap_err(),
+        InferenceError::Upstream { status: 500, .. }
+    ));
 }
 
 #[tokio::test]
-async fn test_service_unavailable_returns_provider_error() {
+async fn test_service_unavailable_returns_upstream_error() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
@@ -93,13 +112,58 @@ async fn test_service_unavailable_returns_provider_error() {
     let result = provider.chat(request).await;
 
     assert!(result.is_err());
-    if let InferenceError::ProviderError(msg) = result.unwrap_err() {
-        assert!(msg.contains("503"));
-    } else {
-        panic!("Expected ProviderError");
+    // Plain-text bodies aren't API errors, so the raw body is kept
+    assert!(matches!(
+        result.unwrap_err(),
+        InferenceError::Upstream { status: 503, .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_html_gateway_error_is_captured_as_upstream() {
+    let server = MockServer::start().await;
+    let html = "<html><head><title>502 Bad Gateway</title></head><body><center><h1>502 Bad Gateway</h1></center><hr><center>nginx</center></body></html>";
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(502).set_body_string(html))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let result = provider.chat(create_test_request()).await;
+
+    match result {
+        Err(InferenceError::Upstream { status, body }) => {
+            assert_eq!(status, 502);
+            assert_eq!(body, html);
+        }
+        other => panic!("Expected Upstream error, got {:?}", other.map(|r| r.content)),
     }
 }
 
+#[tokio::test]
+async fn test_json_error_message_is_surfaced() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(401)
+                .set_body_string(r#"{"error": {"message": "Incorrect API key provided"}}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let result = provider.chat(create_test_request()).await;
+
+    assert!(matches!(
+        result,
+        Err(InferenceError::Http { status: 401, message }) if message.contains("Incorrect API key")
+    ));
+}
+
 // =============================================================================
 // Malformed Response Tests
 // =============================================================================
@@ -178,10 +242,35 @@ async fn test_context_length_exceeded_returns_correct_error() {
     assert!(result.is_err());
     assert!(matches!(
         result.unwrap_err(),
-        InferenceError::ContextLengthExceeded
+        InferenceError::ContextLengthExceeded { .. }
     ));
 }
 
+#[tokio::test]
+async fn test_context_length_exceeded_reports_token_counts() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(
+            r#"{"error": {"message": "This model's maximum context length is 8192 tokens. However, you requested 10240 tokens (9216 in the messages, 1024 in the completion).", "type": "invalid_request_error", "code": "context_length_exceeded"}}"#,
+        ))
+        .expect(1) // Not retried
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let result = provider.chat(create_test_request()).await;
+
+    match result {
+        Err(InferenceError::ContextLengthExceeded { limit, requested }) => {
+            assert_eq!(limit, Some(8192));
+            assert_eq!(requested, Some(10240));
+        }
+        other => panic!("Expected ContextLengthExceeded, got {:?}", other.map(|r| r.content)),
+    }
+}
+
 // =============================================================================
 // Success Path Tests
 // =============================================================================
@@ -226,3 +315,639 @@ async fn test_successful_response_parses_correctly() {
     assert_eq!(usage.completion_tokens, 8);
     assert_eq!(usage.total_tokens, 18);
 }
+
+#[tokio::test]
+async fn test_registry_accumulates_usage_across_calls() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": "Hi"}}],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 8, "total_tokens": 18}
+        })))
+        .mount(&server)
+        .await;
+
+    let registry = ProviderRegistry::new();
+    registry.register("openai", create_provider_with_mock_server(&server).await);
+    assert!(registry.usage_totals().is_empty());
+
+    registry.chat("openai", create_test_request()).await.unwrap();
+    registry.chat_default(create_test_request()).await.unwrap();
+
+    let totals = registry.usage_totals()["openai"];
+    assert_eq!(totals.calls, 2);
+    assert_eq!(totals.prompt_tokens, 20);
+    assert_eq!(totals.completion_tokens, 16);
+    assert_eq!(totals.total_tokens, 36);
+}
+
+const TOOL_CALL_BODY: &str = r#"{
+    "choices": [{
+        "message": {
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": { "name": "get_weather", "arguments": "{\"days\": \"two\"}" }
+            }]
+        }
+    }]
+}"#;
+
+#[tokio::test]
+async fn test_tool_call_arguments_are_validated_against_schema() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(TOOL_CALL_BODY))
+        .mount(&server)
+        .await;
+
+    let provider = std::sync::Arc::new(create_provider_with_mock_server(&server).await);
+    let mut request = create_test_request();
+    request.tools = vec![Tool {
+        name: "get_weather".to_string(),
+        description: "Weather forecast".to_string(),
+        parameters: serde_json::json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" }, "days": { "type": "integer" } },
+            "required": ["city"]
+        }),
+    }];
+
+    // Parsed as-is by the provider itself
+    let response = provider.chat(request.clone()).await.unwrap();
+    assert_eq!(response.content, "");
+    assert_eq!(response.tool_calls[0].name, "get_weather");
+    assert_eq!(response.tool_calls[0].arguments["days"], "two");
+
+    match ToolValidatingProvider::new(provider).chat(request).await {
+        Err(InferenceError::InvalidToolArguments { tool, errors }) => {
+            assert_eq!(tool, "get_weather");
+            assert_eq!(
+                errors,
+                vec![
+                    "/: missing required property 'city'",
+                    "/days: expected integer, got string"
+                ]
+            );
+        }
+        other => panic!("Expected InvalidToolArguments, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_agent_loop_sends_tool_calls_and_results_back() {
+    use brio_kernel::host::BrioHostState;
+    use brio_kernel::inference::AgentTool;
+    use brio_kernel::mesh::MethodRouter;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(TOOL_CALL_BODY))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": "Sunny for two days"}}]
+        })))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(provider))
+        .await
+        .unwrap();
+    let methods = MethodRouter::new()
+        .register_method("get_weather", |_: serde_json::Value| async move { Ok("sunny") });
+    host.register_methods("weather".to_string(), methods);
+    let tool = Tool {
+        name: "get_weather".to_string(),
+        description: "Weather forecast".to_string(),
+        parameters: serde_json::json!({"type": "object"}),
+    };
+
+    let outcome = host
+        .run_agent_loop(create_test_request(), &[AgentTool::new(tool, "weather")], 2)
+        .await
+        .unwrap();
+    assert_eq!(outcome.response.content, "Sunny for two days");
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let second: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(
+        second["messages"][1],
+        serde_json::json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": { "name": "get_weather", "arguments": "{\"days\":\"two\"}" }
+            }]
+        })
+    );
+    assert_eq!(
+        second["messages"][2],
+        serde_json::json!({ "role": "tool", "content": "\"sunny\"", "tool_call_id": "call_1" })
+    );
+}
+
+#[tokio::test]
+async fn test_builder_params_are_sent_to_provider() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(serde_json::json!({
+            "model": "gpt-4",
+            "temperature": 0.25,
+            "max_tokens": 64,
+            "response_format": { "type": "json_object" }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_OK_BODY))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let request = CompletionRequestBuilder::new("gpt-4")
+        .user("Reply in JSON")
+        .temperature(0.25)
+        .max_tokens(64)
+        .json_mode()
+        .build()
+        .unwrap();
+
+    let provider = create_provider_with_mock_server(&server).await;
+    assert!(provider.chat(request).await.is_ok());
+}
+
+// =============================================================================
+// Embeddings Tests
+// =============================================================================
+
+const CHAT_OK_BODY: &str = r#"{
+    "choices": [{"message": {"role": "assistant", "content": "from chat endpoint"}}],
+    "usage": null
+}"#;
+
+const EMBEDDINGS_OK_BODY: &str = r#"{
+    "data": [
+        {"embedding": [0.3, 0.4], "index": 1},
+        {"embedding": [0.1, 0.2], "index": 0}
+    ]
+}"#;
+
+#[tokio::test]
+async fn test_embeddings_parse_in_input_order() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMBEDDINGS_OK_BODY))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let vectors = provider
+        .embed(vec!["first".to_string(), "second".to_string()])
+        .await
+        .unwrap();
+
+    assert_eq!(vectors, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+}
+
+#[tokio::test]
+async fn test_host_embeds_via_default_provider() {
+    use brio_kernel::host::BrioHostState;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "data": [
+                {"embedding": [0.1, 0.2, 0.3], "index": 0},
+                {"embedding": [0.4, 0.5, 0.6], "index": 1},
+                {"embedding": [0.7, 0.8, 0.9], "index": 2}
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(provider))
+        .await
+        .unwrap();
+    let inputs: Vec<String> = ["a", "b", "c"].into_iter().map(String::from).collect();
+
+    let vectors = host.embed(inputs.clone()).await.unwrap();
+
+    assert_eq!(vectors.len(), inputs.len());
+    assert!(vectors.iter().all(|v| v.len() == 3));
+    assert_eq!(vectors[2], vec![0.7, 0.8, 0.9]);
+}
+
+#[tokio::test]
+async fn test_chat_and_embeddings_use_different_endpoints() {
+    let chat_server = MockServer::start().await;
+    let embeddings_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_OK_BODY))
+        .expect(1)
+        .mount(&chat_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMBEDDINGS_OK_BODY))
+        .expect(1)
+        .mount(&embeddings_server)
+        .await;
+
+    let registry = ProviderRegistry::new();
+    registry.register("chat", create_provider_with_mock_server(&chat_server).await);
+    registry.register(
+        "embeddings",
+        create_provider_with_mock_server(&embeddings_server).await,
+    );
+    registry.set_default("chat");
+    registry.set_capability_default(Capability::Embeddings, "embeddings");
+
+    let response = registry.chat_default(create_test_request()).await.unwrap();
+    assert_eq!(response.content, "from chat endpoint");
+
+    let vectors = registry
+        .embed_default(vec!["a".to_string(), "b".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(vectors.len(), 2);
+
+    // Each server must have seen exactly its own request
+    assert_eq!(chat_server.received_requests().await.unwrap().len(), 1);
+    assert_eq!(embeddings_server.received_requests().await.unwrap().len(), 1);
+}
+
+fn embeddings_body(vectors: &[Vec<f32>]) -> String {
+    let data: Vec<serde_json::Value> = vectors
+        .iter()
+        .enumerate()
+        .map(|(index, embedding)| serde_json::json!({"embedding": embedding, "index": index}))
+        .collect();
+    serde_json::json!({ "data": data }).to_string()
+}
+
+fn embeddings_provider(server: &MockServer, model: &str, dimensions: usize) -> OpenAIProvider {
+    let config = OpenAIConfig::new(
+        SecretString::new("test-api-key".into()),
+        Url::parse(&format!("{}/", server.uri())).unwrap(),
+    )
+    .with_max_retries(0)
+    .with_embedding_model(model)
+    .with_embedding_dimensions(dimensions);
+    OpenAIProvider::new(config)
+}
+
+#[tokio::test]
+async fn test_reduced_dimensions_are_passed_to_supporting_models() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .and(body_partial_json(serde_json::json!({"dimensions": 4})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(embeddings_body(&[vec![0.5; 4]])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let provider = embeddings_provider(&server, "text-embedding-3-small", 4);
+    let vectors = provider.embed(vec!["text".to_string()]).await.unwrap();
+
+    assert_eq!(vectors.len(), 1);
+    assert_eq!(vectors[0].len(), 4);
+}
+
+#[tokio::test]
+async fn test_reduced_dimensions_are_applied_locally_otherwise() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(embeddings_body(&[vec![
+            3.0, 4.0, 1.0, 1.0, 1.0, 1.0,
+        ]])))
+        .mount(&server)
+        .await;
+
+    let provider = embeddings_provider(&server, "text-embedding-ada-002", 2);
+    let vectors = provider.embed(vec!["text".to_string()]).await.unwrap();
+
+    assert_eq!(vectors, vec![vec![0.6, 0.8]]);
+    let request: serde_json::Value =
+        serde_json::from_slice(&server.received_requests().await.unwrap()[0].body).unwrap();
+    assert!(request.get("dimensions").is_none());
+
+    // Other sizes can still be asked for per call
+    let vectors = provider.embed_with_dimensions(vec!["text".to_string()], 3).await.unwrap();
+    assert_eq!(vectors[0].len(), 3);
+}
+
+#[tokio::test]
+async fn test_dimensions_above_model_max_are_rejected() {
+    let server = MockServer::start().await;
+
+    let provider = embeddings_provider(&server, "text-embedding-3-small", 2048);
+    let result = provider.embed(vec!["text".to_string()]).await;
+
+    assert!(matches!(result, Err(InferenceError::InvalidRequest(_))));
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+// =============================================================================
+// Retry Budget Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_nested_retries_share_request_budget() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("Service Unavailable"))
+        .mount(&server)
+        .await;
+
+    let config = OpenAIConfig::new(
+        SecretString::new("test-api-key".into()),
+        Url::parse(&format!("{}/", server.uri())).unwrap(),
+    )
+    .with_max_retries(5)
+    .with_base_delay_ms(1);
+    let provider = OpenAIProvider::new(config);
+
+    RequestContext::new(3)
+        .scope(async {
+            // An outer layer retrying the whole call; each attempt would
+            // otherwise make up to 6 requests of its own.
+            for _ in 0..3 {
+                assert!(provider.chat(create_test_request()).await.is_err());
+            }
+        })
+        .await;
+
+    // One initial request per outer attempt, plus the 3 budgeted retries
+    assert_eq!(server.received_requests().await.unwrap().len(), 3 + 3);
+}
+
+// =============================================================================
+// Moderation Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_flagged_prompt_is_blocked_before_chat() {
+    use brio_kernel::inference::{ModeratedProvider, ProviderModerator};
+    use std::sync::Arc;
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/moderations"))
+        .and(body_partial_json(serde_json::json!({ "input": "Hello" })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"results": [{"flagged": true, "categories": {"harassment": true, "violence": false}}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_OK_BODY))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let provider: Arc<dyn LLMProvider> = Arc::new(create_provider_with_mock_server(&server).await);
+    let moderated = ModeratedProvider::new(provider.clone(), Arc::new(ProviderModerator::new(provider)));
+
+    let result = moderated.chat(create_test_request()).await;
+    assert!(matches!(
+        result,
+        Err(InferenceError::ContentBlocked { categories }) if categories == ["harassment"]
+    ));
+}
+
+// =============================================================================
+// Slow Request Logging Tests
+// =============================================================================
+
+/// Collects formatted log output so tests can count warnings.
+#[derive(Clone, Default)]
+struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CapturedLogs {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+async fn chat_with_latency(delay: std::time::Duration, threshold: std::time::Duration) -> String {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_OK_BODY).set_delay(delay))
+        .mount(&server)
+        .await;
+
+    let config = OpenAIConfig::new(
+        SecretString::new("test-api-key".into()),
+        Url::parse(&format!("{}/", server.uri())).unwrap(),
+    )
+    .with_max_retries(0)
+    .with_slow_request_threshold(threshold);
+    let provider = OpenAIProvider::new(config);
+
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .with_max_level(tracing::Level::WARN)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let mut request = create_test_request();
+    request.messages[0].content = "confidential prompt text".to_string();
+    provider.chat(request).await.expect("chat failed");
+    logs.contents()
+}
+
+#[tokio::test]
+async fn test_slow_completion_logs_one_redacted_warning() {
+    let logs = chat_with_latency(
+        std::time::Duration::from_millis(200),
+        std::time::Duration::from_millis(50),
+    )
+    .await;
+
+    assert_eq!(logs.matches("Slow completion request").count(), 1, "logs: {}", logs);
+    assert!(logs.contains("model=gpt-4"));
+    assert!(logs.contains("latency_ms="));
+    assert!(!logs.contains("confidential prompt text"));
+}
+
+#[tokio::test]
+async fn test_fast_completion_logs_nothing() {
+    let logs = chat_with_latency(std::time::Duration::ZERO, std::time::Duration::from_secs(5)).await;
+    assert!(!logs.contains("Slow completion request"), "logs: {}", logs);
+}
+
+#[tokio::test]
+async fn test_custom_response_mapper_unwraps_gateway_response() {
+    let server = MockServer::start().await;
+
+    // A gateway that nests the standard body and renames the choices
+    let response_body = r#"{
+        "status": "ok",
+        "result": {
+            "outputs": [{ "message": { "role": "assistant", "content": "Wrapped hello" } }],
+            "usage": { "prompt_tokens": 4, "completion_tokens": 2, "total_tokens": 6 }
+        }
+    }"#;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(response_body))
+        .mount(&server)
+        .await;
+
+    let config = OpenAIConfig::new(
+        SecretString::new("test-api-key".into()),
+        Url::parse(&format!("{}/", server.uri())).unwrap(),
+    )
+    .with_max_retries(0)
+    .with_response_mapper(|mut body| {
+        let mut result = body["result"].take();
+        let outputs = result["outputs"].take();
+        result["choices"] = outputs;
+        brio_kernel::inference::standard_response(result)
+    });
+    let provider = OpenAIProvider::new(config);
+
+    let response = provider.chat(create_test_request()).await.unwrap();
+    assert_eq!(response.content, "Wrapped hello");
+    assert_eq!(response.usage.unwrap().total_tokens, 6);
+
+    // The default mapper doesn't understand the wrapped shape
+    let err = create_provider_with_mock_server(&server).await.chat(create_test_request()).await.unwrap_err();
+    assert!(matches!(err, InferenceError::ProviderError(ref msg) if msg.contains("Parse error")), "{:?}", err);
+}
+
+// =============================================================================
+// Streaming Tests
+// =============================================================================
+
+fn sse_body(tokens: &[&str]) -> String {
+    let mut body = String::from(": keep-alive\n\n");
+    body.push_str("data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n");
+    for token in tokens {
+        body.push_str(&format!(
+            "data: {}\n\n",
+            serde_json::json!({ "choices": [{ "delta": { "content": token } }] })
+        ));
+    }
+    body.push_str("data: [DONE]\n\n");
+    body
+}
+
+#[tokio::test]
+async fn test_stream_completion_yields_each_delta() {
+    use futures_util::StreamExt;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(serde_json::json!({ "stream": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse_body(&["Hel", "lo", "!"]), "text/event-stream"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let tokens: Vec<String> = provider
+        .stream_completion(create_test_request())
+        .map(|token| token.unwrap())
+        .collect()
+        .await;
+    assert_eq!(tokens, ["Hel", "lo", "!"]);
+}
+
+#[tokio::test]
+async fn test_stream_completion_surfaces_http_errors() {
+    use futures_util::StreamExt;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let items: Vec<_> = provider.stream_completion(create_test_request()).collect().await;
+    assert_eq!(items.len(), 1);
+    assert!(matches!(items[0], Err(InferenceError::RateLimit)));
+}
+
+#[tokio::test]
+async fn test_host_broadcasts_streamed_tokens() {
+    use brio_kernel::host::BrioHostState;
+    use brio_kernel::ws::BroadcastMessage;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse_body(&["Hi", " there"]), "text/event-stream"))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(provider))
+        .await
+        .unwrap();
+    let mut rx = host.broadcaster().subscribe().unwrap();
+
+    let response = host.stream_chat("chat/1", create_test_request()).await.unwrap();
+    assert_eq!(response.content, "Hi there");
+
+    let mut chunks = Vec::new();
+    for _ in 0..2 {
+        let BroadcastMessage::Patch(patch) = rx.recv().await.unwrap() else {
+            panic!("Expected patch");
+        };
+        chunks.push(patch.to_json().unwrap());
+    }
+    assert_eq!(
+        chunks,
+        [
+            r#"[{"op":"add","path":"/streams/chat~11/-","value":"Hi"}]"#,
+            r#"[{"op":"add","path":"/streams/chat~11/-","value":" there"}]"#,
+        ]
+    );
+}
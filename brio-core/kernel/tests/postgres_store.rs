@@ -0,0 +1,231 @@
+//! Store tests against a real PostgreSQL server.
+//!
+//! Built with `--features postgres`; each test is skipped unless
+//! `BRIO_TEST_POSTGRES_URL` points at a database it may create tables in.
+#![cfg(feature = "postgres")]
+
+use anyhow::Result;
+use brio_kernel::host::BrioHostState;
+use brio_kernel::inference::{ChatRequest, ChatResponse, InferenceError, LLMProvider};
+use brio_kernel::store::{
+    ChangeLog, DbPool, LockManager, PrefixPolicy, SqlStore, StoreChange, StoreError, TypedStore,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn database_url() -> Option<String> {
+    let url = std::env::var("BRIO_TEST_POSTGRES_URL").ok();
+    if url.is_none() {
+        eprintln!("BRIO_TEST_POSTGRES_URL not set, skipping PostgreSQL test");
+    }
+    url
+}
+
+async fn connect() -> Result<Option<DbPool>> {
+    let Some(url) = database_url() else {
+        return Ok(None);
+    };
+    let pool = DbPool::connect(&url).await?;
+    assert!(pool.as_sqlite().is_none());
+    Ok(Some(pool))
+}
+
+/// A scope no other test run uses, so tables from earlier runs don't interfere
+fn unique_scope(name: &str) -> String {
+    format!("pgtest_{}_{}", name, uuid::Uuid::new_v4().simple())
+}
+
+#[tokio::test]
+async fn test_postgres_store_round_trip_and_rollback() -> Result<()> {
+    let Some(pool) = connect().await? else {
+        return Ok(());
+    };
+    let store = SqlStore::new(pool.clone(), Box::new(PrefixPolicy));
+    let table = format!("pgtest_{}_data", std::process::id());
+    let scope = table.trim_end_matches("_data").to_string();
+
+    store
+        .execute(&scope, &format!("CREATE TABLE {} (id SERIAL PRIMARY KEY, content TEXT NOT NULL)", table), vec![])
+        .await?;
+
+    store
+        .execute(&scope, &format!("INSERT INTO {} (content) VALUES (?), (?)", table), vec!["a".into(), "b?".into()])
+        .await?;
+
+    // The second write fails, so the first is rolled back with it
+    let failed = store
+        .transaction(|tx| {
+            let (scope, table) = (scope.clone(), table.clone());
+            Box::pin(async move {
+                tx.execute(&scope, &format!("DELETE FROM {} WHERE content = ?", table), vec!["a".into()])
+                    .await?;
+                tx.execute(&scope, &format!("INSERT INTO {} (content) VALUES (NULL)", table), vec![])
+                    .await
+            })
+        })
+        .await;
+    assert!(matches!(failed, Err(StoreError::DbError(_))), "{:?}", failed);
+
+    let rows = store
+        .query(&scope, &format!("SELECT id, content FROM {} ORDER BY id", table), vec![])
+        .await?;
+    let contents: Vec<&str> = rows.iter().map(|row| row.values[1].as_str()).collect();
+    assert_eq!(contents, ["a", "b?"]);
+    assert_eq!(rows[0].values[0], "1");
+
+    // The policy still applies to every statement
+    let denied = store.query("other", &format!("SELECT * FROM {}", table), vec![]).await;
+    assert!(matches!(denied, Err(StoreError::PolicyError(_))));
+
+    store.execute(&scope, &format!("DROP TABLE {}", table), vec![]).await?;
+    pool.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_postgres_typed_store_reads_writes_and_expires() -> Result<()> {
+    let Some(pool) = connect().await? else {
+        return Ok(());
+    };
+    let scope = unique_scope("typed");
+    let store: TypedStore<String> = TypedStore::new(SqlStore::new(pool.clone(), Box::new(PrefixPolicy)), &scope);
+    store.ensure_table().await?;
+    store.ensure_table().await?;
+
+    store.put("task/1", &"first".to_string()).await?;
+    store.put("task/1", &"second".to_string()).await?;
+    store.put("task/2", &"other".to_string()).await?;
+    store.set_with_ttl("task/3", &"brief".to_string(), Duration::from_millis(1)).await?;
+    assert_eq!(store.get_versioned("task/1").await?, Some(("second".to_string(), 2)));
+
+    assert_eq!(store.compare_and_swap("task/1", 2, &"third".to_string()).await?, 3);
+    let conflict = store.compare_and_swap("task/1", 2, &"stale".to_string()).await;
+    assert!(matches!(conflict, Err(StoreError::VersionConflict { .. })), "{:?}", conflict);
+
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    assert_eq!(store.get("task/3").await?, None);
+    assert_eq!(store.compare_and_swap("task/3", 0, &"again".to_string()).await?, 1);
+
+    let keys: Vec<String> = store.list("task/").await?.into_iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, ["task/1", "task/2", "task/3"]);
+    let (page, next) = store.list_paged("task/", None, 2).await?;
+    assert_eq!(page.len(), 2);
+    assert_eq!(store.list_paged("task/", next.as_deref(), 2).await?.0.len(), 1);
+
+    let stats = SqlStore::new(pool.clone(), Box::new(PrefixPolicy)).stats(&scope, "task/").await?;
+    assert_eq!(stats.key_count, 3);
+
+    store.set_with_ttl("task/4", &"brief".to_string(), Duration::from_millis(1)).await?;
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    assert_eq!(store.sweep_expired().await?, 1);
+    assert!(store.delete("task/2").await?);
+
+    drop_tables(&pool, &scope, &[format!("{}_kv", scope)]).await
+}
+
+#[tokio::test]
+async fn test_postgres_typed_store_migrates_an_old_table() -> Result<()> {
+    let Some(pool) = connect().await? else {
+        return Ok(());
+    };
+    let scope = unique_scope("legacy");
+    let table = format!("{}_kv", scope);
+    let sql = SqlStore::new(pool.clone(), Box::new(PrefixPolicy));
+    sql.execute(
+        &scope,
+        &format!("CREATE TABLE {} (id TEXT PRIMARY KEY, value TEXT NOT NULL, updated_at BIGINT NOT NULL)", table),
+        vec![],
+    )
+    .await?;
+
+    let store: TypedStore<String> = TypedStore::new(SqlStore::new(pool.clone(), Box::new(PrefixPolicy)), &scope);
+    store.ensure_table().await?;
+    store.put("key", &"value".to_string()).await?;
+    assert_eq!(store.get_versioned("key").await?, Some(("value".to_string(), 1)));
+
+    drop_tables(&pool, &scope, &[table]).await
+}
+
+#[tokio::test]
+async fn test_postgres_change_log_and_locks() -> Result<()> {
+    let Some(pool) = connect().await? else {
+        return Ok(());
+    };
+    let scope = unique_scope("feed");
+    let log = Arc::new(ChangeLog::new(SqlStore::new(pool.clone(), Box::new(PrefixPolicy)), &scope));
+    log.ensure_table().await?;
+    let store: TypedStore<String> =
+        TypedStore::new(SqlStore::new(pool.clone(), Box::new(PrefixPolicy)), &scope).with_change_log(log.clone());
+    store.ensure_table().await?;
+
+    store.put("a", &"1".to_string()).await?;
+    store.delete("a").await?;
+    let sequence = log
+        .append(&StoreChange {
+            scope: scope.clone(),
+            key: "b".to_string(),
+            value: None,
+        })
+        .await?;
+    let events = log.read(0, "", 10).await?;
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[2].sequence, sequence);
+    assert!(events[0].change.value.is_some() && events[1].change.value.is_none());
+    assert_eq!(log.read(events[0].sequence, "a", 10).await?.len(), 1);
+
+    let lock_name = unique_scope("lock");
+    let locks = |owner: &str| LockManager::new(SqlStore::new(pool.clone(), Box::new(PrefixPolicy)), owner);
+    locks("one").ensure_table().await?;
+    let guard = locks("one").acquire_lock(&lock_name, Duration::from_secs(30)).await?;
+    let guard = guard.expect("a free lock is taken");
+    assert!(locks("two").acquire_lock(&lock_name, Duration::from_secs(30)).await?.is_none());
+    assert!(guard.renew().await?);
+    guard.release().await?;
+    assert!(locks("two").acquire_lock(&lock_name, Duration::from_secs(30)).await?.is_some());
+
+    drop_tables(&pool, &scope, &[format!("{}_kv", scope), format!("{}_changelog", scope)]).await
+}
+
+struct EchoProvider;
+
+#[async_trait::async_trait]
+impl LLMProvider for EchoProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        Ok(ChatResponse {
+            content: format!("{} messages", request.messages.len()),
+            usage: None,
+            tool_calls: Vec::new(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_postgres_host_records_and_replays() -> Result<()> {
+    let Some(url) = database_url() else {
+        return Ok(());
+    };
+    let host = BrioHostState::with_provider(&url, Box::new(EchoProvider))
+        .await?
+        .with_request_recording(Vec::new());
+
+    let ctx = host.request_context();
+    let answer = ctx.clone().scope(host.chat(ChatRequest::default())).await?;
+    let recorded = ctx.recorded_requests();
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(host.replay(&recorded[0]).await?.content, answer.content);
+
+    let lock = unique_scope("host_lock");
+    let guard = host.acquire_lock(&lock, Duration::from_secs(30)).await?;
+    assert!(guard.is_some());
+    assert!(host.acquire_lock(&lock, Duration::from_secs(30)).await?.is_none());
+    Ok(())
+}
+
+async fn drop_tables(pool: &DbPool, scope: &str, tables: &[String]) -> Result<()> {
+    let store = SqlStore::new(pool.clone(), Box::new(PrefixPolicy));
+    for table in tables {
+        store.execute(scope, &format!("DROP TABLE {}", table), vec![]).await?;
+    }
+    pool.close().await;
+    Ok(())
+}
@@ -12,6 +12,7 @@ fn scope_strategy() -> impl Strategy<Value = String> {
 }
 
 /// Strategy for generating table names that match the scope
+#[allow(dead_code)]
 fn matching_table_strategy(scope: String) -> impl Strategy<Value = String> {
     "[a-z]{1,8}".prop_map(move |suffix| format!("{}_{}", scope, suffix))
 }
@@ -2,6 +2,8 @@
 
 use brio_kernel::vfs::manager::SessionManager;
 use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 // =============================================================================
 // Session Manager Tests
@@ -181,6 +183,194 @@ fn test_session_delete_file() {
     let _ = fs::remove_dir_all(&temp);
 }
 
+#[test]
+fn test_list_sessions_reports_open_sessions() {
+    let first = std::env::temp_dir().join("brio_vfs_test_list_first");
+    let second = std::env::temp_dir().join("brio_vfs_test_list_second");
+    for dir in [&first, &second] {
+        if dir.exists() {
+            fs::remove_dir_all(dir).unwrap();
+        }
+        fs::create_dir_all(dir).unwrap();
+    }
+
+    let mut manager = SessionManager::new();
+    let first_id = manager
+        .begin_session(first.to_str().unwrap().to_string())
+        .unwrap();
+    let second_id = manager
+        .begin_session(second.to_str().unwrap().to_string())
+        .unwrap();
+    let second_path = manager.get_session_path(&second_id).unwrap();
+    fs::write(second_path.join("a.txt"), "a").unwrap();
+    fs::write(second_path.join("b.txt"), "b").unwrap();
+
+    let sessions = manager.list_sessions();
+    assert_eq!(sessions.len(), 2);
+    let find = |id: &str| sessions.iter().find(|s| s.session_id == id).unwrap();
+    assert_eq!(find(&first_id).base_path, first);
+    assert_eq!(find(&first_id).pending_changes, 0);
+    assert_eq!(find(&second_id).base_path, second);
+    assert_eq!(find(&second_id).pending_changes, 2);
+    assert!(find(&first_id).created_at <= find(&second_id).created_at);
+
+    manager.commit_session(first_id).unwrap();
+    let sessions = manager.list_sessions();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].session_id, second_id);
+
+    // Cleanup
+    manager.rollback_session(second_id).unwrap();
+    let _ = fs::remove_dir_all(&first);
+    let _ = fs::remove_dir_all(&second);
+}
+
+#[test]
+fn test_idle_sessions_are_rolled_back() {
+    let temp = std::env::temp_dir().join("brio_vfs_test_idle");
+    fs::create_dir_all(&temp).unwrap();
+
+    let now = Arc::new(Mutex::new(SystemTime::now()));
+    let clock = now.clone();
+    let mut manager = SessionManager::new().with_clock(Arc::new(move || *clock.lock().unwrap()));
+    manager.set_idle_timeout(Some(Duration::from_secs(60)));
+
+    let idle_id = manager
+        .begin_session(temp.to_str().unwrap().to_string())
+        .unwrap();
+    let busy_id = manager
+        .begin_session(temp.to_str().unwrap().to_string())
+        .unwrap();
+    let idle_path = manager.get_session_path(&idle_id).unwrap();
+
+    *now.lock().unwrap() += Duration::from_secs(45);
+    assert!(manager.touch_session(&busy_id));
+    assert!(manager.reap_idle_sessions().is_empty());
+
+    *now.lock().unwrap() += Duration::from_secs(30);
+    assert_eq!(manager.reap_idle_sessions(), vec![idle_id.clone()]);
+    assert!(manager.get_session_path(&idle_id).is_none());
+    assert!(!idle_path.exists());
+    assert_eq!(manager.active_session_count(), 1);
+
+    // Cleanup
+    manager.rollback_session(busy_id).unwrap();
+    let _ = fs::remove_dir_all(&temp);
+}
+
+#[test]
+fn test_sessions_edited_in_place_are_not_reaped() {
+    let temp = std::env::temp_dir().join("brio_vfs_test_idle_edits");
+    fs::create_dir_all(&temp).unwrap();
+
+    let now = Arc::new(Mutex::new(SystemTime::now()));
+    let clock = now.clone();
+    let mut manager = SessionManager::new().with_clock(Arc::new(move || *clock.lock().unwrap()));
+    manager.set_idle_timeout(Some(Duration::from_secs(60)));
+    let session_id = manager
+        .begin_session(temp.to_str().unwrap().to_string())
+        .unwrap();
+    let session_path = manager.get_session_path(&session_id).unwrap();
+
+    // A guest writes to the working copy without going through the manager
+    *now.lock().unwrap() += Duration::from_secs(90);
+    let edited = fs::File::create(session_path.join("draft.txt")).unwrap();
+    edited.set_modified(*now.lock().unwrap()).unwrap();
+    assert!(manager.reap_idle_sessions().is_empty());
+
+    *now.lock().unwrap() += Duration::from_secs(90);
+    assert_eq!(manager.reap_idle_sessions(), vec![session_id]);
+    assert!(!session_path.exists());
+
+    let _ = fs::remove_dir_all(&temp);
+}
+
+// =============================================================================
+// Rollback Tests
+// =============================================================================
+
+#[test]
+fn test_rollback_discards_session_changes() {
+    let temp = std::env::temp_dir().join("brio_vfs_test_rollback");
+    if temp.exists() {
+        fs::remove_dir_all(&temp).unwrap();
+    }
+    fs::create_dir_all(&temp).unwrap();
+    fs::write(temp.join("file.txt"), "original").unwrap();
+
+    let mut manager = SessionManager::new();
+    let session_id = manager
+        .begin_session(temp.to_str().unwrap().to_string())
+        .unwrap();
+
+    let session_path = manager.get_session_path(&session_id).unwrap();
+    fs::write(session_path.join("file.txt"), "modified").unwrap();
+    fs::write(session_path.join("new.txt"), "created").unwrap();
+
+    manager.rollback_session(session_id.clone()).unwrap();
+
+    // Base is untouched and the working copy is gone
+    assert_eq!(fs::read_to_string(temp.join("file.txt")).unwrap(), "original");
+    assert!(!temp.join("new.txt").exists());
+    assert!(!session_path.exists());
+    assert!(manager.get_session_path(&session_id).is_none());
+    assert_eq!(manager.active_session_count(), 0);
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp);
+}
+
+#[test]
+fn test_double_rollback_fails() {
+    let temp = std::env::temp_dir().join("brio_vfs_test_double_rollback");
+    fs::create_dir_all(&temp).unwrap();
+
+    let mut manager = SessionManager::new();
+    let session_id = manager
+        .begin_session(temp.to_str().unwrap().to_string())
+        .unwrap();
+
+    manager.rollback_session(session_id.clone()).unwrap();
+    let result = manager.rollback_session(session_id);
+    assert!(result.unwrap_err().contains("not found"));
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp);
+}
+
+#[test]
+fn test_rollback_after_commit_fails() {
+    let temp = std::env::temp_dir().join("brio_vfs_test_rollback_after_commit");
+    if temp.exists() {
+        fs::remove_dir_all(&temp).unwrap();
+    }
+    fs::create_dir_all(&temp).unwrap();
+
+    let mut manager = SessionManager::new();
+    let session_id = manager
+        .begin_session(temp.to_str().unwrap().to_string())
+        .unwrap();
+    let session_path = manager.get_session_path(&session_id).unwrap();
+    fs::write(session_path.join("kept.txt"), "committed").unwrap();
+    manager.commit_session(session_id.clone()).unwrap();
+
+    let result = manager.rollback_session(session_id);
+    assert!(result.unwrap_err().contains("not found"));
+    // The committed change stays
+    assert_eq!(fs::read_to_string(temp.join("kept.txt")).unwrap(), "committed");
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp);
+}
+
+#[test]
+fn test_rollback_nonexistent_session() {
+    let mut manager = SessionManager::new();
+    let result = manager.rollback_session("fake-session-id-12345".to_string());
+
+    assert!(result.unwrap_err().contains("not found"));
+}
+
 // =============================================================================
 // SessionManager Default Trait Test
 // =============================================================================